@@ -135,7 +135,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
     }
-    let pma = DoubleArrayAhoCorasick::new(patterns).map_err(|e| format!("{e}"))?;
+    let pma = DoubleArrayAhoCorasick::new(patterns)?;
 
     // Initialize the stream of termcolor.
     let mut stdout = match args.color {
@@ -109,6 +109,16 @@ impl BuildHelper {
         self.get_mut(base.get()).use_base();
     }
 
+    /// Releases the BASE value so that it can be used again.
+    ///
+    /// # Panic
+    ///
+    /// Panic will arise if active_index_range().contains(&base) == false.
+    #[inline(always)]
+    pub fn unuse_base(&mut self, base: NonZeroU32) {
+        self.get_mut(base.get()).unuse_base();
+    }
+
     /// Uses the index.
     ///
     /// # Panic
@@ -129,6 +139,37 @@ impl BuildHelper {
         }
     }
 
+    /// Releases the index so that it can be used again.
+    ///
+    /// # Panic
+    ///
+    /// Panic will arise if active_index_range().contains(&idx) == false.
+    pub fn unuse_index(&mut self, idx: u32) {
+        debug_assert!(self.get_ref(idx).is_used_index());
+        self.get_mut(idx).unuse_index();
+
+        // Vacant indices are linked in ascending order, so the released index is inserted in front
+        // of the next vacant one.
+        let end = self.active_index_range().end;
+        let next = (idx + 1..end)
+            .find(|&i| !self.is_used_index(i))
+            .or(self.head_idx);
+        if let Some(next) = next {
+            let prev = self.get_ref(next).prev();
+            *self.get_mut(idx).next_mut() = next;
+            *self.get_mut(idx).prev_mut() = prev;
+            *self.get_mut(prev).next_mut() = idx;
+            *self.get_mut(next).prev_mut() = idx;
+            if idx < self.head_idx.unwrap() {
+                self.head_idx = Some(idx);
+            }
+        } else {
+            *self.get_mut(idx).next_mut() = idx;
+            *self.get_mut(idx).prev_mut() = idx;
+            self.head_idx = Some(idx);
+        }
+    }
+
     /// Extends the array by pushing a block back.
     pub fn push_block(&mut self) -> Result<()> {
         if self.num_elements() > u32::MAX - self.block_len {
@@ -271,8 +312,18 @@ impl ListItem {
         self.used_base = true;
     }
 
+    #[inline(always)]
+    pub fn unuse_base(&mut self) {
+        self.used_base = false;
+    }
+
     #[inline(always)]
     pub fn use_index(&mut self) {
         self.used_index = true;
     }
+
+    #[inline(always)]
+    pub fn unuse_index(&mut self) {
+        self.used_index = false;
+    }
 }
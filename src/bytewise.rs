@@ -1,29 +1,77 @@
 //! A byte-wise version of the Double-Array Aho-Corasick.
 
+/// Evaluates `$body` with the constant `$s` set to the specialization `$spec` given by
+/// [`DoubleArrayAhoCorasick::spec()`], so that the search loops are monomorphized for the features
+/// used by the automaton.
+macro_rules! specialize {
+    ($spec:expr, $s:ident => $body:expr) => {
+        specialize!(@arms $spec, $s => $body; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15)
+    };
+    (@arms $spec:expr, $s:ident => $body:expr; $($n:literal),*) => {
+        match $spec {
+            $($n => {
+                const $s: u8 = $n;
+                $body
+            })*
+            _ => unreachable!(),
+        }
+    };
+}
+
 mod builder;
+mod editor;
 pub mod iter;
+mod mapper;
+#[cfg(feature = "std")]
+mod parallel;
+mod prefilter;
+mod tail;
 
-use core::mem;
+use core::fmt::Debug;
 use core::num::NonZeroU32;
+use core::{ascii, mem};
 
+use alloc::string::String;
 use alloc::vec::Vec;
 
 use crate::build_helper::BuildHelper;
+use crate::dot::DotWriter;
 use crate::errors::{DaachorseError, Result};
 use crate::intpack::{U24nU8, U24};
-use crate::serializer::{Serializable, SerializableVec};
+use crate::layout::{self, BlockRelocation, LayoutStats};
+use crate::outputs::OutputStore;
+use crate::serializer::{self, Serializable, SerializableVec, Sink, VecSink};
+use crate::stats::{AutomatonStats, StatsCollector};
 use crate::utils::FromU32;
-use crate::{MatchKind, Output};
+use crate::{DuplicatePolicy, MatchKind, Output, ValueCursor};
 pub use builder::DoubleArrayAhoCorasickBuilder;
+use builder::BLOCK_LEN;
+pub use editor::DoubleArrayAhoCorasickEditor;
 use iter::{
     FindIterator, FindOverlappingIterator, FindOverlappingNoSuffixIterator, LestmostFindIterator,
-    U8SliceIterator,
+    SkipFn, U8SliceIterator,
 };
+use mapper::ByteMapper;
+use prefilter::Prefilter;
+use tail::Tails;
 
 // The root index position.
 const ROOT_STATE_IDX: u32 = 0;
 // The dead index position.
 const DEAD_STATE_IDX: u32 = 1;
+// The row id of states without dense transitions.
+const NO_DENSE_ROW: u32 = u32::MAX;
+// The depth of states unreachable from the root.
+const UNREACHABLE_DEPTH: u32 = u32::MAX;
+
+// The flag of the specialization for automata having tails.
+const SPEC_TAILS: u8 = 1;
+// The flag of the specialization for automata having dense transitions.
+const SPEC_DENSE: u8 = 2;
+// The flag of the specialization for automata relabeling bytes.
+const SPEC_RELABEL: u8 = 4;
+// The flag of the specialization for automata having compact outputs.
+const SPEC_COMPACT: u8 = 8;
 
 /// A fast multiple pattern match automaton implemented with the Aho-Corasick algorithm and compact
 /// double-array data structure.
@@ -49,12 +97,31 @@ const DEAD_STATE_IDX: u32 = 1;
 ///
 /// The maximum number of patterns is limited to 2^24-1. If a larger number of patterns is given,
 /// [`DaachorseError`] will be reported.
+///
+/// The search over slice haystacks skips the bytes that cannot start a match only when the
+/// patterns start with at most 3 distinct bytes. With more distinct first bytes, every byte of the
+/// haystack goes through the state transitions, since scanning for that many bytes costs nearly as
+/// much as the transitions.
 #[derive(Clone, Eq, Hash, PartialEq)]
 pub struct DoubleArrayAhoCorasick<V> {
     states: Vec<State>,
-    outputs: Vec<Output<V>>,
+    // Single-branch suffixes stored out of the double array, following the ids of the states.
+    tails: Tails,
+    // The labels of the transitions are the codes of the bytes.
+    mapper: ByteMapper,
+    outputs: OutputStore<V>,
     match_kind: MatchKind,
     num_states: u32,
+    // The number of the last blocks in which the editor places new states.
+    num_free_blocks: u32,
+    // Precomputed transitions of hot states, containing 256 entries for each state.
+    dense: Vec<u32>,
+    // dense_rows[i] is the row of state i in dense, or NO_DENSE_ROW.
+    dense_rows: Vec<u32>,
+    // Derived from the states, so it is not serialized.
+    prefilter: Prefilter,
+    // The specialization given by Self::spec(), updated whenever the dense transitions are built.
+    spec: u8,
 }
 
 impl<V> DoubleArrayAhoCorasick<V> {
@@ -157,6 +224,7 @@ impl<V> DoubleArrayAhoCorasick<V> {
     ///
     /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
     /// supported and the function will panic.
+    /// [`Self::try_find_iter()`] returns an error instead.
     ///
     /// # Examples
     ///
@@ -186,10 +254,54 @@ impl<V> DoubleArrayAhoCorasick<V> {
         );
         FindIterator {
             pma: self,
-            haystack: U8SliceIterator::new(haystack).enumerate(),
+            haystack: U8SliceIterator::new(haystack),
+            pos: 0,
+            skip: self.prefilter_skip(),
+            spec: self.spec(),
+            values: ValueCursor::default(),
         }
     }
 
+    /// Returns an iterator of non-overlapping matches in the given haystack,
+    /// or an error instead of panicking as [`Self::find_iter()`] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when [`MatchKind::Standard`] is not specified in the
+    /// construction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new().build(&patterns).unwrap();
+    ///
+    /// let mut it = pma.try_find_iter("abcd").unwrap();
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// assert!(pma.try_find_iter("abcd").is_err());
+    /// ```
+    pub fn try_find_iter<P>(&self, haystack: P) -> Result<FindIterator<'_, U8SliceIterator<P>, V>>
+    where
+        P: AsRef<[u8]>,
+    {
+        self.match_kind.check_standard()?;
+        Ok(self.find_iter(haystack))
+    }
+
     /// Returns an iterator of non-overlapping matches in the given haystack iterator.
     ///
     /// # Arguments
@@ -200,6 +312,7 @@ impl<V> DoubleArrayAhoCorasick<V> {
     ///
     /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
     /// supported and the function will panic.
+    /// [`Self::try_find_iter_from_iter()`] returns an error instead.
     ///
     /// # Examples
     ///
@@ -231,10 +344,56 @@ impl<V> DoubleArrayAhoCorasick<V> {
         );
         FindIterator {
             pma: self,
-            haystack: haystack.enumerate(),
+            haystack,
+            pos: 0,
+            skip: None,
+            spec: self.spec(),
+            values: ValueCursor::default(),
         }
     }
 
+    /// Returns an iterator of non-overlapping matches in the given haystack iterator,
+    /// or an error instead of panicking as [`Self::find_iter_from_iter()`] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - [`u8`] iterator to search for.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when [`MatchKind::Standard`] is not specified in the
+    /// construction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let haystack = "ab".as_bytes().iter().chain("cd".as_bytes()).copied();
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new().build(&patterns).unwrap();
+    ///
+    /// let mut it = pma.try_find_iter_from_iter(haystack.clone()).unwrap();
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// assert!(pma.try_find_iter_from_iter(haystack).is_err());
+    /// ```
+    pub fn try_find_iter_from_iter<P>(&self, haystack: P) -> Result<FindIterator<'_, P, V>>
+    where
+        P: Iterator<Item = u8>,
+    {
+        self.match_kind.check_standard()?;
+        Ok(self.find_iter_from_iter(haystack))
+    }
+
     /// Returns an iterator of overlapping matches in the given haystack.
     ///
     /// # Arguments
@@ -245,6 +404,7 @@ impl<V> DoubleArrayAhoCorasick<V> {
     ///
     /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
     /// supported and the function will panic.
+    /// [`Self::try_find_overlapping_iter()`] returns an error instead.
     ///
     /// # Examples
     ///
@@ -280,14 +440,59 @@ impl<V> DoubleArrayAhoCorasick<V> {
         );
         FindOverlappingIterator {
             pma: self,
-            haystack: U8SliceIterator::new(haystack).enumerate(),
+            haystack: U8SliceIterator::new(haystack),
             state_id: ROOT_STATE_IDX,
-            output_pos: None,
             pos: 0,
+            skip: self.prefilter_skip(),
+            spec: self.spec(),
+            output_pos: None,
         }
     }
 
+    /// Returns an iterator of overlapping matches in the given haystack,
+    /// or an error instead of panicking as [`Self::find_overlapping_iter()`] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when [`MatchKind::Standard`] is not specified in the
+    /// construction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new().build(&patterns).unwrap();
+    ///
+    /// let mut it = pma.try_find_overlapping_iter("abcd").unwrap();
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
     ///
+    /// assert!(pma.try_find_overlapping_iter("abcd").is_err());
+    /// ```
+    pub fn try_find_overlapping_iter<P>(
+        &self,
+        haystack: P,
+    ) -> Result<FindOverlappingIterator<'_, U8SliceIterator<P>, V>>
+    where
+        P: AsRef<[u8]>,
+    {
+        self.match_kind.check_standard()?;
+        Ok(self.find_overlapping_iter(haystack))
+    }
+
+    /// Returns the state id from which [`DoubleArrayAhoCorasick::consume()`] starts.
     pub fn start_state(&self) -> u32 {
         ROOT_STATE_IDX
     }
@@ -302,6 +507,7 @@ impl<V> DoubleArrayAhoCorasick<V> {
     ///
     /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
     /// supported and the function will panic.
+    /// [`Self::try_find_overlapping_iter_from_iter()`] returns an error instead.
     ///
     /// # Examples
     ///
@@ -336,13 +542,60 @@ impl<V> DoubleArrayAhoCorasick<V> {
         );
         FindOverlappingIterator {
             pma: self,
-            haystack: haystack.enumerate(),
+            haystack,
             state_id: ROOT_STATE_IDX,
-            output_pos: None,
             pos: 0,
+            skip: None,
+            spec: self.spec(),
+            output_pos: None,
         }
     }
 
+    /// Returns an iterator of overlapping matches in the given haystack iterator,
+    /// or an error instead of panicking as [`Self::find_overlapping_iter_from_iter()`] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - [`u8`] iterator to search for.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when [`MatchKind::Standard`] is not specified in the
+    /// construction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let haystack = "ab".as_bytes().iter().chain("cd".as_bytes()).copied();
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new().build(&patterns).unwrap();
+    ///
+    /// let mut it = pma.try_find_overlapping_iter_from_iter(haystack.clone()).unwrap();
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// assert!(pma.try_find_overlapping_iter_from_iter(haystack).is_err());
+    /// ```
+    pub fn try_find_overlapping_iter_from_iter<P>(
+        &self,
+        haystack: P,
+    ) -> Result<FindOverlappingIterator<'_, P, V>>
+    where
+        P: Iterator<Item = u8>,
+    {
+        self.match_kind.check_standard()?;
+        Ok(self.find_overlapping_iter_from_iter(haystack))
+    }
+
     /// Returns an iterator of overlapping matches without suffixes in the given haystack iterator.
     ///
     /// The Aho-Corasick algorithm reads through the haystack from left to right and reports
@@ -359,6 +612,7 @@ impl<V> DoubleArrayAhoCorasick<V> {
     ///
     /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
     /// supported and the function will panic.
+    /// [`Self::try_find_overlapping_no_suffix_iter()`] returns an error instead.
     ///
     /// # Examples
     ///
@@ -391,11 +645,58 @@ impl<V> DoubleArrayAhoCorasick<V> {
         );
         FindOverlappingNoSuffixIterator {
             pma: self,
-            haystack: U8SliceIterator::new(haystack).enumerate(),
+            haystack: U8SliceIterator::new(haystack),
+            pos: 0,
+            skip: self.prefilter_skip(),
+            spec: self.spec(),
             state_id: ROOT_STATE_IDX,
+            values: ValueCursor::default(),
         }
     }
 
+    /// Returns an iterator of overlapping matches without suffixes in the given haystack,
+    /// or an error instead of panicking as [`Self::find_overlapping_no_suffix_iter()`] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when [`MatchKind::Standard`] is not specified in the
+    /// construction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["bcd", "cd", "abc"];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new().build(&patterns).unwrap();
+    ///
+    /// let mut it = pma.try_find_overlapping_no_suffix_iter("abcd").unwrap();
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 3, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// assert!(pma.try_find_overlapping_no_suffix_iter("abcd").is_err());
+    /// ```
+    pub fn try_find_overlapping_no_suffix_iter<P>(
+        &self,
+        haystack: P,
+    ) -> Result<FindOverlappingNoSuffixIterator<'_, U8SliceIterator<P>, V>>
+    where
+        P: AsRef<[u8]>,
+    {
+        self.match_kind.check_standard()?;
+        Ok(self.find_overlapping_no_suffix_iter(haystack))
+    }
+
     /// Returns an iterator of overlapping matches without suffixes in the given haystack iterator.
     ///
     /// The Aho-Corasick algorithm reads through the haystack from left to right and reports
@@ -412,6 +713,7 @@ impl<V> DoubleArrayAhoCorasick<V> {
     ///
     /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
     /// supported and the function will panic.
+    /// [`Self::try_find_overlapping_no_suffix_iter_from_iter()`] returns an error instead.
     ///
     /// # Examples
     ///
@@ -446,11 +748,60 @@ impl<V> DoubleArrayAhoCorasick<V> {
         );
         FindOverlappingNoSuffixIterator {
             pma: self,
-            haystack: haystack.enumerate(),
+            haystack,
+            pos: 0,
+            skip: None,
+            spec: self.spec(),
             state_id: ROOT_STATE_IDX,
+            values: ValueCursor::default(),
         }
     }
 
+    /// Returns an iterator of overlapping matches without suffixes in the given haystack iterator,
+    /// or an error instead of panicking as [`Self::find_overlapping_no_suffix_iter_from_iter()`] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - [`u8`] iterator to search for.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when [`MatchKind::Standard`] is not specified in the
+    /// construction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let haystack = "ab".as_bytes().iter().chain("cd".as_bytes()).copied();
+    ///
+    /// let patterns = vec!["bcd", "cd", "abc"];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new().build(&patterns).unwrap();
+    ///
+    /// let mut it = pma.try_find_overlapping_no_suffix_iter_from_iter(haystack.clone()).unwrap();
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 3, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// assert!(pma.try_find_overlapping_no_suffix_iter_from_iter(haystack).is_err());
+    /// ```
+    pub fn try_find_overlapping_no_suffix_iter_from_iter<P>(
+        &self,
+        haystack: P,
+    ) -> Result<FindOverlappingNoSuffixIterator<'_, P, V>>
+    where
+        P: Iterator<Item = u8>,
+    {
+        self.match_kind.check_standard()?;
+        Ok(self.find_overlapping_no_suffix_iter_from_iter(haystack))
+    }
+
     /// Returns an iterator of leftmost matches in the given haystack.
     ///
     /// The leftmost match greedily searches the longest possible match at each iteration, and
@@ -474,6 +825,7 @@ impl<V> DoubleArrayAhoCorasick<V> {
     ///
     /// If you do not specify [`MatchKind::LeftmostFirst`] or [`MatchKind::LeftmostLongest`] in
     /// the construction, the iterator is not supported and the function will panic.
+    /// [`Self::try_leftmost_find_iter()`] returns an error instead.
     ///
     /// # Examples
     ///
@@ -526,89 +878,744 @@ impl<V> DoubleArrayAhoCorasick<V> {
             pma: self,
             haystack,
             pos: 0,
+            spec: self.spec(),
+            values: ValueCursor::default(),
         }
     }
 
-    /// Returns the total amount of heap used by this automaton in bytes.
+    /// Returns an iterator of leftmost matches in the given haystack, or an error instead of
+    /// panicking as [`Self::leftmost_find_iter()`] does.
     ///
-    /// # Examples
+    /// # Arguments
     ///
-    /// ```
-    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    /// * `haystack` - String to search for.
     ///
-    /// let patterns = vec!["bcd", "ab", "a"];
-    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    /// # Errors
     ///
-    /// assert_eq!(3108, pma.heap_bytes());
-    /// ```
-    #[must_use]
-    pub fn heap_bytes(&self) -> usize {
-        self.states.len() * mem::size_of::<State>()
-            + self.outputs.len() * mem::size_of::<Output<V>>()
-    }
-
-    /// Returns the total number of states this automaton has.
+    /// [`DaachorseError`] is returned when neither [`MatchKind::LeftmostLongest`] nor
+    /// [`MatchKind::LeftmostFirst`] is specified in the construction.
     ///
     /// # Examples
     ///
     /// ```
-    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
     ///
-    /// let patterns = vec!["bcd", "ab", "a"];
-    /// let pma = DoubleArrayAhoCorasick::<usize>::new(patterns).unwrap();
+    /// let patterns = vec!["ab", "a", "abcd"];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
     ///
-    /// assert_eq!(pma.num_states(), 6);
-    /// ```
-    #[must_use]
-    pub fn num_states(&self) -> usize {
-        usize::from_u32(self.num_states)
-    }
-
-    /// Serializes the automaton into a [`Vec`].
+    /// let mut it = pma.try_leftmost_find_iter("abcd").unwrap();
     ///
-    /// # Examples
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 4, 2), (m.start(), m.end(), m.value()));
     ///
-    /// ```
-    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    /// let pma: DoubleArrayAhoCorasick<u32> =
+    ///     DoubleArrayAhoCorasickBuilder::new().build(&patterns).unwrap();
     ///
-    /// let patterns = vec!["bcd", "ab", "a"];
-    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
-    /// let bytes = pma.serialize();
+    /// assert!(pma.try_leftmost_find_iter("abcd").is_err());
     /// ```
-    #[must_use]
-    pub fn serialize(&self) -> Vec<u8>
+    pub fn try_leftmost_find_iter<P>(&self, haystack: P) -> Result<LestmostFindIterator<'_, P, V>>
     where
-        V: Serializable,
+        P: AsRef<[u8]>,
     {
-        let mut result = Vec::with_capacity(
-            self.states.serialized_bytes()
-                + self.outputs.serialized_bytes()
-                + MatchKind::serialized_bytes()
-                + u32::serialized_bytes(),
-        );
-        self.states.serialize_to_vec(&mut result);
-        self.outputs.serialize_to_vec(&mut result);
-        self.match_kind.serialize_to_vec(&mut result);
-        self.num_states.serialize_to_vec(&mut result);
-        result
+        self.match_kind.check_leftmost()?;
+        Ok(self.leftmost_find_iter(haystack))
     }
 
-    /// Deserializes the automaton from a given slice.
+    /// Converts the automaton into [`DoubleArrayAhoCorasickEditor`] to insert or remove patterns
+    /// without rebuilding it from scratch.
     ///
-    /// # Arguments
+    /// # Errors
     ///
-    /// * `source` - A source slice.
+    /// [`DaachorseError`] is returned when
+    ///   - [`MatchKind::Standard`] is not specified in the construction, or
+    ///   - [`DoubleArrayAhoCorasickBuilder::tail_compression()`] is enabled in the construction.
     ///
-    /// # Returns
+    /// The automaton is dropped in that case.
     ///
-    /// A tuple of the automaton and the slice not used for the deserialization.
+    /// # Examples
     ///
-    /// # Safety
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
     ///
-    /// The given data must be a correct automaton exported by
-    /// [`DoubleArrayAhoCorasick::serialize()`] function.
+    /// let patterns = vec!["bcd", "ab"];
+    /// let pma = DoubleArrayAhoCorasick::new(&patterns).unwrap();
     ///
-    /// # Examples
+    /// let mut editor = pma.into_editor().unwrap();
+    /// editor.insert("a", 2).unwrap();
+    /// let pma = editor.finish();
+    ///
+    /// let mut it = pma.find_iter("abcd");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    ///
+    /// let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// assert!(pma.into_editor().is_err());
+    /// ```
+    pub fn into_editor(self) -> Result<DoubleArrayAhoCorasickEditor<V>>
+    where
+        V: Copy,
+    {
+        self.match_kind.check_standard()?;
+        if !self.tails.is_empty() {
+            return Err(DaachorseError::invalid_conversion(
+                "tail-compressed automaton",
+                "DoubleArrayAhoCorasickEditor",
+            ));
+        }
+        Ok(DoubleArrayAhoCorasickEditor::new(self))
+    }
+
+    /// Merges multiple automata into a new one.
+    ///
+    /// The patterns are reconstructed from the automata and rebuilt with the same match kind, so
+    /// the original pattern lists are not needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `pmas` - List of automata to be merged.
+    /// * `policy` - Policy to resolve the values of patterns appearing in multiple automata.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when
+    ///   - `pmas` is empty,
+    ///   - `pmas` are built with different match kinds,
+    ///   - `pmas` contain the same pattern and [`DuplicatePolicy::Error`] is specified, or
+    ///   - the scale of the resulting automaton exceeds the expected one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DuplicatePolicy};
+    ///
+    /// let pma1 = DoubleArrayAhoCorasick::with_values([("bcd", 1), ("ab", 2)]).unwrap();
+    /// let pma2 = DoubleArrayAhoCorasick::with_values([("ab", 4), ("a", 8)]).unwrap();
+    ///
+    /// let pma =
+    ///     DoubleArrayAhoCorasick::merge(&[&pma1, &pma2], DuplicatePolicy::Merge(|a, b| a | b))
+    ///         .unwrap();
+    ///
+    /// let mut it = pma.find_overlapping_iter("abcd");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 8), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 2, 6), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn merge(pmas: &[&Self], policy: DuplicatePolicy<V>) -> Result<Self>
+    where
+        V: Copy,
+    {
+        let match_kind = pmas
+            .first()
+            .ok_or_else(|| DaachorseError::invalid_argument("pmas.len()", ">=", 1))?
+            .match_kind;
+        for pma in pmas {
+            pma.match_kind.check_same(match_kind)?;
+        }
+        DoubleArrayAhoCorasickBuilder::new()
+            .match_kind(match_kind)
+            .on_duplicate(policy)
+            .build_with_values(pmas.iter().flat_map(|pma| pma.patterns()))
+    }
+
+    /// Reconstructs the pattern-value pairs.
+    ///
+    /// Patterns are returned in the reverse of the depth-first order, so every pattern precedes its
+    /// prefixes and is kept even when rebuilt with [`MatchKind::LeftmostFirst`].
+    fn patterns(&self) -> Vec<(Vec<u8>, V)>
+    where
+        V: Copy,
+    {
+        let inverse = self.mapper.inverse();
+        let mut patvals = vec![];
+        let mut pattern = vec![];
+        let mut stack = vec![(ROOT_STATE_IDX, 0, 0)];
+        while let Some((state_id, depth, c)) = stack.pop() {
+            pattern.truncate(depth);
+            if state_id != ROOT_STATE_IDX {
+                pattern.push(c);
+            }
+            // The outputs with the same length as the pattern are its own values, which are
+            // pushed in reverse since the whole list is reversed at the end.
+            let mut values = vec![];
+            // state_id is always smaller than the number of states because
+            // child_index_unchecked() returns such values.
+            let mut output_pos = unsafe { self.output_pos_unchecked(state_id) };
+            while let Some(pos) = output_pos {
+                let output = self.outputs.get(usize::from_u32(pos.get() - 1));
+                if usize::from_u32(output.length()) != pattern.len() {
+                    break;
+                }
+                values.push(output.value());
+                output_pos = output.parent();
+            }
+            for &value in values.iter().rev() {
+                patvals.push((pattern.clone(), value));
+            }
+            for code in (u8::MIN..=u8::MAX).rev() {
+                if let Some(child_idx) = unsafe { self.child_index_unchecked(state_id, code) } {
+                    stack.push((child_idx, pattern.len(), inverse[usize::from(code)]));
+                }
+            }
+        }
+        patvals.reverse();
+        patvals
+    }
+
+    /// Returns the total amount of heap used by this automaton in bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// assert_eq!(3108, pma.heap_bytes());
+    /// ```
+    #[must_use]
+    pub fn heap_bytes(&self) -> usize {
+        self.states.len() * mem::size_of::<State>()
+            + self.tails.heap_bytes()
+            + self.outputs.heap_bytes()
+            + self.dense.len() * mem::size_of::<u32>()
+            + self.dense_rows.len() * mem::size_of::<u32>()
+    }
+
+    /// Returns the total number of states this automaton has.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<usize>::new(patterns).unwrap();
+    ///
+    /// assert_eq!(pma.num_states(), 6);
+    /// ```
+    #[must_use]
+    pub fn num_states(&self) -> usize {
+        usize::from_u32(self.num_states)
+    }
+
+    /// Returns the locality of the states in the double array, weighting each state with a
+    /// heuristic value that prefers shallow states with many children.
+    ///
+    /// See [`LayoutStats`] for the details, and
+    /// [`DoubleArrayAhoCorasickBuilder::reorder_states()`] for improving the locality.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let stats = pma.layout_stats();
+    /// assert_eq!(1, stats.num_blocks());
+    /// assert_eq!(1, stats.hot_blocks());
+    /// ```
+    #[must_use]
+    pub fn layout_stats(&self) -> LayoutStats {
+        let weights = self.heuristic_state_weights();
+        layout::layout_stats(&layout::block_weights(&weights, BLOCK_LEN), BLOCK_LEN)
+    }
+
+    /// Returns the locality of the states in the double array, weighting each state with the
+    /// number of its visits in searching `sample`.
+    ///
+    /// See [`LayoutStats`] for the details, and
+    /// [`DoubleArrayAhoCorasickBuilder::reorder_sample()`] for improving the locality.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample` - Sample haystack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let stats = pma.layout_stats_with_sample("abcd");
+    /// assert_eq!(1, stats.hot_blocks());
+    /// assert_eq!(5, stats.total_weight());
+    /// ```
+    #[must_use]
+    pub fn layout_stats_with_sample<P>(&self, sample: P) -> LayoutStats
+    where
+        P: AsRef<[u8]>,
+    {
+        let weights = self.sample_state_weights(sample.as_ref());
+        layout::layout_stats(&layout::block_weights(&weights, BLOCK_LEN), BLOCK_LEN)
+    }
+
+    /// Returns the statistics of the automaton, which is useful for tuning the options of
+    /// [`DoubleArrayAhoCorasickBuilder`] and comparing builds.
+    ///
+    /// The elements in the double array do not include the tails (see
+    /// [`DoubleArrayAhoCorasickBuilder::tail_compression()`]), and the mapper uses no heap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let stats = pma.stats();
+    /// assert_eq!(3, stats.num_patterns());
+    /// assert_eq!(6, stats.num_states());
+    /// assert_eq!(256, stats.num_elements());
+    /// assert_eq!(249, stats.num_vacant_elements());
+    /// assert_eq!(3, stats.max_depth());
+    /// assert_eq!(&[4, 1], stats.fail_depths());
+    /// assert_eq!(&[3, 3], stats.output_chain_lengths());
+    /// assert_eq!(pma.heap_bytes(), stats.states_bytes() + stats.outputs_bytes());
+    /// ```
+    #[must_use]
+    pub fn stats(&self) -> AutomatonStats
+    where
+        V: Copy,
+    {
+        let (order, depths) = self.breadth_first_states();
+        let mut collector = StatsCollector::default();
+        for &state_id in &order {
+            let depth = depths[usize::from_u32(state_id)];
+            // state_id is always smaller than the number of states because
+            // child_index_unchecked() returns such values.
+            let mut output_pos = unsafe { self.output_pos_unchecked(state_id) };
+            let (fail_depth, has_pattern) = if state_id == ROOT_STATE_IDX {
+                (None, false)
+            } else {
+                let fail_id = unsafe { self.fail_unchecked(state_id) };
+                let fail_depth = depths[usize::from_u32(fail_id)];
+                // The states without their own outputs inherit those of the failure states.
+                let fail_output_pos = unsafe { self.output_pos_unchecked(fail_id) };
+                (
+                    Some(fail_depth).filter(|&d| d != UNREACHABLE_DEPTH),
+                    output_pos.is_some() && output_pos != fail_output_pos,
+                )
+            };
+            let mut num_outputs = 0;
+            while let Some(pos) = output_pos {
+                num_outputs += 1;
+                output_pos = self.outputs.get(usize::from_u32(pos.get() - 1)).parent();
+            }
+            collector.add_state(depth, fail_depth, num_outputs, has_pattern);
+        }
+        // The dead state is also used.
+        let num_used_elements = order
+            .iter()
+            .filter(|&&state_id| usize::from_u32(state_id) < self.states.len())
+            .count()
+            + 1;
+        AutomatonStats {
+            num_elements: self.states.len(),
+            num_vacant_elements: self.states.len() - num_used_elements,
+            states_bytes: self.states.len() * mem::size_of::<State>() + self.tails.heap_bytes(),
+            outputs_bytes: self.outputs.heap_bytes(),
+            dense_bytes: self.dense.len() * mem::size_of::<u32>()
+                + self.dense_rows.len() * mem::size_of::<u32>(),
+            ..collector.finish()
+        }
+    }
+
+    /// Returns the states reachable from the root in breadth-first order and the depths of all
+    /// the states, where the unreachable ones have [`UNREACHABLE_DEPTH`].
+    fn breadth_first_states(&self) -> (Vec<u32>, Vec<u32>) {
+        let mut depths = vec![UNREACHABLE_DEPTH; self.states.len() + self.tails.len()];
+        depths[usize::from_u32(ROOT_STATE_IDX)] = 0;
+        let mut order = vec![ROOT_STATE_IDX];
+        let mut qi = 0;
+        while let Some(&state_id) = order.get(qi) {
+            qi += 1;
+            let depth = depths[usize::from_u32(state_id)];
+            for code in u8::MIN..=u8::MAX {
+                // state_id is always smaller than the number of states because
+                // child_index_unchecked() returns such values.
+                if let Some(child_id) = unsafe { self.child_index_unchecked(state_id, code) } {
+                    depths[usize::from_u32(child_id)] = depth + 1;
+                    order.push(child_id);
+                }
+            }
+        }
+        (order, depths)
+    }
+
+    /// Returns the automaton in the DOT language of [Graphviz](https://graphviz.org/), which is
+    /// useful for debugging the matching behavior.
+    ///
+    /// Each state is drawn with its id, the escaped bytes from the root, and the values reported
+    /// when it is reached. Goto edges are labeled with the bytes, and failure edges are dashed.
+    /// In leftmost automata, the failure edges of the states that must not fall back point to the
+    /// dead state.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_states` - Maximum number of states to draw. The states are drawn in breadth-first
+    ///   order, and the number of the omitted ones is noted in the graph.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let dot = pma.to_dot(100);
+    /// assert!(dot.starts_with("digraph automaton {"));
+    /// assert!(dot.contains("[label=\"a\"];"));
+    /// assert!(dot.contains("\\\"ab\\\"\\n[1]"));
+    ///
+    /// let dot = pma.to_dot(3);
+    /// assert!(dot.contains("3 more states omitted"));
+    /// ```
+    #[must_use]
+    pub fn to_dot(&self, max_states: usize) -> String
+    where
+        V: Copy + Debug,
+    {
+        let inverse = self.mapper.inverse();
+        let mut writer = DotWriter::new(DEAD_STATE_IDX);
+        // Every failure state is shallower than the state, so it is drawn before.
+        let mut queue = vec![(ROOT_STATE_IDX, String::new())];
+        queue.truncate(max_states);
+        let mut qi = 0;
+        while let Some((state_id, path)) = queue.get_mut(qi).map(|(s, p)| (*s, mem::take(p))) {
+            qi += 1;
+            // state_id is always smaller than the number of states because
+            // child_index_unchecked() returns such values.
+            let output_pos = unsafe { self.output_pos_unchecked(state_id) };
+            let values = core::iter::successors(output_pos, |pos| {
+                self.outputs.get(usize::from_u32(pos.get() - 1)).parent()
+            })
+            .map(|pos| self.outputs.get(usize::from_u32(pos.get() - 1)).value());
+            writer.add_state(state_id, &path, values);
+            if state_id != ROOT_STATE_IDX {
+                writer.add_fail(state_id, unsafe { self.fail_unchecked(state_id) });
+            }
+            for code in u8::MIN..=u8::MAX {
+                if queue.len() == max_states {
+                    break;
+                }
+                if let Some(child_id) = unsafe { self.child_index_unchecked(state_id, code) } {
+                    let label: String = ascii::escape_default(inverse[usize::from(code)])
+                        .map(char::from)
+                        .collect();
+                    writer.add_goto(state_id, child_id, &label);
+                    queue.push((child_id, path.clone() + &label));
+                }
+            }
+        }
+        writer.finish(self.num_states() - queue.len())
+    }
+
+    /// Verifies the invariants of the automaton that the search relies on, which is useful in
+    /// tests and after loading untrusted data with [`Self::deserialize_unchecked()`].
+    ///
+    /// The following invariants are verified:
+    ///
+    /// - the BASE value of every state points to a block in the double array or a tail,
+    /// - every state reachable from the root is the child of a single state,
+    /// - the failure link of every reachable state points to a shallower state, or to the dead
+    ///   state in leftmost automata,
+    /// - the outputs form acyclic chains ending in [`None`],
+    /// - the number of states is that of the reachable ones, and
+    /// - the dense transitions and the byte mapper are consistent.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when an invariant is violated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    /// assert!(pma.verify().is_ok());
+    ///
+    /// let bytes = pma.serialize();
+    /// let (other, _) = unsafe { DoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+    /// assert!(other.verify().is_ok());
+    /// ```
+    pub fn verify(&self) -> Result<()>
+    where
+        V: Copy,
+    {
+        let error =
+            |invariant, state_id| Err(DaachorseError::invalid_automaton(invariant, state_id));
+        if self.states.is_empty() || self.states.len() % usize::from_u32(BLOCK_LEN) != 0 {
+            return error("the double array consists of blocks", None);
+        }
+        if self.num_free_blocks == 0 || BLOCK_LEN.checked_mul(self.num_free_blocks).is_none() {
+            return error("the free blocks fit in u32", None);
+        }
+        let num_ids = match u32::try_from(self.states.len() + self.tails.len()) {
+            Ok(num_ids) => num_ids,
+            Err(_) => return error("state ids fit in u32", None),
+        };
+        if !self.tails.is_valid() {
+            return error("tails are consistent", None);
+        }
+        if !self.mapper.is_valid() {
+            return error("the byte mapper is a permutation", None);
+        }
+        self.outputs.verify()?;
+        for (state_id, state) in (0..).zip(&self.states) {
+            if state.base().map_or(false, |base| num_ids <= base.get()) {
+                return error("BASE values point to the states", Some(state_id));
+            }
+        }
+
+        let mut depths = vec![UNREACHABLE_DEPTH; usize::from_u32(num_ids)];
+        depths[usize::from_u32(ROOT_STATE_IDX)] = 0;
+        let mut order = vec![ROOT_STATE_IDX];
+        let mut qi = 0;
+        while let Some(&state_id) = order.get(qi) {
+            qi += 1;
+            let depth = depths[usize::from_u32(state_id)];
+            for code in u8::MIN..=u8::MAX {
+                // state_id is smaller than the number of states because the BASE values are
+                // verified above.
+                if let Some(child_id) = unsafe { self.child_index_unchecked(state_id, code) } {
+                    let child_depth = &mut depths[usize::from_u32(child_id)];
+                    if child_id == DEAD_STATE_IDX || *child_depth != UNREACHABLE_DEPTH {
+                        return error("every state has a single parent", Some(child_id));
+                    }
+                    *child_depth = depth + 1;
+                    order.push(child_id);
+                }
+            }
+        }
+        if order.len() != self.num_states() {
+            return error("num_states is the number of reachable states", None);
+        }
+
+        for &state_id in &order {
+            let depth = depths[usize::from_u32(state_id)];
+            // state_id is a reachable state.
+            if state_id != ROOT_STATE_IDX {
+                let fail_id = unsafe { self.fail_unchecked(state_id) };
+                let is_dead = fail_id == DEAD_STATE_IDX && self.match_kind.is_leftmost();
+                if !is_dead
+                    && depths
+                        .get(usize::from_u32(fail_id))
+                        .map_or(true, |&d| depth <= d)
+                {
+                    return error("fail links point to shallower states", Some(state_id));
+                }
+            }
+            if let Some(pos) = unsafe { self.output_pos_unchecked(state_id) } {
+                let pos = usize::from_u32(pos.get() - 1);
+                if self.outputs.len() <= pos || depth < self.outputs.get(pos).length() {
+                    return error("outputs are suffixes of the states", Some(state_id));
+                }
+            }
+        }
+
+        if self.dense.len() % 256 != 0 {
+            return error("dense transitions have 256 entries for each row", None);
+        }
+        for (state_id, &row) in (0..).zip(&self.dense_rows) {
+            if row == NO_DENSE_ROW {
+                continue;
+            }
+            let depth = depths
+                .get(usize::from_u32(state_id))
+                .copied()
+                .unwrap_or(UNREACHABLE_DEPTH);
+            let entries = usize::from_u32(row)
+                .checked_mul(256)
+                .and_then(|start| self.dense.get(start..)?.get(..256))
+                .filter(|_| depth != UNREACHABLE_DEPTH);
+            let entries = match entries {
+                Some(entries) => entries,
+                None => return error("dense rows belong to reachable states", Some(state_id)),
+            };
+            // The next state is at most one level deeper.
+            if entries.iter().any(|&next_id| {
+                depths
+                    .get(usize::from_u32(next_id))
+                    .map_or(true, |&d| d == UNREACHABLE_DEPTH || depth + 1 < d)
+            }) {
+                return error(
+                    "dense transitions point to reachable states",
+                    Some(state_id),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes the automaton into a [`Vec`].
+    ///
+    /// The serialized data does not depend on the platform: every integer is stored in
+    /// little-endian, and `usize` and `isize` values are stored as 64-bit integers. Thus, an
+    /// automaton serialized on one platform can be deserialized on another one with a different
+    /// endianness or pointer width, as long as the values fit in the pointer width.
+    ///
+    /// # Format
+    ///
+    /// The data consists of the following fields in order. Every integer is little-endian. A
+    /// vector is its number of elements as `u32` followed by the elements, and a packed vector is
+    /// a vector of `u64` words, the number of values as `u32`, and the bit width `w` as `u8`,
+    /// where the `i`-th value occupies the `w` bits from the `i * w`-th bit of the words. Values
+    /// are written by [`Serializable`].
+    ///
+    /// | Field | Layout |
+    /// |---|---|
+    /// | magic | The 8 bytes `DAACBYTE`. |
+    /// | version | `u32`, which is 1 in this release. |
+    /// | states | Vector of states, each of which is the base as `u32` (0 if none), the fail as `u32`, and the check as `u8` followed by the 24-bit output position (0 if none). |
+    /// | outputs | `u8` tag. 0 is followed by the vector of outputs, each of which is the value, the length as `u32`, and the parent as `u32` (0 if none). 1 is followed by the vector of values, the packed vector of `parent << b \| length`, and `b` as `u8`. |
+    /// | match_kind | `u8`: 0 for `Standard`, 1 for `LeftmostLongest`, and 2 for `LeftmostFirst`. |
+    /// | num_states | `u32`. |
+    /// | num_free_blocks | `u32`. |
+    /// | num_dense_states | `u32`. The dense transitions are not stored but rebuilt on loading. |
+    /// | tails | Packed vector of `fail << 9 \| first << 8 \| label`, vector of `u64` bits marking the positions having outputs, vector of `u32` ranks of the bits for every 64 positions, and packed vector of the output positions. |
+    /// | mapper | `u8` tag. 0 for the identity, or 1 followed by the 256 bytes of the codes of the bytes. |
+    ///
+    /// The version is incremented whenever the layout changes, and the loaders reject data of the
+    /// other versions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    /// let bytes = pma.serialize();
+    /// assert_eq!(b"DAACBYTE", &bytes[..8]);
+    /// ```
+    #[must_use]
+    pub fn serialize(&self) -> Vec<u8>
+    where
+        V: Serializable,
+    {
+        let mut result = Vec::with_capacity(self.serialized_len());
+        match self.serialize_into(&mut VecSink(&mut result)) {
+            Ok(()) => result,
+            Err(e) => match e {},
+        }
+    }
+
+    /// Serializes the automaton into the given sink, writing the same bytes as
+    /// [`Self::serialize()`] without holding the whole serialized data in memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - A destination such as a file.
+    ///
+    /// # Errors
+    ///
+    /// The error of the sink is returned when writing fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, VecSink};
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let mut bytes = Vec::with_capacity(pma.serialized_len());
+    /// pma.serialize_into(&mut VecSink(&mut bytes)).unwrap();
+    /// assert_eq!(pma.serialize(), bytes);
+    /// ```
+    pub fn serialize_into<W>(&self, sink: &mut W) -> Result<(), W::Error>
+    where
+        V: Serializable,
+        W: Sink + ?Sized,
+    {
+        serializer::serialize_header_into(&serializer::BYTEWISE_MAGIC, sink)?;
+        self.states.serialize_into(sink)?;
+        self.outputs.serialize_into(sink)?;
+        serializer::serialize_value_into(&self.match_kind, sink)?;
+        serializer::serialize_value_into(&self.num_states, sink)?;
+        serializer::serialize_value_into(&self.num_free_blocks, sink)?;
+        serializer::serialize_value_into(&self.num_dense_states(), sink)?;
+        self.tails.serialize_into(sink)?;
+        self.mapper.serialize_into(sink)
+    }
+
+    /// Returns the length of the serialized automaton in bytes, i.e., the number of bytes written
+    /// by [`Self::serialize_into()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    /// assert_eq!(pma.serialize().len(), pma.serialized_len());
+    /// ```
+    #[must_use]
+    pub fn serialized_len(&self) -> usize
+    where
+        V: Serializable,
+    {
+        serializer::header_bytes()
+            + self.states.serialized_bytes()
+            + self.outputs.serialized_bytes()
+            + MatchKind::serialized_bytes()
+            + u32::serialized_bytes() * 3
+            + self.tails.serialized_bytes()
+            + self.mapper.serialized_bytes()
+    }
+
+    /// Deserializes the automaton from a given slice.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - A source slice.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of the automaton and the slice not used for the deserialization.
+    ///
+    /// # Safety
+    ///
+    /// The given data must be a correct automaton exported by
+    /// [`DoubleArrayAhoCorasick::serialize()`] function.
+    /// Use [`Self::deserialize()`] to load untrusted data.
+    ///
+    /// # Panics
+    ///
+    /// It panics if the data does not start with the header of the supported format.
+    ///
+    /// # Examples
     ///
     /// ```
     /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
@@ -637,26 +1644,147 @@ impl<V> DoubleArrayAhoCorasick<V> {
     where
         V: Serializable,
     {
+        let source = serializer::deserialize_header_unchecked(&serializer::BYTEWISE_MAGIC, source);
         let (states, source) = Vec::<State>::deserialize_from_slice(source);
-        let (outputs, source) = Vec::<Output<V>>::deserialize_from_slice(source);
+        let (outputs, source) = OutputStore::<V>::deserialize_from_slice(source);
         let (match_kind, source) = MatchKind::deserialize_from_slice(source);
         let (num_states, source) = u32::deserialize_from_slice(source);
-        (
-            Self {
-                states,
-                outputs,
-                match_kind,
-                num_states,
-            },
-            source,
-        )
+        let (num_free_blocks, source) = u32::deserialize_from_slice(source);
+        let (num_dense_states, source) = u32::deserialize_from_slice(source);
+        let (tails, source) = Tails::deserialize_from_slice(source);
+        let (mapper, source) = ByteMapper::deserialize_from_slice(source);
+        let mut pma = Self {
+            states,
+            tails,
+            mapper,
+            outputs,
+            match_kind,
+            num_states,
+            num_free_blocks,
+            dense: vec![],
+            dense_rows: vec![],
+            prefilter: Prefilter::default(),
+            spec: 0,
+        };
+        pma.build_dense_transitions(num_dense_states);
+        pma.build_prefilter();
+        (pma, source)
     }
 
+    /// Deserializes the automaton from a given slice, checking that the data is well-formed and
+    /// satisfies the invariants verified by [`Self::verify()`].
+    ///
+    /// Unlike [`Self::deserialize_unchecked()`], this function is safe to call with untrusted data.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - A source slice.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of the automaton and the slice not used for the deserialization.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when
+    ///   - the data does not start with the header of the supported format version,
+    ///   - the data is truncated or malformed, or
+    ///   - an invariant of the automaton is violated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    /// let bytes = pma.serialize();
+    ///
+    /// let (pma, rest) = DoubleArrayAhoCorasick::<u32>::deserialize(&bytes).unwrap();
+    /// assert!(rest.is_empty());
+    /// assert_eq!(bytes, pma.serialize());
+    ///
+    /// assert!(DoubleArrayAhoCorasick::<u32>::deserialize(&bytes[..bytes.len() - 1]).is_err());
+    /// ```
+    pub fn deserialize(source: &[u8]) -> Result<(Self, &[u8])>
+    where
+        V: Serializable + Copy,
+    {
+        let malformed =
+            || DaachorseError::invalid_automaton("the serialized data is well-formed", None);
+        let source = serializer::deserialize_header(&serializer::BYTEWISE_MAGIC, source)?;
+        let (states, source) =
+            Vec::<State>::deserialize_from_slice_checked(source).ok_or_else(malformed)?;
+        let (outputs, source) =
+            OutputStore::<V>::deserialize_from_slice_checked(source).ok_or_else(malformed)?;
+        let (match_kind, source) =
+            MatchKind::deserialize_from_slice_checked(source).ok_or_else(malformed)?;
+        let (num_states, source) =
+            u32::deserialize_from_slice_checked(source).ok_or_else(malformed)?;
+        let (num_free_blocks, source) =
+            u32::deserialize_from_slice_checked(source).ok_or_else(malformed)?;
+        let (num_dense_states, source) =
+            u32::deserialize_from_slice_checked(source).ok_or_else(malformed)?;
+        let (tails, source) =
+            Tails::deserialize_from_slice_checked(source).ok_or_else(malformed)?;
+        let (mapper, source) =
+            ByteMapper::deserialize_from_slice_checked(source).ok_or_else(malformed)?;
+        let mut pma = Self {
+            states,
+            tails,
+            mapper,
+            outputs,
+            match_kind,
+            num_states,
+            num_free_blocks,
+            dense: vec![],
+            dense_rows: vec![],
+            prefilter: Prefilter::default(),
+            spec: 0,
+        };
+        // The dense transitions and the prefilter walk the automaton, so they are built only after
+        // the verification.
+        pma.verify()?;
+        pma.build_dense_transitions(num_dense_states);
+        pma.build_prefilter();
+        Ok((pma, source))
+    }
+
+    /// Returns the specialization of the search loops for the features used by the automaton.
+    #[inline(always)]
+    pub(crate) const fn spec(&self) -> u8 {
+        self.spec
+    }
+
+    /// Updates the specialization returned by [`Self::spec()`].
+    fn update_spec(&mut self) {
+        let mut spec = 0;
+        if !self.tails.is_empty() {
+            spec |= SPEC_TAILS;
+        }
+        if !self.dense.is_empty() {
+            spec |= SPEC_DENSE;
+        }
+        if !self.mapper.is_identity() {
+            spec |= SPEC_RELABEL;
+        }
+        if self.outputs.is_compact() {
+            spec |= SPEC_COMPACT;
+        }
+        self.spec = spec;
+    }
+
+    /// Returns the child of `state_id` labeled with the code `c`, specialized by `S`.
+    ///
     /// # Safety
     ///
-    /// `state_id` must be smaller than the length of states.
+    /// `state_id` must be smaller than the number of states, and `S` must be given by
+    /// [`Self::spec()`].
     #[inline(always)]
-    unsafe fn child_index_unchecked(&self, state_id: u32, c: u8) -> Option<u32> {
+    unsafe fn child_index_spec<const S: u8>(&self, state_id: u32, c: u8) -> Option<u32> {
+        if S & SPEC_TAILS != 0 {
+            return self.child_index_unchecked(state_id, c);
+        }
         // child_idx is always smaller than states.len() because
         //  - states.len() is 256 * k for some integer k, and
         //  - base() returns smaller than states.len() when it is Some.
@@ -672,37 +1800,329 @@ impl<V> DoubleArrayAhoCorasick<V> {
 
     /// # Safety
     ///
-    /// `state_id` must be smaller than the length of states.
+    /// `state_id` must be smaller than the number of states, and `S` must be given by
+    /// [`Self::spec()`].
+    #[inline(always)]
+    unsafe fn fail_spec<const S: u8>(&self, state_id: u32) -> u32 {
+        if S & SPEC_TAILS != 0 {
+            return self.fail_unchecked(state_id);
+        }
+        self.states.get_unchecked(usize::from_u32(state_id)).fail()
+    }
+
+    /// # Safety
+    ///
+    /// `state_id` must be smaller than the number of states, and `S` must be given by
+    /// [`Self::spec()`].
+    #[inline(always)]
+    pub(crate) unsafe fn output_pos_spec<const S: u8>(&self, state_id: u32) -> Option<NonZeroU32> {
+        if S & SPEC_TAILS != 0 {
+            return self.output_pos_unchecked(state_id);
+        }
+        self.states
+            .get_unchecked(usize::from_u32(state_id))
+            .output_pos()
+    }
+
+    /// # Safety
+    ///
+    /// `output_pos` must be given by the states, and `S` must be given by [`Self::spec()`].
+    #[inline(always)]
+    pub(crate) unsafe fn output_spec<const S: u8>(&self, output_pos: NonZeroU32) -> Output<V>
+    where
+        V: Copy,
+    {
+        self.outputs
+            .get_unchecked_as(usize::from_u32(output_pos.get() - 1), S & SPEC_COMPACT != 0)
+    }
+
+    /// Returns the child of `state_id` labeled with the code `c`.
+    ///
+    /// # Safety
+    ///
+    /// `state_id` must be smaller than the number of states, i.e., the sum of the lengths of the
+    /// states and tails.
+    #[inline(always)]
+    unsafe fn child_index_unchecked(&self, state_id: u32, c: u8) -> Option<u32> {
+        let idx = usize::from_u32(state_id);
+        if let Some(state) = self.states.get(idx) {
+            let base = state.base()?;
+            let child_idx = base.get() ^ u32::from(c);
+            // states.len() is 256 * k for some integer k, so child_idx is out of the states if
+            // and only if base is. Such a base is the id of the first position of a tail.
+            if let Some(child) = self.states.get(usize::from_u32(child_idx)) {
+                return Some(child_idx).filter(|_| child.check() == c);
+            }
+            let pos = usize::from_u32(base.get()) - self.states.len();
+            return Some(base.get()).filter(|_| self.tails.is_first_label(pos, c));
+        }
+        Some(state_id + 1).filter(|_| self.tails.has_next(idx - self.states.len(), c))
+    }
+
+    /// # Safety
+    ///
+    /// `state_id` must be smaller than the number of states.
+    #[inline(always)]
+    unsafe fn fail_unchecked(&self, state_id: u32) -> u32 {
+        let idx = usize::from_u32(state_id);
+        self.states
+            .get(idx)
+            .map_or_else(|| self.tails.fail(idx - self.states.len()), State::fail)
+    }
+
+    /// # Safety
+    ///
+    /// `state_id` must be smaller than the number of states.
+    #[inline(always)]
+    unsafe fn output_pos_unchecked(&self, state_id: u32) -> Option<NonZeroU32> {
+        let idx = usize::from_u32(state_id);
+        self.states.get(idx).map_or_else(
+            || self.tails.output_pos(idx - self.states.len()),
+            State::output_pos,
+        )
+    }
+
+    /// Returns the function skipping slice haystacks by the prefilter, if it is enabled.
+    #[inline(always)]
+    fn prefilter_skip<P>(&self) -> Option<SkipFn<U8SliceIterator<P>>>
+    where
+        P: AsRef<[u8]>,
+    {
+        if self.prefilter.is_enabled() {
+            Some(U8SliceIterator::skip_by_prefilter)
+        } else {
+            None
+        }
+    }
+
+    /// Builds the prefilter from the bytes labeling the children of the root.
+    pub(crate) fn build_prefilter(&mut self) {
+        let mut first_bytes = vec![];
+        for c in u8::MIN..=u8::MAX {
+            // ROOT_STATE_IDX is always smaller than the number of states.
+            if unsafe { self.child_index_unchecked(ROOT_STATE_IDX, self.mapper.get(c)) }.is_some() {
+                first_bytes.push(c);
+            }
+        }
+        self.prefilter = Prefilter::new(&first_bytes);
+    }
+
+    /// Relocates the blocks of the double array so that the blocks of states visited frequently
+    /// come first. The states are weighted with the visits in `sample` if given, or otherwise with
+    /// the heuristic values.
+    pub(crate) fn reorder_blocks(&mut self, sample: Option<&[u8]>) {
+        let weights = sample.map_or_else(
+            || self.heuristic_state_weights(),
+            |sample| self.sample_state_weights(sample),
+        );
+        let relocation =
+            BlockRelocation::new(&layout::block_weights(&weights, BLOCK_LEN), BLOCK_LEN);
+        if relocation.is_identity() {
+            return;
+        }
+        for state in &mut self.states {
+            // The BASE values pointing to the tails are kept.
+            if let Some(base) = state.base() {
+                // The first block is not moved, so non-zero values remain non-zero.
+                state.set_base(NonZeroU32::new(relocation.map(base.get())).unwrap());
+            }
+            state.set_fail(relocation.map(state.fail()));
+        }
+        relocation.apply(&mut self.states);
+        self.tails.map_fails(|fail| relocation.map(fail));
+        self.build_dense_transitions(self.num_dense_states());
+    }
+
+    /// Weights each state in the double array with a heuristic value based on its depth and
+    /// number of children.
+    fn heuristic_state_weights(&self) -> Vec<u64> {
+        // The depths and numbers of children of the states in the double array
+        let mut shapes = vec![];
+        let mut used_labels = [false; 256];
+        let mut stack = vec![(ROOT_STATE_IDX, 0)];
+        while let Some((state_id, depth)) = stack.pop() {
+            let mut num_children = 0;
+            for code in u8::MIN..=u8::MAX {
+                // state_id is always smaller than the number of states.
+                if let Some(child_id) = unsafe { self.child_index_unchecked(state_id, code) } {
+                    num_children += 1;
+                    used_labels[usize::from(code)] = true;
+                    // The states in the tails are not in the double array.
+                    if usize::from_u32(child_id) < self.states.len() {
+                        stack.push((child_id, depth + 1));
+                    }
+                }
+            }
+            shapes.push((state_id, depth, num_children));
+        }
+        let alphabet_size =
+            u32::try_from(used_labels.iter().filter(|&&used| used).count()).unwrap();
+        let mut weights = vec![0; self.states.len()];
+        for (state_id, depth, num_children) in shapes {
+            weights[usize::from_u32(state_id)] =
+                layout::heuristic_weight(depth, num_children, alphabet_size);
+        }
+        weights
+    }
+
+    /// Weights each state in the double array with the number of its visits in searching
+    /// `sample`, including the visits in failure transitions.
+    fn sample_state_weights(&self, sample: &[u8]) -> Vec<u64> {
+        let mut weights = vec![0; self.states.len()];
+        let mut state_id = ROOT_STATE_IDX;
+        for &c in sample {
+            let code = self.mapper.get(c);
+            // In the loop, state_id is always set to values smaller than the number of states,
+            // because child_index_unchecked() and fail_unchecked() return such values.
+            loop {
+                if let Some(weight) = weights.get_mut(usize::from_u32(state_id)) {
+                    *weight += 1;
+                }
+                if let Some(child_id) = unsafe { self.child_index_unchecked(state_id, code) } {
+                    state_id = child_id;
+                    break;
+                }
+                if state_id == ROOT_STATE_IDX {
+                    break;
+                }
+                state_id = unsafe { self.fail_unchecked(state_id) };
+                // Only leftmost automata have the failure links to the dead state.
+                if state_id == DEAD_STATE_IDX {
+                    state_id = ROOT_STATE_IDX;
+                    break;
+                }
+            }
+        }
+        weights
+    }
+
+    /// Precomputes the transitions of the root and the following `num_dense_states - 1` states
+    /// in breadth-first order, which are visited most frequently in general.
+    pub(crate) fn build_dense_transitions(&mut self, num_dense_states: u32) {
+        self.dense.clear();
+        self.dense_rows.clear();
+        self.update_spec();
+
+        let mut dense_states = vec![];
+        let mut queue = vec![ROOT_STATE_IDX];
+        let mut qi = 0;
+        while qi < queue.len() && dense_states.len() < usize::from_u32(num_dense_states) {
+            let state_id = queue[qi];
+            qi += 1;
+            dense_states.push(state_id);
+            for c in u8::MIN..=u8::MAX {
+                // state_id is always smaller than the number of states because
+                // child_index_unchecked() returns such values.
+                if let Some(child_id) =
+                    unsafe { self.child_index_unchecked(state_id, self.mapper.get(c)) }
+                {
+                    queue.push(child_id);
+                }
+            }
+        }
+
+        // The transitions are computed by the sparse lookup before the dense table is enabled.
+        let mut dense = Vec::with_capacity(dense_states.len() * 256);
+        for &state_id in &dense_states {
+            for c in u8::MIN..=u8::MAX {
+                dense.push(specialize!(self.spec(), S => unsafe {
+                    if self.match_kind.is_standard() {
+                        self.next_state_id_unchecked::<S>(state_id, c)
+                    } else {
+                        self.next_state_id_leftmost_unchecked::<S>(state_id, c)
+                    }
+                }));
+            }
+        }
+        let mut dense_rows = vec![];
+        for (row, &state_id) in (0..).zip(&dense_states) {
+            let idx = usize::from_u32(state_id);
+            if dense_rows.len() <= idx {
+                dense_rows.resize(idx + 1, NO_DENSE_ROW);
+            }
+            dense_rows[idx] = row;
+        }
+        self.dense = dense;
+        self.dense_rows = dense_rows;
+        self.update_spec();
+    }
+
+    /// Returns the number of states having precomputed transitions.
+    pub(crate) fn num_dense_states(&self) -> u32 {
+        u32::try_from(self.dense.len() / 256).unwrap()
+    }
+
+    /// Returns the row of `state_id` in the dense transition table.
     #[inline(always)]
-    unsafe fn next_state_id_unchecked(&self, mut state_id: u32, c: u8) -> u32 {
-        // In the loop, state_id is always set to values smaller than states.len(),
-        // because child_index_unchecked() and fail() return such values.
+    fn dense_row(&self, state_id: u32) -> Option<usize> {
+        self.dense_rows
+            .get(usize::from_u32(state_id))
+            .copied()
+            .filter(|&row| row != NO_DENSE_ROW)
+            .map(usize::from_u32)
+    }
+
+    /// # Safety
+    ///
+    /// `state_id` must be smaller than the number of states, and `S` must be given by
+    /// [`Self::spec()`].
+    #[inline(always)]
+    unsafe fn next_state_id_unchecked<const S: u8>(&self, mut state_id: u32, c: u8) -> u32 {
+        let code = if S & SPEC_RELABEL != 0 {
+            self.mapper.get(c)
+        } else {
+            c
+        };
+        // In the loop, state_id is always set to values smaller than the number of states,
+        // because child_index_spec() and fail_spec() return such values.
         loop {
-            if let Some(state_id) = self.child_index_unchecked(state_id, c) {
+            if S & SPEC_DENSE != 0 {
+                if let Some(row) = self.dense_row(state_id) {
+                    // The dense table has 256 entries for each row.
+                    return *self.dense.get_unchecked(row * 256 + usize::from(c));
+                }
+            }
+            if let Some(state_id) = self.child_index_spec::<S>(state_id, code) {
                 return state_id;
             }
             if state_id == ROOT_STATE_IDX {
                 return ROOT_STATE_IDX;
             }
-            state_id = self.states.get_unchecked(usize::from_u32(state_id)).fail();
+            state_id = self.fail_spec::<S>(state_id);
         }
     }
 
     /// # Safety
     ///
-    /// `state_id` must be smaller than the length of states.
+    /// `state_id` must be smaller than the number of states, and `S` must be given by
+    /// [`Self::spec()`].
     #[inline(always)]
-    unsafe fn next_state_id_leftmost_unchecked(&self, mut state_id: u32, c: u8) -> u32 {
-        // In the loop, state_id is always set to values smaller than states.len(),
-        // because child_index_unchecked() and fail() return such values.
+    unsafe fn next_state_id_leftmost_unchecked<const S: u8>(
+        &self,
+        mut state_id: u32,
+        c: u8,
+    ) -> u32 {
+        let code = if S & SPEC_RELABEL != 0 {
+            self.mapper.get(c)
+        } else {
+            c
+        };
+        // In the loop, state_id is always set to values smaller than the number of states,
+        // because child_index_spec() and fail_spec() return such values.
         loop {
-            if let Some(state_id) = self.child_index_unchecked(state_id, c) {
+            if S & SPEC_DENSE != 0 {
+                if let Some(row) = self.dense_row(state_id) {
+                    // The dense table has 256 entries for each row.
+                    return *self.dense.get_unchecked(row * 256 + usize::from(c));
+                }
+            }
+            if let Some(state_id) = self.child_index_spec::<S>(state_id, code) {
                 return state_id;
             }
             if state_id == ROOT_STATE_IDX {
                 return ROOT_STATE_IDX;
             }
-            let fail_id = self.states.get_unchecked(usize::from_u32(state_id)).fail();
+            let fail_id = self.fail_spec::<S>(state_id);
             if fail_id == DEAD_STATE_IDX {
                 return ROOT_STATE_IDX;
             }
@@ -712,7 +2132,7 @@ impl<V> DoubleArrayAhoCorasick<V> {
 }
 
 #[derive(Clone, Copy, Default, Eq, Hash, PartialEq)]
-struct State {
+pub(crate) struct State {
     base: Option<NonZeroU32>,
     fail: u32,
     // 3 bytes for output_pos and 1 byte for check.
@@ -745,6 +2165,11 @@ impl State {
         self.base = Some(x);
     }
 
+    #[inline(always)]
+    pub fn clear_base(&mut self) {
+        self.base = None;
+    }
+
     #[inline(always)]
     pub fn set_check(&mut self, x: u8) {
         self.opos_ch.set_b(x);
@@ -770,19 +2195,19 @@ impl State {
 impl Serializable for State {
     #[inline(always)]
     fn serialize_to_vec(&self, dst: &mut Vec<u8>) {
-        self.base.serialize_to_vec(dst);
+        self.base.map_or(0, NonZeroU32::get).serialize_to_vec(dst);
         self.fail.serialize_to_vec(dst);
         self.opos_ch.serialize_to_vec(dst);
     }
 
     #[inline(always)]
     fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
-        let (base, src) = Option::<NonZeroU32>::deserialize_from_slice(src);
+        let (base, src) = u32::deserialize_from_slice(src);
         let (fail, src) = u32::deserialize_from_slice(src);
         let (opos_ch, src) = U24nU8::deserialize_from_slice(src);
         (
             Self {
-                base,
+                base: NonZeroU32::new(base),
                 fail,
                 opos_ch,
             },
@@ -792,9 +2217,7 @@ impl Serializable for State {
 
     #[inline(always)]
     fn serialized_bytes() -> usize {
-        Option::<NonZeroU32>::serialized_bytes()
-            + u32::serialized_bytes()
-            + U24nU8::serialized_bytes()
+        u32::serialized_bytes() + u32::serialized_bytes() + U24nU8::serialized_bytes()
     }
 }
 
@@ -1018,5 +2441,218 @@ mod tests {
         assert_eq!(pma.outputs, other.outputs);
         assert_eq!(pma.match_kind, other.match_kind);
         assert_eq!(pma.num_states, other.num_states);
+        assert_eq!(pma.num_free_blocks, other.num_free_blocks);
+        assert_eq!(pma.dense, other.dense);
+        assert_eq!(pma.dense_rows, other.dense_rows);
+        assert_eq!(pma.prefilter, other.prefilter);
+    }
+
+    #[test]
+    fn test_dense_transitions() {
+        let patterns = vec!["abba", "baaba", "ababa"];
+        let sparse = DoubleArrayAhoCorasick::<u32>::new(&patterns).unwrap();
+        let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+            .num_dense_states(3)
+            .build(&patterns)
+            .unwrap();
+
+        // The root, a, and b
+        assert_eq!(3, pma.num_dense_states());
+        assert_eq!(3 * 256, pma.dense.len());
+        assert_eq!(
+            sparse.heap_bytes() + (pma.dense.len() + pma.dense_rows.len()) * 4,
+            pma.heap_bytes()
+        );
+        for state_id in 0..u32::try_from(pma.states.len()).unwrap() {
+            let state = &pma.states[usize::from_u32(state_id)];
+            if state.base().is_none() && state.output_pos().is_none() {
+                continue;
+            }
+            for c in u8::MIN..=u8::MAX {
+                assert_eq!(
+                    unsafe { sparse.next_state_id_unchecked::<0>(state_id, c) },
+                    unsafe { pma.next_state_id_unchecked::<SPEC_DENSE>(state_id, c) },
+                );
+            }
+        }
+
+        // The dense transitions are rebuilt on loading.
+        let bytes = pma.serialize();
+        assert_eq!(sparse.serialize().len(), bytes.len());
+        let (other, rest) = unsafe { DoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+        assert!(rest.is_empty());
+        assert_eq!(pma.dense, other.dense);
+        assert_eq!(pma.dense_rows, other.dense_rows);
+        let (other, rest) = DoubleArrayAhoCorasick::<u32>::deserialize(&bytes).unwrap();
+        assert!(rest.is_empty());
+        assert!(pma == other);
+    }
+
+    #[test]
+    fn test_prefilter() {
+        let patterns = vec!["xyz", "yzz", "zz", "zzzz"];
+        let haystack = "aaaaaaaaaaxyzzzzaaaaaaaaaaaaaaaaaazzaaaaaaaaaaaaaaaaaaxy";
+        let pma = DoubleArrayAhoCorasick::<u32>::new(&patterns).unwrap();
+        assert!(pma.prefilter.is_enabled());
+
+        let expected: Vec<_> = pma.find_iter_from_iter(haystack.bytes()).collect();
+        assert_eq!(3, expected.len());
+        assert_eq!(expected, pma.find_iter(haystack).collect::<Vec<_>>());
+
+        let expected: Vec<_> = pma
+            .find_overlapping_iter_from_iter(haystack.bytes())
+            .collect();
+        assert_eq!(7, expected.len());
+        assert_eq!(
+            expected,
+            pma.find_overlapping_iter(haystack).collect::<Vec<_>>()
+        );
+
+        let expected: Vec<_> = pma
+            .find_overlapping_no_suffix_iter_from_iter(haystack.bytes())
+            .collect();
+        assert_eq!(
+            expected,
+            pma.find_overlapping_no_suffix_iter(haystack)
+                .collect::<Vec<_>>()
+        );
+
+        for match_kind in [MatchKind::LeftmostLongest, MatchKind::LeftmostFirst] {
+            let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                .match_kind(match_kind)
+                .build(&patterns)
+                .unwrap();
+            assert!(pma.prefilter.is_enabled());
+            let matches: Vec<_> = pma
+                .leftmost_find_iter(haystack)
+                .map(|m| (m.start(), m.end(), m.value()))
+                .collect();
+            assert_eq!(vec![(10, 13, 0), (13, 15, 2), (34, 36, 2)], matches);
+        }
+
+        // More than 3 distinct first bytes
+        let pma = DoubleArrayAhoCorasick::<u32>::new(["w", "x", "y", "z"]).unwrap();
+        assert!(!pma.prefilter.is_enabled());
+    }
+
+    #[test]
+    fn test_merge() {
+        let pma1 =
+            DoubleArrayAhoCorasick::with_values([("abba", 0), ("baaba", 1), ("ab", 2)]).unwrap();
+        let pma2 =
+            DoubleArrayAhoCorasick::with_values([("ab", 3), ("abbab", 4), ("a", 6)]).unwrap();
+        for (policy, value) in [
+            (DuplicatePolicy::KeepFirst, 2),
+            (DuplicatePolicy::KeepLast, 3),
+            (DuplicatePolicy::Merge(|a, b| a + b), 5),
+        ] {
+            let expected = DoubleArrayAhoCorasick::with_values([
+                ("abba", 0),
+                ("baaba", 1),
+                ("ab", value),
+                ("abbab", 4),
+                ("a", 6),
+            ])
+            .unwrap();
+            let merged = DoubleArrayAhoCorasick::merge(&[&pma1, &pma2], policy).unwrap();
+            assert_eq!(expected.states, merged.states);
+            assert_eq!(expected.outputs, merged.outputs);
+            assert_eq!(expected.num_states, merged.num_states);
+        }
+    }
+
+    #[test]
+    fn test_merge_leftmost() {
+        for match_kind in [MatchKind::LeftmostLongest, MatchKind::LeftmostFirst] {
+            let builder = || DoubleArrayAhoCorasickBuilder::new().match_kind(match_kind);
+            let pma1 = builder()
+                .build_with_values([("abba", 0), ("baaba", 1), ("ab", 2)])
+                .unwrap();
+            let pma2 = builder()
+                .build_with_values([("ab", 3), ("abbab", 4), ("a", 6)])
+                .unwrap();
+            let expected = builder()
+                .build_with_values([("abba", 0), ("baaba", 1), ("ab", 2), ("abbab", 4), ("a", 6)])
+                .unwrap();
+            let merged =
+                DoubleArrayAhoCorasick::merge(&[&pma1, &pma2], DuplicatePolicy::KeepFirst).unwrap();
+            assert_eq!(expected.states, merged.states);
+            assert_eq!(expected.outputs, merged.outputs);
+            assert_eq!(expected.match_kind, merged.match_kind);
+        }
+    }
+
+    #[test]
+    fn test_merge_empty() {
+        assert!(DoubleArrayAhoCorasick::<u32>::merge(&[], DuplicatePolicy::KeepFirst).is_err());
+    }
+
+    #[test]
+    fn test_verify() {
+        let patterns = vec!["abc", "bc", "b", "x"];
+        let pma = DoubleArrayAhoCorasickBuilder::<u32>::new()
+            .num_dense_states(2)
+            .build(&patterns)
+            .unwrap();
+        assert!(pma.verify().is_ok());
+
+        // The children of the root are smaller than the number of states.
+        let (a, b, x) = unsafe {
+            (
+                pma.child_index_unchecked(ROOT_STATE_IDX, b'a').unwrap(),
+                pma.child_index_unchecked(ROOT_STATE_IDX, b'b').unwrap(),
+                pma.child_index_unchecked(ROOT_STATE_IDX, b'x').unwrap(),
+            )
+        };
+        let state = |id| usize::from_u32(id);
+        let assert_invalid = |invariant, corrupt: &dyn Fn(&mut DoubleArrayAhoCorasick<u32>)| {
+            let mut other = pma.clone();
+            corrupt(&mut other);
+            let e = other.verify().expect_err(invariant);
+            assert!(format!("{e}").contains(invariant), "{e}");
+        };
+
+        // Failure link to a state at the same depth
+        assert_invalid("fail", &|pma| pma.states[state(a)].set_fail(b));
+        // Failure link to the dead state in a standard automaton
+        assert_invalid("fail", &|pma| pma.states[state(a)].set_fail(DEAD_STATE_IDX));
+        // BASE out of the states
+        assert_invalid("BASE", &|pma| {
+            pma.states[state(x)].set_base(NonZeroU32::new(u32::MAX).unwrap());
+        });
+        // State having two parents
+        assert_invalid("single parent", &|pma| {
+            let base = pma.states[state(a)].base().unwrap();
+            pma.states[state(x)].set_base(base);
+        });
+        // Output out of the outputs
+        assert_invalid("outputs are suffixes", &|pma| {
+            pma.states[state(x)]
+                .set_output_pos(NonZeroU32::new(100))
+                .unwrap();
+        });
+        // Output longer than the state
+        assert_invalid("outputs are suffixes", &|pma| {
+            let abc_pos = pma
+                .outputs
+                .iter()
+                .position(|out| out.length() == 3)
+                .unwrap();
+            pma.states[state(a)]
+                .set_output_pos(NonZeroU32::new(u32::try_from(abc_pos).unwrap() + 1))
+                .unwrap();
+        });
+        // Wrong number of states
+        assert_invalid("num_states", &|pma| pma.num_states += 1);
+        // No free block
+        assert_invalid("free blocks", &|pma| pma.num_free_blocks = 0);
+        // Dense transition to an unreachable state
+        assert_invalid("dense", &|pma| pma.dense[0] = u32::MAX);
+
+        let pma = DoubleArrayAhoCorasickBuilder::<u32>::new()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(&patterns)
+            .unwrap();
+        assert!(pma.verify().is_ok());
     }
 }
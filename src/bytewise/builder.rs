@@ -1,35 +1,56 @@
+use core::mem;
 use core::num::NonZeroU32;
 
+use alloc::boxed::Box;
 use alloc::vec::Vec;
 
+use crate::bytewise::mapper::ByteMapper;
+use crate::bytewise::tail::{TailPosition, Tails};
 use crate::bytewise::{
-    BuildHelper, DoubleArrayAhoCorasick, MatchKind, State, DEAD_STATE_IDX, ROOT_STATE_IDX,
+    BuildHelper, DoubleArrayAhoCorasick, MatchKind, Prefilter, State, DEAD_STATE_IDX,
+    ROOT_STATE_IDX,
 };
-use crate::errors::{DaachorseError, Result};
+use crate::errors::{BuildError, DaachorseError, Result};
 use crate::intpack::U24;
-use crate::nfa_builder::{NfaBuilder, DEAD_STATE_ID, ROOT_STATE_ID};
-use crate::utils::FromU32;
+use crate::nfa_builder::{NfaBuilder, NfaView, DEAD_STATE_ID, ROOT_STATE_ID};
+use crate::outputs::OutputStore;
+use crate::sorted_nfa_builder::SortedNfaBuilder;
+use crate::utils::{self, FromU32};
+use crate::{BuildPhase, BuildProgress, DuplicatePolicy, ProgressReporter};
 
 // The length of each double-array block.
-const BLOCK_LEN: u32 = 256;
+pub(crate) const BLOCK_LEN: u32 = 256;
 
 // Specialized [`NfaBuilder`] handling labels of `u8`.
 type BytewiseNfaBuilder<V> = NfaBuilder<u8, V>;
 
 /// Builder of [`DoubleArrayAhoCorasick`].
-pub struct DoubleArrayAhoCorasickBuilder {
-    states: Vec<State>,
+///
+/// `V` is the type of the values of the automaton to be built. It is usually inferred from the use
+/// of the automaton, and defaults to `usize` where the builder type is written without it.
+pub struct DoubleArrayAhoCorasickBuilder<V = usize> {
+    pub(crate) states: Vec<State>,
+    mapper: ByteMapper,
     match_kind: MatchKind,
+    duplicate_policy: DuplicatePolicy<V>,
     num_free_blocks: u32,
+    num_dense_states: u32,
+    compact_outputs: bool,
+    tail_compression: bool,
+    relabel_bytes: bool,
+    reorder_states: bool,
+    reorder_sample: Option<Vec<u8>>,
+    memory_budget: Option<usize>,
+    progress: ProgressReporter,
 }
 
-impl Default for DoubleArrayAhoCorasickBuilder {
+impl<V> Default for DoubleArrayAhoCorasickBuilder<V> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl DoubleArrayAhoCorasickBuilder {
+impl<V> DoubleArrayAhoCorasickBuilder<V> {
     /// Creates a new [`DoubleArrayAhoCorasickBuilder`].
     ///
     /// # Examples
@@ -56,8 +77,18 @@ impl DoubleArrayAhoCorasickBuilder {
     pub const fn new() -> Self {
         Self {
             states: vec![],
+            mapper: ByteMapper::identity(),
             match_kind: MatchKind::Standard,
+            duplicate_policy: DuplicatePolicy::Error,
             num_free_blocks: 16,
+            num_dense_states: 0,
+            compact_outputs: false,
+            tail_compression: false,
+            relabel_bytes: false,
+            reorder_states: false,
+            reorder_sample: None,
+            memory_budget: None,
+            progress: ProgressReporter::new(None),
         }
     }
 
@@ -91,42 +122,677 @@ impl DoubleArrayAhoCorasickBuilder {
         self
     }
 
+    /// Specifies [`DuplicatePolicy`] to handle patterns registered more than once.
+    ///
+    /// The default policy is [`DuplicatePolicy::Error`].
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Duplicate policy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasickBuilder, DuplicatePolicy};
+    ///
+    /// let patvals = vec![("ab", 1), ("a", 2), ("ab", 4)];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .on_duplicate(DuplicatePolicy::Merge(|a, b| a | b))
+    ///     .build_with_values(patvals)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.find_overlapping_iter("ab");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 2, 5), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    #[must_use]
+    pub const fn on_duplicate(mut self, policy: DuplicatePolicy<V>) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
     /// Specifies the number of last blocks to search bases.
     ///
-    /// The smaller the number is, the faster the construction time will be;
-    /// however, the memory efficiency can be degraded.
+    /// The smaller the number is, the faster the construction time will be;
+    /// however, the memory efficiency can be degraded.
+    ///
+    /// A fixed length of memory is allocated in proportion to this value in construction. If an
+    /// allocation error occurs during building the automaton even though the pattern set is small,
+    /// try setting a smaller value.
+    ///
+    /// The number is kept in the automaton, and [`DoubleArrayAhoCorasick::into_editor()`] places
+    /// new states in the same number of last blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number of last blocks, which must be greater than or equal to 1. An invalid
+    ///   value is reported by [`Self::validate()`] and the build methods.
+    #[must_use]
+    pub const fn num_free_blocks(mut self, n: u32) -> Self {
+        self.num_free_blocks = n;
+        self
+    }
+
+    /// Specifies the number of states whose transitions are precomputed for all bytes.
+    ///
+    /// The root and the following states in breadth-first order, which are visited most
+    /// frequently in general, are given tables of 256 next states. A transition from such a state
+    /// is resolved in a single lookup without following failure links, at the cost of 1 KiB of
+    /// memory per state. The default value is 0, i.e., no transitions are precomputed.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number of states with precomputed transitions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasickBuilder;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .num_dense_states(4)
+    ///     .build(patterns)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.find_iter("abcd");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    #[must_use]
+    pub const fn num_dense_states(mut self, n: u32) -> Self {
+        self.num_dense_states = n;
+        self
+    }
+
+    /// Specifies whether to store the outputs in the compact representation.
+    ///
+    /// Each output consists of the value, the length of the pattern, and the position of the next
+    /// output, which take `size_of::<V>() + 8` bytes with padding by default. In the compact
+    /// representation, the values are stored in a separate array, and the lengths and positions
+    /// are bit-packed with the minimum widths required for the pattern set. This reduces the
+    /// memory usage and the serialized size, at the cost of slightly slower access to the outputs
+    /// on matches. By default, the outputs are not compacted.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to compact the outputs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder};
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma: DoubleArrayAhoCorasick<u64> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .compact_outputs(true)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    /// let other: DoubleArrayAhoCorasick<u64> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .build(&patterns)
+    ///     .unwrap();
+    /// assert!(pma.heap_bytes() < other.heap_bytes());
+    ///
+    /// let mut it = pma.find_iter("abcd");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    #[must_use]
+    pub const fn compact_outputs(mut self, enable: bool) -> Self {
+        self.compact_outputs = enable;
+        self
+    }
+
+    /// Specifies whether to store the single-branch suffixes of the trie in tails.
+    ///
+    /// Long patterns such as URLs and file paths create long chains of states having only one
+    /// child, each of which takes a state of 12 bytes in the double array. With this option, the
+    /// descendants of a state are moved out of the double array into a tail if they form a single
+    /// path. The labels of a tail are compared directly in the goto traversal, and the failure
+    /// links and outputs of the states in it are bit-packed with the minimum widths. This reduces
+    /// the memory usage for dictionaries of long patterns severalfold, at the cost of slightly
+    /// slower transitions in the tails. By default, tails are not used.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to use tails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder};
+    ///
+    /// let mut patterns: Vec<_> = (0..100)
+    ///     .map(|i| format!("https://example.com/{i}/index.html"))
+    ///     .collect();
+    /// patterns.push("index".to_string());
+    ///
+    /// let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .tail_compression(true)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    /// let other: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .build(&patterns)
+    ///     .unwrap();
+    /// assert!(pma.heap_bytes() < other.heap_bytes());
+    ///
+    /// let mut it = pma.find_overlapping_iter("https://example.com/42/index.html");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((23, 28, 100), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 33, 42), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    #[must_use]
+    pub const fn tail_compression(mut self, enable: bool) -> Self {
+        self.tail_compression = enable;
+        self
+    }
+
+    /// Specifies whether to relabel the bytes by their frequencies.
+    ///
+    /// By default, the bytes are used as the labels of the transitions as they are. With this
+    /// option, the bytes are relabeled with codes assigned in descending order of the number of
+    /// transitions labeled with them in the trie, like the characters in
+    /// [`CharwiseDoubleArrayAhoCorasick`](crate::CharwiseDoubleArrayAhoCorasick). For patterns
+    /// with skewed alphabets, the frequent labels are packed into a small range of codes, which
+    /// can make the double array denser and shorten the search for vacant elements. The
+    /// relabeling table is serialized with the automaton, and each byte of the haystack is
+    /// converted by a table lookup in the search.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to relabel the bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasickBuilder;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .relabel_bytes(true)
+    ///     .build(patterns)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.find_iter("abcd");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    #[must_use]
+    pub const fn relabel_bytes(mut self, enable: bool) -> Self {
+        self.relabel_bytes = enable;
+        self
+    }
+
+    /// Specifies whether to reorder the states in the double array after the construction.
+    ///
+    /// The states are placed in the double array in the depth-first order of the trie, so the
+    /// states visited frequently in the search, such as the ones close to the root, are scattered
+    /// over the array. With this option, the blocks of the array are relocated so that the blocks
+    /// containing such states are clustered at the beginning of the array, which improves the
+    /// cache efficiency of the search in large automata. The frequencies are estimated from the
+    /// depths and the numbers of children of the states, or measured in the sample specified with
+    /// [`Self::reorder_sample()`]. The achieved locality is reported by
+    /// [`DoubleArrayAhoCorasick::layout_stats()`].
+    ///
+    /// The automata rebuilt by [`DoubleArrayAhoCorasickEditor`](crate::DoubleArrayAhoCorasickEditor)
+    /// are not reordered.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to reorder the states.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder};
+    ///
+    /// let patterns: Vec<_> = (0..5000).map(|i| format!("{}", i * 7919)).collect();
+    /// let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .build(&patterns)
+    ///     .unwrap();
+    /// let reordered: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .reorder_states(true)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let stats = reordered.layout_stats();
+    /// assert!(stats.hot_blocks() < pma.layout_stats().hot_blocks());
+    ///
+    /// assert_eq!(
+    ///     pma.find_overlapping_iter("1234567").collect::<Vec<_>>(),
+    ///     reordered.find_overlapping_iter("1234567").collect::<Vec<_>>(),
+    /// );
+    /// ```
+    #[must_use]
+    pub const fn reorder_states(mut self, enable: bool) -> Self {
+        self.reorder_states = enable;
+        self
+    }
+
+    /// Specifies a sample haystack to measure the frequencies of the states in reordering them,
+    /// and enables the reordering. See [`Self::reorder_states()`] for the details.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample` - Sample haystack, which should resemble the haystacks searched in practice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder};
+    ///
+    /// let patterns: Vec<_> = (0..5000).map(|i| format!("{}", i * 7919)).collect();
+    /// let sample = "31415926535897932384626433832795028841971693993751";
+    /// let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .build(&patterns)
+    ///     .unwrap();
+    /// let reordered: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .reorder_sample(sample)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let stats = reordered.layout_stats_with_sample(sample);
+    /// assert!(stats.hot_blocks() < pma.layout_stats_with_sample(sample).hot_blocks());
+    /// ```
+    #[must_use]
+    pub fn reorder_sample<P>(mut self, sample: P) -> Self
+    where
+        P: AsRef<[u8]>,
+    {
+        self.reorder_states = true;
+        self.reorder_sample = Some(sample.as_ref().to_vec());
+        self
+    }
+
+    /// Specifies the maximum amount of heap memory in bytes used for the construction.
+    ///
+    /// The estimated memory usage of the trie is checked every time a pattern is inserted, and
+    /// that of the trie and the double array is checked every time the array is extended. The
+    /// resulting automaton is also checked, including the precomputed transitions. If the budget
+    /// is exceeded, the construction is aborted. By default, no budget is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The maximum memory usage in bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder};
+    ///
+    /// let patterns: Vec<_> = (0..1000).map(|i| format!("pattern{i}")).collect();
+    ///
+    /// let result: Result<DoubleArrayAhoCorasick<u32>, _> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .memory_budget(1 << 10)
+    ///     .build(&patterns);
+    /// assert!(result.is_err());
+    ///
+    /// let result: Result<DoubleArrayAhoCorasick<u32>, _> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .memory_budget(1 << 20)
+    ///     .build(&patterns);
+    /// assert!(result.is_ok());
+    /// ```
+    #[must_use]
+    pub const fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    /// Specifies a callback receiving the progress of the construction.
+    ///
+    /// The callback is called at the beginning and end of each [`BuildPhase`], and whenever
+    /// 65536 more items are processed in between.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - Closure called with the progress.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::mpsc;
+    ///
+    /// use aneubeck_daachorse::{BuildPhase, DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder};
+    ///
+    /// let (sender, receiver) = mpsc::channel();
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .progress(move |progress| {
+    ///         if progress.phase() == BuildPhase::DoubleArrayPlacement {
+    ///             sender.send(progress.count()).unwrap();
+    ///         }
+    ///     })
+    ///     .build(patterns)
+    ///     .unwrap();
+    ///
+    /// // All the states except the dead one are placed.
+    /// assert_eq!(Some(pma.num_states()), receiver.try_iter().last());
+    /// ```
+    #[must_use]
+    pub fn progress<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(BuildProgress) + Send + 'static,
+    {
+        self.progress = ProgressReporter::new(Some(Box::new(callback)));
+        self
+    }
+
+    /// Checks that the specified options are valid.
+    ///
+    /// The build methods call this function before inserting any patterns, so the options need
+    /// not be validated in advance unless an error is desired early.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when
+    ///   - `num_free_blocks` is zero, or
+    ///   - the number of elements in the free blocks exceeds [`u32::MAX`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasickBuilder;
+    ///
+    /// let builder: DoubleArrayAhoCorasickBuilder =
+    ///     DoubleArrayAhoCorasickBuilder::new().num_free_blocks(0);
+    /// assert!(builder.validate().is_err());
+    ///
+    /// let builder: DoubleArrayAhoCorasickBuilder =
+    ///     DoubleArrayAhoCorasickBuilder::new().num_free_blocks(16);
+    /// assert!(builder.validate().is_ok());
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        if self.num_free_blocks == 0 {
+            return Err(DaachorseError::invalid_argument("num_free_blocks", ">=", 1));
+        }
+        if BLOCK_LEN.checked_mul(self.num_free_blocks).is_none() {
+            return Err(DaachorseError::automaton_scale(
+                "block_len * num_free_blocks",
+                u32::MAX,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Builds and returns a new [`DoubleArrayAhoCorasick`] from input patterns. The value `i` is
+    /// automatically associated with `patterns[i]`.
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - List of patterns.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when
+    ///   - the options are invalid as checked by [`Self::validate()`],
+    ///   - `patterns` is empty,
+    ///   - `patterns` contains entries of length zero,
+    ///   - `patterns` contains duplicate entries and [`DuplicatePolicy::Error`] is specified,
+    ///   - the conversion from the index `i` to the specified type `V` fails,
+    ///   - the scale of `patterns` exceeds the expected one, or
+    ///   - the scale of the resulting automaton exceeds the expected one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasickBuilder;
+    ///
+    /// let builder = DoubleArrayAhoCorasickBuilder::new();
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = builder.build(patterns).unwrap();
+    ///
+    /// let mut it = pma.find_iter("abcd");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn build<I, P>(self, patterns: I) -> Result<DoubleArrayAhoCorasick<V>>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+        V: Copy + TryFrom<usize>,
+    {
+        // The following code implicitly replaces large indices with 0,
+        // but build_with_values() returns an error variant for such iterators.
+        let patvals: Vec<_> = patterns
+            .into_iter()
+            .enumerate()
+            .map(|(i, p)| V::try_from(i).map(|i| (p, i)))
+            .collect::<Result<_, _>>()
+            .map_err(|_| DaachorseError::invalid_conversion("index", "V"))?;
+        self.build_with_values(patvals)
+    }
+
+    /// Builds and returns a new [`DoubleArrayAhoCorasick`] from input pattern-value pairs.
+    ///
+    /// # Arguments
+    ///
+    /// * `patvals` - List of pattern-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when
+    ///   - the options are invalid as checked by [`Self::validate()`],
+    ///   - `patvals` is empty,
+    ///   - `patvals` contains patterns of length zero,
+    ///   - `patvals` contains duplicate patterns and [`DuplicatePolicy::Error`] is specified,
+    ///   - the scale of `patvals` exceeds the expected one, or
+    ///   - the scale of the resulting automaton exceeds the expected one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasickBuilder;
+    ///
+    /// let builder = DoubleArrayAhoCorasickBuilder::new();
+    ///
+    /// let patvals = vec![("bcd", 0), ("ab", 1), ("a", 2), ("e", 1)];
+    /// let pma = builder.build_with_values(patvals).unwrap();
+    ///
+    /// let mut it = pma.find_iter("abcde");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((4, 5, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn build_with_values<I, P>(mut self, patvals: I) -> Result<DoubleArrayAhoCorasick<V>>
+    where
+        I: IntoIterator<Item = (P, V)>,
+        P: AsRef<[u8]>,
+        V: Copy,
+    {
+        self.validate()?;
+        let nfa = self.build_sparse_nfa(patvals)?;
+        self.build_automaton(nfa)
+    }
+
+    /// Builds and returns a new [`DoubleArrayAhoCorasick`] from fallible input patterns, such as
+    /// lines read from a file. The value `i` is automatically associated with `patterns[i]`.
+    ///
+    /// The patterns are inserted while being read, so they need not be collected in advance.
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - List of patterns, each of which can be an error.
+    ///
+    /// # Errors
+    ///
+    /// [`BuildError::Input`] is returned with the first error in `patterns`, and
+    /// [`BuildError::Daachorse`] is returned in the same cases as [`Self::build()`].
     ///
-    /// A fixed length of memory is allocated in proportion to this value in construction. If an
-    /// allocation error occurs during building the automaton even though the pattern set is small,
-    /// try setting a smaller value.
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{BufRead, Cursor};
+    ///
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasickBuilder;
+    ///
+    /// let reader = Cursor::new("bcd\nab\na\n");
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .try_build(reader.lines())
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.find_iter("abcd");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn try_build<I, P, E>(self, patterns: I) -> Result<DoubleArrayAhoCorasick<V>, BuildError<E>>
+    where
+        I: IntoIterator<Item = Result<P, E>>,
+        P: AsRef<[u8]>,
+        V: Copy + TryFrom<usize>,
+    {
+        let patvals = patterns.into_iter().enumerate().map(|(i, pattern)| {
+            let pattern = pattern.map_err(BuildError::Input)?;
+            let value =
+                V::try_from(i).map_err(|_| DaachorseError::invalid_conversion("index", "V"))?;
+            Ok((pattern, value))
+        });
+        self.try_build_fallible(patvals)
+    }
+
+    /// Builds and returns a new [`DoubleArrayAhoCorasick`] from fallible input pattern-value
+    /// pairs.
+    ///
+    /// The pairs are inserted while being read, so they need not be collected in advance.
     ///
     /// # Arguments
     ///
-    /// * `n` - The number of last blocks.
+    /// * `patvals` - List of pattern-value pairs, each of which can be an error.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// `n` must be greater than or equal to 1.
-    #[must_use]
-    pub fn num_free_blocks(mut self, n: u32) -> Self {
-        assert!(n >= 1);
-        self.num_free_blocks = n;
-        self
+    /// [`BuildError::Input`] is returned with the first error in `patvals`, and
+    /// [`BuildError::Daachorse`] is returned in the same cases as [`Self::build_with_values()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::errors::BuildError;
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasickBuilder;
+    ///
+    /// let parse = |line: &'static str| {
+    ///     let (pattern, value) = line.split_once(' ').ok_or(line)?;
+    ///     value.parse::<u32>().map(|v| (pattern, v)).map_err(|_| line)
+    /// };
+    ///
+    /// let lines = ["bcd 0", "ab 1", "a 2"];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .try_build_with_values(lines.into_iter().map(parse))
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.find_iter("abcd");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let lines = ["bcd 0", "ab", "a 2"];
+    /// let result = DoubleArrayAhoCorasickBuilder::new()
+    ///     .try_build_with_values(lines.into_iter().map(parse));
+    /// assert!(matches!(result, Err(BuildError::Input("ab"))));
+    /// ```
+    pub fn try_build_with_values<I, P, E>(
+        self,
+        patvals: I,
+    ) -> Result<DoubleArrayAhoCorasick<V>, BuildError<E>>
+    where
+        I: IntoIterator<Item = Result<(P, V), E>>,
+        P: AsRef<[u8]>,
+        V: Copy,
+    {
+        self.try_build_fallible(
+            patvals
+                .into_iter()
+                .map(|patval| patval.map_err(BuildError::Input)),
+        )
     }
 
-    /// Builds and returns a new [`DoubleArrayAhoCorasick`] from input patterns. The value `i` is
-    /// automatically associated with `patterns[i]`.
+    fn try_build_fallible<I, P, E>(
+        mut self,
+        patvals: I,
+    ) -> Result<DoubleArrayAhoCorasick<V>, BuildError<E>>
+    where
+        I: IntoIterator<Item = Result<(P, V), BuildError<E>>>,
+        P: AsRef<[u8]>,
+        V: Copy,
+    {
+        self.validate()?;
+        let mut error = None;
+        let nfa = self.build_sparse_nfa(utils::stop_at_error(patvals, &mut error));
+        // The error in the input takes precedence since the automaton is built only from the
+        // patterns before it.
+        if let Some(e) = error {
+            return Err(e);
+        }
+        Ok(self.build_automaton(nfa?)?)
+    }
+
+    /// Builds and returns a new [`DoubleArrayAhoCorasick`] from input patterns sorted in
+    /// lexicographical order. The value `i` is automatically associated with `patterns[i]`.
+    ///
+    /// The result is identical to that of [`Self::build()`], but the trie is built directly from
+    /// the sorted patterns, which takes much less memory than [`Self::build()`].
     ///
     /// # Arguments
     ///
-    /// * `patterns` - List of patterns.
+    /// * `patterns` - List of patterns sorted in lexicographical order.
     ///
     /// # Errors
     ///
     /// [`DaachorseError`] is returned when
+    ///   - `patterns` is not sorted,
     ///   - `patterns` is empty,
     ///   - `patterns` contains entries of length zero,
-    ///   - `patterns` contains duplicate entries,
+    ///   - `patterns` contains duplicate entries and [`DuplicatePolicy::Error`] is specified,
     ///   - the conversion from the index `i` to the specified type `V` fails,
     ///   - the scale of `patterns` exceeds the expected one, or
     ///   - the scale of the resulting automaton exceeds the expected one.
@@ -138,48 +804,61 @@ impl DoubleArrayAhoCorasickBuilder {
     ///
     /// let builder = DoubleArrayAhoCorasickBuilder::new();
     ///
-    /// let patterns = vec!["bcd", "ab", "a"];
-    /// let pma = builder.build(patterns).unwrap();
+    /// let patterns = vec!["a", "ab", "bcd"];
+    /// let pma = builder.build_sorted(patterns).unwrap();
     ///
     /// let mut it = pma.find_iter("abcd");
     ///
     /// let m = it.next().unwrap();
-    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    /// assert_eq!((0, 1, 0), (m.start(), m.end(), m.value()));
     ///
     /// let m = it.next().unwrap();
-    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    /// assert_eq!((1, 4, 2), (m.start(), m.end(), m.value()));
     ///
     /// assert_eq!(None, it.next());
     /// ```
-    pub fn build<I, P, V>(self, patterns: I) -> Result<DoubleArrayAhoCorasick<V>>
+    pub fn build_sorted<I, P>(mut self, patterns: I) -> Result<DoubleArrayAhoCorasick<V>>
     where
         I: IntoIterator<Item = P>,
         P: AsRef<[u8]>,
         V: Copy + TryFrom<usize>,
     {
-        // The following code implicitly replaces large indices with 0,
-        // but build_with_values() returns an error variant for such iterators.
-        let patvals: Vec<_> = patterns
-            .into_iter()
-            .enumerate()
-            .map(|(i, p)| V::try_from(i).map(|i| (p, i)))
-            .collect::<Result<_, _>>()
-            .map_err(|_| DaachorseError::invalid_conversion("index", "V"))?;
-        self.build_with_values(patvals)
+        self.validate()?;
+        let mut nfa = SortedNfaBuilder::new(self.match_kind, self.duplicate_policy);
+        let mut count = 0;
+        self.progress.start(BuildPhase::TrieInsertion, None);
+        for pattern in patterns {
+            let value =
+                V::try_from(count).map_err(|_| DaachorseError::invalid_conversion("index", "V"))?;
+            nfa.add(pattern.as_ref(), value)
+                .map_err(|e| e.with_pattern_index(count))?;
+            count += 1;
+            self.inserted(count, &nfa)?;
+        }
+        self.progress.finish(BuildPhase::TrieInsertion, count);
+        Self::check_num_patterns(nfa.len)?;
+        nfa.build(&mut self.progress);
+        self.build_automaton(nfa)
     }
 
-    /// Builds and returns a new [`DoubleArrayAhoCorasick`] from input pattern-value pairs.
+    /// Builds and returns a new [`DoubleArrayAhoCorasick`] from input pattern-value pairs sorted
+    /// in lexicographical order of the patterns.
+    ///
+    /// The result is identical to that of [`Self::build_with_values()`], but the trie is built
+    /// directly from the sorted patterns, which takes much less memory than
+    /// [`Self::build_with_values()`].
     ///
     /// # Arguments
     ///
-    /// * `patvals` - List of pattern-value pairs.
+    /// * `patvals` - List of pattern-value pairs sorted in lexicographical order of the patterns.
     ///
     /// # Errors
     ///
     /// [`DaachorseError`] is returned when
+    ///   - `patvals` is not sorted,
     ///   - `patvals` is empty,
     ///   - `patvals` contains patterns of length zero,
-    ///   - `patvals` contains duplicate patterns,
+    ///   - `patvals` contains duplicate patterns and [`DuplicatePolicy::Error`] is specified,
     ///   - the scale of `patvals` exceeds the expected one, or
     ///   - the scale of the resulting automaton exceeds the expected one.
     ///
@@ -190,8 +869,8 @@ impl DoubleArrayAhoCorasickBuilder {
     ///
     /// let builder = DoubleArrayAhoCorasickBuilder::new();
     ///
-    /// let patvals = vec![("bcd", 0), ("ab", 1), ("a", 2), ("e", 1)];
-    /// let pma = builder.build_with_values(patvals).unwrap();
+    /// let patvals = vec![("a", 2), ("ab", 1), ("bcd", 0), ("e", 1)];
+    /// let pma = builder.build_sorted_with_values(patvals).unwrap();
     ///
     /// let mut it = pma.find_iter("abcde");
     ///
@@ -206,82 +885,209 @@ impl DoubleArrayAhoCorasickBuilder {
     ///
     /// assert_eq!(None, it.next());
     /// ```
-    pub fn build_with_values<I, P, V>(mut self, patvals: I) -> Result<DoubleArrayAhoCorasick<V>>
+    pub fn build_sorted_with_values<I, P>(mut self, patvals: I) -> Result<DoubleArrayAhoCorasick<V>>
     where
         I: IntoIterator<Item = (P, V)>,
         P: AsRef<[u8]>,
         V: Copy,
     {
-        let nfa = self.build_sparse_nfa(patvals)?;
-        self.build_double_array(&nfa)?;
+        self.validate()?;
+        let mut nfa = SortedNfaBuilder::new(self.match_kind, self.duplicate_policy);
+        let mut count = 0;
+        self.progress.start(BuildPhase::TrieInsertion, None);
+        for (pattern, value) in patvals {
+            nfa.add(pattern.as_ref(), value)
+                .map_err(|e| e.with_pattern_index(count))?;
+            count += 1;
+            self.inserted(count, &nfa)?;
+        }
+        self.progress.finish(BuildPhase::TrieInsertion, count);
+        Self::check_num_patterns(nfa.len)?;
+        nfa.build(&mut self.progress);
+        self.build_automaton(nfa)
+    }
+
+    fn build_automaton<N>(mut self, nfa: N) -> Result<DoubleArrayAhoCorasick<V>>
+    where
+        N: NfaView<u8, V>,
+        V: Copy,
+    {
+        let tails = self.build_double_array(&nfa)?;
 
         // -1 is for dead state
-        let num_states = u32::try_from(nfa.states.len() - 1)
+        let num_states = u32::try_from(nfa.num_states() - 1)
             .map_err(|_| DaachorseError::automaton_scale("num_states", u32::MAX))?;
 
-        Ok(DoubleArrayAhoCorasick {
+        let mut pma = DoubleArrayAhoCorasick {
             states: self.states,
-            outputs: nfa.outputs,
+            tails,
+            mapper: self.mapper,
+            outputs: OutputStore::new(nfa.into_outputs(), self.compact_outputs),
             match_kind: self.match_kind,
             num_states,
-        })
+            num_free_blocks: self.num_free_blocks,
+            dense: vec![],
+            dense_rows: vec![],
+            prefilter: Prefilter::default(),
+            spec: 0,
+        };
+        if self.reorder_states {
+            pma.reorder_blocks(self.reorder_sample.as_deref());
+        }
+        pma.build_dense_transitions(self.num_dense_states);
+        pma.build_prefilter();
+        if let Some(budget) = self.memory_budget {
+            if pma.heap_bytes() > budget {
+                return Err(DaachorseError::memory_budget(budget));
+            }
+        }
+        Ok(pma)
     }
 
-    fn build_sparse_nfa<I, P, V>(&self, patvals: I) -> Result<BytewiseNfaBuilder<V>>
+    fn build_sparse_nfa<I, P>(&mut self, patvals: I) -> Result<BytewiseNfaBuilder<V>>
     where
         I: IntoIterator<Item = (P, V)>,
         P: AsRef<[u8]>,
         V: Copy,
     {
-        let mut nfa = BytewiseNfaBuilder::new(self.match_kind);
+        let mut nfa = BytewiseNfaBuilder::new(self.match_kind, self.duplicate_policy);
+        let mut count = 0;
+        self.progress.start(BuildPhase::TrieInsertion, None);
         for (pattern, value) in patvals {
-            nfa.add(pattern.as_ref(), value)?;
-        }
-        if nfa.len == 0 {
-            return Err(DaachorseError::invalid_argument("patvals.len()", ">=", 1));
-        }
-        if nfa.len > usize::from_u32(U24::MAX) {
-            return Err(DaachorseError::automaton_scale("patvals.len()", U24::MAX));
+            nfa.add(pattern.as_ref(), value)
+                .map_err(|e| e.with_pattern_index(count))?;
+            count += 1;
+            self.inserted(count, &nfa)?;
         }
+        self.progress.finish(BuildPhase::TrieInsertion, count);
+        Self::check_num_patterns(nfa.len)?;
         let q = match self.match_kind {
-            MatchKind::Standard => nfa.build_fails(),
-            MatchKind::LeftmostLongest | MatchKind::LeftmostFirst => nfa.build_fails_leftmost(),
+            MatchKind::Standard => nfa.build_fails(&mut self.progress),
+            MatchKind::LeftmostLongest | MatchKind::LeftmostFirst => {
+                nfa.build_fails_leftmost(&mut self.progress)
+            }
         };
         nfa.build_outputs(&q);
         Ok(nfa)
     }
 
-    fn build_double_array<V>(&mut self, nfa: &BytewiseNfaBuilder<V>) -> Result<()> {
+    /// Reports the progress of the insertion and checks the memory usage of the trie.
+    #[inline(always)]
+    fn inserted<N>(&mut self, count: usize, nfa: &N) -> Result<()>
+    where
+        N: NfaView<u8, V>,
+    {
+        self.progress.tick(BuildPhase::TrieInsertion, count, None);
+        self.check_memory_budget(nfa.heap_bytes())
+    }
+
+    #[inline(always)]
+    fn check_memory_budget(&self, bytes: usize) -> Result<()> {
+        match self.memory_budget {
+            Some(budget) if bytes > budget => Err(DaachorseError::memory_budget(budget)),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_num_patterns(len: usize) -> Result<()> {
+        if len == 0 {
+            return Err(DaachorseError::invalid_argument("patvals.len()", ">=", 1));
+        }
+        if len > usize::from_u32(U24::MAX) {
+            return Err(DaachorseError::automaton_scale("patvals.len()", U24::MAX));
+        }
+        Ok(())
+    }
+
+    fn build_double_array<N>(&mut self, nfa: &N) -> Result<Tails>
+    where
+        N: NfaView<u8, V>,
+    {
         let mut helper = self.init_array()?;
+        self.mapper = if self.relabel_bytes {
+            ByteMapper::new(&Self::count_labels(nfa))
+        } else {
+            ByteMapper::default()
+        };
 
-        let mut state_id_map = vec![DEAD_STATE_IDX; nfa.states.len()];
+        let mut state_id_map = vec![DEAD_STATE_IDX; nfa.num_states()];
         state_id_map[usize::from_u32(ROOT_STATE_ID)] = ROOT_STATE_IDX;
+        let is_chain = if self.tail_compression {
+            Self::find_chains(nfa)
+        } else {
+            vec![]
+        };
+        let nfa_bytes = nfa.heap_bytes()
+            + state_id_map.len() * mem::size_of::<u32>()
+            + is_chain.len() * mem::size_of::<bool>();
+
+        // The states moved to the tails in order, with their labels and the flags indicating the
+        // first positions of the tails.
+        let mut tail_states = vec![];
+        // The pairs of the indices of the states linked to the tails and the first positions.
+        let mut tail_links = vec![];
+
+        // The dead state is not placed.
+        let num_states = nfa.num_states() - 1;
+        let mut count = 0;
+        self.progress
+            .start(BuildPhase::DoubleArrayPlacement, Some(num_states));
 
         // Arranges base & check values
         let mut stack = vec![ROOT_STATE_ID];
+        let mut edges = vec![];
         let mut labels = vec![];
 
         while let Some(state_id) = stack.pop() {
             debug_assert_ne!(state_id, DEAD_STATE_ID);
-            let state = &nfa.states[usize::from_u32(state_id)];
+            count += 1;
+            self.progress
+                .tick(BuildPhase::DoubleArrayPlacement, count, Some(num_states));
 
             let state_idx = usize::from_u32(state_id_map[usize::from_u32(state_id)]);
             debug_assert_ne!(state_idx, usize::from_u32(DEAD_STATE_IDX));
 
-            let s = &state.borrow();
-            if s.edges.is_empty() {
+            edges.clear();
+            nfa.push_edges(state_id, &mut edges);
+            if edges.is_empty() {
+                continue;
+            }
+
+            if state_id != ROOT_STATE_ID && is_chain.get(usize::from_u32(state_id)) == Some(&true) {
+                // The descendants form a single path, so they are moved to a tail.
+                tail_links.push((state_idx, tail_states.len()));
+                let mut is_first = true;
+                while let Some(&(c, child_id)) = edges.first() {
+                    count += 1;
+                    self.progress
+                        .tick(BuildPhase::DoubleArrayPlacement, count, Some(num_states));
+                    tail_states.push((child_id, self.mapper.get(c), is_first));
+                    is_first = false;
+                    edges.clear();
+                    nfa.push_edges(child_id, &mut edges);
+                }
                 continue;
             }
 
+            for (c, _) in &mut edges {
+                *c = self.mapper.get(*c);
+            }
+            edges.sort_unstable_by_key(|&(c, _)| c);
             labels.clear();
-            s.edges.keys().for_each(|&k| labels.push(k));
+            edges.iter().for_each(|&(c, _)| labels.push(c));
 
             let base = self.find_base(&labels, &helper);
             if usize::from_u32(base.get()) >= self.states.len() {
+                let array_len = self.states.len() + usize::from_u32(BLOCK_LEN);
+                self.check_memory_budget(
+                    nfa_bytes
+                        + array_len * mem::size_of::<State>()
+                        + tail_states.len() * mem::size_of::<(u32, u8, bool)>(),
+                )?;
                 self.extend_array(&mut helper)?;
             }
 
-            for (&c, &child_id) in &s.edges {
+            for &(c, child_id) in &edges {
                 let child_idx = base.get() ^ u32::from(c);
                 helper.use_index(child_idx);
                 self.states[usize::from_u32(child_idx)].set_check(c);
@@ -292,34 +1098,105 @@ impl DoubleArrayAhoCorasickBuilder {
             helper.use_base(base);
         }
 
+        // The ids of the tails follow the states. Since the length of the states is a multiple of
+        // BLOCK_LEN, the bases linking to the tails never point to the states.
+        for (pos, &(state_id, _, _)) in (self.states.len()..).zip(&tail_states) {
+            state_id_map[usize::from_u32(state_id)] = u32::try_from(pos)
+                .map_err(|_| DaachorseError::automaton_scale("num_states", u32::MAX))?;
+        }
+        for &(state_idx, pos) in &tail_links {
+            let first_idx = state_id_map[usize::from_u32(tail_states[pos].0)];
+            // The ids of the tails are larger than those of the root and dead states.
+            self.states[state_idx].set_base(NonZeroU32::new(first_idx).unwrap());
+        }
+
         // Sets fail & output_pos values
-        for (i, state) in nfa.states.iter().enumerate() {
-            if i == usize::from_u32(DEAD_STATE_ID) {
+        let fail_idx = |state_id| {
+            let fail_id = nfa.fail(state_id);
+            if fail_id == DEAD_STATE_ID {
+                DEAD_STATE_IDX
+            } else {
+                let fail_idx = state_id_map[usize::from_u32(fail_id)];
+                debug_assert_ne!(fail_idx, DEAD_STATE_IDX);
+                fail_idx
+            }
+        };
+        for (state_id, &idx) in (0..).zip(&state_id_map) {
+            if state_id == DEAD_STATE_ID {
                 continue;
             }
 
-            let idx = usize::from_u32(state_id_map[i]);
+            let idx = usize::from_u32(idx);
             debug_assert_ne!(idx, usize::from_u32(DEAD_STATE_IDX));
+            if idx >= self.states.len() {
+                continue;
+            }
 
-            let s = &state.borrow();
-            self.states[idx].set_output_pos(s.output_pos)?;
-
-            let fail_id = s.fail;
-            if fail_id == DEAD_STATE_ID {
-                self.states[idx].set_fail(DEAD_STATE_IDX);
-            } else {
-                let fail_idx = state_id_map[usize::from_u32(fail_id)];
-                debug_assert_ne!(fail_idx, DEAD_STATE_IDX);
-                self.states[idx].set_fail(fail_idx);
+            self.states[idx].set_output_pos(nfa.output_pos(state_id))?;
+            self.states[idx].set_fail(fail_idx(state_id));
+        }
+        let mut positions = Vec::with_capacity(tail_states.len());
+        for &(state_id, label, is_first) in &tail_states {
+            let output_pos = nfa.output_pos(state_id);
+            if output_pos.map_or(0, NonZeroU32::get) > U24::MAX {
+                return Err(DaachorseError::automaton_scale("output_pos", U24::MAX));
             }
+            positions.push(TailPosition {
+                label,
+                is_first,
+                fail: fail_idx(state_id),
+                output_pos,
+            });
         }
 
         for closed_block_idx in helper.active_block_range() {
             self.remove_invalid_checks(closed_block_idx, &helper);
         }
         self.states.shrink_to_fit();
+        self.progress
+            .finish(BuildPhase::DoubleArrayPlacement, num_states);
 
-        Ok(())
+        Ok(Tails::new(&positions))
+    }
+
+    /// Counts the transitions labeled with each byte.
+    fn count_labels<N>(nfa: &N) -> [u32; 256]
+    where
+        N: NfaView<u8, V>,
+    {
+        let mut freqs = [0; 256];
+        let mut edges = vec![];
+        // The ids of the states fit in u32.
+        for state_id in 0..u32::try_from(nfa.num_states()).unwrap() {
+            edges.clear();
+            nfa.push_edges(state_id, &mut edges);
+            for &(c, _) in &edges {
+                freqs[usize::from(c)] += 1;
+            }
+        }
+        freqs
+    }
+
+    /// Checks for each state if its descendants form a single path, i.e., each of them has at
+    /// most one child.
+    fn find_chains<N>(nfa: &N) -> Vec<bool>
+    where
+        N: NfaView<u8, V>,
+    {
+        let mut is_chain = vec![false; nfa.num_states()];
+        let mut edges = vec![];
+        // The ids of the states fit in u32, and children always have larger ids than their
+        // parents.
+        for state_id in (0..u32::try_from(nfa.num_states()).unwrap()).rev() {
+            edges.clear();
+            nfa.push_edges(state_id, &mut edges);
+            is_chain[usize::from_u32(state_id)] = match edges[..] {
+                [] => true,
+                [(_, child_id)] => is_chain[usize::from_u32(child_id)],
+                _ => false,
+            };
+        }
+        is_chain
     }
 
     fn init_array(&mut self) -> Result<BuildHelper> {
@@ -332,8 +1209,53 @@ impl DoubleArrayAhoCorasickBuilder {
         Ok(helper)
     }
 
+    /// Restores [`BuildHelper`] from the array built beforehand so that the last blocks can be
+    /// used again.
+    ///
+    /// The used elements are found by traversing the transitions from the root, since states
+    /// having neither a BASE value nor an output, such as leaves left by removal, cannot be
+    /// distinguished from vacant elements by themselves.
+    pub(crate) fn restore_helper(&self) -> Result<BuildHelper> {
+        let mut helper = BuildHelper::new(BLOCK_LEN, self.num_free_blocks)?;
+        for _ in 0..self.states.len() / usize::from_u32(BLOCK_LEN) {
+            helper.push_block()?;
+        }
+        let active_range = helper.active_index_range();
+        for idx in [ROOT_STATE_IDX, DEAD_STATE_IDX] {
+            if active_range.contains(&idx) {
+                helper.use_index(idx);
+            }
+        }
+        let mut stack = vec![ROOT_STATE_IDX];
+        while let Some(idx) = stack.pop() {
+            let base = match self.states[usize::from_u32(idx)].base() {
+                Some(base) => base,
+                None => continue,
+            };
+            if active_range.contains(&base.get()) {
+                helper.use_base(base);
+            }
+            for c in u8::MIN..=u8::MAX {
+                let child_idx = base.get() ^ u32::from(c);
+                // The CHECK values of the vacant elements are set not to match any used BASE
+                // value by remove_invalid_checks().
+                if child_idx == ROOT_STATE_IDX
+                    || child_idx == DEAD_STATE_IDX
+                    || self.states[usize::from_u32(child_idx)].check() != c
+                {
+                    continue;
+                }
+                if active_range.contains(&child_idx) {
+                    helper.use_index(child_idx);
+                }
+                stack.push(child_idx);
+            }
+        }
+        Ok(helper)
+    }
+
     #[inline(always)]
-    fn find_base(&self, labels: &[u8], helper: &BuildHelper) -> NonZeroU32 {
+    pub(crate) fn find_base(&self, labels: &[u8], helper: &BuildHelper) -> NonZeroU32 {
         for idx in helper.vacant_iter() {
             let base = idx ^ u32::from(labels[0]);
             if let Some(base) = Self::check_valid_base(base, labels, helper) {
@@ -358,7 +1280,7 @@ impl DoubleArrayAhoCorasickBuilder {
         NonZeroU32::new(base)
     }
 
-    fn extend_array(&mut self, helper: &mut BuildHelper) -> Result<()> {
+    pub(crate) fn extend_array(&mut self, helper: &mut BuildHelper) -> Result<()> {
         if self.states.len() > usize::from_u32(u32::MAX - BLOCK_LEN) {
             return Err(DaachorseError::automaton_scale("states.len()", u32::MAX));
         }
@@ -377,7 +1299,7 @@ impl DoubleArrayAhoCorasickBuilder {
     }
 
     /// Embeds valid CHECK values for all vacant elements in the block to avoid invalid transitions.
-    fn remove_invalid_checks(&mut self, block_idx: u32, helper: &BuildHelper) {
+    pub(crate) fn remove_invalid_checks(&mut self, block_idx: u32, helper: &BuildHelper) {
         if let Some(unused_base) = helper.unused_base_in_block(block_idx) {
             for c in u8::MIN..=u8::MAX {
                 let idx = unused_base ^ u32::from(c);
@@ -388,3 +1310,209 @@ impl DoubleArrayAhoCorasickBuilder {
         }
     }
 }
+
+#[cfg(feature = "std")]
+impl<V> DoubleArrayAhoCorasickBuilder<V>
+where
+    V: Copy + Send + Sync,
+{
+    /// Builds and returns a new [`DoubleArrayAhoCorasick`] from input patterns with multiple
+    /// threads. The value `i` is automatically associated with `patterns[i]`.
+    ///
+    /// The result is identical to that of [`Self::build()`]. See
+    /// [`Self::par_build_with_values()`] for the parallelized parts.
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - List of patterns.
+    /// * `num_threads` - The number of threads.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned in the same cases as [`Self::build()`], or when
+    /// `num_threads` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder};
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma: DoubleArrayAhoCorasick<usize> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .par_build(&patterns, 2)
+    ///     .unwrap();
+    /// let other: DoubleArrayAhoCorasick<usize> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(other.serialize(), pma.serialize());
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn par_build<I, P>(
+        self,
+        patterns: I,
+        num_threads: usize,
+    ) -> Result<DoubleArrayAhoCorasick<V>>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]> + Sync,
+        V: TryFrom<usize>,
+    {
+        let patvals: Vec<_> = patterns
+            .into_iter()
+            .enumerate()
+            .map(|(i, p)| V::try_from(i).map(|i| (p, i)))
+            .collect::<Result<_, _>>()
+            .map_err(|_| DaachorseError::invalid_conversion("index", "V"))?;
+        self.par_build_with_values(patvals, num_threads)
+    }
+
+    /// Builds and returns a new [`DoubleArrayAhoCorasick`] from input pattern-value pairs with
+    /// multiple threads.
+    ///
+    /// The patterns are grouped by their first bytes, and the tries of the groups are built in
+    /// parallel. The fails of the states in each BFS level are also computed in parallel. The
+    /// arrangement of the double array is sequential, so the result is identical to that of
+    /// [`Self::build_with_values()`], including the output of
+    /// [`DoubleArrayAhoCorasick::serialize()`] and the error returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `patvals` - List of pattern-value pairs.
+    /// * `num_threads` - The number of threads.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned in the same cases as [`Self::build_with_values()`], or when
+    /// `num_threads` is zero. During the insertion, the memory budget is checked against the total
+    /// memory usage of the tries of all the threads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasickBuilder;
+    ///
+    /// let patvals = vec![("bcd", 0), ("ab", 1), ("a", 2), ("e", 1)];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .par_build_with_values(patvals, 4)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.find_iter("abcde");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((4, 5, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn par_build_with_values<I, P>(
+        mut self,
+        patvals: I,
+        num_threads: usize,
+    ) -> Result<DoubleArrayAhoCorasick<V>>
+    where
+        I: IntoIterator<Item = (P, V)>,
+        P: AsRef<[u8]> + Sync,
+    {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        self.validate()?;
+        if num_threads == 0 {
+            return Err(DaachorseError::invalid_argument("num_threads", ">=", 1));
+        }
+        let patvals: Vec<_> = patvals.into_iter().collect();
+        self.progress.start(BuildPhase::TrieInsertion, None);
+
+        // Distributes the patterns to the threads so that the patterns with the same first byte
+        // belong to the same thread. Empty patterns are given to the first thread and rejected
+        // there.
+        let mut counts = [0; 256];
+        for (pattern, _) in &patvals {
+            if let Some(&c) = pattern.as_ref().first() {
+                counts[usize::from(c)] += 1;
+            }
+        }
+        let mut thread_ids = [0; 256];
+        let mut acc = 0;
+        for (thread_id, &count) in thread_ids.iter_mut().zip(&counts) {
+            *thread_id = acc * num_threads / patvals.len().max(1);
+            acc += count;
+        }
+        let mut indices = vec![vec![]; num_threads];
+        for (j, (pattern, _)) in patvals.iter().enumerate() {
+            let thread_id = pattern
+                .as_ref()
+                .first()
+                .map_or(0, |&c| thread_ids[usize::from(c)]);
+            indices[thread_id].push(j);
+        }
+
+        let (match_kind, duplicate_policy) = (self.match_kind, self.duplicate_policy);
+        let memory_budget = self.memory_budget;
+        let patvals = &patvals;
+        // The sum of the memory usage of the tries of all the threads, which is checked against
+        // the budget after every insertion so that all the threads stop soon after it is exceeded.
+        let total_bytes = &AtomicUsize::new(0);
+        let results: Vec<_> = std::thread::scope(|s| {
+            let handles: Vec<_> = indices
+                .iter()
+                .map(|indices| {
+                    s.spawn(move || {
+                        let mut nfa = BytewiseNfaBuilder::new(match_kind, duplicate_policy);
+                        let mut heap_bytes = 0;
+                        for &j in indices {
+                            let (pattern, value) = &patvals[j];
+                            // The error of the smallest index is reported as in the sequential
+                            // build.
+                            nfa.add(pattern.as_ref(), *value)
+                                .map_err(|e| (j, e.with_pattern_index(j)))?;
+                            if let Some(budget) = memory_budget {
+                                // The trie never shrinks during the insertion.
+                                let diff = nfa.heap_bytes() - heap_bytes;
+                                heap_bytes += diff;
+                                if total_bytes.fetch_add(diff, Ordering::Relaxed) + diff > budget {
+                                    return Err((j, DaachorseError::memory_budget(budget)));
+                                }
+                            }
+                        }
+                        Ok(nfa)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut nfas = Vec::with_capacity(num_threads);
+        let mut first_error: Option<(usize, DaachorseError)> = None;
+        for result in results {
+            match result {
+                Ok(other) => nfas.push(other),
+                Err((j, e)) => {
+                    if first_error.as_ref().map_or(true, |&(k, _)| j < k) {
+                        first_error = Some((j, e));
+                    }
+                }
+            }
+        }
+        if let Some((_, e)) = first_error {
+            return Err(e);
+        }
+        let mut nfa = BytewiseNfaBuilder::new(match_kind, duplicate_policy);
+        for other in nfas {
+            nfa.graft(other)?;
+        }
+        self.progress
+            .finish(BuildPhase::TrieInsertion, patvals.len());
+        self.check_memory_budget(nfa.heap_bytes())?;
+        Self::check_num_patterns(nfa.len)?;
+        let q = nfa.par_build_fails(num_threads, &mut self.progress);
+        nfa.build_outputs(&q);
+        self.build_automaton(nfa)
+    }
+}
@@ -0,0 +1,986 @@
+//! Editor for incrementally updating a built [`DoubleArrayAhoCorasick`].
+
+use core::num::NonZeroU32;
+
+use alloc::vec::Vec;
+
+use crate::build_helper::BuildHelper;
+use crate::bytewise::mapper::ByteMapper;
+use crate::bytewise::tail::Tails;
+use crate::bytewise::{
+    DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, Prefilter, State, DEAD_STATE_IDX,
+    ROOT_STATE_IDX,
+};
+use crate::errors::{DaachorseError, Result};
+use crate::intpack::U24;
+use crate::outputs::OutputStore;
+use crate::utils::FromU32;
+use crate::{DuplicatePolicy, MatchKind, Output};
+
+// The terminator of reverse failure lists.
+// The root state never appears in the lists, so its index can be used.
+const NIL: u32 = ROOT_STATE_IDX;
+
+/// Editor of [`DoubleArrayAhoCorasick`], created by [`DoubleArrayAhoCorasick::into_editor()`].
+///
+/// The editor inserts new patterns into the existing double array, placing new states in the
+/// vacant elements of the last blocks, and removes patterns by detaching their outputs. Only the
+/// failure links and output chains affected by each operation are updated, so updating a large
+/// automaton with a small number of patterns is much faster than building it from scratch.
+///
+/// The layout of the resulting automaton depends on the editing history, so it can be different
+/// from (and less compact than) the one built from the same patterns at once. Call
+/// [`Self::compact()`] to rebuild it in the compact layout.
+///
+/// Automata with tails (see [`DoubleArrayAhoCorasickBuilder::tail_compression()`]) cannot be
+/// edited since the editor updates the states in place.
+pub struct DoubleArrayAhoCorasickEditor<V> {
+    builder: DoubleArrayAhoCorasickBuilder<V>,
+    helper: BuildHelper,
+    outputs: Vec<Output<V>>,
+    mapper: ByteMapper,
+    num_states: u32,
+    num_dense_states: u32,
+    num_free_blocks: u32,
+    compact_outputs: bool,
+    // Reverse failure links.
+    // fail_head[i] is the first state whose failure link points to i, and the other such states
+    // are doubly linked by fail_next and fail_prev.
+    fail_head: Vec<u32>,
+    fail_next: Vec<u32>,
+    fail_prev: Vec<u32>,
+}
+
+impl<V> DoubleArrayAhoCorasickEditor<V>
+where
+    V: Copy,
+{
+    pub(crate) fn new(pma: DoubleArrayAhoCorasick<V>) -> Self {
+        let num_dense_states = pma.num_dense_states();
+        let compact_outputs = pma.outputs.is_compact();
+        let mut builder = DoubleArrayAhoCorasickBuilder::new().num_free_blocks(pma.num_free_blocks);
+        builder.states = pma.states;
+
+        // The helper can be always restored since the array was built with the same parameters.
+        let helper = builder.restore_helper().unwrap();
+
+        let len = builder.states.len();
+        let mut editor = Self {
+            builder,
+            helper,
+            outputs: pma.outputs.into_vec(),
+            mapper: pma.mapper,
+            num_states: pma.num_states,
+            num_dense_states,
+            num_free_blocks: pma.num_free_blocks,
+            compact_outputs,
+            fail_head: vec![NIL; len],
+            fail_next: vec![NIL; len],
+            fail_prev: vec![NIL; len],
+        };
+        // The states are traversed from the root so that tombstones left by removal are also
+        // linked. The labels are the codes of the bytes.
+        let mut stack = vec![ROOT_STATE_IDX];
+        while let Some(idx) = stack.pop() {
+            for c in u8::MIN..=u8::MAX {
+                if let Some(child_idx) = editor.child_index(idx, c) {
+                    editor.link_fail(child_idx);
+                    stack.push(child_idx);
+                }
+            }
+        }
+        editor
+    }
+
+    /// Inserts a pattern-value pair into the automaton.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Pattern to be inserted.
+    /// * `value` - Value associated with the pattern.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when
+    ///   - `pattern` is empty,
+    ///   - `pattern` is already registered, or
+    ///   - the scale of the resulting automaton exceeds the expected one.
+    ///
+    /// If the scale error is returned, the editor must not be used any more.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab"];
+    /// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let mut editor = pma.into_editor().unwrap();
+    /// editor.insert("a", 2).unwrap();
+    /// assert!(editor.insert("ab", 3).is_err());
+    /// let pma = editor.finish();
+    ///
+    /// let mut it = pma.find_overlapping_iter("abcd");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 2, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn insert<P>(&mut self, pattern: P, value: V) -> Result<()>
+    where
+        P: AsRef<[u8]>,
+    {
+        let pattern = pattern.as_ref();
+        let length = u32::try_from(pattern.len())
+            .map_err(|_| DaachorseError::invalid_argument("pattern.len()", "<=", u32::MAX))?;
+        if length == 0 {
+            return Err(DaachorseError::empty_pattern());
+        }
+
+        let mut state_idx = ROOT_STATE_IDX;
+        for &c in pattern {
+            let c = self.mapper.get(c);
+            state_idx = match self.child_index(state_idx, c) {
+                Some(child_idx) => child_idx,
+                None => self.add_state(state_idx, c)?,
+            };
+        }
+
+        if self.has_own_output(state_idx) {
+            return Err(DaachorseError::duplicate_pattern(format!("{pattern:?}")));
+        }
+        let output_pos = u32::try_from(self.outputs.len() + 1)
+            .ok()
+            .and_then(NonZeroU32::new)
+            .ok_or_else(|| DaachorseError::automaton_scale("output_pos", U24::MAX))?;
+        let parent = self.state(state_idx).output_pos();
+        self.state_mut(state_idx).set_output_pos(Some(output_pos))?;
+        self.outputs.push(Output::new(value, length, parent));
+        self.propagate_output_pos(state_idx, parent)
+    }
+
+    /// Removes a pattern from the automaton and returns the value associated with it, or [`None`]
+    /// if the pattern is not registered. If the pattern has multiple values, all of them are
+    /// removed and the first one is returned.
+    ///
+    /// The output of the pattern is detached from the output chains, and the states that no longer
+    /// lead to any pattern are pruned. Pruned states in the last blocks are released for reuse,
+    /// while the others are left as tombstones without outputs. Tombstones and detached outputs
+    /// keep occupying memory until [`Self::compact()`] is called, but they never affect the search
+    /// results.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Pattern to be removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let mut editor = pma.into_editor().unwrap();
+    /// assert_eq!(Some(1), editor.remove("ab"));
+    /// assert_eq!(None, editor.remove("ab"));
+    /// let pma = editor.finish();
+    ///
+    /// let mut it = pma.find_overlapping_iter("abcd");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn remove<P>(&mut self, pattern: P) -> Option<V>
+    where
+        P: AsRef<[u8]>,
+    {
+        let mut path = vec![ROOT_STATE_IDX];
+        for &c in pattern.as_ref() {
+            path.push(self.child_index(*path.last().unwrap(), self.mapper.get(c))?);
+        }
+        let state_idx = *path.last().unwrap();
+        if !self.has_own_output(state_idx) {
+            return None;
+        }
+
+        // has_own_output() ensures that output_pos is Some.
+        let output_pos = self.state(state_idx).output_pos();
+        let value = self.outputs[usize::from_u32(output_pos.unwrap().get() - 1)].value();
+        let parent = self.outputs[self.last_own_output(output_pos.unwrap())].parent();
+        // The parent is the output position of the failure state, so it always fits in U24.
+        self.state_mut(state_idx).set_output_pos(parent).unwrap();
+        self.propagate_output_pos(state_idx, output_pos).unwrap();
+
+        self.prune(path);
+        Some(value)
+    }
+
+    /// Rebuilds the automaton from the registered patterns to release the memory occupied by
+    /// tombstones and detached outputs.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when no pattern is registered. The editor is left unchanged
+    /// in that case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let mut editor = pma.into_editor().unwrap();
+    /// editor.remove("bcd");
+    /// editor.compact().unwrap();
+    /// let pma = editor.finish();
+    ///
+    /// assert_eq!(3, pma.num_states());
+    /// ```
+    pub fn compact(&mut self) -> Result<()> {
+        let inverse = self.mapper.inverse();
+        let mut patvals = vec![];
+        let mut pattern = vec![];
+        let mut stack = vec![(ROOT_STATE_IDX, 0, 0)];
+        while let Some((idx, depth, c)) = stack.pop() {
+            pattern.truncate(depth);
+            if idx != ROOT_STATE_IDX {
+                pattern.push(inverse[usize::from(c)]);
+            }
+            if self.has_own_output(idx) {
+                // has_own_output() ensures that output_pos is Some.
+                let output_pos = self.state(idx).output_pos().unwrap();
+                let last = self.last_own_output(output_pos);
+                let mut pos = usize::from_u32(output_pos.get() - 1);
+                loop {
+                    patvals.push((output_pos, pattern.clone(), self.outputs[pos].value()));
+                    if pos == last {
+                        break;
+                    }
+                    // The outputs of the same pattern are always chained.
+                    pos = usize::from_u32(self.outputs[pos].parent().unwrap().get() - 1);
+                }
+            }
+            for c in (u8::MIN..=u8::MAX).rev() {
+                if let Some(child_idx) = self.child_index(idx, c) {
+                    stack.push((child_idx, pattern.len(), c));
+                }
+            }
+        }
+        // Keeps the registration order of the outputs. The stable sort keeps the order of the
+        // values of each pattern.
+        patvals.sort_by_key(|&(output_pos, _, _)| output_pos);
+
+        let pma = DoubleArrayAhoCorasickBuilder::new()
+            .on_duplicate(DuplicatePolicy::KeepAll)
+            .num_dense_states(self.num_dense_states)
+            .num_free_blocks(self.num_free_blocks)
+            .compact_outputs(self.compact_outputs)
+            .relabel_bytes(!self.mapper.is_identity())
+            .build_with_values(patvals.into_iter().map(|(_, p, v)| (p, v)))?;
+        *self = Self::new(pma);
+        Ok(())
+    }
+
+    /// Finishes editing and returns the updated automaton.
+    #[must_use]
+    pub fn finish(mut self) -> DoubleArrayAhoCorasick<V> {
+        for closed_block_idx in self.helper.active_block_range() {
+            self.builder
+                .remove_invalid_checks(closed_block_idx, &self.helper);
+        }
+        let mut states = self.builder.states;
+        states.shrink_to_fit();
+        let mut pma = DoubleArrayAhoCorasick {
+            states,
+            tails: Tails::new(&[]),
+            mapper: self.mapper,
+            outputs: OutputStore::new(self.outputs, self.compact_outputs),
+            match_kind: MatchKind::Standard,
+            num_states: self.num_states,
+            num_free_blocks: self.num_free_blocks,
+            dense: vec![],
+            dense_rows: vec![],
+            prefilter: Prefilter::default(),
+            spec: 0,
+        };
+        pma.build_dense_transitions(self.num_dense_states);
+        pma.build_prefilter();
+        pma
+    }
+
+    /// Creates a new child of `parent_idx` with label `c` and returns its index.
+    fn add_state(&mut self, parent_idx: u32, c: u8) -> Result<u32> {
+        let child_idx = self.place_child(parent_idx, c)?;
+        self.num_states += 1;
+
+        let fail_idx = self.find_fail(parent_idx, c);
+        let output_pos = self.state(fail_idx).output_pos();
+        let child = self.state_mut(child_idx);
+        child.set_fail(fail_idx);
+        child.set_output_pos(output_pos)?;
+        self.link_fail(child_idx);
+
+        // States that have the new state as the longest proper suffix are searched for in the
+        // subtree of the parent in the failure tree. The search is pruned at states having a child
+        // with label `c` because the children already have longer suffixes.
+        let mut redirected = vec![];
+        let mut stack = vec![];
+        self.push_fail_children(parent_idx, &mut stack);
+        while let Some(idx) = stack.pop() {
+            if let Some(idx) = self.child_index(idx, c) {
+                redirected.push(idx);
+            } else {
+                self.push_fail_children(idx, &mut stack);
+            }
+        }
+        for idx in redirected {
+            self.set_fail(idx, child_idx)?;
+        }
+
+        Ok(child_idx)
+    }
+
+    /// Allocates an element for the child of `parent_idx` with label `c`. If the element is already
+    /// used, existing children are moved to another place together with the new one.
+    fn place_child(&mut self, parent_idx: u32, c: u8) -> Result<u32> {
+        let old_base = self.state(parent_idx).base();
+        if let Some(base) = old_base {
+            let child_idx = base.get() ^ u32::from(c);
+            if !self.is_used_index(child_idx) {
+                self.helper.use_index(child_idx);
+                self.init_state(child_idx, c);
+                return Ok(child_idx);
+            }
+        }
+
+        let mut labels: Vec<u8> = (u8::MIN..=u8::MAX)
+            .filter(|&label| self.child_index(parent_idx, label).is_some())
+            .collect();
+        labels.push(c);
+
+        let base = self.builder.find_base(&labels, &self.helper);
+        if usize::from_u32(base.get()) >= self.builder.states.len() {
+            self.extend_array()?;
+        }
+        for &label in &labels {
+            self.helper.use_index(base.get() ^ u32::from(label));
+        }
+        for &label in &labels {
+            let new_idx = base.get() ^ u32::from(label);
+            if label == c {
+                self.init_state(new_idx, c);
+            } else {
+                // old_base is always Some since the parent has children.
+                let old_idx = old_base.unwrap().get() ^ u32::from(label);
+                self.move_state(old_idx, new_idx);
+            }
+        }
+        self.state_mut(parent_idx).set_base(base);
+        self.helper.use_base(base);
+        // Every used BASE value must have a child in the block so that vacant elements always get
+        // invalid CHECK values in remove_invalid_checks().
+        if let Some(old_base) = old_base {
+            if self.helper.active_index_range().contains(&old_base.get()) {
+                self.helper.unuse_base(old_base);
+            }
+        }
+
+        Ok(base.get() ^ u32::from(c))
+    }
+
+    /// Removes the states without children and outputs, walking up `path` from the root to the
+    /// last state.
+    ///
+    /// States out of the active blocks are left as tombstones since their elements cannot be
+    /// reused and their CHECK values cannot be invalidated.
+    fn prune(&mut self, mut path: Vec<u32>) {
+        while let [.., parent_idx, idx] = path[..] {
+            if !self.helper.active_index_range().contains(&idx)
+                || self.has_own_output(idx)
+                || self.has_children(idx)
+            {
+                break;
+            }
+
+            // The state has no own output, so the redirection keeps the output chains unchanged.
+            let fail_idx = self.state(idx).fail();
+            let mut stack = vec![];
+            self.push_fail_children(idx, &mut stack);
+            for child_idx in stack {
+                self.unlink_fail(child_idx);
+                self.state_mut(child_idx).set_fail(fail_idx);
+                self.link_fail(child_idx);
+            }
+            self.unlink_fail(idx);
+
+            // The CHECK value is kept so that the vacant element is not reached from other states.
+            let check = self.state(idx).check();
+            let vacant = self.state_mut(idx);
+            *vacant = State::default();
+            vacant.set_check(check);
+            self.helper.unuse_index(idx);
+            self.num_states -= 1;
+
+            if !self.has_children(parent_idx) {
+                // The child was in the active blocks, so is the BASE value.
+                let base = self.state(parent_idx).base().unwrap();
+                self.state_mut(parent_idx).clear_base();
+                self.helper.unuse_base(base);
+            }
+            path.pop();
+        }
+    }
+
+    /// Moves the state at `old_idx` to the vacant element `new_idx`.
+    fn move_state(&mut self, old_idx: u32, new_idx: u32) {
+        let state = *self.state(old_idx);
+        *self.state_mut(new_idx) = state;
+
+        // The CHECK value is kept so that the vacant element is not reached from other states.
+        let vacant = self.state_mut(old_idx);
+        *vacant = State::default();
+        vacant.set_check(state.check());
+        if self.helper.active_index_range().contains(&old_idx) {
+            self.helper.unuse_index(old_idx);
+        }
+
+        let (old, new) = (usize::from_u32(old_idx), usize::from_u32(new_idx));
+        let prev = self.fail_prev[old];
+        let next = self.fail_next[old];
+        self.fail_prev[new] = prev;
+        self.fail_next[new] = next;
+        if prev == NIL {
+            self.fail_head[usize::from_u32(state.fail())] = new_idx;
+        } else {
+            self.fail_next[usize::from_u32(prev)] = new_idx;
+        }
+        if next != NIL {
+            self.fail_prev[usize::from_u32(next)] = new_idx;
+        }
+
+        let mut idx = self.fail_head[old];
+        self.fail_head[new] = idx;
+        self.fail_head[old] = NIL;
+        while idx != NIL {
+            self.state_mut(idx).set_fail(new_idx);
+            idx = self.fail_next[usize::from_u32(idx)];
+        }
+    }
+
+    fn init_state(&mut self, idx: u32, c: u8) {
+        let state = self.state_mut(idx);
+        *state = State::default();
+        state.set_check(c);
+        self.fail_head[usize::from_u32(idx)] = NIL;
+    }
+
+    fn extend_array(&mut self) -> Result<()> {
+        self.builder.extend_array(&mut self.helper)?;
+        let len = self.builder.states.len();
+        self.fail_head.resize(len, NIL);
+        self.fail_next.resize(len, NIL);
+        self.fail_prev.resize(len, NIL);
+        Ok(())
+    }
+
+    /// Finds the failure link of the child of `parent_idx` with label `c`.
+    fn find_fail(&self, parent_idx: u32, c: u8) -> u32 {
+        if parent_idx == ROOT_STATE_IDX {
+            return ROOT_STATE_IDX;
+        }
+        let mut fail_idx = self.state(parent_idx).fail();
+        loop {
+            if let Some(child_idx) = self.child_index(fail_idx, c) {
+                return child_idx;
+            }
+            if fail_idx == ROOT_STATE_IDX {
+                return ROOT_STATE_IDX;
+            }
+            fail_idx = self.state(fail_idx).fail();
+        }
+    }
+
+    /// Replaces the failure link of `idx` and updates the output chains depending on it.
+    fn set_fail(&mut self, idx: u32, fail_idx: u32) -> Result<()> {
+        let own_output = self.has_own_output(idx);
+        self.unlink_fail(idx);
+        self.state_mut(idx).set_fail(fail_idx);
+        self.link_fail(idx);
+
+        let output_pos = self.state(fail_idx).output_pos();
+        let old_output_pos = self.state(idx).output_pos();
+        if own_output {
+            // own_output ensures that old_output_pos is Some.
+            let last = self.last_own_output(old_output_pos.unwrap());
+            self.outputs[last].parent = output_pos;
+            Ok(())
+        } else {
+            self.state_mut(idx).set_output_pos(output_pos)?;
+            self.propagate_output_pos(idx, old_output_pos)
+        }
+    }
+
+    /// Propagates the output position of `idx`, which was `old_output_pos`, to the states in the
+    /// subtree of the failure tree.
+    fn propagate_output_pos(&mut self, idx: u32, old_output_pos: Option<NonZeroU32>) -> Result<()> {
+        let output_pos = self.state(idx).output_pos();
+        if output_pos == old_output_pos {
+            return Ok(());
+        }
+        let mut stack = vec![idx];
+        while let Some(idx) = stack.pop() {
+            let mut child_idx = self.fail_head[usize::from_u32(idx)];
+            while child_idx != NIL {
+                let child_output_pos = self.state(child_idx).output_pos();
+                if child_output_pos == old_output_pos {
+                    self.state_mut(child_idx).set_output_pos(output_pos)?;
+                    stack.push(child_idx);
+                } else {
+                    // The child has its own output, which is always Some.
+                    let last = self.last_own_output(child_output_pos.unwrap());
+                    self.outputs[last].parent = output_pos;
+                }
+                child_idx = self.fail_next[usize::from_u32(child_idx)];
+            }
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn child_index(&self, state_idx: u32, c: u8) -> Option<u32> {
+        let base = self.state(state_idx).base()?;
+        let child_idx = base.get() ^ u32::from(c);
+        Some(child_idx).filter(|&idx| {
+            idx != ROOT_STATE_IDX
+                && idx != DEAD_STATE_IDX
+                && self.state(idx).check() == c
+                && self.is_used_index(idx)
+        })
+    }
+
+    /// Checks if the element is used. Elements out of the active blocks are regarded as used since
+    /// they are never reused.
+    #[inline(always)]
+    fn is_used_index(&self, idx: u32) -> bool {
+        !self.helper.active_index_range().contains(&idx) || self.helper.is_used_index(idx)
+    }
+
+    fn has_children(&self, idx: u32) -> bool {
+        (u8::MIN..=u8::MAX).any(|c| self.child_index(idx, c).is_some())
+    }
+
+    /// Returns the index of the last output of the pattern whose first output is at `output_pos`.
+    /// The following outputs belong to the suffixes.
+    fn last_own_output(&self, output_pos: NonZeroU32) -> usize {
+        let mut last = usize::from_u32(output_pos.get() - 1);
+        while let Some(parent) = self.outputs[last].parent() {
+            let parent = usize::from_u32(parent.get() - 1);
+            if self.outputs[parent].length() != self.outputs[last].length() {
+                break;
+            }
+            last = parent;
+        }
+        last
+    }
+
+    #[inline(always)]
+    fn has_own_output(&self, idx: u32) -> bool {
+        let output_pos = self.state(idx).output_pos();
+        output_pos.is_some() && output_pos != self.state(self.state(idx).fail()).output_pos()
+    }
+
+    fn push_fail_children(&self, idx: u32, stack: &mut Vec<u32>) {
+        let mut child_idx = self.fail_head[usize::from_u32(idx)];
+        while child_idx != NIL {
+            stack.push(child_idx);
+            child_idx = self.fail_next[usize::from_u32(child_idx)];
+        }
+    }
+
+    fn link_fail(&mut self, idx: u32) {
+        let fail = usize::from_u32(self.state(idx).fail());
+        let next = self.fail_head[fail];
+        self.fail_next[usize::from_u32(idx)] = next;
+        self.fail_prev[usize::from_u32(idx)] = NIL;
+        if next != NIL {
+            self.fail_prev[usize::from_u32(next)] = idx;
+        }
+        self.fail_head[fail] = idx;
+    }
+
+    fn unlink_fail(&mut self, idx: u32) {
+        let prev = self.fail_prev[usize::from_u32(idx)];
+        let next = self.fail_next[usize::from_u32(idx)];
+        if prev == NIL {
+            let fail = usize::from_u32(self.state(idx).fail());
+            self.fail_head[fail] = next;
+        } else {
+            self.fail_next[usize::from_u32(prev)] = next;
+        }
+        if next != NIL {
+            self.fail_prev[usize::from_u32(next)] = prev;
+        }
+    }
+
+    #[inline(always)]
+    fn state(&self, idx: u32) -> &State {
+        &self.builder.states[usize::from_u32(idx)]
+    }
+
+    #[inline(always)]
+    fn state_mut(&mut self, idx: u32) -> &mut State {
+        &mut self.builder.states[usize::from_u32(idx)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::string::String;
+
+    struct Xorshift(u64);
+
+    impl Xorshift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn bytes(&mut self, alphabet: &[u8], max_len: u64) -> Vec<u8> {
+            let len = self.next() % max_len + 1;
+            (0..len)
+                .map(|_| alphabet[usize::try_from(self.next()).unwrap() % alphabet.len()])
+                .collect()
+        }
+    }
+
+    fn generate_patterns(rng: &mut Xorshift, alphabet: &[u8], n: usize) -> Vec<Vec<u8>> {
+        let mut patterns = vec![];
+        while patterns.len() < n {
+            let pattern = rng.bytes(alphabet, 8);
+            if !patterns.contains(&pattern) {
+                patterns.push(pattern);
+            }
+        }
+        patterns
+    }
+
+    fn assert_same_matches(
+        expected: &DoubleArrayAhoCorasick<u32>,
+        actual: &DoubleArrayAhoCorasick<u32>,
+        haystack: &[u8],
+    ) {
+        assert_eq!(
+            expected.find_overlapping_iter(haystack).collect::<Vec<_>>(),
+            actual.find_overlapping_iter(haystack).collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            expected.find_iter(haystack).collect::<Vec<_>>(),
+            actual.find_iter(haystack).collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            expected
+                .find_overlapping_no_suffix_iter(haystack)
+                .collect::<Vec<_>>(),
+            actual
+                .find_overlapping_no_suffix_iter(haystack)
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    fn check_insertion(alphabet: &[u8], num_built: usize, num_inserted: usize) {
+        let mut rng = Xorshift(0x2545_f491_4f6c_dd1d);
+        let patterns = generate_patterns(&mut rng, alphabet, num_built + num_inserted);
+
+        let expected = DoubleArrayAhoCorasick::<u32>::new(&patterns).unwrap();
+
+        let mut editor = DoubleArrayAhoCorasick::new(&patterns[..num_built])
+            .unwrap()
+            .into_editor()
+            .unwrap();
+        for (i, pattern) in patterns.iter().enumerate().skip(num_built) {
+            editor.insert(pattern, u32::try_from(i).unwrap()).unwrap();
+        }
+        let actual = editor.finish();
+
+        assert_eq!(expected.num_states(), actual.num_states());
+        for _ in 0..100 {
+            let haystack = rng.bytes(alphabet, 100);
+            assert_same_matches(&expected, &actual, &haystack);
+        }
+    }
+
+    fn check_removal(alphabet: &[u8], num_built: usize, num_removed: usize) {
+        let mut rng = Xorshift(0x2545_f491_4f6c_dd1d);
+        let patterns = generate_patterns(&mut rng, alphabet, num_built);
+        let removed: Vec<usize> = (0..num_removed)
+            .map(|_| usize::try_from(rng.next()).unwrap() % num_built)
+            .collect();
+
+        let expected = DoubleArrayAhoCorasick::<u32>::with_values(
+            patterns
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !removed.contains(i))
+                .map(|(i, p)| (p, u32::try_from(i).unwrap())),
+        )
+        .unwrap();
+
+        let mut editor = DoubleArrayAhoCorasick::<u32>::new(&patterns)
+            .unwrap()
+            .into_editor()
+            .unwrap();
+        for (j, &i) in removed.iter().enumerate() {
+            let value = editor.remove(&patterns[i]);
+            if removed[..j].contains(&i) {
+                assert_eq!(None, value);
+            } else {
+                assert_eq!(Some(u32::try_from(i).unwrap()), value);
+            }
+        }
+        let actual = editor.finish();
+
+        for _ in 0..100 {
+            let haystack = rng.bytes(alphabet, 100);
+            assert_same_matches(&expected, &actual, &haystack);
+        }
+
+        let mut editor = actual.into_editor().unwrap();
+        editor.compact().unwrap();
+        let actual = editor.finish();
+        assert_eq!(expected.states, actual.states);
+        assert_eq!(expected.outputs, actual.outputs);
+        assert_eq!(expected.num_states, actual.num_states);
+    }
+
+    #[test]
+    fn test_insert_small_alphabet() {
+        check_insertion(b"abc", 50, 50);
+    }
+
+    #[test]
+    fn test_insert_into_single_pattern() {
+        check_insertion(b"abcd", 1, 200);
+    }
+
+    #[test]
+    fn test_insert_relocation() {
+        // A large alphabet causes many relocations and array extensions.
+        let alphabet: Vec<u8> = (u8::MIN..=u8::MAX).collect();
+        check_insertion(&alphabet, 300, 3000);
+    }
+
+    #[test]
+    fn test_insert_nothing() {
+        let patterns = vec!["abba", "baaba", "ababa"];
+        let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+        let other = pma.clone().into_editor().unwrap().finish();
+        assert_eq!(pma.states, other.states);
+        assert_eq!(pma.outputs, other.outputs);
+        assert_eq!(pma.num_states, other.num_states);
+    }
+
+    #[test]
+    fn test_insert_invalid_patterns() {
+        let pma = DoubleArrayAhoCorasick::<u32>::new(["abc", "b"]).unwrap();
+        let mut editor = pma.into_editor().unwrap();
+        assert!(editor.insert("", 2).is_err());
+        assert!(editor.insert("abc", 2).is_err());
+        assert!(editor.insert(String::from("b"), 2).is_err());
+        assert!(editor.insert("ab", 2).is_ok());
+        assert!(editor.insert("ab", 3).is_err());
+    }
+
+    #[test]
+    fn test_remove_small_alphabet() {
+        check_removal(b"abc", 100, 50);
+    }
+
+    #[test]
+    fn test_remove_large_alphabet() {
+        let alphabet: Vec<u8> = (u8::MIN..=u8::MAX).collect();
+        check_removal(&alphabet, 3000, 2000);
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut rng = Xorshift(0x2545_f491_4f6c_dd1d);
+        let alphabet: Vec<u8> = (u8::MIN..=u8::MAX).collect();
+        let patterns = generate_patterns(&mut rng, &alphabet, 2000);
+
+        // Inserts the even-numbered patterns after removing the odd-numbered ones.
+        let mut editor = DoubleArrayAhoCorasick::new(&patterns[..1000])
+            .unwrap()
+            .into_editor()
+            .unwrap();
+        for (i, pattern) in patterns.iter().enumerate() {
+            if i % 2 == 1 && i < 1000 {
+                assert_eq!(Some(u32::try_from(i).unwrap()), editor.remove(pattern));
+            } else if i % 2 == 0 && i >= 1000 {
+                editor.insert(pattern, u32::try_from(i).unwrap()).unwrap();
+            }
+        }
+        let actual = editor.finish();
+
+        let expected = DoubleArrayAhoCorasick::<u32>::with_values(
+            patterns
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| i % 2 == 0)
+                .map(|(i, p)| (p, u32::try_from(i).unwrap())),
+        )
+        .unwrap();
+        for _ in 0..100 {
+            let haystack = rng.bytes(&alphabet, 100);
+            assert_same_matches(&expected, &actual, &haystack);
+        }
+    }
+
+    #[test]
+    fn test_reopen_with_tombstones() {
+        let mut rng = Xorshift(0x2545_f491_4f6c_dd1d);
+        let alphabet: Vec<u8> = (u8::MIN..=u8::MAX).collect();
+        let patterns = generate_patterns(&mut rng, &alphabet, 3000);
+
+        // Leaves out of the free blocks are left as tombstones by the removal.
+        let pma = DoubleArrayAhoCorasickBuilder::new()
+            .num_free_blocks(1)
+            .build(&patterns[..2000])
+            .unwrap();
+        let mut editor = pma.into_editor().unwrap();
+        for pattern in patterns[..2000].iter().step_by(2) {
+            editor.remove(pattern).unwrap();
+        }
+        let mut pma = editor.finish();
+        assert_eq!(1, pma.num_free_blocks);
+        let editor = pma.clone().into_editor().unwrap();
+        assert_eq!(1, editor.helper.active_block_range().len());
+
+        // The tombstones in the reopened blocks must not be reused even though they have neither
+        // children nor outputs.
+        pma.num_free_blocks = 16;
+        let mut editor = pma.into_editor().unwrap();
+        for (i, pattern) in patterns.iter().enumerate().skip(2000) {
+            editor.insert(pattern, u32::try_from(i).unwrap()).unwrap();
+        }
+        let actual = editor.finish();
+        actual.verify().unwrap();
+
+        let expected = DoubleArrayAhoCorasick::<u32>::with_values(
+            patterns
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| i % 2 == 1 || i >= 2000)
+                .map(|(i, p)| (p, u32::try_from(i).unwrap())),
+        )
+        .unwrap();
+        for _ in 0..100 {
+            let haystack = rng.bytes(&alphabet, 100);
+            assert_same_matches(&expected, &actual, &haystack);
+        }
+    }
+
+    #[test]
+    fn test_keep_dense_states() {
+        let mut rng = Xorshift(0x2545_f491_4f6c_dd1d);
+        let alphabet = b"abcd";
+        let patterns = generate_patterns(&mut rng, alphabet, 200);
+
+        let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+            .num_dense_states(10)
+            .build(&patterns[..100])
+            .unwrap();
+        let mut editor = pma.into_editor().unwrap();
+        for (i, pattern) in patterns.iter().enumerate().skip(100) {
+            editor.insert(pattern, u32::try_from(i).unwrap()).unwrap();
+        }
+        let actual = editor.finish();
+        assert_eq!(10, actual.num_dense_states());
+
+        let expected = DoubleArrayAhoCorasick::<u32>::new(&patterns).unwrap();
+        for _ in 0..100 {
+            let haystack = rng.bytes(alphabet, 100);
+            assert_same_matches(&expected, &actual, &haystack);
+        }
+    }
+
+    #[test]
+    fn test_remove_unregistered_patterns() {
+        let pma = DoubleArrayAhoCorasick::<u32>::new(["abc", "b"]).unwrap();
+        let mut editor = pma.into_editor().unwrap();
+        assert_eq!(None, editor.remove(""));
+        assert_eq!(None, editor.remove("ab"));
+        assert_eq!(None, editor.remove("abcd"));
+        assert_eq!(None, editor.remove("c"));
+        assert_eq!(Some(0), editor.remove("abc"));
+        assert_eq!(Some(1), editor.remove("b"));
+        assert_eq!(None, editor.remove("b"));
+        assert!(editor.compact().is_err());
+
+        let pma = editor.finish();
+        assert_eq!(None, pma.find_overlapping_iter("abc").next());
+    }
+
+    #[test]
+    fn test_multiple_values() {
+        let builder =
+            || DoubleArrayAhoCorasickBuilder::new().on_duplicate(DuplicatePolicy::KeepAll);
+
+        // The new output of "bc" is propagated to the values of "abc".
+        let pma = builder()
+            .build_with_values([("abc", 1), ("bcd", 2), ("abc", 4), ("c", 3)])
+            .unwrap();
+        let mut editor = pma.into_editor().unwrap();
+        editor.insert("bc", 0).unwrap();
+        let pma = editor.finish();
+        let values: Vec<_> = pma
+            .find_overlapping_iter("abc")
+            .map(|m| m.value())
+            .collect();
+        assert_eq!(vec![1, 4, 0, 3], values);
+
+        // The failure link of "xabc" is redirected from "c" to "abc".
+        let pma = builder()
+            .build_with_values([("xabc", 7), ("c", 3), ("xabc", 8)])
+            .unwrap();
+        let mut editor = pma.into_editor().unwrap();
+        editor.insert("abc", 1).unwrap();
+        let pma = editor.finish();
+        let values: Vec<_> = pma
+            .find_overlapping_iter("xabc")
+            .map(|m| m.value())
+            .collect();
+        assert_eq!(vec![7, 8, 1, 3], values);
+
+        let mut editor = pma.into_editor().unwrap();
+        assert_eq!(Some(7), editor.remove("xabc"));
+        assert_eq!(None, editor.remove("xabc"));
+        editor.insert("xabc", 9).unwrap();
+        editor.compact().unwrap();
+        let pma = editor.finish();
+        let values: Vec<_> = pma
+            .find_overlapping_iter("xabc")
+            .map(|m| m.value())
+            .collect();
+        assert_eq!(vec![9, 1, 3], values);
+    }
+}
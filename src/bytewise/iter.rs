@@ -1,12 +1,12 @@
 //! Iterators for [`DoubleArrayAhoCorasick`].
 
-use core::iter::Enumerate;
 use core::num::NonZeroU32;
 
+use crate::bytewise::prefilter::Prefilter;
 use crate::bytewise::DoubleArrayAhoCorasick;
-use crate::Match;
+use crate::{Match, ValueCursor};
 
-use crate::bytewise::ROOT_STATE_IDX;
+use crate::bytewise::{ROOT_STATE_IDX, SPEC_COMPACT};
 use crate::utils::FromU32;
 
 /// Iterator for some struct that implements [`AsRef<[u8]>`].
@@ -24,8 +24,21 @@ where
     pub(crate) fn new(inner: P) -> Self {
         Self { inner, pos: 0 }
     }
+
+    /// Skips the bytes that cannot start a match and returns the number of skipped bytes.
+    #[inline(always)]
+    pub(crate) fn skip_by_prefilter(&mut self, prefilter: &Prefilter) -> usize {
+        // self.pos never exceeds the length of the slice.
+        let rest = unsafe { self.inner.as_ref().get_unchecked(self.pos..) };
+        let skipped = prefilter.skip_len(rest);
+        self.pos += skipped;
+        skipped
+    }
 }
 
+/// Function skipping the haystack while the automaton stays at the root.
+pub(crate) type SkipFn<P> = fn(&mut P, &Prefilter) -> usize;
+
 impl<P> Iterator for U8SliceIterator<P>
 where
     P: AsRef<[u8]>,
@@ -43,44 +56,60 @@ where
 /// Iterator created by [`DoubleArrayAhoCorasick::find_iter()`].
 pub struct FindIterator<'a, P, V> {
     pub(crate) pma: &'a DoubleArrayAhoCorasick<V>,
-    pub(crate) haystack: Enumerate<P>,
+    pub(crate) haystack: P,
+    pub(crate) pos: usize,
+    pub(crate) skip: Option<SkipFn<P>>,
+    pub(crate) spec: u8,
+    pub(crate) values: ValueCursor,
 }
 
-impl<'a, P, V> Iterator for FindIterator<'a, P, V>
+impl<'a, P, V> FindIterator<'a, P, V>
 where
     P: Iterator<Item = u8>,
     V: Copy,
 {
-    type Item = Match<V>;
-
+    // S is always given by self.pma.spec() in the Iterator implementation.
     #[inline(always)]
-    fn next(&mut self) -> Option<Self::Item> {
+    fn next_spec<const S: u8>(&mut self) -> Option<Match<V>> {
+        // self.values always holds the output positions of self.pma.
+        if let Some(m) = unsafe {
+            self.values
+                .next_as(&self.pma.outputs, S & SPEC_COMPACT != 0)
+        } {
+            return Some(m);
+        }
         let mut state_id = ROOT_STATE_IDX;
-        for (pos, c) in self.haystack.by_ref() {
-            // state_id is always smaller than self.pma.states.len() because
+        loop {
+            if state_id == ROOT_STATE_IDX {
+                if let Some(skip) = self.skip {
+                    self.pos += skip(&mut self.haystack, &self.pma.prefilter);
+                }
+            }
+            let c = self.haystack.next()?;
+            self.pos += 1;
+            // state_id is always smaller than the number of states because
             // self.pma.next_state_id_unchecked() ensures to return such a value.
-            state_id = unsafe { self.pma.next_state_id_unchecked(state_id, c) };
-            if let Some(output_pos) = unsafe {
-                self.pma
-                    .states
-                    .get_unchecked(usize::from_u32(state_id))
-                    .output_pos()
-            } {
+            state_id = unsafe { self.pma.next_state_id_unchecked::<S>(state_id, c) };
+            if let Some(output_pos) = unsafe { self.pma.output_pos_spec::<S>(state_id) } {
                 // output_pos is always smaller than self.pma.outputs.len() because
                 // State::output_pos() ensures to return such a value when it is Some.
-                let out = unsafe {
-                    self.pma
-                        .outputs
-                        .get_unchecked(usize::from_u32(output_pos.get() - 1))
-                };
-                return Some(Match {
-                    length: usize::from_u32(out.length()),
-                    end: pos + 1,
-                    value: out.value(),
-                });
+                let out = unsafe { self.pma.output_spec::<S>(output_pos) };
+                return Some(self.values.start(out, self.pos));
             }
         }
-        None
+    }
+}
+
+impl<'a, P, V> Iterator for FindIterator<'a, P, V>
+where
+    P: Iterator<Item = u8>,
+    V: Copy,
+{
+    type Item = Match<V>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        specialize!(self.spec, S => self.next_spec::<S>())
     }
 }
 
@@ -92,7 +121,8 @@ pub struct OverlappingStepperIterator<'a, V> {
 }
 
 impl<V: Copy> DoubleArrayAhoCorasick<V> {
-    ///
+    /// Transitions from `state_id` by the byte `c` and returns the next state id together with an
+    /// iterator of the overlapping matches ending at `pos`.
     #[inline(always)]
     pub fn consume(
         &self,
@@ -100,14 +130,12 @@ impl<V: Copy> DoubleArrayAhoCorasick<V> {
         pos: usize,
         c: u8,
     ) -> (u32, OverlappingStepperIterator<V>) {
-        // self.state_id is always smaller than self.pma.states.len() because
+        // self.state_id is always smaller than the number of states because
         // self.pma.next_state_id_unchecked() ensures to return such a value.
-        let state_id = unsafe { self.next_state_id_unchecked(state_id, c) };
-        let output_pos = unsafe {
-            self.states
-                .get_unchecked(usize::from_u32(state_id))
-                .output_pos()
-        };
+        let (state_id, output_pos) = specialize!(self.spec(), S => unsafe {
+            let state_id = self.next_state_id_unchecked::<S>(state_id, c);
+            (state_id, self.output_pos_spec::<S>(state_id))
+        });
         (
             state_id,
             OverlappingStepperIterator {
@@ -144,29 +172,26 @@ impl<'a, V: Copy> Iterator for OverlappingStepperIterator<'a, V> {
 /// Iterator created by [`DoubleArrayAhoCorasick::find_overlapping_iter()`].
 pub struct FindOverlappingIterator<'a, P, V> {
     pub(crate) pma: &'a DoubleArrayAhoCorasick<V>,
-    pub(crate) haystack: Enumerate<P>,
+    pub(crate) haystack: P,
     pub(crate) state_id: u32,
     pub(crate) pos: usize,
+    pub(crate) skip: Option<SkipFn<P>>,
+    pub(crate) spec: u8,
     pub(crate) output_pos: Option<NonZeroU32>,
 }
 
-impl<'a, P, V> Iterator for FindOverlappingIterator<'a, P, V>
+impl<'a, P, V> FindOverlappingIterator<'a, P, V>
 where
     P: Iterator<Item = u8>,
     V: Copy,
 {
-    type Item = Match<V>;
-
+    // S is always given by self.pma.spec() in the Iterator implementation.
     #[inline(always)]
-    fn next(&mut self) -> Option<Self::Item> {
+    fn next_spec<const S: u8>(&mut self) -> Option<Match<V>> {
         if let Some(output_pos) = self.output_pos {
             // output_pos.get() is always smaller than self.pma.outputs.len() because
             // Output::parent() ensures to return such a value when it is Some.
-            let out = unsafe {
-                self.pma
-                    .outputs
-                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
-            };
+            let out = unsafe { self.pma.output_spec::<S>(output_pos) };
             self.output_pos = out.parent();
             return Some(Match {
                 length: usize::from_u32(out.length()),
@@ -174,24 +199,21 @@ where
                 value: out.value(),
             });
         }
-        for (pos, c) in self.haystack.by_ref() {
-            // self.state_id is always smaller than self.pma.states.len() because
+        loop {
+            if self.state_id == ROOT_STATE_IDX {
+                if let Some(skip) = self.skip {
+                    self.pos += skip(&mut self.haystack, &self.pma.prefilter);
+                }
+            }
+            let c = self.haystack.next()?;
+            self.pos += 1;
+            // self.state_id is always smaller than the number of states because
             // self.pma.next_state_id_unchecked() ensures to return such a value.
-            self.state_id = unsafe { self.pma.next_state_id_unchecked(self.state_id, c) };
-            if let Some(output_pos) = unsafe {
-                self.pma
-                    .states
-                    .get_unchecked(usize::from_u32(self.state_id))
-                    .output_pos()
-            } {
-                self.pos = pos + 1;
+            self.state_id = unsafe { self.pma.next_state_id_unchecked::<S>(self.state_id, c) };
+            if let Some(output_pos) = unsafe { self.pma.output_pos_spec::<S>(self.state_id) } {
                 // output_pos.get() is always smaller than self.pma.outputs.len() because
                 // State::output_pos() ensures to return such a value when it is Some.
-                let out = unsafe {
-                    self.pma
-                        .outputs
-                        .get_unchecked(usize::from_u32(output_pos.get() - 1))
-                };
+                let out = unsafe { self.pma.output_spec::<S>(output_pos) };
                 self.output_pos = out.parent();
                 return Some(Match {
                     length: usize::from_u32(out.length()),
@@ -200,51 +222,79 @@ where
                 });
             }
         }
-        None
+    }
+}
+
+impl<'a, P, V> Iterator for FindOverlappingIterator<'a, P, V>
+where
+    P: Iterator<Item = u8>,
+    V: Copy,
+{
+    type Item = Match<V>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        specialize!(self.spec, S => self.next_spec::<S>())
     }
 }
 
 /// Iterator created by [`DoubleArrayAhoCorasick::find_overlapping_no_suffix_iter()`].
 pub struct FindOverlappingNoSuffixIterator<'a, P, V> {
     pub(crate) pma: &'a DoubleArrayAhoCorasick<V>,
-    pub(crate) haystack: Enumerate<P>,
+    pub(crate) haystack: P,
     pub(crate) state_id: u32,
+    pub(crate) pos: usize,
+    pub(crate) skip: Option<SkipFn<P>>,
+    pub(crate) spec: u8,
+    pub(crate) values: ValueCursor,
 }
 
-impl<'a, P, V> Iterator for FindOverlappingNoSuffixIterator<'a, P, V>
+impl<'a, P, V> FindOverlappingNoSuffixIterator<'a, P, V>
 where
     P: Iterator<Item = u8>,
     V: Copy,
 {
-    type Item = Match<V>;
-
+    // S is always given by self.pma.spec() in the Iterator implementation.
     #[inline(always)]
-    fn next(&mut self) -> Option<Self::Item> {
-        for (pos, c) in self.haystack.by_ref() {
-            // self.state_id is always smaller than self.pma.states.len() because
+    fn next_spec<const S: u8>(&mut self) -> Option<Match<V>> {
+        // self.values always holds the output positions of self.pma.
+        if let Some(m) = unsafe {
+            self.values
+                .next_as(&self.pma.outputs, S & SPEC_COMPACT != 0)
+        } {
+            return Some(m);
+        }
+        loop {
+            if self.state_id == ROOT_STATE_IDX {
+                if let Some(skip) = self.skip {
+                    self.pos += skip(&mut self.haystack, &self.pma.prefilter);
+                }
+            }
+            let c = self.haystack.next()?;
+            self.pos += 1;
+            // self.state_id is always smaller than the number of states because
             // self.pma.next_state_id_unchecked() ensures to return such a value.
-            self.state_id = unsafe { self.pma.next_state_id_unchecked(self.state_id, c) };
-            if let Some(output_pos) = unsafe {
-                self.pma
-                    .states
-                    .get_unchecked(usize::from_u32(self.state_id))
-                    .output_pos()
-            } {
+            self.state_id = unsafe { self.pma.next_state_id_unchecked::<S>(self.state_id, c) };
+            if let Some(output_pos) = unsafe { self.pma.output_pos_spec::<S>(self.state_id) } {
                 // output_pos is always smaller than self.pma.outputs.len() because
                 // State::output_pos() ensures to return such a value when it is Some.
-                let out = unsafe {
-                    self.pma
-                        .outputs
-                        .get_unchecked(usize::from_u32(output_pos.get() - 1))
-                };
-                return Some(Match {
-                    length: usize::from_u32(out.length()),
-                    end: pos + 1,
-                    value: out.value(),
-                });
+                let out = unsafe { self.pma.output_spec::<S>(output_pos) };
+                return Some(self.values.start(out, self.pos));
             }
         }
-        None
+    }
+}
+
+impl<'a, P, V> Iterator for FindOverlappingNoSuffixIterator<'a, P, V>
+where
+    P: Iterator<Item = u8>,
+    V: Copy,
+{
+    type Item = Match<V>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        specialize!(self.spec, S => self.next_spec::<S>())
     }
 }
 
@@ -256,66 +306,80 @@ where
     pub(crate) pma: &'a DoubleArrayAhoCorasick<V>,
     pub(crate) haystack: P,
     pub(crate) pos: usize,
+    pub(crate) spec: u8,
+    pub(crate) values: ValueCursor,
 }
 
-impl<'a, P, V> Iterator for LestmostFindIterator<'a, P, V>
+impl<'a, P, V> LestmostFindIterator<'a, P, V>
 where
     P: AsRef<[u8]>,
     V: Copy,
 {
-    type Item = Match<V>;
-
+    // S is always given by self.pma.spec() in the Iterator implementation.
     #[inline(always)]
-    fn next(&mut self) -> Option<Self::Item> {
+    fn next_spec<const S: u8>(&mut self) -> Option<Match<V>> {
+        // self.values always holds the output positions of self.pma.
+        if let Some(m) = unsafe {
+            self.values
+                .next_as(&self.pma.outputs, S & SPEC_COMPACT != 0)
+        } {
+            return Some(m);
+        }
         let mut state_id = ROOT_STATE_IDX;
         let mut last_output_pos: Option<NonZeroU32> = None;
 
         let haystack = self.haystack.as_ref();
-        for (pos, &c) in haystack.iter().enumerate().skip(self.pos) {
-            // state_id is always smaller than self.pma.states.len() because
+        let mut pos = self.pos;
+        while pos < haystack.len() {
+            if state_id == ROOT_STATE_IDX && self.pma.prefilter.is_enabled() {
+                // pos is always smaller than haystack.len().
+                pos += self
+                    .pma
+                    .prefilter
+                    .skip_len(unsafe { haystack.get_unchecked(pos..) });
+                if pos == haystack.len() {
+                    break;
+                }
+            }
+            // pos is always smaller than haystack.len().
+            let c = unsafe { *haystack.get_unchecked(pos) };
+            // state_id is always smaller than the number of states because
             // self.pma.next_state_id_leftmost_unchecked() ensures to return such a value.
-            state_id = unsafe { self.pma.next_state_id_leftmost_unchecked(state_id, c) };
+            state_id = unsafe { self.pma.next_state_id_leftmost_unchecked::<S>(state_id, c) };
             if state_id == ROOT_STATE_IDX {
                 if let Some(output_pos) = last_output_pos {
                     // last_output_pos is always smaller than self.pma.outputs.len() because
                     // State::output_pos() ensures to return such a value when it is Some.
-                    let out = unsafe {
-                        self.pma
-                            .outputs
-                            .get_unchecked(usize::from_u32(output_pos.get() - 1))
-                    };
-                    return Some(Match {
-                        length: usize::from_u32(out.length()),
-                        end: self.pos,
-                        value: out.value(),
-                    });
+                    let out = unsafe { self.pma.output_spec::<S>(output_pos) };
+                    return Some(self.values.start(out, self.pos));
                 }
-            // state_id is always smaller than self.pma.states.len() because
+            // state_id is always smaller than the number of states because
             // self.pma.next_state_id_leftmost_unchecked() ensures to return such a value.
-            } else if let Some(output_pos) = unsafe {
-                self.pma
-                    .states
-                    .get_unchecked(usize::from_u32(state_id))
-                    .output_pos()
-            } {
+            } else if let Some(output_pos) = unsafe { self.pma.output_pos_spec::<S>(state_id) } {
                 last_output_pos.replace(output_pos);
                 self.pos = pos + 1;
             }
+            pos += 1;
         }
 
         last_output_pos.map(|output_pos| {
             // last_output_pos is always smaller than self.pma.outputs.len() because
             // State::output_pos() ensures to return such a value when it is Some.
-            let out = unsafe {
-                self.pma
-                    .outputs
-                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
-            };
-            Match {
-                length: usize::from_u32(out.length()),
-                end: self.pos,
-                value: out.value(),
-            }
+            let out = unsafe { self.pma.output_spec::<S>(output_pos) };
+            self.values.start(out, self.pos)
         })
     }
 }
+
+impl<'a, P, V> Iterator for LestmostFindIterator<'a, P, V>
+where
+    P: AsRef<[u8]>,
+    V: Copy,
+{
+    type Item = Match<V>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        specialize!(self.spec, S => self.next_spec::<S>())
+    }
+}
@@ -0,0 +1,173 @@
+use alloc::vec::Vec;
+
+use crate::serializer::{self, Serializable, Sink};
+
+/// Permutation of bytes assigning smaller codes to more frequent bytes.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ByteMapper {
+    table: [u8; 256],
+    // Cached so that the search loops are specialized without comparing the table.
+    identity: bool,
+}
+
+impl Default for ByteMapper {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl ByteMapper {
+    /// Creates the mapper keeping the bytes as they are.
+    pub const fn identity() -> Self {
+        let mut table = [0; 256];
+        let mut i = 0;
+        let mut c = 0u8;
+        while i < table.len() {
+            table[i] = c;
+            c = c.wrapping_add(1);
+            i += 1;
+        }
+        Self {
+            table,
+            identity: true,
+        }
+    }
+
+    fn from_table(table: [u8; 256]) -> Self {
+        let identity = table == Self::identity().table;
+        Self { table, identity }
+    }
+
+    pub fn new(freqs: &[u32; 256]) -> Self {
+        let mut sorted: Vec<_> = (u8::MIN..=u8::MAX).zip(freqs).collect();
+        // Note: `c1.cmp(c2)` is necessary to uniquely determine the sort result.
+        sorted.sort_unstable_by(|(c1, f1), (c2, f2)| f2.cmp(f1).then_with(|| c1.cmp(c2)));
+        let mut table = [0; 256];
+        for (code, &(c, _)) in (u8::MIN..=u8::MAX).zip(&sorted) {
+            table[usize::from(c)] = code;
+        }
+        Self::from_table(table)
+    }
+
+    #[inline(always)]
+    pub fn get(&self, c: u8) -> u8 {
+        self.table[usize::from(c)]
+    }
+
+    /// Returns the table to convert codes into the original bytes.
+    pub fn inverse(&self) -> [u8; 256] {
+        let mut inverse = [0; 256];
+        for (c, &code) in (u8::MIN..=u8::MAX).zip(&self.table) {
+            inverse[usize::from(code)] = c;
+        }
+        inverse
+    }
+
+    /// Checks if every byte has a distinct code.
+    pub fn is_valid(&self) -> bool {
+        let mut used = [false; 256];
+        for &code in &self.table {
+            if used[usize::from(code)] {
+                return false;
+            }
+            used[usize::from(code)] = true;
+        }
+        true
+    }
+
+    #[inline(always)]
+    pub const fn is_identity(&self) -> bool {
+        self.identity
+    }
+
+    pub fn serialize_into<W>(&self, dst: &mut W) -> Result<(), W::Error>
+    where
+        W: Sink + ?Sized,
+    {
+        if self.is_identity() {
+            serializer::serialize_value_into(&0u8, dst)
+        } else {
+            serializer::serialize_value_into(&1u8, dst)?;
+            dst.write_bytes(&self.table)
+        }
+    }
+
+    pub fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
+        let (tag, src) = u8::deserialize_from_slice(src);
+        if tag == 0 {
+            return (Self::default(), src);
+        }
+        let table = src[..256].try_into().unwrap();
+        (Self::from_table(table), &src[256..])
+    }
+
+    pub fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])> {
+        let (tag, src) = u8::deserialize_from_slice_checked(src)?;
+        match tag {
+            0 => Some((Self::default(), src)),
+            1 => {
+                let table = src.get(..256)?.try_into().unwrap();
+                Some((Self::from_table(table), &src[256..]))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn serialized_bytes(&self) -> usize {
+        if self.is_identity() {
+            u8::serialized_bytes()
+        } else {
+            u8::serialized_bytes() + 256
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::serializer::VecSink;
+
+    #[test]
+    fn test_bytewise_byte_mapper() {
+        let mut freqs = [0; 256];
+        freqs[usize::from(b'a')] = 3;
+        freqs[usize::from(b'b')] = 6;
+        freqs[usize::from(b'c')] = 3;
+        freqs[0xff] = 4;
+        let mapper = ByteMapper::new(&freqs);
+
+        assert_eq!(0, mapper.get(b'b'));
+        assert_eq!(1, mapper.get(0xff));
+        assert_eq!(2, mapper.get(b'a'));
+        assert_eq!(3, mapper.get(b'c'));
+        // The other bytes follow in ascending order.
+        assert_eq!(4, mapper.get(0x00));
+        assert_eq!(5, mapper.get(0x01));
+        assert_eq!(255, mapper.get(0xfe));
+
+        let inverse = mapper.inverse();
+        for c in u8::MIN..=u8::MAX {
+            assert_eq!(c, inverse[usize::from(mapper.get(c))]);
+        }
+        assert!(!mapper.is_identity());
+        assert!(ByteMapper::new(&[0; 256]).is_identity());
+
+        assert!(mapper.is_valid());
+        assert!(!ByteMapper::from_table([0; 256]).is_valid());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let mut freqs = [0; 256];
+        freqs[usize::from(b'x')] = 1;
+        for mapper in [ByteMapper::default(), ByteMapper::new(&freqs)] {
+            let mut data = vec![];
+            mapper.serialize_into(&mut VecSink(&mut data)).unwrap();
+            assert_eq!(data.len(), mapper.serialized_bytes());
+            let (other, rest) = ByteMapper::deserialize_from_slice(&data);
+            assert!(rest.is_empty());
+            assert_eq!(mapper, other);
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! Parallel search of [`DoubleArrayAhoCorasick`] over large haystacks.
+
+use core::cmp;
+
+use alloc::vec::Vec;
+
+use std::thread;
+
+use crate::bytewise::DoubleArrayAhoCorasick;
+use crate::errors::{DaachorseError, Result};
+use crate::utils::FromU32;
+use crate::Match;
+
+impl<V> DoubleArrayAhoCorasick<V>
+where
+    V: Copy + Send + Sync,
+{
+    /// Returns all the overlapping matches in the given haystack, searching it with multiple
+    /// threads.
+    ///
+    /// The haystack is split into `num_threads` chunks, each of which is extended to the left by
+    /// the longest pattern length minus one so that the matches crossing chunk borders are found.
+    /// Each match is reported by the chunk containing its end position, so the result is the same
+    /// as that of [`Self::find_overlapping_iter()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `num_threads` - The number of threads.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`](crate::MatchKind::Standard) in the
+    /// construction, or `num_threads` is zero, the function will panic.
+    /// [`Self::try_par_find_overlapping()`] returns an error instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<usize>::new(patterns).unwrap();
+    ///
+    /// let matches = pma.par_find_overlapping("abcdabcd", 2);
+    ///
+    /// let expected: Vec<_> = pma.find_overlapping_iter("abcdabcd").collect();
+    /// assert_eq!(expected, matches);
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    #[must_use]
+    pub fn par_find_overlapping<P>(&self, haystack: P, num_threads: usize) -> Vec<Match<V>>
+    where
+        P: AsRef<[u8]>,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        assert!(num_threads >= 1);
+
+        let haystack = haystack.as_ref();
+        let max_length = self.max_pattern_len();
+        let bounds = chunk_bounds(haystack.len(), num_threads);
+
+        let results: Vec<Vec<Match<V>>> = thread::scope(|s| {
+            let handles: Vec<_> = bounds
+                .windows(2)
+                .map(|w| {
+                    let (begin, end) = (w[0], w[1]);
+                    // A match ending after `begin` starts at `begin + 1 - max_length` or later.
+                    let offset = begin.saturating_sub(max_length.saturating_sub(1));
+                    s.spawn(move || {
+                        self.find_overlapping_iter(&haystack[offset..end])
+                            .map(|m| shift_match(m, offset))
+                            .filter(|m| m.end > begin)
+                            .collect()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        results.concat()
+    }
+
+    /// Returns all the overlapping matches in the given haystack, searching it with multiple
+    /// threads, or an error instead of panicking as [`Self::par_find_overlapping()`] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `num_threads` - The number of threads.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when
+    ///   - [`MatchKind::Standard`](crate::MatchKind::Standard) is not specified in the
+    ///     construction, or
+    ///   - `num_threads` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<usize>::new(&patterns).unwrap();
+    ///
+    /// let matches = pma.try_par_find_overlapping("abcdabcd", 2).unwrap();
+    /// assert_eq!(pma.par_find_overlapping("abcdabcd", 2), matches);
+    /// assert!(pma.try_par_find_overlapping("abcdabcd", 0).is_err());
+    ///
+    /// let pma: DoubleArrayAhoCorasick<usize> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// assert!(pma.try_par_find_overlapping("abcdabcd", 2).is_err());
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn try_par_find_overlapping<P>(
+        &self,
+        haystack: P,
+        num_threads: usize,
+    ) -> Result<Vec<Match<V>>>
+    where
+        P: AsRef<[u8]>,
+    {
+        self.match_kind.check_standard()?;
+        if num_threads == 0 {
+            return Err(DaachorseError::invalid_argument("num_threads", ">=", 1));
+        }
+        Ok(self.par_find_overlapping(haystack, num_threads))
+    }
+
+    /// Returns all the leftmost matches in the given haystack, searching it with multiple
+    /// threads.
+    ///
+    /// Each chunk is searched speculatively from its beginning. Since a match crossing a chunk
+    /// border changes the position where the search of the next chunk has to start, the next
+    /// chunk is searched again from the end of such a match until the result coincides with the
+    /// speculative one, so the result is the same as that of [`Self::leftmost_find_iter()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `num_threads` - The number of threads.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::LeftmostFirst`](crate::MatchKind::LeftmostFirst) or
+    /// [`MatchKind::LeftmostLongest`](crate::MatchKind::LeftmostLongest) in the construction, or
+    /// `num_threads` is zero, the function will panic.
+    /// [`Self::try_par_leftmost_find()`] returns an error instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["ab", "abcd", "cdab"];
+    /// let pma: DoubleArrayAhoCorasick<usize> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let matches = pma.par_leftmost_find("abcdabcdab", 3);
+    ///
+    /// let expected: Vec<_> = pma.leftmost_find_iter("abcdabcdab").collect();
+    /// assert_eq!(expected, matches);
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    #[must_use]
+    pub fn par_leftmost_find<P>(&self, haystack: P, num_threads: usize) -> Vec<Match<V>>
+    where
+        P: AsRef<[u8]>,
+    {
+        assert!(
+            self.match_kind.is_leftmost(),
+            "Error: match_kind must be leftmost."
+        );
+        assert!(num_threads >= 1);
+
+        let haystack = haystack.as_ref();
+        let max_length = self.max_pattern_len();
+        let bounds = chunk_bounds(haystack.len(), num_threads);
+        // A match starting before `end` ends at `end - 1 + max_length` or earlier, so the search
+        // does not have to go beyond it.
+        let limit = |end: usize| cmp::min(end + max_length.saturating_sub(1), haystack.len());
+
+        // Matches starting in each chunk, assuming that the search starts at its beginning.
+        let results: Vec<Vec<Match<V>>> = thread::scope(|s| {
+            let handles: Vec<_> = bounds
+                .windows(2)
+                .map(|w| {
+                    let (begin, end) = (w[0], w[1]);
+                    s.spawn(move || {
+                        self.leftmost_find_iter(&haystack[begin..limit(end)])
+                            .map(|m| shift_match(m, begin))
+                            .take_while(|m| m.start() < end)
+                            .collect()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut matches = vec![];
+        // The position where the sequential search resumes.
+        let mut pos = 0;
+        for (w, speculative) in bounds.windows(2).zip(results) {
+            let (begin, end) = (w[0], w[1]);
+            let mut rest = &speculative[..];
+            if pos > begin {
+                // The previous match crosses the border, so the chunk is searched again until
+                // it is synchronized with the speculative result.
+                rest = &[];
+                let offset = pos;
+                for m in self.leftmost_find_iter(&haystack[offset..limit(end)]) {
+                    let m = shift_match(m, offset);
+                    if m.start() >= end {
+                        break;
+                    }
+                    // The first one is found since a pattern can have multiple values.
+                    let i = speculative.partition_point(|x| x.start() < m.start());
+                    if speculative
+                        .get(i)
+                        .map_or(false, |x| x.start() == m.start() && x.end == m.end)
+                    {
+                        rest = &speculative[i..];
+                        break;
+                    }
+                    pos = m.end;
+                    matches.push(m);
+                }
+            }
+            if let Some(m) = rest.last() {
+                pos = m.end;
+            }
+            matches.extend_from_slice(rest);
+        }
+        matches
+    }
+
+    /// Returns all the leftmost matches in the given haystack, searching it with multiple
+    /// threads, or an error instead of panicking as [`Self::par_leftmost_find()`] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `num_threads` - The number of threads.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when
+    ///   - neither [`MatchKind::LeftmostFirst`](crate::MatchKind::LeftmostFirst) nor
+    ///     [`MatchKind::LeftmostLongest`](crate::MatchKind::LeftmostLongest) is specified in the
+    ///     construction, or
+    ///   - `num_threads` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["ab", "abcd", "cdab"];
+    /// let pma: DoubleArrayAhoCorasick<usize> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let matches = pma.try_par_leftmost_find("abcdabcdab", 3).unwrap();
+    /// assert_eq!(pma.par_leftmost_find("abcdabcdab", 3), matches);
+    /// assert!(pma.try_par_leftmost_find("abcdabcdab", 0).is_err());
+    ///
+    /// let pma = DoubleArrayAhoCorasick::<usize>::new(&patterns).unwrap();
+    /// assert!(pma.try_par_leftmost_find("abcdabcdab", 3).is_err());
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn try_par_leftmost_find<P>(&self, haystack: P, num_threads: usize) -> Result<Vec<Match<V>>>
+    where
+        P: AsRef<[u8]>,
+    {
+        self.match_kind.check_leftmost()?;
+        if num_threads == 0 {
+            return Err(DaachorseError::invalid_argument("num_threads", ">=", 1));
+        }
+        Ok(self.par_leftmost_find(haystack, num_threads))
+    }
+}
+
+impl<V> DoubleArrayAhoCorasick<V>
+where
+    V: Copy,
+{
+    /// Returns the length of the longest pattern, or zero if there are no patterns.
+    fn max_pattern_len(&self) -> usize {
+        self.outputs
+            .iter()
+            .map(|out| usize::from_u32(out.length()))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Returns the `num_chunks + 1` borders splitting `len` bytes into almost equal chunks.
+fn chunk_bounds(len: usize, num_chunks: usize) -> Vec<usize> {
+    (0..=num_chunks)
+        .map(|i| {
+            // len * i / num_chunks is computed in u128 to avoid overflow.
+            let pos = u128::try_from(len).unwrap() * u128::try_from(i).unwrap()
+                / u128::try_from(num_chunks).unwrap();
+            usize::try_from(pos).unwrap()
+        })
+        .collect()
+}
+
+/// Shifts the positions of the match by `offset`.
+#[inline(always)]
+const fn shift_match<V>(m: Match<V>, offset: usize) -> Match<V>
+where
+    V: Copy,
+{
+    Match {
+        length: m.length,
+        end: m.end + offset,
+        value: m.value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bytewise::DoubleArrayAhoCorasickBuilder;
+    use crate::{DuplicatePolicy, MatchKind};
+
+    #[test]
+    fn test_chunk_bounds() {
+        assert_eq!(vec![0, 3, 6, 10], chunk_bounds(10, 3));
+        assert_eq!(vec![0, 0, 1, 1, 2], chunk_bounds(2, 4));
+        assert_eq!(vec![0, 0], chunk_bounds(0, 1));
+    }
+
+    #[test]
+    fn test_par_find_overlapping() {
+        let patterns = vec!["abab", "bab", "ba", "b", "abababab"];
+        let pma = DoubleArrayAhoCorasick::<u32>::new(&patterns).unwrap();
+        let haystack = "abababababxababababba".repeat(3);
+        let expected: Vec<_> = pma.find_overlapping_iter(&haystack).collect();
+        for num_threads in 1..=haystack.len() + 1 {
+            assert_eq!(expected, pma.par_find_overlapping(&haystack, num_threads));
+        }
+    }
+
+    #[test]
+    fn test_par_leftmost_find() {
+        let patterns = vec!["abab", "bab", "ba", "b", "abababab"];
+        for match_kind in [MatchKind::LeftmostLongest, MatchKind::LeftmostFirst] {
+            let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                .match_kind(match_kind)
+                .build(&patterns)
+                .unwrap();
+            let haystack = "abababababxababababba".repeat(3);
+            let expected: Vec<_> = pma.leftmost_find_iter(&haystack).collect();
+            for num_threads in 1..=haystack.len() + 1 {
+                assert_eq!(expected, pma.par_leftmost_find(&haystack, num_threads));
+            }
+        }
+    }
+
+    #[test]
+    fn test_par_find_random() {
+        let patvals = [
+            ("ab", 0),
+            ("ab", 1),
+            ("bba", 2),
+            ("abbab", 3),
+            ("a", 4),
+            ("bab", 5),
+        ];
+        // Generates a pseudo-random haystack of a and b.
+        let mut x = 0x2545_f491_4f6c_dd1d_u64;
+        let haystack: Vec<u8> = (0..300)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                if x % 3 == 0 {
+                    b'a'
+                } else {
+                    b'b'
+                }
+            })
+            .collect();
+        for match_kind in [
+            MatchKind::Standard,
+            MatchKind::LeftmostLongest,
+            MatchKind::LeftmostFirst,
+        ] {
+            let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                .match_kind(match_kind)
+                .on_duplicate(DuplicatePolicy::KeepAll)
+                .build_with_values(patvals)
+                .unwrap();
+            for num_threads in [1, 2, 3, 7, 16, 100] {
+                if match_kind.is_standard() {
+                    let expected: Vec<_> = pma.find_overlapping_iter(&haystack).collect();
+                    assert_eq!(expected, pma.par_find_overlapping(&haystack, num_threads));
+                } else {
+                    let expected: Vec<_> = pma.leftmost_find_iter(&haystack).collect();
+                    assert_eq!(expected, pma.par_leftmost_find(&haystack, num_threads));
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_threads() {
+        let pma = DoubleArrayAhoCorasick::<u32>::new(["a"]).unwrap();
+        let _ = pma.par_find_overlapping("a", 0);
+    }
+}
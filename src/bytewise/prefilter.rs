@@ -0,0 +1,109 @@
+//! Prefilter skipping haystack bytes that cannot start a match.
+
+use core::mem;
+
+// The number of bytes processed at once.
+const WORD_LEN: usize = mem::size_of::<usize>();
+// 0x0101...01
+const LO_BITS: usize = usize::MAX / 0xff;
+// 0x8080...80
+const HI_BITS: usize = LO_BITS << 7;
+
+// The maximum number of first bytes scanned by the prefilter.
+// Beyond this, scanning costs nearly as much as traversing the automaton.
+const MAX_NUM_BYTES: usize = 3;
+
+/// Set of bytes from which the root state has transitions.
+///
+/// Any byte not contained in the set keeps the automaton at the root, so runs of such bytes can be
+/// skipped by a word-at-a-time (SWAR) scan instead of the state transitions.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Prefilter {
+    bytes: [u8; MAX_NUM_BYTES],
+    // 0 means that the prefilter is disabled.
+    num_bytes: u8,
+}
+
+impl Prefilter {
+    /// Creates a prefilter from the first bytes of patterns.
+    ///
+    /// The prefilter is disabled if `first_bytes` is empty or contains more than 3 bytes.
+    pub fn new(first_bytes: &[u8]) -> Self {
+        if first_bytes.is_empty() || first_bytes.len() > MAX_NUM_BYTES {
+            return Self::default();
+        }
+        let mut bytes = [0; MAX_NUM_BYTES];
+        bytes[..first_bytes.len()].copy_from_slice(first_bytes);
+        Self {
+            bytes,
+            // first_bytes.len() <= MAX_NUM_BYTES
+            num_bytes: u8::try_from(first_bytes.len()).unwrap(),
+        }
+    }
+
+    #[inline(always)]
+    pub const fn is_enabled(&self) -> bool {
+        self.num_bytes != 0
+    }
+
+    /// Returns the number of leading bytes of `haystack` not contained in the set.
+    #[inline(always)]
+    pub fn skip_len(&self, haystack: &[u8]) -> usize {
+        let bytes = &self.bytes[..usize::from(self.num_bytes)];
+        if bytes.is_empty() {
+            return 0;
+        }
+        let mut chunks = haystack.chunks_exact(WORD_LEN);
+        let mut pos = 0;
+        for chunk in chunks.by_ref() {
+            let word = usize::from_ne_bytes(chunk.try_into().unwrap());
+            if bytes
+                .iter()
+                .any(|&b| has_zero_byte(word ^ (LO_BITS * usize::from(b))))
+            {
+                break;
+            }
+            pos += WORD_LEN;
+        }
+        pos + haystack[pos..]
+            .iter()
+            .position(|c| bytes.contains(c))
+            .unwrap_or(haystack.len() - pos)
+    }
+}
+
+/// Checks if any byte of `x` is zero.
+#[inline(always)]
+const fn has_zero_byte(x: usize) -> bool {
+    x.wrapping_sub(LO_BITS) & !x & HI_BITS != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_len() {
+        let haystack = b"the quick brown fox jumps over the lazy dog";
+        for first_bytes in [&b"q"[..], b"zx", b"jyv", b"!", b"d"] {
+            let prefilter = Prefilter::new(first_bytes);
+            assert!(prefilter.is_enabled());
+            for start in 0..haystack.len() {
+                let expected = haystack[start..]
+                    .iter()
+                    .position(|c| first_bytes.contains(c))
+                    .unwrap_or(haystack.len() - start);
+                assert_eq!(expected, prefilter.skip_len(&haystack[start..]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_disabled() {
+        for first_bytes in [&b""[..], b"abcd"] {
+            let prefilter = Prefilter::new(first_bytes);
+            assert!(!prefilter.is_enabled());
+            assert_eq!(0, prefilter.skip_len(b"xyz"));
+        }
+    }
+}
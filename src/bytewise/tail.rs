@@ -0,0 +1,337 @@
+//! Tails storing the suffixes of the trie having no branches out of the double array.
+
+use core::mem;
+use core::num::NonZeroU32;
+
+use alloc::vec::Vec;
+
+use crate::intpack::PackedVec;
+use crate::serializer::{SerializableVec, Sink};
+use crate::utils::FromU32;
+
+// The number of bits of the label in each entry.
+const LABEL_BITS: u32 = u8::BITS;
+// The flag in each entry indicating the first position of a tail.
+const FIRST_FLAG: u64 = 1 << LABEL_BITS;
+// The shift of the fail in each entry.
+const FAIL_SHIFT: u32 = LABEL_BITS + 1;
+
+/// A position in a tail, given to [`Tails::new()`].
+#[derive(Clone, Copy)]
+pub struct TailPosition {
+    pub(crate) label: u8,
+    pub(crate) is_first: bool,
+    pub(crate) fail: u32,
+    pub(crate) output_pos: Option<NonZeroU32>,
+}
+
+/// Single-branch suffixes of the trie, each of which is stored as a sequence of positions.
+///
+/// A state in a tail has the id `offset + i`, where `offset` is the length of the double array
+/// and `i` is its position. The position following a non-last one is its only child, so only the
+/// labels are compared in the goto traversal. The fails and outputs are kept for every position
+/// so that the failure transitions are the same as those in the double array.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Tails {
+    // The label, the flag of the first position, and the fail of each position, packed from the
+    // lowest bits.
+    entries: PackedVec,
+    // Bits indicating the positions having outputs.
+    output_bits: Vec<u64>,
+    // output_ranks[i] is the number of set bits in output_bits[..i].
+    output_ranks: Vec<u32>,
+    // The output positions of the positions having outputs, in order.
+    output_pos: PackedVec,
+}
+
+impl Tails {
+    pub fn new(positions: &[TailPosition]) -> Self {
+        let entries: Vec<_> = positions
+            .iter()
+            .map(|p| {
+                let first = if p.is_first { FIRST_FLAG } else { 0 };
+                u64::from(p.fail) << FAIL_SHIFT | first | u64::from(p.label)
+            })
+            .collect();
+
+        let word_bits = usize::from_u32(u64::BITS);
+        let mut output_bits = vec![0; (positions.len() + word_bits - 1) / word_bits];
+        let mut output_ranks = Vec::with_capacity(output_bits.len());
+        let mut output_pos = vec![];
+        for (i, p) in positions.iter().enumerate() {
+            if i % word_bits == 0 {
+                output_ranks.push(u32::try_from(output_pos.len()).unwrap());
+            }
+            if let Some(pos) = p.output_pos {
+                output_bits[i / word_bits] |= 1 << (i % word_bits);
+                output_pos.push(u64::from(pos.get()));
+            }
+        }
+
+        Self {
+            entries: PackedVec::new(&entries),
+            output_bits,
+            output_ranks,
+            output_pos: PackedVec::new(&output_pos),
+        }
+    }
+
+    /// Returns the number of positions.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        usize::from_u32(self.entries.len())
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.entries.len() == 0
+    }
+
+    /// Checks if the label of the first position `pos` of a tail is `c`.
+    ///
+    /// # Safety
+    ///
+    /// `pos` must be smaller than the length.
+    #[inline(always)]
+    pub unsafe fn is_first_label(&self, pos: usize, c: u8) -> bool {
+        self.entries.get_unchecked(pos) & u64::from(u8::MAX) == u64::from(c)
+    }
+
+    /// Checks if the position following `pos` is in the same tail and labeled with `c`.
+    ///
+    /// # Safety
+    ///
+    /// `pos` must be smaller than the length.
+    #[inline(always)]
+    pub unsafe fn has_next(&self, pos: usize, c: u8) -> bool {
+        let next = pos + 1;
+        next < self.len()
+            && self.entries.get_unchecked(next) & (FIRST_FLAG | u64::from(u8::MAX)) == u64::from(c)
+    }
+
+    /// # Safety
+    ///
+    /// `pos` must be smaller than the length.
+    #[inline(always)]
+    pub unsafe fn fail(&self, pos: usize) -> u32 {
+        // The fail was given as a u32 value.
+        u32::try_from(self.entries.get_unchecked(pos) >> FAIL_SHIFT).unwrap_unchecked()
+    }
+
+    /// # Safety
+    ///
+    /// `pos` must be smaller than the length.
+    #[inline(always)]
+    pub unsafe fn output_pos(&self, pos: usize) -> Option<NonZeroU32> {
+        let word_bits = usize::from_u32(u64::BITS);
+        let word = *self.output_bits.get_unchecked(pos / word_bits);
+        let bit = pos % word_bits;
+        if word >> bit & 1 == 0 {
+            return None;
+        }
+        let rank = *self.output_ranks.get_unchecked(pos / word_bits)
+            + (word & ((1 << bit) - 1)).count_ones();
+        // The output position was given as a NonZeroU32 value.
+        NonZeroU32::new(
+            u32::try_from(self.output_pos.get_unchecked(usize::from_u32(rank))).unwrap_unchecked(),
+        )
+    }
+
+    /// Converts the fails of all positions with `f`.
+    pub fn map_fails<F>(&mut self, f: F)
+    where
+        F: Fn(u32) -> u32,
+    {
+        let entries: Vec<_> = (0..self.len())
+            .map(|pos| {
+                // pos is smaller than the length.
+                let (entry, fail) = unsafe { (self.entries.get_unchecked(pos), self.fail(pos)) };
+                u64::from(f(fail)) << FAIL_SHIFT | entry & (FIRST_FLAG | u64::from(u8::MAX))
+            })
+            .collect();
+        self.entries = PackedVec::new(&entries);
+    }
+
+    /// Checks if the packed entries and the output bits are consistent, which the unchecked
+    /// accessors rely on.
+    pub fn is_valid(&self) -> bool {
+        let word_bits = usize::from_u32(u64::BITS);
+        if !self.entries.is_valid()
+            || !self.output_pos.is_valid()
+            // The fails and output positions are u32 values.
+            || self.entries.width() > FAIL_SHIFT + u32::BITS
+            || self.output_pos.width() > u32::BITS
+            || self.output_bits.len() != (self.len() + word_bits - 1) / word_bits
+            || self.output_ranks.len() != self.output_bits.len()
+        {
+            return false;
+        }
+        let mut rank = 0;
+        for (&word, &word_rank) in self.output_bits.iter().zip(&self.output_ranks) {
+            if word_rank != rank {
+                return false;
+            }
+            rank += word.count_ones();
+        }
+        rank == self.output_pos.len()
+    }
+
+    pub fn heap_bytes(&self) -> usize {
+        self.entries.heap_bytes()
+            + self.output_bits.len() * mem::size_of::<u64>()
+            + self.output_ranks.len() * mem::size_of::<u32>()
+            + self.output_pos.heap_bytes()
+    }
+
+    pub fn serialize_into<W>(&self, dst: &mut W) -> Result<(), W::Error>
+    where
+        W: Sink + ?Sized,
+    {
+        self.entries.serialize_into(dst)?;
+        self.output_bits.serialize_into(dst)?;
+        self.output_ranks.serialize_into(dst)?;
+        self.output_pos.serialize_into(dst)
+    }
+
+    pub fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
+        let (entries, src) = PackedVec::deserialize_from_slice(src);
+        let (output_bits, src) = Vec::<u64>::deserialize_from_slice(src);
+        let (output_ranks, src) = Vec::<u32>::deserialize_from_slice(src);
+        let (output_pos, src) = PackedVec::deserialize_from_slice(src);
+        (
+            Self {
+                entries,
+                output_bits,
+                output_ranks,
+                output_pos,
+            },
+            src,
+        )
+    }
+
+    pub fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])> {
+        let (entries, src) = PackedVec::deserialize_from_slice_checked(src)?;
+        let (output_bits, src) = Vec::<u64>::deserialize_from_slice_checked(src)?;
+        let (output_ranks, src) = Vec::<u32>::deserialize_from_slice_checked(src)?;
+        let (output_pos, src) = PackedVec::deserialize_from_slice_checked(src)?;
+        Some((
+            Self {
+                entries,
+                output_bits,
+                output_ranks,
+                output_pos,
+            },
+            src,
+        ))
+    }
+
+    pub fn serialized_bytes(&self) -> usize {
+        self.entries.serialized_bytes()
+            + self.output_bits.serialized_bytes()
+            + self.output_ranks.serialized_bytes()
+            + self.output_pos.serialized_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::serializer::VecSink;
+
+    #[test]
+    fn test_tails() {
+        // Two tails "abc" and "de".
+        let positions: Vec<_> = (0..5)
+            .map(|i| TailPosition {
+                label: b"abcde"[i],
+                is_first: i == 0 || i == 3,
+                fail: u32::try_from(i).unwrap() * 1000,
+                output_pos: NonZeroU32::new(u32::try_from(i % 2 * (i + 1)).unwrap()),
+            })
+            .collect();
+        let tails = Tails::new(&positions);
+        assert_eq!(5, tails.len());
+        unsafe {
+            assert!(tails.is_first_label(0, b'a'));
+            assert!(!tails.is_first_label(0, b'b'));
+            assert!(tails.has_next(0, b'b'));
+            assert!(!tails.has_next(0, b'c'));
+            assert!(tails.has_next(1, b'c'));
+            // The next position belongs to another tail.
+            assert!(!tails.has_next(2, b'd'));
+            assert!(tails.has_next(3, b'e'));
+            assert!(!tails.has_next(4, b'e'));
+            for (i, p) in positions.iter().enumerate() {
+                assert_eq!(p.fail, tails.fail(i));
+                assert_eq!(p.output_pos, tails.output_pos(i));
+            }
+        }
+        assert!(tails.is_valid());
+    }
+
+    #[test]
+    fn test_tails_output_ranks() {
+        let positions: Vec<_> = (0..1000)
+            .map(|i| TailPosition {
+                label: 0,
+                is_first: i % 10 == 0,
+                fail: 0,
+                output_pos: NonZeroU32::new(if i % 3 == 0 { i + 1 } else { 0 }),
+            })
+            .collect();
+        let tails = Tails::new(&positions);
+        for (i, p) in positions.iter().enumerate() {
+            assert_eq!(p.output_pos, unsafe { tails.output_pos(i) });
+        }
+        assert!(tails.is_valid());
+
+        let mut broken = tails.clone();
+        broken.output_ranks[3] += 1;
+        assert!(!broken.is_valid());
+        let mut broken = tails;
+        broken.output_bits[15] |= 1 << 63;
+        assert!(!broken.is_valid());
+    }
+
+    #[test]
+    fn test_tails_map_fails() {
+        let positions: Vec<_> = (0..10)
+            .map(|i| TailPosition {
+                label: u8::try_from(i).unwrap(),
+                is_first: i % 4 == 0,
+                fail: i,
+                output_pos: None,
+            })
+            .collect();
+        let mut tails = Tails::new(&positions);
+        tails.map_fails(|fail| fail * 1000);
+        for (i, p) in positions.iter().enumerate() {
+            assert_eq!(p.fail * 1000, unsafe { tails.fail(i) });
+        }
+        unsafe {
+            assert!(tails.is_first_label(4, 4));
+            assert!(tails.has_next(4, 5));
+            assert!(!tails.has_next(3, 4));
+        }
+    }
+
+    #[test]
+    fn test_serialize() {
+        let positions: Vec<_> = (0..100)
+            .map(|i| TailPosition {
+                label: u8::try_from(i).unwrap(),
+                is_first: i % 7 == 0,
+                fail: i * 3,
+                output_pos: NonZeroU32::new(i % 5),
+            })
+            .collect();
+        let tails = Tails::new(&positions);
+        let mut bytes = vec![];
+        tails.serialize_into(&mut VecSink(&mut bytes)).unwrap();
+        assert_eq!(tails.serialized_bytes(), bytes.len());
+        let (other, rest) = Tails::deserialize_from_slice(&bytes);
+        assert!(rest.is_empty());
+        assert_eq!(tails, other);
+    }
+}
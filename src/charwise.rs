@@ -4,19 +4,26 @@ mod builder;
 pub mod iter;
 mod mapper;
 
+use core::fmt::Debug;
 use core::mem;
 use core::num::NonZeroU32;
 
+use alloc::string::String;
 use alloc::vec::Vec;
 
-use crate::errors::Result;
-use crate::serializer::{Serializable, SerializableVec};
+use crate::dot::DotWriter;
+use crate::errors::{DaachorseError, Result};
+use crate::layout::{self, BlockRelocation, LayoutStats};
+use crate::outputs::OutputStore;
+use crate::serializer::{self, Serializable, SerializableVec, Sink, VecSink};
+use crate::stats::{AutomatonStats, StatsCollector};
 use crate::utils::FromU32;
-use crate::{MatchKind, Output};
+use crate::{DuplicatePolicy, MatchKind, ValueCursor};
 pub use builder::CharwiseDoubleArrayAhoCorasickBuilder;
 use iter::{
     CharWithEndOffsetIterator, FindIterator, FindOverlappingIterator,
-    FindOverlappingNoSuffixIterator, LestmostFindIterator, StrIterator,
+    FindOverlappingNoSuffixIterator, LestmostFindIterator, StrIterator, Utf8CheckedIterator,
+    Utf8CheckedMatchIterator,
 };
 use mapper::CodeMapper;
 
@@ -24,6 +31,8 @@ use mapper::CodeMapper;
 const ROOT_STATE_IDX: u32 = 0;
 // The dead index position.
 const DEAD_STATE_IDX: u32 = 1;
+// The depth of states unreachable from the root.
+const UNREACHABLE_DEPTH: u32 = u32::MAX;
 
 /// A fast multiple pattern match automaton implemented with the Aho-Corasick algorithm and
 /// character-wise double-array data structure.
@@ -58,7 +67,7 @@ const DEAD_STATE_IDX: u32 = 1;
 pub struct CharwiseDoubleArrayAhoCorasick<V> {
     states: Vec<State>,
     mapper: CodeMapper,
-    outputs: Vec<Output<V>>,
+    outputs: OutputStore<V>,
     match_kind: MatchKind,
     num_states: u32,
 }
@@ -160,6 +169,7 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     ///
     /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
     /// supported and the function will panic.
+    /// [`Self::try_find_iter()`] returns an error instead.
     ///
     /// # Examples
     ///
@@ -190,9 +200,52 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
         FindIterator {
             pma: self,
             haystack: unsafe { CharWithEndOffsetIterator::new(StrIterator::new(haystack)) },
+            values: ValueCursor::default(),
         }
     }
 
+    /// Returns an iterator of non-overlapping matches in the given haystack,
+    /// or an error instead of panicking as [`Self::find_iter()`] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when [`MatchKind::Standard`] is not specified in the
+    /// construction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{
+    ///     CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, MatchKind,
+    /// };
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new().build(&patterns).unwrap();
+    ///
+    /// let mut it = pma.try_find_iter("全世界中に").unwrap();
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let pma: CharwiseDoubleArrayAhoCorasick<u32> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// assert!(pma.try_find_iter("全世界中に").is_err());
+    /// ```
+    pub fn try_find_iter<P>(&self, haystack: P) -> Result<FindIterator<'_, StrIterator<P>, V>>
+    where
+        P: AsRef<str>,
+    {
+        self.match_kind.check_standard()?;
+        Ok(self.find_iter(haystack))
+    }
+
     /// Returns an iterator of non-overlapping matches in the given haystack iterator.
     ///
     /// # Arguments
@@ -203,6 +256,7 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     ///
     /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
     /// supported and the function will panic.
+    /// [`Self::try_find_iter_from_iter()`] returns an error instead.
     ///
     /// # Safety
     ///
@@ -239,9 +293,67 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
         FindIterator {
             pma: self,
             haystack: CharWithEndOffsetIterator::new(haystack),
+            values: ValueCursor::default(),
         }
     }
 
+    /// Returns an iterator of non-overlapping matches in the given haystack iterator,
+    /// or an error instead of panicking as [`Self::find_iter_from_iter()`] does.
+    ///
+    /// Unlike [`Self::find_iter_from_iter()`], this function is safe because `haystack` is
+    /// validated as UTF-8 while it is consumed. The iterator yields the matches in the valid
+    /// prefix of `haystack`, and then an error if an invalid sequence follows.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when [`MatchKind::Standard`] is not specified in the
+    /// construction. [`DaachorseError::InvalidUtf8`] is yielded by the iterator when `haystack`
+    /// does not represent a valid UTF-8 string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::CharwiseDoubleArrayAhoCorasickBuilder;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new().build(&patterns).unwrap();
+    ///
+    /// let haystack = "全世界".as_bytes().iter().chain("中に".as_bytes()).copied();
+    ///
+    /// let mut it = pma.try_find_iter_from_iter(haystack).unwrap();
+    ///
+    /// let m = it.next().unwrap().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// // The last character is truncated.
+    /// let haystack = "世界全".as_bytes()[..8].iter().copied();
+    /// let mut it = pma.try_find_iter_from_iter(haystack).unwrap();
+    ///
+    /// let m = it.next().unwrap().unwrap();
+    /// assert_eq!((0, 6, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// assert!(it.next().unwrap().is_err());
+    /// assert!(it.next().is_none());
+    /// ```
+    pub fn try_find_iter_from_iter<P>(
+        &self,
+        haystack: P,
+    ) -> Result<Utf8CheckedMatchIterator<FindIterator<'_, Utf8CheckedIterator<P>, V>>>
+    where
+        P: Iterator<Item = u8>,
+    {
+        self.match_kind.check_standard()?;
+        // Utf8CheckedIterator yields only the valid UTF-8 prefix of the haystack.
+        Ok(Utf8CheckedMatchIterator {
+            inner: unsafe { self.find_iter_from_iter(Utf8CheckedIterator::new(haystack)) },
+            done: false,
+        })
+    }
+
     /// Returns an iterator of overlapping matches in the given haystack.
     ///
     /// # Arguments
@@ -252,6 +364,7 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     ///
     /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
     /// supported and the function will panic.
+    /// [`Self::try_find_overlapping_iter()`] returns an error instead.
     ///
     /// # Examples
     ///
@@ -294,6 +407,51 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
         }
     }
 
+    /// Returns an iterator of overlapping matches in the given haystack,
+    /// or an error instead of panicking as [`Self::find_overlapping_iter()`] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when [`MatchKind::Standard`] is not specified in the
+    /// construction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{
+    ///     CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, MatchKind,
+    /// };
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new().build(&patterns).unwrap();
+    ///
+    /// let mut it = pma.try_find_overlapping_iter("全世界中に").unwrap();
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let pma: CharwiseDoubleArrayAhoCorasick<u32> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// assert!(pma.try_find_overlapping_iter("全世界中に").is_err());
+    /// ```
+    pub fn try_find_overlapping_iter<P>(
+        &self,
+        haystack: P,
+    ) -> Result<FindOverlappingIterator<'_, StrIterator<P>, V>>
+    where
+        P: AsRef<str>,
+    {
+        self.match_kind.check_standard()?;
+        Ok(self.find_overlapping_iter(haystack))
+    }
+
     /// Returns an iterator of overlapping matches in the given haystack iterator.
     ///
     /// # Arguments
@@ -304,6 +462,7 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     ///
     /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
     /// supported and the function will panic.
+    /// [`Self::try_find_overlapping_iter_from_iter()`] returns an error instead.
     ///
     /// # Safety
     ///
@@ -352,6 +511,65 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
         }
     }
 
+    /// Returns an iterator of overlapping matches in the given haystack iterator,
+    /// or an error instead of panicking as [`Self::find_overlapping_iter_from_iter()`] does.
+    ///
+    /// Unlike [`Self::find_overlapping_iter_from_iter()`], this function is safe because `haystack` is
+    /// validated as UTF-8 while it is consumed. The iterator yields the matches in the valid
+    /// prefix of `haystack`, and then an error if an invalid sequence follows.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when [`MatchKind::Standard`] is not specified in the
+    /// construction. [`DaachorseError::InvalidUtf8`] is yielded by the iterator when `haystack`
+    /// does not represent a valid UTF-8 string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::CharwiseDoubleArrayAhoCorasickBuilder;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new().build(&patterns).unwrap();
+    ///
+    /// let haystack = "全世界".as_bytes().iter().chain("中に".as_bytes()).copied();
+    ///
+    /// let mut it = pma.try_find_overlapping_iter_from_iter(haystack).unwrap();
+    ///
+    /// let m = it.next().unwrap().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// // The last character is truncated.
+    /// let haystack = "世界全".as_bytes()[..8].iter().copied();
+    /// let mut it = pma.try_find_overlapping_iter_from_iter(haystack).unwrap();
+    ///
+    /// let m = it.next().unwrap().unwrap();
+    /// assert_eq!((0, 6, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// assert!(it.next().unwrap().is_err());
+    /// assert!(it.next().is_none());
+    /// ```
+    pub fn try_find_overlapping_iter_from_iter<P>(
+        &self,
+        haystack: P,
+    ) -> Result<Utf8CheckedMatchIterator<FindOverlappingIterator<'_, Utf8CheckedIterator<P>, V>>>
+    where
+        P: Iterator<Item = u8>,
+    {
+        self.match_kind.check_standard()?;
+        // Utf8CheckedIterator yields only the valid UTF-8 prefix of the haystack.
+        Ok(Utf8CheckedMatchIterator {
+            inner: unsafe {
+                self.find_overlapping_iter_from_iter(Utf8CheckedIterator::new(haystack))
+            },
+            done: false,
+        })
+    }
+
     /// Returns an iterator of overlapping matches without suffixes in the given haystack.
     ///
     /// The Aho-Corasick algorithm reads through the haystack from left to right and reports
@@ -368,6 +586,7 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     ///
     /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
     /// supported and the function will call panic!.
+    /// [`Self::try_find_overlapping_no_suffix_iter()`] returns an error instead.
     ///
     /// # Examples
     ///
@@ -402,9 +621,55 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
             pma: self,
             haystack: unsafe { CharWithEndOffsetIterator::new(StrIterator::new(haystack)) },
             state_id: ROOT_STATE_IDX,
+            values: ValueCursor::default(),
         }
     }
 
+    /// Returns an iterator of overlapping matches without suffixes in the given haystack,
+    /// or an error instead of panicking as [`Self::find_overlapping_no_suffix_iter()`] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when [`MatchKind::Standard`] is not specified in the
+    /// construction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{
+    ///     CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, MatchKind,
+    /// };
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new().build(&patterns).unwrap();
+    ///
+    /// let mut it = pma.try_find_overlapping_no_suffix_iter("全世界中に").unwrap();
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let pma: CharwiseDoubleArrayAhoCorasick<u32> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// assert!(pma.try_find_overlapping_no_suffix_iter("全世界中に").is_err());
+    /// ```
+    pub fn try_find_overlapping_no_suffix_iter<P>(
+        &self,
+        haystack: P,
+    ) -> Result<FindOverlappingNoSuffixIterator<'_, StrIterator<P>, V>>
+    where
+        P: AsRef<str>,
+    {
+        self.match_kind.check_standard()?;
+        Ok(self.find_overlapping_no_suffix_iter(haystack))
+    }
+
     /// Returns an iterator of overlapping matches without suffixes in the given haystack iterator.
     ///
     /// The Aho-Corasick algorithm reads through the haystack from left to right and reports
@@ -421,6 +686,7 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     ///
     /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
     /// supported and the function will panic.
+    /// [`Self::try_find_overlapping_no_suffix_iter_from_iter()`] returns an error instead.
     ///
     /// # Safety
     ///
@@ -461,9 +727,71 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
             pma: self,
             haystack: CharWithEndOffsetIterator::new(haystack),
             state_id: ROOT_STATE_IDX,
+            values: ValueCursor::default(),
         }
     }
 
+    /// Returns an iterator of overlapping matches without suffixes in the given haystack iterator,
+    /// or an error instead of panicking as [`Self::find_overlapping_no_suffix_iter_from_iter()`] does.
+    ///
+    /// Unlike [`Self::find_overlapping_no_suffix_iter_from_iter()`], this function is safe because `haystack` is
+    /// validated as UTF-8 while it is consumed. The iterator yields the matches in the valid
+    /// prefix of `haystack`, and then an error if an invalid sequence follows.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when [`MatchKind::Standard`] is not specified in the
+    /// construction. [`DaachorseError::InvalidUtf8`] is yielded by the iterator when `haystack`
+    /// does not represent a valid UTF-8 string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::CharwiseDoubleArrayAhoCorasickBuilder;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new().build(&patterns).unwrap();
+    ///
+    /// let haystack = "全世界".as_bytes().iter().chain("中に".as_bytes()).copied();
+    ///
+    /// let mut it = pma.try_find_overlapping_no_suffix_iter_from_iter(haystack).unwrap();
+    ///
+    /// let m = it.next().unwrap().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// // The last character is truncated.
+    /// let haystack = "世界全".as_bytes()[..8].iter().copied();
+    /// let mut it = pma.try_find_overlapping_no_suffix_iter_from_iter(haystack).unwrap();
+    ///
+    /// let m = it.next().unwrap().unwrap();
+    /// assert_eq!((0, 6, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// assert!(it.next().unwrap().is_err());
+    /// assert!(it.next().is_none());
+    /// ```
+    pub fn try_find_overlapping_no_suffix_iter_from_iter<P>(
+        &self,
+        haystack: P,
+    ) -> Result<
+        Utf8CheckedMatchIterator<FindOverlappingNoSuffixIterator<'_, Utf8CheckedIterator<P>, V>>,
+    >
+    where
+        P: Iterator<Item = u8>,
+    {
+        self.match_kind.check_standard()?;
+        // Utf8CheckedIterator yields only the valid UTF-8 prefix of the haystack.
+        Ok(Utf8CheckedMatchIterator {
+            inner: unsafe {
+                self.find_overlapping_no_suffix_iter_from_iter(Utf8CheckedIterator::new(haystack))
+            },
+            done: false,
+        })
+    }
+
     /// Returns an iterator of leftmost matches in the given haystack.
     ///
     /// The leftmost match greedily searches the longest possible match at each iteration, and the
@@ -487,6 +815,7 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     ///
     /// If you do not specify [`MatchKind::LeftmostFirst`] or [`MatchKind::LeftmostLongest`] in the
     /// construction, the iterator is not supported and the function will call panic!.
+    /// [`Self::try_leftmost_find_iter()`] returns an error instead.
     ///
     /// # Examples
     ///
@@ -539,7 +868,156 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
             pma: self,
             haystack,
             pos: 0,
+            values: ValueCursor::default(),
+        }
+    }
+
+    /// Returns an iterator of leftmost matches in the given haystack, or an error instead of
+    /// panicking as [`Self::leftmost_find_iter()`] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when neither [`MatchKind::LeftmostLongest`] nor
+    /// [`MatchKind::LeftmostFirst`] is specified in the construction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{
+    ///     CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, MatchKind,
+    /// };
+    ///
+    /// let patterns = vec!["世界", "世", "世界中に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.try_leftmost_find_iter("世界中に").unwrap();
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 12, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let pma: CharwiseDoubleArrayAhoCorasick<u32> =
+    ///     CharwiseDoubleArrayAhoCorasickBuilder::new().build(&patterns).unwrap();
+    ///
+    /// assert!(pma.try_leftmost_find_iter("世界中に").is_err());
+    /// ```
+    pub fn try_leftmost_find_iter<P>(&self, haystack: P) -> Result<LestmostFindIterator<'_, P, V>>
+    where
+        P: AsRef<str>,
+    {
+        self.match_kind.check_leftmost()?;
+        Ok(self.leftmost_find_iter(haystack))
+    }
+
+    /// Merges multiple automata into a new one.
+    ///
+    /// The patterns are reconstructed from the automata and rebuilt with the same match kind, so
+    /// the original pattern lists are not needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `pmas` - List of automata to be merged.
+    /// * `policy` - Policy to resolve the values of patterns appearing in multiple automata.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`](super::errors::DaachorseError) is returned when
+    ///   - `pmas` is empty,
+    ///   - `pmas` are built with different match kinds,
+    ///   - `pmas` contain the same pattern and [`DuplicatePolicy::Error`] is specified, or
+    ///   - the scale of the resulting automaton exceeds the expected one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{CharwiseDoubleArrayAhoCorasick, DuplicatePolicy};
+    ///
+    /// let pma1 = CharwiseDoubleArrayAhoCorasick::with_values([("全世界", 1), ("世界", 2)]).unwrap();
+    /// let pma2 = CharwiseDoubleArrayAhoCorasick::with_values([("世界", 4), ("に", 8)]).unwrap();
+    ///
+    /// let pma = CharwiseDoubleArrayAhoCorasick::merge(&[&pma1, &pma2], DuplicatePolicy::KeepLast)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.find_overlapping_iter("全世界中に");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((3, 9, 4), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((12, 15, 8), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn merge(pmas: &[&Self], policy: DuplicatePolicy<V>) -> Result<Self>
+    where
+        V: Copy,
+    {
+        let match_kind = pmas
+            .first()
+            .ok_or_else(|| DaachorseError::invalid_argument("pmas.len()", ">=", 1))?
+            .match_kind;
+        for pma in pmas {
+            pma.match_kind.check_same(match_kind)?;
         }
+        CharwiseDoubleArrayAhoCorasickBuilder::new()
+            .match_kind(match_kind)
+            .on_duplicate(policy)
+            .build_with_values(pmas.iter().flat_map(|pma| pma.patterns()))
+    }
+
+    /// Reconstructs the pattern-value pairs.
+    ///
+    /// Patterns are returned in the reverse of the depth-first order, so every pattern precedes its
+    /// prefixes and is kept even when rebuilt with [`MatchKind::LeftmostFirst`].
+    fn patterns(&self) -> Vec<(String, V)>
+    where
+        V: Copy,
+    {
+        let inverse = self.mapper.inverse();
+        let mut patvals = vec![];
+        let mut pattern = String::new();
+        let mut stack = vec![(ROOT_STATE_IDX, 0, '\0')];
+        while let Some((state_id, len, c)) = stack.pop() {
+            pattern.truncate(len);
+            if state_id != ROOT_STATE_IDX {
+                pattern.push(c);
+            }
+            let state = &self.states[usize::from_u32(state_id)];
+            // The outputs with the same length as the pattern are its own values, which are
+            // pushed in reverse since the whole list is reversed at the end.
+            let mut values = vec![];
+            let mut output_pos = state.output_pos();
+            while let Some(pos) = output_pos {
+                let output = self.outputs.get(usize::from_u32(pos.get() - 1));
+                if usize::from_u32(output.length()) != pattern.len() {
+                    break;
+                }
+                values.push(output.value());
+                output_pos = output.parent();
+            }
+            for &value in values.iter().rev() {
+                patvals.push((pattern.clone(), value));
+            }
+            if let Some(base) = state.base() {
+                for (code, &c) in (0..self.mapper.alphabet_size()).zip(&inverse).rev() {
+                    let child_idx = base.get() ^ code;
+                    if self.states[usize::from_u32(child_idx)].check() == state_id {
+                        stack.push((child_idx, pattern.len(), c));
+                    }
+                }
+            }
+        }
+        patvals.reverse();
+        patvals
     }
 
     /// Returns the total number of states this automaton has.
@@ -576,6 +1054,323 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
         self.states.len()
     }
 
+    /// Returns the locality of the states in the double array, weighting each state with a
+    /// heuristic value that prefers shallow states with many children.
+    ///
+    /// See [`LayoutStats`] for the details, and
+    /// [`CharwiseDoubleArrayAhoCorasickBuilder::reorder_states()`] for improving the locality.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let stats = pma.layout_stats();
+    /// assert_eq!(4, stats.block_len());
+    /// assert_eq!(2, stats.num_blocks());
+    /// ```
+    #[must_use]
+    pub fn layout_stats(&self) -> LayoutStats {
+        let block_len = self.block_len();
+        let weights = self.heuristic_state_weights();
+        layout::layout_stats(&layout::block_weights(&weights, block_len), block_len)
+    }
+
+    /// Returns the locality of the states in the double array, weighting each state with the
+    /// number of its visits in searching `sample`.
+    ///
+    /// See [`LayoutStats`] for the details, and
+    /// [`CharwiseDoubleArrayAhoCorasickBuilder::reorder_sample()`] for improving the locality.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample` - Sample haystack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let stats = pma.layout_stats_with_sample("全世界中に");
+    /// assert_eq!(4, stats.total_weight());
+    /// ```
+    #[must_use]
+    pub fn layout_stats_with_sample<P>(&self, sample: P) -> LayoutStats
+    where
+        P: AsRef<str>,
+    {
+        let block_len = self.block_len();
+        let weights = self.sample_state_weights(sample.as_ref());
+        layout::layout_stats(&layout::block_weights(&weights, block_len), block_len)
+    }
+
+    /// Returns the statistics of the automaton, which is useful for tuning the options of
+    /// [`CharwiseDoubleArrayAhoCorasickBuilder`] and comparing builds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let stats = pma.stats();
+    /// assert_eq!(3, stats.num_patterns());
+    /// assert_eq!(7, stats.num_states());
+    /// assert_eq!(Some(4), stats.alphabet_size());
+    /// assert_eq!(pma.num_elements(), stats.num_elements());
+    /// assert_eq!(3, stats.max_depth());
+    /// assert_eq!(
+    ///     pma.heap_bytes(),
+    ///     stats.states_bytes() + stats.outputs_bytes() + stats.mapper_bytes(),
+    /// );
+    /// ```
+    #[must_use]
+    pub fn stats(&self) -> AutomatonStats
+    where
+        V: Copy,
+    {
+        let (order, depths) = self.breadth_first_states();
+        let mut collector = StatsCollector::default();
+        for &state_id in &order {
+            let state = &self.states[usize::from_u32(state_id)];
+            let depth = depths[usize::from_u32(state_id)];
+            let mut output_pos = state.output_pos();
+            let (fail_depth, has_pattern) = if state_id == ROOT_STATE_IDX {
+                (None, false)
+            } else {
+                let fail = &self.states[usize::from_u32(state.fail())];
+                let fail_depth = depths[usize::from_u32(state.fail())];
+                // The states without their own outputs inherit those of the failure states.
+                (
+                    Some(fail_depth).filter(|&d| d != UNREACHABLE_DEPTH),
+                    output_pos.is_some() && output_pos != fail.output_pos(),
+                )
+            };
+            let mut num_outputs = 0;
+            while let Some(pos) = output_pos {
+                num_outputs += 1;
+                output_pos = self.outputs.get(usize::from_u32(pos.get() - 1)).parent();
+            }
+            collector.add_state(depth, fail_depth, num_outputs, has_pattern);
+        }
+        AutomatonStats {
+            num_elements: self.states.len(),
+            // The dead state is also used.
+            num_vacant_elements: self.states.len() - order.len() - 1,
+            states_bytes: self.states.len() * mem::size_of::<State>(),
+            outputs_bytes: self.outputs.heap_bytes(),
+            mapper_bytes: self.mapper.heap_bytes(),
+            alphabet_size: Some(self.mapper.alphabet_size()),
+            ..collector.finish()
+        }
+    }
+
+    /// Returns the states reachable from the root in breadth-first order and the depths of all
+    /// the states, where the unreachable ones have [`UNREACHABLE_DEPTH`].
+    fn breadth_first_states(&self) -> (Vec<u32>, Vec<u32>) {
+        let mut depths = vec![UNREACHABLE_DEPTH; self.states.len()];
+        depths[usize::from_u32(ROOT_STATE_IDX)] = 0;
+        let mut order = vec![ROOT_STATE_IDX];
+        let mut qi = 0;
+        while let Some(&state_id) = order.get(qi) {
+            qi += 1;
+            let depth = depths[usize::from_u32(state_id)];
+            for mapped_c in 0..self.mapper.alphabet_size() {
+                // state_id is always smaller than the length of states.
+                if let Some(child_id) = unsafe { self.child_index_unchecked(state_id, mapped_c) } {
+                    depths[usize::from_u32(child_id)] = depth + 1;
+                    order.push(child_id);
+                }
+            }
+        }
+        (order, depths)
+    }
+
+    /// Returns the automaton in the DOT language of [Graphviz](https://graphviz.org/), which is
+    /// useful for debugging the matching behavior.
+    ///
+    /// Each state is drawn with its id, the escaped characters from the root, and the values
+    /// reported when it is reached. Goto edges are labeled with the characters decoded from their
+    /// codes, and failure edges are dashed. In leftmost automata, the failure edges of the states
+    /// that must not fall back point to the dead state.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_states` - Maximum number of states to draw. The states are drawn in breadth-first
+    ///   order, and the number of the omitted ones is noted in the graph.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{
+    ///     CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, MatchKind,
+    /// };
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma: CharwiseDoubleArrayAhoCorasick<u32> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(patterns)
+    ///     .unwrap();
+    ///
+    /// let dot = pma.to_dot(100);
+    /// assert!(dot.contains("[label=\"世\"];"));
+    /// assert!(dot.contains("\\\"全世界\\\"\\n[0]"));
+    /// assert!(dot.contains("1 [label=\"1\\nDEAD\", shape=box];"));
+    /// ```
+    #[must_use]
+    pub fn to_dot(&self, max_states: usize) -> String
+    where
+        V: Copy + Debug,
+    {
+        let inverse = self.mapper.inverse();
+        let mut writer = DotWriter::new(DEAD_STATE_IDX);
+        // Every failure state is shallower than the state, so it is drawn before.
+        let mut queue = vec![(ROOT_STATE_IDX, String::new())];
+        queue.truncate(max_states);
+        let mut qi = 0;
+        while let Some((state_id, path)) = queue.get_mut(qi).map(|(s, p)| (*s, mem::take(p))) {
+            qi += 1;
+            let state = &self.states[usize::from_u32(state_id)];
+            let values = core::iter::successors(state.output_pos(), |pos| {
+                self.outputs.get(usize::from_u32(pos.get() - 1)).parent()
+            })
+            .map(|pos| self.outputs.get(usize::from_u32(pos.get() - 1)).value());
+            writer.add_state(state_id, &path, values);
+            if state_id != ROOT_STATE_IDX {
+                writer.add_fail(state_id, state.fail());
+            }
+            for (mapped_c, &c) in (0..self.mapper.alphabet_size()).zip(&inverse) {
+                if queue.len() == max_states {
+                    break;
+                }
+                // state_id is always smaller than the length of states.
+                if let Some(child_id) = unsafe { self.child_index_unchecked(state_id, mapped_c) } {
+                    let label: String = c.escape_debug().collect();
+                    writer.add_goto(state_id, child_id, &label);
+                    queue.push((child_id, path.clone() + &label));
+                }
+            }
+        }
+        writer.finish(self.num_states() - queue.len())
+    }
+
+    /// Verifies the invariants of the automaton that the search relies on, which is useful in
+    /// tests and after loading untrusted data with [`Self::deserialize_unchecked()`].
+    ///
+    /// The following invariants are verified:
+    ///
+    /// - the codes of the characters are distinct and smaller than the alphabet size,
+    /// - the BASE value of every state points to a block in the double array,
+    /// - every state reachable from the root is the child of a single state,
+    /// - the failure link of every reachable state points to a shallower state, or to the dead
+    ///   state in leftmost automata,
+    /// - the outputs form acyclic chains ending in [`None`], and
+    /// - the number of states is that of the reachable ones.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when an invariant is violated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    /// assert!(pma.verify().is_ok());
+    ///
+    /// let bytes = pma.serialize();
+    /// let (other, _) =
+    ///     unsafe { CharwiseDoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+    /// assert!(other.verify().is_ok());
+    /// ```
+    pub fn verify(&self) -> Result<()>
+    where
+        V: Copy,
+    {
+        let error =
+            |invariant, state_id| Err(DaachorseError::invalid_automaton(invariant, state_id));
+        if !self.mapper.is_valid() {
+            return error(
+                "codes are distinct and smaller than the alphabet size",
+                None,
+            );
+        }
+        let block_len = usize::from_u32(self.block_len());
+        if self.states.is_empty() || self.states.len() % block_len != 0 {
+            return error("the double array consists of blocks", None);
+        }
+        let num_states = match u32::try_from(self.states.len()) {
+            Ok(num_states) => num_states,
+            Err(_) => return error("state ids fit in u32", None),
+        };
+        self.outputs.verify()?;
+        for (state_id, state) in (0..).zip(&self.states) {
+            if state.base().map_or(false, |base| num_states <= base.get()) {
+                return error("BASE values point to the states", Some(state_id));
+            }
+        }
+
+        // The depths are measured in bytes to be compared with the lengths of the outputs.
+        let inverse = self.mapper.inverse();
+        let mut depths = vec![UNREACHABLE_DEPTH; self.states.len()];
+        depths[usize::from_u32(ROOT_STATE_IDX)] = 0;
+        let mut order = vec![ROOT_STATE_IDX];
+        let mut qi = 0;
+        while let Some(&state_id) = order.get(qi) {
+            qi += 1;
+            let depth = depths[usize::from_u32(state_id)];
+            for (mapped_c, &c) in (0..self.mapper.alphabet_size()).zip(&inverse) {
+                // state_id is smaller than the length of states, and so is the child index
+                // because the BASE values and the block length are verified above.
+                if let Some(child_id) = unsafe { self.child_index_unchecked(state_id, mapped_c) } {
+                    let child_depth = &mut depths[usize::from_u32(child_id)];
+                    if child_id == DEAD_STATE_IDX || *child_depth != UNREACHABLE_DEPTH {
+                        return error("every state has a single parent", Some(child_id));
+                    }
+                    // The length of a character is at most 4.
+                    *child_depth = depth + u32::try_from(c.len_utf8()).unwrap();
+                    order.push(child_id);
+                }
+            }
+        }
+        if order.len() != self.num_states() {
+            return error("num_states is the number of reachable states", None);
+        }
+
+        for &state_id in &order {
+            let state = &self.states[usize::from_u32(state_id)];
+            let depth = depths[usize::from_u32(state_id)];
+            if state_id != ROOT_STATE_IDX {
+                let fail_id = state.fail();
+                let is_dead = fail_id == DEAD_STATE_IDX && self.match_kind.is_leftmost();
+                if !is_dead
+                    && depths
+                        .get(usize::from_u32(fail_id))
+                        .map_or(true, |&d| depth <= d)
+                {
+                    return error("fail links point to shallower states", Some(state_id));
+                }
+            }
+            if let Some(pos) = state.output_pos() {
+                let pos = usize::from_u32(pos.get() - 1);
+                if self.outputs.len() <= pos || depth < self.outputs.get(pos).length() {
+                    return error("outputs are suffixes of the states", Some(state_id));
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Returns the total amount of heap used by this automaton in bytes.
     ///
     /// # Examples
@@ -592,11 +1387,35 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     pub fn heap_bytes(&self) -> usize {
         self.states.len() * mem::size_of::<State>()
             + self.mapper.heap_bytes()
-            + self.outputs.len() * mem::size_of::<Output<V>>()
+            + self.outputs.heap_bytes()
     }
 
     /// Serializes the automaton into a [`Vec`].
     ///
+    /// The serialized data does not depend on the platform: every integer is stored in
+    /// little-endian, and `usize` and `isize` values are stored as 64-bit integers. Thus, an
+    /// automaton serialized on one platform can be deserialized on another one with a different
+    /// endianness or pointer width, as long as the values fit in the pointer width.
+    ///
+    /// # Format
+    ///
+    /// The data consists of the following fields in order, where the integers, vectors, and
+    /// outputs are written as described in
+    /// [`DoubleArrayAhoCorasick::serialize()`](crate::DoubleArrayAhoCorasick::serialize).
+    ///
+    /// | Field | Layout |
+    /// |---|---|
+    /// | magic | The 8 bytes `DAACCHAR`. |
+    /// | version | `u32`, which is 1 in this release. |
+    /// | states | Vector of states, each of which is the base as `u32` (0 if none), the check as `u32`, the fail as `u32`, and the output position as `u32` (0 if none). |
+    /// | mapper | Vector of `u32` holding the code of each character, or `u32::MAX` if it is not used, followed by the alphabet size as `u32`. |
+    /// | outputs | The same as that of [`DoubleArrayAhoCorasick`](crate::DoubleArrayAhoCorasick). |
+    /// | match_kind | `u8`: 0 for `Standard`, 1 for `LeftmostLongest`, and 2 for `LeftmostFirst`. |
+    /// | num_states | `u32`. |
+    ///
+    /// The version is incremented whenever the layout changes, and the loaders reject data of the
+    /// other versions.
+    ///
     /// # Examples
     ///
     /// ```
@@ -605,25 +1424,79 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     /// let patterns = vec!["全世界", "世界", "に"];
     /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
     /// let bytes = pma.serialize();
+    /// assert_eq!(b"DAACCHAR", &bytes[..8]);
     /// ```
     #[must_use]
     pub fn serialize(&self) -> Vec<u8>
     where
         V: Serializable,
     {
-        let mut result = Vec::with_capacity(
-            self.states.serialized_bytes()
-                + self.mapper.serialized_bytes()
-                + self.outputs.serialized_bytes()
-                + MatchKind::serialized_bytes()
-                + u32::serialized_bytes(),
-        );
-        self.states.serialize_to_vec(&mut result);
-        self.mapper.serialize_to_vec(&mut result);
-        self.outputs.serialize_to_vec(&mut result);
-        self.match_kind.serialize_to_vec(&mut result);
-        self.num_states.serialize_to_vec(&mut result);
-        result
+        let mut result = Vec::with_capacity(self.serialized_len());
+        match self.serialize_into(&mut VecSink(&mut result)) {
+            Ok(()) => result,
+            Err(e) => match e {},
+        }
+    }
+
+    /// Serializes the automaton into the given sink, writing the same bytes as
+    /// [`Self::serialize()`] without holding the whole serialized data in memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - A destination such as a file.
+    ///
+    /// # Errors
+    ///
+    /// The error of the sink is returned when writing fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{CharwiseDoubleArrayAhoCorasick, VecSink};
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let mut bytes = Vec::with_capacity(pma.serialized_len());
+    /// pma.serialize_into(&mut VecSink(&mut bytes)).unwrap();
+    /// assert_eq!(pma.serialize(), bytes);
+    /// ```
+    pub fn serialize_into<W>(&self, sink: &mut W) -> Result<(), W::Error>
+    where
+        V: Serializable,
+        W: Sink + ?Sized,
+    {
+        serializer::serialize_header_into(&serializer::CHARWISE_MAGIC, sink)?;
+        self.states.serialize_into(sink)?;
+        self.mapper.serialize_into(sink)?;
+        self.outputs.serialize_into(sink)?;
+        serializer::serialize_value_into(&self.match_kind, sink)?;
+        serializer::serialize_value_into(&self.num_states, sink)
+    }
+
+    /// Returns the length of the serialized automaton in bytes, i.e., the number of bytes written
+    /// by [`Self::serialize_into()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    /// assert_eq!(pma.serialize().len(), pma.serialized_len());
+    /// ```
+    #[must_use]
+    pub fn serialized_len(&self) -> usize
+    where
+        V: Serializable,
+    {
+        serializer::header_bytes()
+            + self.states.serialized_bytes()
+            + self.mapper.serialized_bytes()
+            + self.outputs.serialized_bytes()
+            + MatchKind::serialized_bytes()
+            + u32::serialized_bytes()
     }
 
     /// Deserializes the automaton from a given slice.
@@ -640,6 +1513,11 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     ///
     /// The given data must be a correct automaton exported by
     /// [`CharwiseDoubleArrayAhoCorasick::serialize()`] function.
+    /// Use [`Self::deserialize()`] to load untrusted data.
+    ///
+    /// # Panics
+    ///
+    /// It panics if the data does not start with the header of the supported format.
     ///
     /// # Examples
     ///
@@ -650,7 +1528,8 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
     /// let bytes = pma.serialize();
     ///
-    /// let (pma, _) = unsafe { CharwiseDoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+    /// let (pma, _) =
+    ///     unsafe { CharwiseDoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
     ///
     /// let mut it = pma.find_overlapping_iter("全世界中に");
     ///
@@ -670,9 +1549,10 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     where
         V: Serializable,
     {
+        let source = serializer::deserialize_header_unchecked(&serializer::CHARWISE_MAGIC, source);
         let (states, source) = Vec::<State>::deserialize_from_slice(source);
         let (mapper, source) = CodeMapper::deserialize_from_slice(source);
-        let (outputs, source) = Vec::<Output<V>>::deserialize_from_slice(source);
+        let (outputs, source) = OutputStore::<V>::deserialize_from_slice(source);
         let (match_kind, source) = MatchKind::deserialize_from_slice(source);
         let (num_states, source) = u32::deserialize_from_slice(source);
         (
@@ -687,6 +1567,157 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
         )
     }
 
+    /// Deserializes the automaton from a given slice, checking that the data is well-formed and
+    /// satisfies the invariants verified by [`Self::verify()`].
+    ///
+    /// Unlike [`Self::deserialize_unchecked()`], this function is safe to call with untrusted data.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - A source slice.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of the automaton and the slice not used for the deserialization.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when
+    ///   - the data does not start with the header of the supported format version,
+    ///   - the data is truncated or malformed, or
+    ///   - an invariant of the automaton is violated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    /// let bytes = pma.serialize();
+    ///
+    /// let (pma, rest) = CharwiseDoubleArrayAhoCorasick::<u32>::deserialize(&bytes).unwrap();
+    /// assert!(rest.is_empty());
+    /// assert_eq!(bytes, pma.serialize());
+    ///
+    /// assert!(CharwiseDoubleArrayAhoCorasick::<u32>::deserialize(&bytes[..bytes.len() - 1]).is_err());
+    /// ```
+    pub fn deserialize(source: &[u8]) -> Result<(Self, &[u8])>
+    where
+        V: Serializable + Copy,
+    {
+        let malformed =
+            || DaachorseError::invalid_automaton("the serialized data is well-formed", None);
+        let source = serializer::deserialize_header(&serializer::CHARWISE_MAGIC, source)?;
+        let (states, source) =
+            Vec::<State>::deserialize_from_slice_checked(source).ok_or_else(malformed)?;
+        let (mapper, source) =
+            CodeMapper::deserialize_from_slice_checked(source).ok_or_else(malformed)?;
+        let (outputs, source) =
+            OutputStore::<V>::deserialize_from_slice_checked(source).ok_or_else(malformed)?;
+        let (match_kind, source) =
+            MatchKind::deserialize_from_slice_checked(source).ok_or_else(malformed)?;
+        let (num_states, source) =
+            u32::deserialize_from_slice_checked(source).ok_or_else(malformed)?;
+        let pma = Self {
+            states,
+            mapper,
+            outputs,
+            match_kind,
+            num_states,
+        };
+        pma.verify()?;
+        Ok((pma, source))
+    }
+
+    /// Returns the length of the blocks in the double array.
+    fn block_len(&self) -> u32 {
+        self.mapper.alphabet_size().next_power_of_two().max(2)
+    }
+
+    /// Relocates the blocks of the double array so that the blocks of states visited frequently
+    /// come first. The states are weighted with the visits in `sample` if given, or otherwise with
+    /// the heuristic values.
+    pub(crate) fn reorder_blocks(&mut self, sample: Option<&str>) {
+        let block_len = self.block_len();
+        let weights = sample.map_or_else(
+            || self.heuristic_state_weights(),
+            |sample| self.sample_state_weights(sample),
+        );
+        let relocation =
+            BlockRelocation::new(&layout::block_weights(&weights, block_len), block_len);
+        if relocation.is_identity() {
+            return;
+        }
+        for state in &mut self.states {
+            if let Some(base) = state.base() {
+                // The first block is not moved, so non-zero values remain non-zero.
+                state.set_base(NonZeroU32::new(relocation.map(base.get())).unwrap());
+            }
+            state.set_check(relocation.map(state.check()));
+            state.set_fail(relocation.map(state.fail()));
+        }
+        relocation.apply(&mut self.states);
+    }
+
+    /// Weights each state with a heuristic value based on its depth and number of children.
+    fn heuristic_state_weights(&self) -> Vec<u64> {
+        let alphabet_size = self.mapper.alphabet_size();
+        let mut weights = vec![0; self.states.len()];
+        let mut stack = vec![(ROOT_STATE_IDX, 0)];
+        let mut children = vec![];
+        while let Some((state_id, depth)) = stack.pop() {
+            children.clear();
+            for mapped_c in 0..alphabet_size {
+                // state_id is always smaller than the length of states.
+                if let Some(child_id) = unsafe { self.child_index_unchecked(state_id, mapped_c) } {
+                    children.push(child_id);
+                }
+            }
+            // The number of children is at most the alphabet size.
+            let num_children = u32::try_from(children.len()).unwrap();
+            weights[usize::from_u32(state_id)] =
+                layout::heuristic_weight(depth, num_children, alphabet_size);
+            stack.extend(children.iter().map(|&child_id| (child_id, depth + 1)));
+        }
+        weights
+    }
+
+    /// Weights each state with the number of its visits in searching `sample`, including the
+    /// visits in failure transitions.
+    fn sample_state_weights(&self, sample: &str) -> Vec<u64> {
+        let mut weights = vec![0; self.states.len()];
+        let mut state_id = ROOT_STATE_IDX;
+        for c in sample.chars() {
+            let mapped_c = match self.mapper.get(c) {
+                Some(mapped_c) => mapped_c,
+                None => {
+                    state_id = ROOT_STATE_IDX;
+                    continue;
+                }
+            };
+            // In the loop, state_id is always set to values smaller than the length of states,
+            // because child_index_unchecked() and fail() return such values.
+            loop {
+                weights[usize::from_u32(state_id)] += 1;
+                if let Some(child_id) = unsafe { self.child_index_unchecked(state_id, mapped_c) } {
+                    state_id = child_id;
+                    break;
+                }
+                if state_id == ROOT_STATE_IDX {
+                    break;
+                }
+                state_id = self.states[usize::from_u32(state_id)].fail();
+                // Only leftmost automata have the failure links to the dead state.
+                if state_id == DEAD_STATE_IDX {
+                    state_id = ROOT_STATE_IDX;
+                    break;
+                }
+            }
+        }
+        weights
+    }
+
     /// # Safety
     ///
     /// `state_id` must be smaller than the length of states.
@@ -827,24 +1858,26 @@ impl State {
 impl Serializable for State {
     #[inline(always)]
     fn serialize_to_vec(&self, dst: &mut Vec<u8>) {
-        self.base.serialize_to_vec(dst);
+        self.base.map_or(0, NonZeroU32::get).serialize_to_vec(dst);
         self.check.serialize_to_vec(dst);
         self.fail.serialize_to_vec(dst);
-        self.output_pos.serialize_to_vec(dst);
+        self.output_pos
+            .map_or(0, NonZeroU32::get)
+            .serialize_to_vec(dst);
     }
 
     #[inline(always)]
     fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
-        let (base, src) = Option::<NonZeroU32>::deserialize_from_slice(src);
+        let (base, src) = u32::deserialize_from_slice(src);
         let (check, src) = u32::deserialize_from_slice(src);
         let (fail, src) = u32::deserialize_from_slice(src);
-        let (output_pos, src) = Option::<NonZeroU32>::deserialize_from_slice(src);
+        let (output_pos, src) = u32::deserialize_from_slice(src);
         (
             Self {
-                base,
+                base: NonZeroU32::new(base),
                 check,
                 fail,
-                output_pos,
+                output_pos: NonZeroU32::new(output_pos),
             },
             src,
         )
@@ -852,10 +1885,7 @@ impl Serializable for State {
 
     #[inline(always)]
     fn serialized_bytes() -> usize {
-        Option::<NonZeroU32>::serialized_bytes()
-            + u32::serialized_bytes()
-            + u32::serialized_bytes()
-            + Option::<NonZeroU32>::serialized_bytes()
+        u32::serialized_bytes() * 4
     }
 }
 
@@ -1066,4 +2096,124 @@ mod tests {
         assert_eq!(pma.match_kind, other.match_kind);
         assert_eq!(pma.num_states, other.num_states);
     }
+
+    #[test]
+    fn test_merge() {
+        let pma1 = CharwiseDoubleArrayAhoCorasick::with_values([
+            ("全世界", 0),
+            ("世界中", 1),
+            ("世界", 2),
+        ])
+        .unwrap();
+        let pma2 =
+            CharwiseDoubleArrayAhoCorasick::with_values([("世界", 3), ("全世界中", 4), ("全", 6)])
+                .unwrap();
+        for (policy, value) in [
+            (DuplicatePolicy::KeepFirst, 2),
+            (DuplicatePolicy::KeepLast, 3),
+            (DuplicatePolicy::Merge(|a, b| a + b), 5),
+        ] {
+            let expected = CharwiseDoubleArrayAhoCorasick::with_values([
+                ("全世界", 0),
+                ("世界中", 1),
+                ("世界", value),
+                ("全世界中", 4),
+                ("全", 6),
+            ])
+            .unwrap();
+            let merged = CharwiseDoubleArrayAhoCorasick::merge(&[&pma1, &pma2], policy).unwrap();
+            assert_eq!(expected.states, merged.states);
+            assert_eq!(expected.outputs, merged.outputs);
+            assert_eq!(expected.num_states, merged.num_states);
+        }
+    }
+
+    #[test]
+    fn test_merge_leftmost() {
+        for match_kind in [MatchKind::LeftmostLongest, MatchKind::LeftmostFirst] {
+            let builder = || CharwiseDoubleArrayAhoCorasickBuilder::new().match_kind(match_kind);
+            let pma1 = builder()
+                .build_with_values([("全世界", 0), ("世界中", 1), ("世界", 2)])
+                .unwrap();
+            let pma2 = builder()
+                .build_with_values([("世界", 3), ("全世界中", 4), ("全", 6)])
+                .unwrap();
+            let expected = builder()
+                .build_with_values([
+                    ("全世界", 0),
+                    ("世界中", 1),
+                    ("世界", 2),
+                    ("全世界中", 4),
+                    ("全", 6),
+                ])
+                .unwrap();
+            let merged =
+                CharwiseDoubleArrayAhoCorasick::merge(&[&pma1, &pma2], DuplicatePolicy::KeepFirst)
+                    .unwrap();
+            assert_eq!(expected.states, merged.states);
+            assert_eq!(expected.outputs, merged.outputs);
+            assert_eq!(expected.match_kind, merged.match_kind);
+        }
+    }
+
+    #[test]
+    fn test_merge_empty() {
+        assert!(
+            CharwiseDoubleArrayAhoCorasick::<u32>::merge(&[], DuplicatePolicy::KeepFirst).is_err()
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let patterns = vec!["全世界", "世界", "世", "x"];
+        let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(&patterns).unwrap();
+        assert!(pma.verify().is_ok());
+
+        // The children of the root are smaller than the length of states.
+        let child = |c| unsafe {
+            pma.child_index_unchecked(ROOT_STATE_IDX, pma.mapper.get(c).unwrap())
+                .unwrap()
+        };
+        let (zen, se, x) = (child('全'), child('世'), child('x'));
+        let state = |id| usize::from_u32(id);
+        let assert_invalid =
+            |invariant, corrupt: &dyn Fn(&mut CharwiseDoubleArrayAhoCorasick<u32>)| {
+                let mut other = pma.clone();
+                corrupt(&mut other);
+                let e = other.verify().expect_err(invariant);
+                assert!(format!("{e}").contains(invariant), "{e}");
+            };
+
+        // Failure link to a state at the same depth
+        assert_invalid("fail", &|pma| pma.states[state(zen)].set_fail(se));
+        // Failure link to the dead state in a standard automaton
+        assert_invalid("fail", &|pma| {
+            pma.states[state(zen)].set_fail(DEAD_STATE_IDX);
+        });
+        // BASE out of the states
+        assert_invalid("BASE", &|pma| {
+            pma.states[state(x)].set_base(NonZeroU32::new(u32::MAX).unwrap());
+        });
+        // Check of a vacant element, which makes the state unreachable
+        assert_invalid("num_states", &|pma| {
+            pma.states[state(x)].set_check(DEAD_STATE_IDX);
+        });
+        // Output longer than the state
+        assert_invalid("outputs are suffixes", &|pma| {
+            let pos = pma
+                .outputs
+                .iter()
+                .position(|out| out.length() == 9)
+                .unwrap();
+            pma.states[state(zen)].set_output_pos(NonZeroU32::new(u32::try_from(pos).unwrap() + 1));
+        });
+        // Wrong number of states
+        assert_invalid("num_states", &|pma| pma.num_states += 1);
+
+        let pma = CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(&patterns)
+            .unwrap();
+        assert!(pma.verify().is_ok());
+    }
 }
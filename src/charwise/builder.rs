@@ -1,35 +1,50 @@
+use core::mem;
 use core::num::NonZeroU32;
 
+use alloc::boxed::Box;
+use alloc::string::String;
 use alloc::vec::Vec;
 
 use crate::charwise::{CharwiseDoubleArrayAhoCorasick, CodeMapper, MatchKind, State};
-use crate::errors::{DaachorseError, Result};
-use crate::nfa_builder::NfaBuilder;
-use crate::utils::FromU32;
-use crate::BuildHelper;
+use crate::errors::{BuildError, DaachorseError, Result};
+use crate::nfa_builder::{NfaBuilder, NfaView};
+use crate::outputs::OutputStore;
+use crate::sorted_nfa_builder::SortedNfaBuilder;
+use crate::utils::{self, FromU32};
+use crate::{BuildHelper, BuildPhase, BuildProgress, DuplicatePolicy, ProgressReporter};
 
 use crate::charwise::{DEAD_STATE_IDX, ROOT_STATE_IDX};
 use crate::nfa_builder::{DEAD_STATE_ID, ROOT_STATE_ID};
 
 // Specialized [`NfaBuilder`] handling labels of `char`.
 type CharwiseNfaBuilder<V> = NfaBuilder<char, V>;
+type CharwiseSortedNfaBuilder<V> = SortedNfaBuilder<char, V>;
 
 /// Builder for [`CharwiseDoubleArrayAhoCorasick`].
-pub struct CharwiseDoubleArrayAhoCorasickBuilder {
+///
+/// `V` is the type of the values of the automaton to be built. It is usually inferred from the use
+/// of the automaton, and defaults to `usize` where the builder type is written without it.
+pub struct CharwiseDoubleArrayAhoCorasickBuilder<V = usize> {
     states: Vec<State>,
     mapper: CodeMapper,
     match_kind: MatchKind,
+    duplicate_policy: DuplicatePolicy<V>,
     block_len: u32,
     num_free_blocks: u32,
+    compact_outputs: bool,
+    reorder_states: bool,
+    reorder_sample: Option<String>,
+    memory_budget: Option<usize>,
+    progress: ProgressReporter,
 }
 
-impl Default for CharwiseDoubleArrayAhoCorasickBuilder {
+impl<V> Default for CharwiseDoubleArrayAhoCorasickBuilder<V> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl CharwiseDoubleArrayAhoCorasickBuilder {
+impl<V> CharwiseDoubleArrayAhoCorasickBuilder<V> {
     /// Creates a new [`CharwiseDoubleArrayAhoCorasickBuilder`].
     ///
     /// # Examples
@@ -58,8 +73,14 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
             states: vec![],
             mapper: CodeMapper::default(),
             match_kind: MatchKind::Standard,
+            duplicate_policy: DuplicatePolicy::Error,
             block_len: 0,
             num_free_blocks: 16,
+            compact_outputs: false,
+            reorder_states: false,
+            reorder_sample: None,
+            memory_budget: None,
+            progress: ProgressReporter::new(None),
         }
     }
 
@@ -74,6 +95,41 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
         self
     }
 
+    /// Specifies [`DuplicatePolicy`] to handle patterns registered more than once.
+    ///
+    /// The default policy is [`DuplicatePolicy::Error`].
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Duplicate policy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{CharwiseDoubleArrayAhoCorasickBuilder, DuplicatePolicy};
+    ///
+    /// let patvals = vec![("世界", 1), ("世", 2), ("世界", 4)];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .on_duplicate(DuplicatePolicy::KeepLast)
+    ///     .build_with_values(patvals)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.find_overlapping_iter("世界");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 3, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 6, 4), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    #[must_use]
+    pub const fn on_duplicate(mut self, policy: DuplicatePolicy<V>) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
     /// Specifies the number of last blocks to search bases.
     ///
     /// The smaller the number is, the faster the construction time will be; however, the memory
@@ -81,18 +137,255 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
     ///
     /// # Arguments
     ///
-    /// * `n` - The number of last blocks.
-    ///
-    /// # Panics
-    ///
-    /// `n` must be greater than or equal to 1.
+    /// * `n` - The number of last blocks, which must be greater than or equal to 1. An invalid
+    ///   value is reported by [`Self::validate()`] and the build methods.
     #[must_use]
     pub const fn num_free_blocks(mut self, n: u32) -> Self {
-        assert!(n >= 1);
         self.num_free_blocks = n;
         self
     }
 
+    /// Specifies whether to store the outputs in the compact representation.
+    ///
+    /// Each output consists of the value, the length of the pattern, and the position of the next
+    /// output, which take `size_of::<V>() + 8` bytes with padding by default. In the compact
+    /// representation, the values are stored in a separate array, and the lengths and positions
+    /// are bit-packed with the minimum widths required for the pattern set. This reduces the
+    /// memory usage and the serialized size, at the cost of slightly slower access to the outputs
+    /// on matches. By default, the outputs are not compacted.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to compact the outputs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{
+    ///     CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+    /// };
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma: CharwiseDoubleArrayAhoCorasick<u64> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .compact_outputs(true)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    /// let other: CharwiseDoubleArrayAhoCorasick<u64> =
+    ///     CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///         .build(&patterns)
+    ///         .unwrap();
+    /// assert!(pma.heap_bytes() < other.heap_bytes());
+    ///
+    /// let mut it = pma.find_iter("全世界中に");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((12, 15, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    #[must_use]
+    pub const fn compact_outputs(mut self, enable: bool) -> Self {
+        self.compact_outputs = enable;
+        self
+    }
+
+    /// Specifies whether to reorder the states in the double array after the construction.
+    ///
+    /// The states are placed in the double array in the depth-first order of the trie, so the
+    /// states visited frequently in the search, such as the ones close to the root, are scattered
+    /// over the array. With this option, the blocks of the array are relocated so that the blocks
+    /// containing such states are clustered at the beginning of the array, which improves the
+    /// cache efficiency of the search in large automata. The frequencies are estimated from the
+    /// depths and the numbers of children of the states, or measured in the sample specified with
+    /// [`Self::reorder_sample()`]. The achieved locality is reported by
+    /// [`CharwiseDoubleArrayAhoCorasick::layout_stats()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to reorder the states.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{
+    ///     CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+    /// };
+    ///
+    /// let patterns: Vec<_> = (0..5000).map(|i| format!("{}", i * 7919)).collect();
+    /// let pma: CharwiseDoubleArrayAhoCorasick<u32> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .build(&patterns)
+    ///     .unwrap();
+    /// let reordered: CharwiseDoubleArrayAhoCorasick<u32> =
+    ///     CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///         .reorder_states(true)
+    ///         .build(&patterns)
+    ///         .unwrap();
+    ///
+    /// let stats = reordered.layout_stats();
+    /// assert!(stats.hot_blocks() < pma.layout_stats().hot_blocks());
+    ///
+    /// assert_eq!(
+    ///     pma.find_overlapping_iter("1234567").collect::<Vec<_>>(),
+    ///     reordered.find_overlapping_iter("1234567").collect::<Vec<_>>(),
+    /// );
+    /// ```
+    #[must_use]
+    pub const fn reorder_states(mut self, enable: bool) -> Self {
+        self.reorder_states = enable;
+        self
+    }
+
+    /// Specifies a sample haystack to measure the frequencies of the states in reordering them,
+    /// and enables the reordering. See [`Self::reorder_states()`] for the details.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample` - Sample haystack, which should resemble the haystacks searched in practice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{
+    ///     CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+    /// };
+    ///
+    /// let patterns: Vec<_> = (0..5000).map(|i| format!("{}", i * 7919)).collect();
+    /// let sample = "31415926535897932384626433832795028841971693993751";
+    /// let pma: CharwiseDoubleArrayAhoCorasick<u32> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .build(&patterns)
+    ///     .unwrap();
+    /// let reordered: CharwiseDoubleArrayAhoCorasick<u32> =
+    ///     CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///         .reorder_sample(sample)
+    ///         .build(&patterns)
+    ///         .unwrap();
+    ///
+    /// let stats = reordered.layout_stats_with_sample(sample);
+    /// assert!(stats.hot_blocks() < pma.layout_stats_with_sample(sample).hot_blocks());
+    /// ```
+    #[must_use]
+    pub fn reorder_sample<P>(mut self, sample: P) -> Self
+    where
+        P: AsRef<str>,
+    {
+        self.reorder_states = true;
+        self.reorder_sample = Some(sample.as_ref().into());
+        self
+    }
+
+    /// Specifies the maximum amount of heap memory in bytes used for the construction.
+    ///
+    /// The estimated memory usage of the trie is checked every time a pattern is inserted, and
+    /// that of the trie and the double array is checked every time the array is extended. The
+    /// resulting automaton is also checked. If the budget is exceeded, the construction is
+    /// aborted. By default, no budget is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The maximum memory usage in bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::{
+    ///     CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+    /// };
+    ///
+    /// let patterns: Vec<_> = (0..1000).map(|i| format!("パターン{i}")).collect();
+    ///
+    /// let result: Result<CharwiseDoubleArrayAhoCorasick<u32>, _> =
+    ///     CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///         .memory_budget(1 << 10)
+    ///         .build(&patterns);
+    /// assert!(result.is_err());
+    ///
+    /// let result: Result<CharwiseDoubleArrayAhoCorasick<u32>, _> =
+    ///     CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///         .memory_budget(1 << 20)
+    ///         .build(&patterns);
+    /// assert!(result.is_ok());
+    /// ```
+    #[must_use]
+    pub const fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    /// Specifies a callback receiving the progress of the construction.
+    ///
+    /// The callback is called at the beginning and end of each [`BuildPhase`], and whenever
+    /// 65536 more items are processed in between.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - Closure called with the progress.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::mpsc;
+    ///
+    /// use aneubeck_daachorse::{
+    ///     BuildPhase, CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+    /// };
+    ///
+    /// let (sender, receiver) = mpsc::channel();
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let _pma: CharwiseDoubleArrayAhoCorasick<u32> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .progress(move |progress| {
+    ///         if progress.phase() == BuildPhase::TrieInsertion {
+    ///             sender.send(progress.count()).unwrap();
+    ///         }
+    ///     })
+    ///     .build(patterns)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Some(3), receiver.try_iter().last());
+    /// ```
+    #[must_use]
+    pub fn progress<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(BuildProgress) + Send + 'static,
+    {
+        self.progress = ProgressReporter::new(Some(Box::new(callback)));
+        self
+    }
+
+    /// Checks that the specified options are valid.
+    ///
+    /// The build methods call this function before inserting any patterns, so the options need
+    /// not be validated in advance unless an error is desired early. Since the block length
+    /// depends on the alphabet of the patterns, the total size of the free blocks is checked
+    /// during the build.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when `num_free_blocks` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::CharwiseDoubleArrayAhoCorasickBuilder;
+    ///
+    /// let builder: CharwiseDoubleArrayAhoCorasickBuilder =
+    ///     CharwiseDoubleArrayAhoCorasickBuilder::new().num_free_blocks(0);
+    /// assert!(builder.validate().is_err());
+    ///
+    /// let builder: CharwiseDoubleArrayAhoCorasickBuilder =
+    ///     CharwiseDoubleArrayAhoCorasickBuilder::new().num_free_blocks(16);
+    /// assert!(builder.validate().is_ok());
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        if self.num_free_blocks == 0 {
+            return Err(DaachorseError::invalid_argument("num_free_blocks", ">=", 1));
+        }
+        Ok(())
+    }
+
     /// Builds and returns a new [`CharwiseDoubleArrayAhoCorasick`] from input patterns. The value
     /// `i` is automatically associated with `patterns[i]`.
     ///
@@ -103,9 +396,10 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
     /// # Errors
     ///
     /// [`DaachorseError`] is returned when
+    ///   - the options are invalid as checked by [`Self::validate()`],
     ///   - `patterns` is empty,
     ///   - `patterns` contains entries of length zero,
-    ///   - `patterns` contains duplicate entries,
+    ///   - `patterns` contains duplicate entries and [`DuplicatePolicy::Error`] is specified,
     ///   - the conversion from the index `i` to the specified type `V` fails,
     ///   - the scale of `patterns` exceeds the expected one, or
     ///   - the scale of the resulting automaton exceeds the expected one.
@@ -130,7 +424,7 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
     ///
     /// assert_eq!(None, it.next());
     /// ```
-    pub fn build<I, P, V>(self, patterns: I) -> Result<CharwiseDoubleArrayAhoCorasick<V>>
+    pub fn build<I, P>(self, patterns: I) -> Result<CharwiseDoubleArrayAhoCorasick<V>>
     where
         I: IntoIterator<Item = P>,
         P: AsRef<str>,
@@ -156,9 +450,10 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
     /// # Errors
     ///
     /// [`DaachorseError`] is returned when
+    ///   - the options are invalid as checked by [`Self::validate()`],
     ///   - `patvals` is empty,
     ///   - `patvals` contains patterns of length zero,
-    ///   - `patvals` contains duplicate patterns,
+    ///   - `patvals` contains duplicate patterns and [`DuplicatePolicy::Error`] is specified,
     ///   - the scale of `patvals` exceeds the expected one, or
     ///   - the scale of the resulting automaton exceeds the expected one.
     ///
@@ -182,7 +477,7 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
     ///
     /// assert_eq!(None, it.next());
     /// ```
-    pub fn build_with_values<I, P, V>(
+    pub fn build_with_values<I, P>(
         mut self,
         patvals: I,
     ) -> Result<CharwiseDoubleArrayAhoCorasick<V>>
@@ -192,48 +487,301 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
         V: Copy,
     {
         let nfa = self.build_original_nfa_and_mapper(patvals)?;
+        self.build_automaton(nfa)
+    }
+
+    /// Builds and returns a new [`CharwiseDoubleArrayAhoCorasick`] from fallible input patterns,
+    /// such as lines read from a file. The value `i` is automatically associated with
+    /// `patterns[i]`.
+    ///
+    /// The patterns are inserted while being read, so they need not be collected in advance.
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - List of patterns, each of which can be an error.
+    ///
+    /// # Errors
+    ///
+    /// [`BuildError::Input`] is returned with the first error in `patterns`, and
+    /// [`BuildError::Daachorse`] is returned in the same cases as [`Self::build()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{BufRead, Cursor};
+    ///
+    /// use aneubeck_daachorse::CharwiseDoubleArrayAhoCorasickBuilder;
+    ///
+    /// let reader = Cursor::new("全世界\n世界\nに\n");
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .try_build(reader.lines())
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.find_iter("全世界中に");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((12, 15, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn try_build<I, P, E>(
+        self,
+        patterns: I,
+    ) -> Result<CharwiseDoubleArrayAhoCorasick<V>, BuildError<E>>
+    where
+        I: IntoIterator<Item = Result<P, E>>,
+        P: AsRef<str>,
+        V: Copy + TryFrom<usize>,
+    {
+        let patvals = patterns.into_iter().enumerate().map(|(i, pattern)| {
+            let pattern = pattern.map_err(BuildError::Input)?;
+            let value =
+                V::try_from(i).map_err(|_| DaachorseError::invalid_conversion("index", "V"))?;
+            Ok((pattern, value))
+        });
+        self.try_build_fallible(patvals)
+    }
+
+    /// Builds and returns a new [`CharwiseDoubleArrayAhoCorasick`] from fallible input
+    /// pattern-value pairs.
+    ///
+    /// The pairs are inserted while being read, so they need not be collected in advance.
+    ///
+    /// # Arguments
+    ///
+    /// * `patvals` - List of pattern-value pairs, each of which can be an error.
+    ///
+    /// # Errors
+    ///
+    /// [`BuildError::Input`] is returned with the first error in `patvals`, and
+    /// [`BuildError::Daachorse`] is returned in the same cases as [`Self::build_with_values()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::errors::BuildError;
+    /// use aneubeck_daachorse::CharwiseDoubleArrayAhoCorasickBuilder;
+    ///
+    /// let parse = |line: &'static str| {
+    ///     let (pattern, value) = line.split_once(' ').ok_or(line)?;
+    ///     value.parse::<u32>().map(|v| (pattern, v)).map_err(|_| line)
+    /// };
+    ///
+    /// let lines = ["全世界 0", "世界 10", "に 100"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .try_build_with_values(lines.into_iter().map(parse))
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.find_iter("全世界中に");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let lines = ["全世界 0", "世界", "に 100"];
+    /// let result = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .try_build_with_values(lines.into_iter().map(parse));
+    /// assert!(matches!(result, Err(BuildError::Input("世界"))));
+    /// ```
+    pub fn try_build_with_values<I, P, E>(
+        self,
+        patvals: I,
+    ) -> Result<CharwiseDoubleArrayAhoCorasick<V>, BuildError<E>>
+    where
+        I: IntoIterator<Item = Result<(P, V), E>>,
+        P: AsRef<str>,
+        V: Copy,
+    {
+        self.try_build_fallible(
+            patvals
+                .into_iter()
+                .map(|patval| patval.map_err(BuildError::Input)),
+        )
+    }
+
+    fn try_build_fallible<I, P, E>(
+        mut self,
+        patvals: I,
+    ) -> Result<CharwiseDoubleArrayAhoCorasick<V>, BuildError<E>>
+    where
+        I: IntoIterator<Item = Result<(P, V), BuildError<E>>>,
+        P: AsRef<str>,
+        V: Copy,
+    {
+        let mut error = None;
+        let nfa = self.build_original_nfa_and_mapper(utils::stop_at_error(patvals, &mut error));
+        // The error in the input takes precedence since the automaton is built only from the
+        // patterns before it.
+        if let Some(e) = error {
+            return Err(e);
+        }
+        Ok(self.build_automaton(nfa?)?)
+    }
+
+    /// Builds and returns a new [`CharwiseDoubleArrayAhoCorasick`] from input patterns sorted in
+    /// lexicographical order. The value `i` is automatically associated with `patterns[i]`.
+    ///
+    /// The result is identical to that of [`Self::build()`], but the trie is built directly from
+    /// the sorted patterns, which takes much less memory than [`Self::build()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - List of patterns sorted in lexicographical order.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when
+    ///   - `patterns` is not sorted,
+    ///   - `patterns` is empty,
+    ///   - `patterns` contains entries of length zero,
+    ///   - `patterns` contains duplicate entries and [`DuplicatePolicy::Error`] is specified,
+    ///   - the conversion from the index `i` to the specified type `V` fails,
+    ///   - the scale of `patterns` exceeds the expected one, or
+    ///   - the scale of the resulting automaton exceeds the expected one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::CharwiseDoubleArrayAhoCorasickBuilder;
+    ///
+    /// let builder = CharwiseDoubleArrayAhoCorasickBuilder::new();
+    ///
+    /// let patterns = vec!["に", "世界", "全世界"];
+    /// let pma = builder.build_sorted(patterns).unwrap();
+    ///
+    /// let mut it = pma.find_iter("全世界中に");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((12, 15, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn build_sorted<I, P>(mut self, patterns: I) -> Result<CharwiseDoubleArrayAhoCorasick<V>>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+        V: Copy + TryFrom<usize>,
+    {
+        let patvals = patterns.into_iter().enumerate().map(|(i, p)| {
+            V::try_from(i)
+                .map(|v| (p, v))
+                .map_err(|_| DaachorseError::invalid_conversion("index", "V"))
+        });
+        let nfa = self.build_sorted_nfa_and_mapper(patvals)?;
+        self.build_automaton(nfa)
+    }
+
+    /// Builds and returns a new [`CharwiseDoubleArrayAhoCorasick`] from input pattern-value pairs
+    /// sorted in lexicographical order of the patterns.
+    ///
+    /// The result is identical to that of [`Self::build_with_values()`], but the trie is built
+    /// directly from the sorted patterns, which takes much less memory than
+    /// [`Self::build_with_values()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `patvals` - List of pattern-value pairs sorted in lexicographical order of the patterns.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when
+    ///   - `patvals` is not sorted,
+    ///   - `patvals` is empty,
+    ///   - `patvals` contains patterns of length zero,
+    ///   - `patvals` contains duplicate patterns and [`DuplicatePolicy::Error`] is specified,
+    ///   - the scale of `patvals` exceeds the expected one, or
+    ///   - the scale of the resulting automaton exceeds the expected one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::CharwiseDoubleArrayAhoCorasickBuilder;
+    ///
+    /// let builder = CharwiseDoubleArrayAhoCorasickBuilder::new();
+    ///
+    /// let patvals = vec![("に", 100), ("世界", 10), ("全世界", 0)];
+    /// let pma = builder.build_sorted_with_values(patvals).unwrap();
+    ///
+    /// let mut it = pma.find_iter("全世界中に");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((12, 15, 100), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn build_sorted_with_values<I, P>(
+        mut self,
+        patvals: I,
+    ) -> Result<CharwiseDoubleArrayAhoCorasick<V>>
+    where
+        I: IntoIterator<Item = (P, V)>,
+        P: AsRef<str>,
+        V: Copy,
+    {
+        let nfa = self.build_sorted_nfa_and_mapper(patvals.into_iter().map(Ok))?;
+        self.build_automaton(nfa)
+    }
 
+    fn build_automaton<N>(mut self, nfa: N) -> Result<CharwiseDoubleArrayAhoCorasick<V>>
+    where
+        N: NfaView<char, V>,
+        V: Copy,
+    {
         self.build_double_array(&nfa)?;
 
         // -1 is for dead state
-        let num_states = u32::try_from(nfa.states.len() - 1)
+        let num_states = u32::try_from(nfa.num_states() - 1)
             .map_err(|_| DaachorseError::automaton_scale("num_states", u32::MAX))?;
 
-        Ok(CharwiseDoubleArrayAhoCorasick {
+        let mut pma = CharwiseDoubleArrayAhoCorasick {
             states: self.states,
             mapper: self.mapper,
-            outputs: nfa.outputs,
+            outputs: OutputStore::new(nfa.into_outputs(), self.compact_outputs),
             match_kind: self.match_kind,
             num_states,
-        })
+        };
+        if self.reorder_states {
+            pma.reorder_blocks(self.reorder_sample.as_deref());
+        }
+        if let Some(budget) = self.memory_budget {
+            if pma.heap_bytes() > budget {
+                return Err(DaachorseError::memory_budget(budget));
+            }
+        }
+        Ok(pma)
     }
 
-    fn build_original_nfa_and_mapper<I, P, V>(
-        &mut self,
-        patvals: I,
-    ) -> Result<CharwiseNfaBuilder<V>>
+    fn build_original_nfa_and_mapper<I, P>(&mut self, patvals: I) -> Result<CharwiseNfaBuilder<V>>
     where
         I: IntoIterator<Item = (P, V)>,
         P: AsRef<str>,
         V: Copy,
     {
-        let mut nfa = CharwiseNfaBuilder::new(self.match_kind);
+        self.validate()?;
+        let mut nfa = CharwiseNfaBuilder::new(self.match_kind, self.duplicate_policy);
         let mut freqs = vec![];
         {
             let mut chars = vec![];
+            let mut count = 0;
+            self.progress.start(BuildPhase::TrieInsertion, None);
             for (pattern, value) in patvals {
                 chars.clear();
                 pattern.as_ref().chars().for_each(|c| chars.push(c));
-                nfa.add(&chars, value)?;
-
-                for &c in &chars {
-                    let c = usize::from_u32(u32::from(c));
-                    if freqs.len() <= c {
-                        freqs.resize(c + 1, 0);
-                    }
-                    freqs[c] += 1;
-                }
+                nfa.add(&chars, value)
+                    .map_err(|e| e.with_pattern_index(count))?;
+                count_freqs(&chars, &mut freqs);
+                count += 1;
+                self.inserted(count, &nfa)?;
             }
+            self.progress.finish(BuildPhase::TrieInsertion, count);
         }
         self.mapper = CodeMapper::new(&freqs);
 
@@ -241,43 +789,116 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
             return Err(DaachorseError::invalid_argument("patvals.len()", ">=", 1));
         }
         let q = match self.match_kind {
-            MatchKind::Standard => nfa.build_fails(),
-            MatchKind::LeftmostLongest | MatchKind::LeftmostFirst => nfa.build_fails_leftmost(),
+            MatchKind::Standard => nfa.build_fails(&mut self.progress),
+            MatchKind::LeftmostLongest | MatchKind::LeftmostFirst => {
+                nfa.build_fails_leftmost(&mut self.progress)
+            }
         };
         nfa.build_outputs(&q);
         Ok(nfa)
     }
 
-    fn build_double_array<V>(&mut self, nfa: &CharwiseNfaBuilder<V>) -> Result<()> {
+    fn build_sorted_nfa_and_mapper<I, P>(
+        &mut self,
+        patvals: I,
+    ) -> Result<CharwiseSortedNfaBuilder<V>>
+    where
+        I: IntoIterator<Item = Result<(P, V)>>,
+        P: AsRef<str>,
+        V: Copy,
+    {
+        self.validate()?;
+        let mut nfa = CharwiseSortedNfaBuilder::new(self.match_kind, self.duplicate_policy);
+        let mut freqs = vec![];
+        {
+            let mut chars = vec![];
+            let mut count = 0;
+            self.progress.start(BuildPhase::TrieInsertion, None);
+            for patval in patvals {
+                let (pattern, value) = patval?;
+                chars.clear();
+                pattern.as_ref().chars().for_each(|c| chars.push(c));
+                nfa.add(&chars, value)
+                    .map_err(|e| e.with_pattern_index(count))?;
+                count_freqs(&chars, &mut freqs);
+                count += 1;
+                self.inserted(count, &nfa)?;
+            }
+            self.progress.finish(BuildPhase::TrieInsertion, count);
+        }
+        self.mapper = CodeMapper::new(&freqs);
+
+        if nfa.len == 0 {
+            return Err(DaachorseError::invalid_argument("patvals.len()", ">=", 1));
+        }
+        nfa.build(&mut self.progress);
+        Ok(nfa)
+    }
+
+    /// Reports the progress of the insertion and checks the memory usage of the trie.
+    #[inline(always)]
+    fn inserted<N>(&mut self, count: usize, nfa: &N) -> Result<()>
+    where
+        N: NfaView<char, V>,
+    {
+        self.progress.tick(BuildPhase::TrieInsertion, count, None);
+        self.check_memory_budget(nfa.heap_bytes())
+    }
+
+    #[inline(always)]
+    fn check_memory_budget(&self, bytes: usize) -> Result<()> {
+        match self.memory_budget {
+            Some(budget) if bytes > budget => Err(DaachorseError::memory_budget(budget)),
+            _ => Ok(()),
+        }
+    }
+
+    fn build_double_array<N>(&mut self, nfa: &N) -> Result<()>
+    where
+        N: NfaView<char, V>,
+    {
         let mut helper = self.init_array()?;
 
-        let mut state_id_map = vec![DEAD_STATE_IDX; nfa.states.len()];
+        let mut state_id_map = vec![DEAD_STATE_IDX; nfa.num_states()];
         state_id_map[usize::from_u32(ROOT_STATE_ID)] = ROOT_STATE_IDX;
+        let nfa_bytes = nfa.heap_bytes() + state_id_map.len() * mem::size_of::<u32>();
+
+        // The dead state is not placed.
+        let num_states = nfa.num_states() - 1;
+        let mut count = 0;
+        self.progress
+            .start(BuildPhase::DoubleArrayPlacement, Some(num_states));
 
         // Arranges base & check values
         let mut stack = vec![ROOT_STATE_ID];
+        let mut edges = vec![];
         let mut mapped = vec![];
 
         while let Some(state_id) = stack.pop() {
             debug_assert_ne!(state_id, DEAD_STATE_ID);
-            let state = &nfa.states[usize::from_u32(state_id)];
+            count += 1;
+            self.progress
+                .tick(BuildPhase::DoubleArrayPlacement, count, Some(num_states));
 
             let state_idx = state_id_map[usize::from_u32(state_id)];
             debug_assert_ne!(state_idx, DEAD_STATE_IDX);
 
-            let s = &state.borrow();
-            if s.edges.is_empty() {
+            edges.clear();
+            nfa.push_edges(state_id, &mut edges);
+            if edges.is_empty() {
                 continue;
             }
 
             mapped.clear();
-            for (&label, &child_id) in &s.edges {
+            for &(label, child_id) in &edges {
                 mapped.push((self.mapper.get(label).unwrap(), child_id));
             }
             mapped.sort_by(|(c1, _), (c2, _)| c1.cmp(c2));
 
             let base = self.find_base(&mapped, &helper);
             if self.states.len() <= usize::from_u32(base.get()) {
+                let array_len = self.states.len() + usize::from_u32(self.block_len);
+                self.check_memory_budget(nfa_bytes + array_len * mem::size_of::<State>())?;
                 self.extend_array(&mut helper)?;
             }
 
@@ -292,18 +913,17 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
         }
 
         // Sets fail & output_pos values
-        for (i, state) in nfa.states.iter().enumerate() {
-            if i == usize::from_u32(DEAD_STATE_ID) {
+        for (state_id, &idx) in (0..).zip(&state_id_map) {
+            if state_id == DEAD_STATE_ID {
                 continue;
             }
 
-            let idx = usize::from_u32(state_id_map[i]);
+            let idx = usize::from_u32(idx);
             debug_assert_ne!(idx, usize::from_u32(DEAD_STATE_IDX));
 
-            let s = &state.borrow();
-            self.states[idx].set_output_pos(s.output_pos);
+            self.states[idx].set_output_pos(nfa.output_pos(state_id));
 
-            let fail_id = s.fail;
+            let fail_id = nfa.fail(state_id);
             if fail_id == DEAD_STATE_ID {
                 self.states[idx].set_fail(DEAD_STATE_IDX);
             } else {
@@ -314,6 +934,8 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
         }
 
         self.states.shrink_to_fit();
+        self.progress
+            .finish(BuildPhase::DoubleArrayPlacement, num_states);
         Ok(())
     }
 
@@ -370,3 +992,14 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
         Ok(())
     }
 }
+
+/// Counts the occurrences of each character in `chars`, indexed by its code point.
+fn count_freqs(chars: &[char], freqs: &mut Vec<u32>) {
+    for &c in chars {
+        let c = usize::from_u32(u32::from(c));
+        if freqs.len() <= c {
+            freqs.resize(c + 1, 0);
+        }
+        freqs[c] += 1;
+    }
+}
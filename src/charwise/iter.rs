@@ -1,13 +1,13 @@
 //! Iterators for [`CharwiseDoubleArrayAhoCorasick`].
 
-use core::iter::Enumerate;
 use core::num::NonZeroU32;
 
 use crate::charwise::CharwiseDoubleArrayAhoCorasick;
 
 use crate::charwise::ROOT_STATE_IDX;
+use crate::errors::{DaachorseError, Result};
 use crate::utils::FromU32;
-use crate::Match;
+use crate::{Match, ValueCursor};
 
 /// Iterator for some struct that implements [`AsRef<str>`].
 #[doc(hidden)]
@@ -43,7 +43,8 @@ where
 /// Iterator for UTF-8 strings with end positions.
 #[doc(hidden)]
 pub struct CharWithEndOffsetIterator<I> {
-    inner: Enumerate<I>,
+    inner: I,
+    pos: usize,
 }
 
 impl<I> CharWithEndOffsetIterator<I>
@@ -56,9 +57,7 @@ where
     ///
     /// `inner` must represent a correct UTF-8 string.
     pub unsafe fn new(inner: I) -> Self {
-        Self {
-            inner: inner.enumerate(),
-        }
+        Self { inner, pos: 0 }
     }
 }
 
@@ -70,34 +69,160 @@ where
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
-        let (i, first) = self.inner.next()?;
-        let (end_offset, c) = if first < 0x80 {
+        let first = self.inner.next()?;
+        let (len, c) = if first < 0x80 {
             // 1 byte
-            (i + 1, u32::from(first))
+            (1, u32::from(first))
         } else {
             // 2 bytes ~
-            let (i, rest) = unsafe { self.inner.next().unwrap_unchecked() };
+            let rest = unsafe { self.inner.next().unwrap_unchecked() };
             let c = u32::from(rest & 0x3f);
             if first < 0xe0 {
-                (i + 1, u32::from(first & 0x1f) << 6 | c)
+                (2, u32::from(first & 0x1f) << 6 | c)
             } else {
                 // 3 bytes ~
-                let (i, rest) = unsafe { self.inner.next().unwrap_unchecked() };
+                let rest = unsafe { self.inner.next().unwrap_unchecked() };
                 let c = c << 6 | u32::from(rest & 0x3f);
                 if first < 0xf0 {
-                    (i + 1, u32::from(first & 0x0f) << 12 | c)
+                    (3, u32::from(first & 0x0f) << 12 | c)
                 } else {
                     // 4 bytes
-                    let (i, rest) = unsafe { self.inner.next().unwrap_unchecked() };
+                    let rest = unsafe { self.inner.next().unwrap_unchecked() };
                     let c = c << 6 | u32::from(rest & 0x3f);
-                    (i + 1, u32::from(first & 0x07) << 18 | c)
+                    (4, u32::from(first & 0x07) << 18 | c)
                 }
             }
         };
-        Some((end_offset, unsafe { char::from_u32_unchecked(c) }))
+        self.pos += len;
+        Some((self.pos, unsafe { char::from_u32_unchecked(c) }))
+    }
+}
+
+/// Iterator for bytes that stops before the first invalid UTF-8 sequence, so that the yielded
+/// bytes can be given to [`CharWithEndOffsetIterator::new()`].
+#[doc(hidden)]
+pub struct Utf8CheckedIterator<P> {
+    inner: P,
+    // The bytes of the current character, validated but not yielded yet.
+    buf: [u8; 4],
+    buf_pos: usize,
+    buf_len: usize,
+    // The number of the validated bytes.
+    valid_up_to: usize,
+    invalid: bool,
+}
+
+impl<P> Utf8CheckedIterator<P>
+where
+    P: Iterator<Item = u8>,
+{
+    pub(crate) const fn new(inner: P) -> Self {
+        Self {
+            inner,
+            buf: [0; 4],
+            buf_pos: 0,
+            buf_len: 0,
+            valid_up_to: 0,
+            invalid: false,
+        }
+    }
+
+    /// Returns the error if an invalid UTF-8 sequence has been found.
+    pub(crate) fn error(&self) -> Option<DaachorseError> {
+        self.invalid
+            .then(|| DaachorseError::invalid_utf8(self.valid_up_to))
+    }
+}
+
+impl<P> Iterator for Utf8CheckedIterator<P>
+where
+    P: Iterator<Item = u8>,
+{
+    type Item = u8;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf_pos < self.buf_len {
+            let b = self.buf[self.buf_pos];
+            self.buf_pos += 1;
+            return Some(b);
+        }
+        if self.invalid {
+            return None;
+        }
+        let first = self.inner.next()?;
+        if first < 0x80 {
+            self.valid_up_to += 1;
+            return Some(first);
+        }
+        // The length of the sequence starting with the byte, or 1 if it cannot start a sequence.
+        let width = match first {
+            0xc2..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf4 => 4,
+            _ => 1,
+        };
+        self.buf[0] = first;
+        let mut len = 1;
+        while len < width {
+            match self.inner.next() {
+                Some(b) if b & 0xc0 == 0x80 => {
+                    self.buf[len] = b;
+                    len += 1;
+                }
+                _ => break,
+            }
+        }
+        // The sequence is validated by the same rules as str.
+        if core::str::from_utf8(&self.buf[..len]).is_err() {
+            self.invalid = true;
+            return None;
+        }
+        self.valid_up_to += len;
+        self.buf_pos = 1;
+        self.buf_len = len;
+        Some(first)
     }
 }
 
+/// Iterator created by [`CharwiseDoubleArrayAhoCorasick::try_find_iter_from_iter()`] and its
+/// siblings, validating the haystack as it is consumed.
+///
+/// The matches in the valid prefix of the haystack are yielded first. If an invalid UTF-8
+/// sequence is found, a [`DaachorseError::InvalidUtf8`] is yielded and the iteration stops.
+pub struct Utf8CheckedMatchIterator<I> {
+    pub(crate) inner: I,
+    pub(crate) done: bool,
+}
+
+macro_rules! impl_utf8_checked_match_iterator {
+    ( $iter:ident ) => {
+        impl<'a, P, V> Iterator for Utf8CheckedMatchIterator<$iter<'a, Utf8CheckedIterator<P>, V>>
+        where
+            P: Iterator<Item = u8>,
+            V: Copy,
+        {
+            type Item = Result<Match<V>>;
+
+            #[inline(always)]
+            fn next(&mut self) -> Option<Self::Item> {
+                if self.done {
+                    return None;
+                }
+                if let Some(m) = self.inner.next() {
+                    return Some(Ok(m));
+                }
+                self.done = true;
+                self.inner.haystack.inner.error().map(Err)
+            }
+        }
+    };
+}
+
+impl_utf8_checked_match_iterator!(FindIterator);
+impl_utf8_checked_match_iterator!(FindOverlappingIterator);
+impl_utf8_checked_match_iterator!(FindOverlappingNoSuffixIterator);
+
 /// Iterator created by [`CharwiseDoubleArrayAhoCorasick::find_iter()`].
 pub struct FindOverlappingIterator<'a, P, V> {
     pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
@@ -111,6 +236,7 @@ pub struct FindOverlappingIterator<'a, P, V> {
 pub struct FindIterator<'a, P, V> {
     pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
     pub(crate) haystack: CharWithEndOffsetIterator<P>,
+    pub(crate) values: ValueCursor,
 }
 
 /// Iterator created by [`CharwiseDoubleArrayAhoCorasick::find_overlapping_no_suffix_iter()`].
@@ -118,6 +244,7 @@ pub struct FindOverlappingNoSuffixIterator<'a, P, V> {
     pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
     pub(crate) haystack: CharWithEndOffsetIterator<P>,
     pub(crate) state_id: u32,
+    pub(crate) values: ValueCursor,
 }
 
 /// Iterator created by [`CharwiseDoubleArrayAhoCorasick::leftmost_find_iter()`].
@@ -125,6 +252,7 @@ pub struct LestmostFindIterator<'a, P, V> {
     pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
     pub(crate) haystack: P,
     pub(crate) pos: usize,
+    pub(crate) values: ValueCursor,
 }
 
 impl<'a, P, V> Iterator for FindOverlappingIterator<'a, P, V>
@@ -192,6 +320,10 @@ where
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
+        // self.values always holds the output positions of self.pma.
+        if let Some(m) = unsafe { self.values.next(&self.pma.outputs) } {
+            return Some(m);
+        }
         let mut state_id = ROOT_STATE_IDX;
         for (pos, c) in self.haystack.by_ref() {
             // self.state_id is always smaller than self.pma.states.len() because
@@ -210,11 +342,7 @@ where
                         .outputs
                         .get_unchecked(usize::from_u32(output_pos.get() - 1))
                 };
-                return Some(Match {
-                    length: usize::from_u32(out.length()),
-                    end: pos,
-                    value: out.value(),
-                });
+                return Some(self.values.start(out, pos));
             }
         }
         None
@@ -230,6 +358,10 @@ where
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
+        // self.values always holds the output positions of self.pma.
+        if let Some(m) = unsafe { self.values.next(&self.pma.outputs) } {
+            return Some(m);
+        }
         for (pos, c) in self.haystack.by_ref() {
             // self.state_id is always smaller than self.pma.states.len() because
             // self.pma.next_state_id_unchecked() ensures to return such a value.
@@ -247,11 +379,7 @@ where
                         .outputs
                         .get_unchecked(usize::from_u32(output_pos.get() - 1))
                 };
-                return Some(Match {
-                    length: usize::from_u32(out.length()),
-                    end: pos,
-                    value: out.value(),
-                });
+                return Some(self.values.start(out, pos));
             }
         }
         None
@@ -267,6 +395,10 @@ where
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
+        // self.values always holds the output positions of self.pma.
+        if let Some(m) = unsafe { self.values.next(&self.pma.outputs) } {
+            return Some(m);
+        }
         let mut state_id = ROOT_STATE_IDX;
         let mut last_output_pos: Option<NonZeroU32> = None;
 
@@ -286,11 +418,7 @@ where
                             .outputs
                             .get_unchecked(usize::from_u32(output_pos.get() - 1))
                     };
-                    return Some(Match {
-                        length: usize::from_u32(out.length()),
-                        end: self.pos,
-                        value: out.value(),
-                    });
+                    return Some(self.values.start(out, self.pos));
                 }
             // state_id is always smaller than self.pma.states.len() because
             // self.pma.next_state_id_leftmost_unchecked() ensures to return such a value.
@@ -314,11 +442,7 @@ where
                     .outputs
                     .get_unchecked(usize::from_u32(output_pos.get() - 1))
             };
-            Match {
-                length: usize::from_u32(out.length()),
-                end: self.pos,
-                value: out.value(),
-            }
+            self.values.start(out, self.pos)
         })
     }
 }
@@ -327,6 +451,8 @@ where
 mod tests {
     use super::*;
 
+    use alloc::vec::Vec;
+
     #[test]
     fn test_char_with_end_offset_iterator() {
         let test_string =
@@ -379,4 +505,42 @@ mod tests {
         assert_eq!(None, it.next());
         assert_eq!(None, it.next());
     }
+
+    #[test]
+    fn test_utf8_checked_iterator() {
+        let cases: &[&[u8]] = &[
+            b"",
+            "\u{0000}\u{007f}\u{0080}\u{07ff}\u{0800}\u{d7ff}\u{e000}\u{ffff}\u{10000}\u{10ffff}"
+                .as_bytes(),
+            b"ab\x80",
+            b"ab\xc0\x80",
+            b"ab\xc1\xbf",
+            b"ab\xc2",
+            b"ab\xc2\x7f",
+            b"ab\xe0\x9f\x80",
+            b"ab\xe0\xa0",
+            b"ab\xed\xa0\x80",
+            b"ab\xef\xbf\xc0",
+            b"ab\xf0\x8f\x80\x80",
+            b"ab\xf4\x90\x80\x80",
+            b"ab\xf4\x8f\xbf",
+            b"ab\xf5\x80\x80\x80",
+            b"ab\xff",
+            "全世界\u{10ffff}".as_bytes(),
+        ];
+        for &bytes in cases {
+            let expected = core::str::from_utf8(bytes)
+                .map(|_| ())
+                .map_err(|e| e.valid_up_to());
+            let mut it = Utf8CheckedIterator::new(bytes.iter().copied());
+            let valid: Vec<u8> = it.by_ref().collect();
+            let actual = it.error().map_or(Ok(()), |e| match e {
+                DaachorseError::InvalidUtf8(e) => Err(e.valid_up_to()),
+                _ => unreachable!(),
+            });
+            assert_eq!(expected, actual, "{bytes:?}");
+            let valid_up_to = expected.map_or_else(|e| e, |_| bytes.len());
+            assert_eq!(&bytes[..valid_up_to], &valid[..], "{bytes:?}");
+        }
+    }
 }
@@ -1,6 +1,6 @@
 use alloc::vec::Vec;
 
-use crate::serializer::{Serializable, SerializableVec};
+use crate::serializer::{self, Serializable, SerializableVec, Sink};
 
 use crate::utils::FromU32;
 
@@ -41,6 +41,37 @@ impl CodeMapper {
             .filter(|&code| code != INVALID_CODE)
     }
 
+    /// Returns the table to convert codes into the original characters.
+    pub fn inverse(&self) -> Vec<char> {
+        let mut inverse = vec!['\0'; usize::from_u32(self.alphabet_size)];
+        for (c, &code) in self.table.iter().enumerate() {
+            if code != INVALID_CODE {
+                // The table is indexed by valid code points.
+                inverse[usize::from_u32(code)] = char::from_u32(u32::try_from(c).unwrap()).unwrap();
+            }
+        }
+        inverse
+    }
+
+    /// Checks if the valid codes are distinct and cover all the values smaller than the alphabet
+    /// size.
+    pub fn is_valid(&self) -> bool {
+        if self.table.len() < usize::from_u32(self.alphabet_size) {
+            return false;
+        }
+        let mut used = vec![false; usize::from_u32(self.alphabet_size)];
+        for &code in &self.table {
+            if code == INVALID_CODE {
+                continue;
+            }
+            match used.get_mut(usize::from_u32(code)) {
+                Some(used) if !*used => *used = true,
+                _ => return false,
+            }
+        }
+        used.iter().all(|&used| used)
+    }
+
     #[inline(always)]
     pub const fn alphabet_size(&self) -> u32 {
         self.alphabet_size
@@ -54,10 +85,12 @@ impl CodeMapper {
 }
 
 impl SerializableVec for CodeMapper {
-    #[inline(always)]
-    fn serialize_to_vec(&self, dst: &mut Vec<u8>) {
-        self.table.serialize_to_vec(dst);
-        self.alphabet_size.serialize_to_vec(dst);
+    fn serialize_into<W>(&self, dst: &mut W) -> Result<(), W::Error>
+    where
+        W: Sink + ?Sized,
+    {
+        self.table.serialize_into(dst)?;
+        serializer::serialize_value_into(&self.alphabet_size, dst)
     }
 
     #[inline(always)]
@@ -73,6 +106,19 @@ impl SerializableVec for CodeMapper {
         )
     }
 
+    #[inline(always)]
+    fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])> {
+        let (table, src) = Vec::<u32>::deserialize_from_slice_checked(src)?;
+        let (alphabet_size, src) = u32::deserialize_from_slice_checked(src)?;
+        Some((
+            Self {
+                table,
+                alphabet_size,
+            },
+            src,
+        ))
+    }
+
     #[inline(always)]
     fn serialized_bytes(&self) -> usize {
         self.table.serialized_bytes() + u32::serialized_bytes()
@@ -83,6 +129,8 @@ impl SerializableVec for CodeMapper {
 mod tests {
     use super::*;
 
+    use crate::serializer::VecSink;
+
     #[test]
     fn test_charwise_code_mapper() {
         let freqs = vec![3, 6, 0, 2, 3, 0, 3];
@@ -96,6 +144,14 @@ mod tests {
         assert_eq!(mapper.get(5 as char), None);
         assert_eq!(mapper.get(6 as char), Some(3));
         assert_eq!(mapper.get(7 as char), None); // out-of-range
+
+        assert!(mapper.is_valid());
+        let mut broken = mapper.clone();
+        broken.table[2] = 5;
+        assert!(!broken.is_valid());
+        let mut broken = mapper;
+        broken.table[0] = 2;
+        assert!(!broken.is_valid());
     }
 
     #[test]
@@ -104,7 +160,7 @@ mod tests {
         let mapper = CodeMapper::new(&freqs);
 
         let mut data = vec![];
-        mapper.serialize_to_vec(&mut data);
+        mapper.serialize_into(&mut VecSink(&mut data)).unwrap();
         assert_eq!(data.len(), mapper.serialized_bytes());
         let (other, rest) = CodeMapper::deserialize_from_slice(&data);
         assert!(rest.is_empty());
@@ -0,0 +1,149 @@
+//! Graphviz DOT export of automata.
+
+use core::fmt::{self, Debug, Write};
+
+use alloc::string::String;
+
+/// Writes the states and the transitions of an automaton in the DOT language.
+///
+/// Every state is drawn with its id, the path from the root, and the values reported when it is
+/// reached. Since the failure links always point to shallower states, the states should be added
+/// in breadth-first order so that every failure state is drawn before it is referred to.
+pub struct DotWriter {
+    dot: String,
+    dead_state_id: u32,
+    has_dead_state: bool,
+}
+
+impl DotWriter {
+    pub fn new(dead_state_id: u32) -> Self {
+        let mut dot = String::new();
+        dot.push_str("digraph automaton {\n");
+        dot.push_str("  rankdir=LR;\n");
+        Self {
+            dot,
+            dead_state_id,
+            has_dead_state: false,
+        }
+    }
+
+    /// Adds a state. `path` is the escaped labels from the root to the state, and `values` are
+    /// the values of the output chain.
+    pub fn add_state<I, V>(&mut self, state_id: u32, path: &str, values: I)
+    where
+        I: IntoIterator<Item = V>,
+        V: Debug,
+    {
+        let mut label = String::new();
+        // Writing to String never fails.
+        write!(label, "{state_id}\n\"{path}\"").unwrap();
+        let mut values = values.into_iter().peekable();
+        let shape = if values.peek().is_some() {
+            label.push_str("\n[");
+            for (i, value) in values.enumerate() {
+                if i != 0 {
+                    label.push_str(", ");
+                }
+                write!(label, "{value:?}").unwrap();
+            }
+            label.push(']');
+            "doublecircle"
+        } else {
+            "circle"
+        };
+        writeln!(
+            self.dot,
+            "  {state_id} [label=\"{}\", shape={shape}];",
+            Escaped(&label)
+        )
+        .unwrap();
+    }
+
+    /// Adds a goto edge. `label` is the escaped label of the transition.
+    pub fn add_goto(&mut self, state_id: u32, child_id: u32, label: &str) {
+        writeln!(
+            self.dot,
+            "  {state_id} -> {child_id} [label=\"{}\"];",
+            Escaped(label)
+        )
+        .unwrap();
+    }
+
+    /// Adds a failure edge, which may point to the dead state in leftmost automata.
+    pub fn add_fail(&mut self, state_id: u32, fail_id: u32) {
+        if fail_id == self.dead_state_id {
+            self.has_dead_state = true;
+        }
+        writeln!(
+            self.dot,
+            "  {state_id} -> {fail_id} [style=dashed, color=gray];"
+        )
+        .unwrap();
+    }
+
+    /// Finishes the graph, noting the number of the states omitted due to the size limit.
+    pub fn finish(mut self, num_omitted: usize) -> String {
+        if self.has_dead_state {
+            writeln!(
+                self.dot,
+                "  {} [label=\"{}\\nDEAD\", shape=box];",
+                self.dead_state_id, self.dead_state_id
+            )
+            .unwrap();
+        }
+        if num_omitted != 0 {
+            writeln!(
+                self.dot,
+                "  omitted [label=\"{num_omitted} more states omitted\", shape=plaintext];"
+            )
+            .unwrap();
+        }
+        self.dot.push_str("}\n");
+        self.dot
+    }
+}
+
+/// Escapes double quotes and backslashes in a quoted string of the DOT language, keeping the
+/// line breaks.
+struct Escaped<'a>(&'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' | '\\' => write!(f, "\\{c}")?,
+                '\n' => f.write_str("\\n")?,
+                _ => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dot_writer() {
+        let mut writer = DotWriter::new(1);
+        writer.add_state(0, "", core::iter::empty::<u32>());
+        writer.add_state(2, "a\\\"", [3, 4]);
+        writer.add_goto(0, 2, "a");
+        writer.add_fail(2, 1);
+        assert_eq!(
+            "digraph automaton {\n  rankdir=LR;\n  \
+             0 [label=\"0\\n\\\"\\\"\", shape=circle];\n  \
+             2 [label=\"2\\n\\\"a\\\\\\\"\\\"\\n[3, 4]\", shape=doublecircle];\n  \
+             0 -> 2 [label=\"a\"];\n  \
+             2 -> 1 [style=dashed, color=gray];\n  \
+             1 [label=\"1\\nDEAD\", shape=box];\n}\n",
+            writer.finish(0),
+        );
+
+        let writer = DotWriter::new(1);
+        assert!(writer
+            .finish(5)
+            .contains("omitted [label=\"5 more states omitted\", shape=plaintext];"));
+    }
+}
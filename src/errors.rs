@@ -5,8 +5,13 @@ use core::result;
 use alloc::fmt;
 use alloc::string::String;
 
+use crate::MatchKind;
+
 /// Errors in daachorse.
+///
+/// New variants may be added in minor releases, so matches on this enum need a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum DaachorseError {
     /// Contains [`InvalidArgumentError`].
     InvalidArgument(InvalidArgumentError),
@@ -19,6 +24,24 @@ pub enum DaachorseError {
 
     /// Contains [`InvalidConversionError`].
     InvalidConversion(InvalidConversionError),
+
+    /// Contains [`UnsortedPatternError`].
+    UnsortedPattern(UnsortedPatternError),
+
+    /// Contains [`InvalidAutomatonError`].
+    InvalidAutomaton(InvalidAutomatonError),
+
+    /// Contains [`MatchKindMismatchError`].
+    MatchKindMismatch(MatchKindMismatchError),
+
+    /// Contains [`EmptyPatternError`].
+    EmptyPattern(EmptyPatternError),
+
+    /// Contains [`InvalidUtf8Error`].
+    InvalidUtf8(InvalidUtf8Error),
+
+    /// Contains [`UnsupportedFormatError`].
+    UnsupportedFormat(UnsupportedFormatError),
 }
 
 impl fmt::Display for DaachorseError {
@@ -28,26 +51,117 @@ impl fmt::Display for DaachorseError {
             Self::DuplicatePattern(e) => e.fmt(f),
             Self::AutomatonScale(e) => e.fmt(f),
             Self::InvalidConversion(e) => e.fmt(f),
+            Self::UnsortedPattern(e) => e.fmt(f),
+            Self::InvalidAutomaton(e) => e.fmt(f),
+            Self::MatchKindMismatch(e) => e.fmt(f),
+            Self::EmptyPattern(e) => e.fmt(f),
+            Self::InvalidUtf8(e) => e.fmt(f),
+            Self::UnsupportedFormat(e) => e.fmt(f),
         }
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for DaachorseError {}
+
 impl DaachorseError {
+    /// Returns the index of the pattern causing the error in the input, if available.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aneubeck_daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "", "a"];
+    /// let e = DoubleArrayAhoCorasick::<u32>::new(patterns).err().unwrap();
+    /// assert_eq!(Some(2), e.pattern_index());
+    ///
+    /// let patterns = vec!["bcd", "ab", "a", "ab"];
+    /// let e = DoubleArrayAhoCorasick::<u32>::new(patterns).err().unwrap();
+    /// assert_eq!(Some(3), e.pattern_index());
+    /// ```
+    #[must_use]
+    pub const fn pattern_index(&self) -> Option<usize> {
+        match self {
+            Self::InvalidArgument(e) => e.pattern_index,
+            Self::DuplicatePattern(e) => e.pattern_index,
+            Self::EmptyPattern(e) => e.pattern_index,
+            _ => None,
+        }
+    }
+
+    /// Sets the index of the pattern causing the error in the input.
+    pub(crate) fn with_pattern_index(mut self, index: usize) -> Self {
+        match &mut self {
+            Self::InvalidArgument(e) => e.pattern_index = Some(index),
+            Self::DuplicatePattern(e) => e.pattern_index = Some(index),
+            Self::EmptyPattern(e) => e.pattern_index = Some(index),
+            _ => {}
+        }
+        self
+    }
+
     pub(crate) const fn invalid_argument(arg: &'static str, op: &'static str, value: u32) -> Self {
-        Self::InvalidArgument(InvalidArgumentError { arg, op, value })
+        Self::InvalidArgument(InvalidArgumentError {
+            arg,
+            op,
+            value,
+            pattern_index: None,
+        })
     }
 
     pub(crate) const fn duplicate_pattern(pattern: String) -> Self {
-        Self::DuplicatePattern(DuplicatePatternError { pattern })
+        Self::DuplicatePattern(DuplicatePatternError {
+            pattern,
+            pattern_index: None,
+        })
+    }
+
+    pub(crate) fn automaton_scale(arg: &'static str, max_value: u32) -> Self {
+        Self::AutomatonScale(AutomatonScaleError {
+            arg,
+            max_value: u64::from(max_value),
+        })
     }
 
-    pub(crate) const fn automaton_scale(arg: &'static str, max_value: u32) -> Self {
-        Self::AutomatonScale(AutomatonScaleError { arg, max_value })
+    pub(crate) fn memory_budget(max_bytes: usize) -> Self {
+        Self::AutomatonScale(AutomatonScaleError {
+            arg: "memory_bytes",
+            // usize is at most 64 bits.
+            max_value: u64::try_from(max_bytes).unwrap(),
+        })
     }
 
     pub(crate) const fn invalid_conversion(arg: &'static str, target: &'static str) -> Self {
         Self::InvalidConversion(InvalidConversionError { arg, target })
     }
+
+    pub(crate) const fn unsorted_pattern(pattern: String) -> Self {
+        Self::UnsortedPattern(UnsortedPatternError { pattern })
+    }
+
+    pub(crate) const fn invalid_automaton(invariant: &'static str, index: Option<u32>) -> Self {
+        Self::InvalidAutomaton(InvalidAutomatonError { invariant, index })
+    }
+
+    pub(crate) const fn match_kind_mismatch(expected: &'static str, actual: MatchKind) -> Self {
+        Self::MatchKindMismatch(MatchKindMismatchError { expected, actual })
+    }
+
+    pub(crate) const fn empty_pattern() -> Self {
+        Self::EmptyPattern(EmptyPatternError {
+            pattern_index: None,
+        })
+    }
+
+    pub(crate) const fn invalid_utf8(valid_up_to: usize) -> Self {
+        Self::InvalidUtf8(InvalidUtf8Error { valid_up_to })
+    }
+
+    pub(crate) const fn unsupported_format(version: Option<u32>, supported: u32) -> Self {
+        Self::UnsupportedFormat(UnsupportedFormatError { version, supported })
+    }
 }
 
 /// Error used when the argument is invalid.
@@ -61,6 +175,17 @@ pub struct InvalidArgumentError {
 
     /// Condition value.
     value: u32,
+
+    /// Index of the invalid pattern in the input.
+    pattern_index: Option<usize>,
+}
+
+impl InvalidArgumentError {
+    /// Returns the index of the invalid pattern in the input, if the argument is a pattern.
+    #[must_use]
+    pub const fn pattern_index(&self) -> Option<usize> {
+        self.pattern_index
+    }
 }
 
 impl fmt::Display for InvalidArgumentError {
@@ -69,23 +194,45 @@ impl fmt::Display for InvalidArgumentError {
             f,
             "InvalidArgumentError: {} must be {} {}",
             self.arg, self.op, self.value
-        )
+        )?;
+        write_pattern_index(f, self.pattern_index)
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for InvalidArgumentError {}
+
 /// Error used when some patterns are duplicated.
 #[derive(Debug)]
 pub struct DuplicatePatternError {
     /// A duplicate pattern.
     pattern: String,
+
+    /// Index of the duplicate pattern in the input.
+    pattern_index: Option<usize>,
+}
+
+impl DuplicatePatternError {
+    /// Returns the index of the duplicate pattern in the input, i.e., that of the second
+    /// occurrence, if available.
+    #[must_use]
+    pub const fn pattern_index(&self) -> Option<usize> {
+        self.pattern_index
+    }
 }
 
 impl fmt::Display for DuplicatePatternError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "DuplicatePatternError: {}", self.pattern)
+        write!(f, "DuplicatePatternError: {}", self.pattern)?;
+        write_pattern_index(f, self.pattern_index)
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for DuplicatePatternError {}
+
 /// Error used when the scale of the automaton exceeds the expected one.
 #[derive(Debug)]
 pub struct AutomatonScaleError {
@@ -93,7 +240,7 @@ pub struct AutomatonScaleError {
     arg: &'static str,
 
     /// The maximum value (inclusive).
-    max_value: u32,
+    max_value: u64,
 }
 
 impl fmt::Display for AutomatonScaleError {
@@ -106,6 +253,10 @@ impl fmt::Display for AutomatonScaleError {
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for AutomatonScaleError {}
+
 /// Error used when the conversion fails.
 #[derive(Debug)]
 pub struct InvalidConversionError {
@@ -126,5 +277,235 @@ impl fmt::Display for InvalidConversionError {
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for InvalidConversionError {}
+
+/// Error used when patterns are not sorted in lexicographical order.
+#[derive(Debug)]
+pub struct UnsortedPatternError {
+    /// A pattern smaller than the previous one.
+    pattern: String,
+}
+
+impl fmt::Display for UnsortedPatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "UnsortedPatternError: {} is smaller than the previous pattern",
+            self.pattern
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for UnsortedPatternError {}
+
+/// Error used when the automaton violates an invariant.
+#[derive(Debug)]
+pub struct InvalidAutomatonError {
+    /// Description of the invariant.
+    invariant: &'static str,
+
+    /// Index of the violating element, such as a state or an output.
+    index: Option<u32>,
+}
+
+impl fmt::Display for InvalidAutomatonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "InvalidAutomatonError: {} is violated", self.invariant)?;
+        if let Some(index) = self.index {
+            write!(f, " at {index}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for InvalidAutomatonError {}
+
+/// Error used when the operation is not supported by the match kind of the automaton.
+#[derive(Debug)]
+pub struct MatchKindMismatchError {
+    /// Description of the required match kind.
+    expected: &'static str,
+
+    /// The match kind of the automaton.
+    actual: MatchKind,
+}
+
+impl MatchKindMismatchError {
+    /// Returns the match kind of the automaton causing the error.
+    #[must_use]
+    pub const fn match_kind(&self) -> MatchKind {
+        self.actual
+    }
+}
+
+impl fmt::Display for MatchKindMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MatchKindMismatchError: match_kind must be {}, but is {:?}",
+            self.expected, self.actual
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for MatchKindMismatchError {}
+
+/// Error used when a pattern is empty.
+#[derive(Debug)]
+pub struct EmptyPatternError {
+    /// Index of the empty pattern in the input.
+    pattern_index: Option<usize>,
+}
+
+impl EmptyPatternError {
+    /// Returns the index of the empty pattern in the input, if available.
+    #[must_use]
+    pub const fn pattern_index(&self) -> Option<usize> {
+        self.pattern_index
+    }
+}
+
+impl fmt::Display for EmptyPatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EmptyPatternError: patterns must not be empty")?;
+        write_pattern_index(f, self.pattern_index)
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for EmptyPatternError {}
+
+/// Error used when a haystack is not a valid UTF-8 sequence.
+#[derive(Debug)]
+pub struct InvalidUtf8Error {
+    /// Length of the longest valid prefix in bytes.
+    valid_up_to: usize,
+}
+
+impl InvalidUtf8Error {
+    /// Returns the length of the longest valid UTF-8 prefix of the haystack in bytes.
+    #[must_use]
+    pub const fn valid_up_to(&self) -> usize {
+        self.valid_up_to
+    }
+}
+
+impl fmt::Display for InvalidUtf8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "InvalidUtf8Error: invalid UTF-8 sequence after {} valid bytes",
+            self.valid_up_to
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for InvalidUtf8Error {}
+
+/// Error used when serialized data is not an automaton in the supported format.
+#[derive(Debug)]
+pub struct UnsupportedFormatError {
+    /// Format version of the data, or [`None`] if the data has no valid header.
+    version: Option<u32>,
+
+    /// Format version supported by this library.
+    supported: u32,
+}
+
+impl UnsupportedFormatError {
+    /// Returns the format version of the data, or [`None`] if the data does not start with the
+    /// header of a serialized automaton of the expected type.
+    #[must_use]
+    pub const fn version(&self) -> Option<u32> {
+        self.version
+    }
+}
+
+impl fmt::Display for UnsupportedFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.version {
+            Some(version) => write!(
+                f,
+                "UnsupportedFormatError: format version {version} is not supported (expected {})",
+                self.supported
+            ),
+            None => write!(
+                f,
+                "UnsupportedFormatError: the data does not start with the header of the automaton"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for UnsupportedFormatError {}
+
+fn write_pattern_index(f: &mut fmt::Formatter, pattern_index: Option<usize>) -> fmt::Result {
+    if let Some(index) = pattern_index {
+        write!(f, " (pattern index {index})")?;
+    }
+    Ok(())
+}
+
+/// Errors in building an automaton from fallible input, returned by
+/// [`DoubleArrayAhoCorasickBuilder::try_build()`](crate::DoubleArrayAhoCorasickBuilder::try_build())
+/// and the like.
+///
+/// The error is transparent: [`Display`](fmt::Display) and
+/// [`Error::source()`](std::error::Error::source) forward to the contained error, so the error is
+/// shown as if the contained one was returned directly.
+#[derive(Debug)]
+pub enum BuildError<E> {
+    /// Contains the error yielded by the input.
+    Input(E),
+
+    /// Contains [`DaachorseError`].
+    Daachorse(DaachorseError),
+}
+
+impl<E> From<DaachorseError> for BuildError<E> {
+    fn from(e: DaachorseError) -> Self {
+        Self::Daachorse(e)
+    }
+}
+
+impl<E> fmt::Display for BuildError<E>
+where
+    E: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Input(e) => e.fmt(f),
+            Self::Daachorse(e) => e.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<E> std::error::Error for BuildError<E>
+where
+    E: std::error::Error,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Input(e) => e.source(),
+            Self::Daachorse(e) => e.source(),
+        }
+    }
+}
+
 /// A specialized Result type for Daachorse.
 pub type Result<T, E = DaachorseError> = result::Result<T, E>;
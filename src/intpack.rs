@@ -1,6 +1,8 @@
+use core::mem;
+
 use alloc::vec::Vec;
 
-use crate::serializer::Serializable;
+use crate::serializer::{self, Serializable, SerializableVec, Sink};
 
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash)]
 pub struct U24(u32);
@@ -27,6 +29,10 @@ impl TryFrom<u32> for U24 {
     }
 }
 
+/// A 24-bit integer `a` and an 8-bit integer `b` packed into a `u32` as `a << 8 | b`.
+///
+/// The packed value is serialized in little-endian, so `b` is the first byte followed by `a` in
+/// little-endian.
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash)]
 pub struct U24nU8(u32);
 
@@ -69,3 +75,170 @@ impl Serializable for U24nU8 {
         u32::serialized_bytes()
     }
 }
+
+/// Vector of unsigned integers bit-packed with a fixed width.
+///
+/// The `i`-th value occupies `width` bits starting at bit `i * width` of `words`. Since the width
+/// is at most 64, a value spans at most two words.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PackedVec {
+    words: Vec<u64>,
+    len: u32,
+    width: u8,
+}
+
+impl PackedVec {
+    /// Packs `values` with the minimum width to represent all of them.
+    ///
+    /// # Panics
+    ///
+    /// The length of `values` must fit in `u32`.
+    pub fn new(values: &[u64]) -> Self {
+        // The width is at least 1 so that the mask in get_unchecked() does not overflow.
+        let width = bit_width(values.iter().copied().max().unwrap_or(0)).max(1);
+        let word_bits = u64::from(u64::BITS);
+        let num_bits = u64::try_from(values.len()).unwrap() * u64::from(width);
+        let mut words = vec![0; usize::try_from((num_bits + word_bits - 1) / word_bits).unwrap()];
+        for (i, &x) in (0..).zip(values) {
+            let bit = i * u64::from(width);
+            let word = usize::try_from(bit / word_bits).unwrap();
+            let offset = bit % word_bits;
+            words[word] |= x << offset;
+            if offset + u64::from(width) > word_bits {
+                words[word + 1] |= x >> (word_bits - offset);
+            }
+        }
+        Self {
+            words,
+            len: u32::try_from(values.len()).unwrap(),
+            // The width is at most 64.
+            width: u8::try_from(width).unwrap(),
+        }
+    }
+
+    #[inline(always)]
+    pub const fn len(&self) -> u32 {
+        self.len
+    }
+
+    #[inline(always)]
+    pub fn width(&self) -> u32 {
+        u32::from(self.width)
+    }
+
+    /// Checks if the words hold all the values, which [`Self::get_unchecked()`] relies on.
+    pub fn is_valid(&self) -> bool {
+        // usize is at most 64 bits.
+        let num_bits = u64::try_from(self.words.len()).unwrap() * u64::from(u64::BITS);
+        (1..=u64::BITS).contains(&self.width())
+            && u64::from(self.len) * u64::from(self.width) <= num_bits
+    }
+
+    /// # Safety
+    ///
+    /// `i` must be smaller than the length.
+    #[inline(always)]
+    pub unsafe fn get_unchecked(&self, i: usize) -> u64 {
+        let width = u32::from(self.width);
+        // usize is at most 64 bits.
+        let bit = u64::try_from(i).unwrap_unchecked() * u64::from(width);
+        let word = usize::try_from(bit / u64::from(u64::BITS)).unwrap_unchecked();
+        let offset = u32::try_from(bit % u64::from(u64::BITS)).unwrap_unchecked();
+        let mut x = *self.words.get_unchecked(word) >> offset;
+        if offset + width > u64::BITS {
+            x |= *self.words.get_unchecked(word + 1) << (u64::BITS - offset);
+        }
+        x & u64::MAX >> (u64::BITS - width)
+    }
+
+    pub fn heap_bytes(&self) -> usize {
+        self.words.len() * mem::size_of::<u64>()
+    }
+
+    pub fn serialize_into<W>(&self, dst: &mut W) -> Result<(), W::Error>
+    where
+        W: Sink + ?Sized,
+    {
+        self.words.serialize_into(dst)?;
+        serializer::serialize_value_into(&self.len, dst)?;
+        serializer::serialize_value_into(&self.width, dst)
+    }
+
+    pub fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
+        let (words, src) = Vec::<u64>::deserialize_from_slice(src);
+        let (len, src) = u32::deserialize_from_slice(src);
+        let (width, src) = u8::deserialize_from_slice(src);
+        (Self { words, len, width }, src)
+    }
+
+    pub fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])> {
+        let (words, src) = Vec::<u64>::deserialize_from_slice_checked(src)?;
+        let (len, src) = u32::deserialize_from_slice_checked(src)?;
+        let (width, src) = u8::deserialize_from_slice_checked(src)?;
+        Some((Self { words, len, width }, src))
+    }
+
+    pub fn serialized_bytes(&self) -> usize {
+        self.words.serialized_bytes() + u32::serialized_bytes() + u8::serialized_bytes()
+    }
+}
+
+/// Returns the number of bits needed to represent `x`.
+pub const fn bit_width(x: u64) -> u32 {
+    u64::BITS - x.leading_zeros()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::serializer::VecSink;
+
+    #[test]
+    fn test_packed_vec() {
+        for max_value in [0, 1, 5, 1 << 20, u64::from(u32::MAX), u64::MAX] {
+            let values: Vec<u64> = (0..1000u64)
+                .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) % max_value.max(1))
+                .chain([max_value])
+                .collect();
+            let packed = PackedVec::new(&values);
+            assert_eq!(1001, packed.len());
+            assert_eq!(bit_width(max_value).max(1), u32::from(packed.width));
+            for (i, &x) in values.iter().enumerate() {
+                assert_eq!(x, unsafe { packed.get_unchecked(i) });
+            }
+            assert!(packed.is_valid());
+            let mut broken = packed.clone();
+            broken.words.pop();
+            assert!(!broken.is_valid());
+
+            let mut bytes = vec![];
+            packed.serialize_into(&mut VecSink(&mut bytes)).unwrap();
+            assert_eq!(packed.serialized_bytes(), bytes.len());
+            let (other, rest) = PackedVec::deserialize_from_slice(&bytes);
+            assert!(rest.is_empty());
+            assert_eq!(packed, other);
+        }
+    }
+
+    #[test]
+    fn test_u24nu8() {
+        let mut x = U24nU8::default();
+        x.set_a(U24::try_from(0x0012_3456).unwrap());
+        x.set_b(0x78);
+        let mut data = vec![];
+        x.serialize_to_vec(&mut data);
+        assert_eq!(vec![0x78, 0x56, 0x34, 0x12], data);
+        let (y, rest) = U24nU8::deserialize_from_slice(&data);
+        assert!(rest.is_empty());
+        assert_eq!((0x0012_3456, 0x78), (y.a().get(), y.b()));
+    }
+
+    #[test]
+    fn test_packed_vec_empty() {
+        let packed = PackedVec::new(&[]);
+        assert_eq!(0, packed.len());
+        assert_eq!(0, packed.heap_bytes());
+        assert!(packed.is_valid());
+    }
+}
@@ -0,0 +1,208 @@
+//! Relocation of double-array blocks to improve the locality of memory accesses.
+
+use alloc::vec::Vec;
+
+use crate::utils::FromU32;
+
+// The heuristic weight of a state at depth zero without children.
+const HEURISTIC_SCALE: u64 = 1 << 32;
+
+// The ratio of the total weight in percent that the hot blocks receive.
+const HOT_WEIGHT_PERCENT: u64 = 90;
+
+/// Locality of the states in the double array of an automaton.
+///
+/// Each state is weighted by the expected frequency of its accesses: the number of visits in a
+/// sample haystack if it is given, or otherwise a heuristic value that prefers shallow states with
+/// many children, assuming a haystack of random labels. The locality is measured by the number of
+/// the leading blocks of the array that receive 90% of the total weight, which is smaller when
+/// the hot states are clustered at the beginning of the array.
+///
+/// This is returned by [`DoubleArrayAhoCorasick::layout_stats()`],
+/// [`CharwiseDoubleArrayAhoCorasick::layout_stats()`], and their variants taking a sample.
+///
+/// [`DoubleArrayAhoCorasick::layout_stats()`]: crate::DoubleArrayAhoCorasick::layout_stats
+/// [`CharwiseDoubleArrayAhoCorasick::layout_stats()`]: crate::CharwiseDoubleArrayAhoCorasick::layout_stats
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct LayoutStats {
+    num_blocks: usize,
+    block_len: usize,
+    hot_blocks: usize,
+    total_weight: u64,
+}
+
+impl LayoutStats {
+    /// The number of blocks in the double array.
+    #[inline(always)]
+    #[must_use]
+    pub const fn num_blocks(&self) -> usize {
+        self.num_blocks
+    }
+
+    /// The number of elements in each block.
+    #[inline(always)]
+    #[must_use]
+    pub const fn block_len(&self) -> usize {
+        self.block_len
+    }
+
+    /// The minimum number of the leading blocks that receive 90% of the total weight.
+    #[inline(always)]
+    #[must_use]
+    pub const fn hot_blocks(&self) -> usize {
+        self.hot_blocks
+    }
+
+    /// The total weight of the states.
+    #[inline(always)]
+    #[must_use]
+    pub const fn total_weight(&self) -> u64 {
+        self.total_weight
+    }
+}
+
+/// Returns the heuristic weight of a state at `depth` with `num_children` children.
+///
+/// The weight is proportional to the probability that a haystack of random labels drawn from
+/// `alphabet_size` ones reaches the state, multiplied by the number of children plus one.
+pub fn heuristic_weight(depth: u32, num_children: u32, alphabet_size: u32) -> u64 {
+    u64::from(alphabet_size.max(1))
+        .checked_pow(depth)
+        .map_or(0, |x| HEURISTIC_SCALE / x)
+        * (u64::from(num_children) + 1)
+}
+
+/// Sums the weights of the states in each block.
+pub fn block_weights(state_weights: &[u64], block_len: u32) -> Vec<u64> {
+    state_weights
+        .chunks(usize::from_u32(block_len))
+        .map(|weights| weights.iter().sum())
+        .collect()
+}
+
+pub fn layout_stats(block_weights: &[u64], block_len: u32) -> LayoutStats {
+    let total_weight: u64 = block_weights.iter().sum();
+    let mut hot_blocks = 0;
+    let mut weight = 0;
+    // Compares in u128 to avoid overflow.
+    while u128::from(weight) * 100 < u128::from(total_weight) * u128::from(HOT_WEIGHT_PERCENT) {
+        weight += block_weights[hot_blocks];
+        hot_blocks += 1;
+    }
+    LayoutStats {
+        num_blocks: block_weights.len(),
+        block_len: usize::from_u32(block_len),
+        hot_blocks,
+        total_weight,
+    }
+}
+
+/// Permutation of the blocks placing heavier blocks earlier.
+///
+/// The first block is never moved since it contains the root and dead states, whose indices are
+/// fixed. Since every child of a state is placed in the block of its BASE value, relocating whole
+/// blocks only requires converting the indices stored in the states.
+pub struct BlockRelocation {
+    // new_blocks[i] is the new position of block i.
+    new_blocks: Vec<u32>,
+    block_len: u32,
+}
+
+impl BlockRelocation {
+    /// # Panics
+    ///
+    /// `block_len` must be a power of two.
+    pub fn new(block_weights: &[u64], block_len: u32) -> Self {
+        assert!(block_len.is_power_of_two());
+        let mut order: Vec<u32> = (1..u32::try_from(block_weights.len()).unwrap()).collect();
+        // The stable sort keeps the original order of blocks with the same weight.
+        order.sort_by(|&i, &j| {
+            block_weights[usize::from_u32(j)].cmp(&block_weights[usize::from_u32(i)])
+        });
+        let mut new_blocks = vec![0; block_weights.len()];
+        for (new_block, &block) in (1..).zip(&order) {
+            new_blocks[usize::from_u32(block)] = new_block;
+        }
+        Self {
+            new_blocks,
+            block_len,
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        (0..).zip(&self.new_blocks).all(|(i, &j)| i == j)
+    }
+
+    /// Returns the new index of the element at `idx`. Indices out of the array are kept.
+    #[inline(always)]
+    pub fn map(&self, idx: u32) -> u32 {
+        self.new_blocks
+            .get(usize::from_u32(idx / self.block_len))
+            .map_or(idx, |&block| {
+                (block * self.block_len) | (idx & (self.block_len - 1))
+            })
+    }
+
+    /// Moves the elements to their new positions.
+    pub fn apply<T>(&self, items: &mut Vec<T>)
+    where
+        T: Copy + Default,
+    {
+        let mut relocated = vec![T::default(); items.len()];
+        for (idx, &item) in (0..).zip(items.iter()) {
+            relocated[usize::from_u32(self.map(idx))] = item;
+        }
+        *items = relocated;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_relocation() {
+        let relocation = BlockRelocation::new(&[1, 5, 9, 5, 0], 4);
+        assert!(!relocation.is_identity());
+        assert_eq!(vec![0, 2, 1, 3, 4], relocation.new_blocks);
+        assert_eq!(1, relocation.map(1));
+        assert_eq!(9, relocation.map(5));
+        assert_eq!(6, relocation.map(10));
+        assert_eq!(15, relocation.map(15));
+        // Out of the array
+        assert_eq!(20, relocation.map(20));
+
+        let mut items: Vec<u32> = (0..20).collect();
+        relocation.apply(&mut items);
+        assert_eq!(
+            vec![0, 1, 2, 3, 8, 9, 10, 11, 4, 5, 6, 7, 12, 13, 14, 15, 16, 17, 18, 19],
+            items,
+        );
+
+        assert!(BlockRelocation::new(&[0, 3, 2, 2], 2).is_identity());
+    }
+
+    #[test]
+    fn test_layout_stats() {
+        let stats = layout_stats(&[50, 0, 30, 0, 10, 10], 256);
+        assert_eq!(6, stats.num_blocks());
+        assert_eq!(256, stats.block_len());
+        assert_eq!(100, stats.total_weight());
+        assert_eq!(5, stats.hot_blocks());
+
+        let stats = layout_stats(&[50, 30, 10, 10, 0, 0], 256);
+        assert_eq!(3, stats.hot_blocks());
+
+        let stats = layout_stats(&[0, 0], 256);
+        assert_eq!(0, stats.hot_blocks());
+    }
+
+    #[test]
+    fn test_heuristic_weight() {
+        assert_eq!(3 << 32, heuristic_weight(0, 2, 4));
+        assert_eq!(1 << 30, heuristic_weight(1, 0, 4));
+        assert_eq!(3 << 28, heuristic_weight(2, 2, 4));
+        assert_eq!(0, heuristic_weight(17, 3, 4));
+        assert_eq!(0, heuristic_weight(100, 3, 1 << 21));
+    }
+}
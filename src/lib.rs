@@ -188,23 +188,50 @@ compile_error!("`target_pointer_width` must be 32 or 64");
 #[macro_use]
 extern crate alloc;
 
+#[cfg(feature = "std")]
+extern crate std;
+
 mod build_helper;
 pub mod bytewise;
 pub mod charwise;
+mod dot;
 pub mod errors;
 mod intpack;
+mod layout;
 mod nfa_builder;
+mod outputs;
+#[cfg(feature = "serde")]
+mod serde_impls;
 mod serializer;
+mod sorted_nfa_builder;
+mod stats;
 mod utils;
 
 use core::num::NonZeroU32;
 
+use alloc::boxed::Box;
 use alloc::vec::Vec;
 
 use build_helper::BuildHelper;
-pub use bytewise::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder};
+pub use bytewise::{
+    DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasickEditor,
+};
 pub use charwise::{CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder};
-pub use serializer::Serializable;
+use errors::{DaachorseError, Result};
+pub use layout::LayoutStats;
+use outputs::OutputStore;
+use serializer::FixedBytes;
+#[cfg(feature = "std")]
+pub use serializer::IoSink;
+pub use serializer::{Serializable, Sink, VecSink};
+pub use stats::AutomatonStats;
+use utils::FromU32;
+
+// Items used by the exported macros.
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::vec::Vec;
+}
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 struct Output<V> {
@@ -254,27 +281,42 @@ where
     fn serialize_to_vec(&self, dst: &mut Vec<u8>) {
         self.value.serialize_to_vec(dst);
         self.length.serialize_to_vec(dst);
-        self.parent.serialize_to_vec(dst);
+        self.parent.map_or(0, NonZeroU32::get).serialize_to_vec(dst);
     }
 
     #[inline(always)]
     fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
         let (value, src) = V::deserialize_from_slice(src);
         let (length, src) = u32::deserialize_from_slice(src);
-        let (parent, src) = Option::<NonZeroU32>::deserialize_from_slice(src);
+        let (parent, src) = u32::deserialize_from_slice(src);
         (
             Self {
                 value,
                 length,
-                parent,
+                parent: NonZeroU32::new(parent),
             },
             src,
         )
     }
 
+    #[inline(always)]
+    fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])> {
+        let (value, src) = V::deserialize_from_slice_checked(src)?;
+        let (length, src) = u32::deserialize_from_slice_checked(src)?;
+        let (parent, src) = u32::deserialize_from_slice_checked(src)?;
+        Some((
+            Self {
+                value,
+                length,
+                parent: NonZeroU32::new(parent),
+            },
+            src,
+        ))
+    }
+
     #[inline(always)]
     fn serialized_bytes() -> usize {
-        V::serialized_bytes() + u32::serialized_bytes() + Option::<NonZeroU32>::serialized_bytes()
+        V::serialized_bytes() + u32::serialized_bytes() * 2
     }
 }
 
@@ -315,6 +357,75 @@ where
     }
 }
 
+/// Cursor over the values of a pattern following the one reported by a non-overlapping iterator.
+#[derive(Clone, Copy, Default)]
+struct ValueCursor {
+    output_pos: Option<NonZeroU32>,
+    length: u32,
+    end: usize,
+}
+
+impl ValueCursor {
+    /// Returns the match of `out` and remembers the following values of the same pattern.
+    #[inline(always)]
+    fn start<V>(&mut self, out: Output<V>, end: usize) -> Match<V>
+    where
+        V: Copy,
+    {
+        self.output_pos = out.parent();
+        self.length = out.length();
+        self.end = end;
+        Match {
+            length: usize::from_u32(out.length()),
+            end,
+            value: out.value(),
+        }
+    }
+
+    /// Returns the match of the next value of the same pattern.
+    ///
+    /// # Safety
+    ///
+    /// The output positions must be given by [`Self::start()`] with the outputs of the same
+    /// automaton.
+    #[inline(always)]
+    unsafe fn next<V>(&mut self, outputs: &OutputStore<V>) -> Option<Match<V>>
+    where
+        V: Copy,
+    {
+        self.next_as(outputs, outputs.is_compact())
+    }
+
+    /// Returns the match of the next value of the same pattern, assuming that the outputs are
+    /// compact if and only if `compact` is true.
+    ///
+    /// # Safety
+    ///
+    /// The output positions must be given by [`Self::start()`] with the outputs of the same
+    /// automaton, and `compact` must be the same as [`OutputStore::is_compact()`].
+    #[inline(always)]
+    unsafe fn next_as<V>(&mut self, outputs: &OutputStore<V>, compact: bool) -> Option<Match<V>>
+    where
+        V: Copy,
+    {
+        let output_pos = self.output_pos?;
+        // output_pos.get() is always smaller than outputs.len() because Output::parent() ensures
+        // to return such a value when it is Some.
+        let out = outputs.get_unchecked_as(usize::from_u32(output_pos.get() - 1), compact);
+        // The values of the same pattern are chained in front of the shorter suffixes.
+        if out.length() != self.length {
+            self.output_pos = None;
+            return None;
+        }
+        self.output_pos = out.parent();
+        Some(Match {
+            length: usize::from_u32(self.length),
+            end: self.end,
+            value: out.value(),
+        })
+    }
+}
+
 /// A search option of the Aho-Corasick automaton
 /// specified in [`DoubleArrayAhoCorasickBuilder::match_kind`].
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -355,6 +466,41 @@ impl MatchKind {
     pub(crate) fn is_leftmost_first(self) -> bool {
         self == Self::LeftmostFirst
     }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Standard => "Standard",
+            Self::LeftmostLongest => "LeftmostLongest",
+            Self::LeftmostFirst => "LeftmostFirst",
+        }
+    }
+
+    /// Returns an error if the match kind does not support the standard search functions.
+    pub(crate) fn check_standard(self) -> Result<()> {
+        if !self.is_standard() {
+            return Err(DaachorseError::match_kind_mismatch("Standard", self));
+        }
+        Ok(())
+    }
+
+    /// Returns an error if the match kind does not support the leftmost search functions.
+    pub(crate) fn check_leftmost(self) -> Result<()> {
+        if !self.is_leftmost() {
+            return Err(DaachorseError::match_kind_mismatch(
+                "LeftmostLongest or LeftmostFirst",
+                self,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns an error if the match kind differs from `expected`.
+    pub(crate) fn check_same(self, expected: Self) -> Result<()> {
+        if self != expected {
+            return Err(DaachorseError::match_kind_mismatch(expected.name(), self));
+        }
+        Ok(())
+    }
 }
 
 impl From<u8> for MatchKind {
@@ -388,12 +534,142 @@ impl Serializable for MatchKind {
         (Self::from(src[0]), &src[1..])
     }
 
+    #[inline(always)]
+    fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])> {
+        let (&x, src) = src.split_first()?;
+        let match_kind = Self::from(x);
+        (u8::from(match_kind) == x).then_some((match_kind, src))
+    }
+
     #[inline(always)]
     fn serialized_bytes() -> usize {
         1
     }
 }
 
+impl FixedBytes for MatchKind {
+    type Bytes = [u8; 1];
+
+    #[inline(always)]
+    fn to_bytes(&self) -> Self::Bytes {
+        [u8::from(*self)]
+    }
+}
+
+/// A policy to handle patterns registered more than once, specified in
+/// [`DoubleArrayAhoCorasickBuilder::on_duplicate()`] and
+/// [`CharwiseDoubleArrayAhoCorasickBuilder::on_duplicate()`].
+#[derive(Clone, Copy, Debug)]
+pub enum DuplicatePolicy<V> {
+    /// Returns [`DuplicatePatternError`](errors::DuplicatePatternError).
+    Error,
+
+    /// Keeps the value of the first occurrence.
+    KeepFirst,
+
+    /// Keeps the value of the last occurrence.
+    KeepLast,
+
+    /// Combines the value of the previous occurrences with the new one using the function.
+    Merge(fn(V, V) -> V),
+
+    /// Keeps all the values. Each value is reported as a separate match in the registration
+    /// order.
+    KeepAll,
+}
+
+/// A phase of the automaton construction, reported to the callback specified in
+/// [`DoubleArrayAhoCorasickBuilder::progress()`] and
+/// [`CharwiseDoubleArrayAhoCorasickBuilder::progress()`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum BuildPhase {
+    /// Inserting the patterns into the trie. The count is the number of patterns inserted.
+    TrieInsertion,
+
+    /// Computing the failure links. The count is the number of states processed.
+    FailLinks,
+
+    /// Placing the states in the double array. The count is the number of states placed.
+    DoubleArrayPlacement,
+}
+
+/// Progress of the automaton construction.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct BuildProgress {
+    phase: BuildPhase,
+    count: usize,
+    total: Option<usize>,
+}
+
+impl BuildProgress {
+    /// Phase in progress.
+    #[inline(always)]
+    #[must_use]
+    pub const fn phase(&self) -> BuildPhase {
+        self.phase
+    }
+
+    /// The number of items processed in the phase.
+    #[inline(always)]
+    #[must_use]
+    pub const fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The number of items to be processed in the phase, or `None` if it is unknown in advance
+    /// as in [`BuildPhase::TrieInsertion`].
+    #[inline(always)]
+    #[must_use]
+    pub const fn total(&self) -> Option<usize> {
+        self.total
+    }
+}
+
+/// Invokes the progress callback of a builder at regular intervals.
+#[derive(Default)]
+pub(crate) struct ProgressReporter {
+    callback: Option<Box<dyn FnMut(BuildProgress) + Send>>,
+}
+
+impl ProgressReporter {
+    // The number of items processed between two reports.
+    const INTERVAL: usize = 1 << 16;
+
+    pub(crate) const fn new(callback: Option<Box<dyn FnMut(BuildProgress) + Send>>) -> Self {
+        Self { callback }
+    }
+
+    /// Reports the beginning of the phase.
+    pub(crate) fn start(&mut self, phase: BuildPhase, total: Option<usize>) {
+        self.report(phase, 0, total);
+    }
+
+    /// Reports the end of the phase processing `total` items.
+    pub(crate) fn finish(&mut self, phase: BuildPhase, total: usize) {
+        self.report(phase, total, Some(total));
+    }
+
+    /// Reports the progress in the middle of the phase if `count` is a multiple of the interval.
+    #[inline(always)]
+    pub(crate) fn tick(&mut self, phase: BuildPhase, count: usize, total: Option<usize>) {
+        if count % Self::INTERVAL == 0 && total != Some(count) {
+            self.report(phase, count, total);
+        }
+    }
+
+    /// Reports the progress.
+    #[inline(always)]
+    pub(crate) fn report(&mut self, phase: BuildPhase, count: usize, total: Option<usize>) {
+        if let Some(callback) = self.callback.as_mut() {
+            callback(BuildProgress {
+                phase,
+                count,
+                total,
+            });
+        }
+    }
+}
+
 /// Empty value type.
 ///
 /// This is a unit struct with no field. This type can be used to reduce memory consumption when
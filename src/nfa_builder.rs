@@ -1,11 +1,13 @@
 use core::cell::RefCell;
+use core::mem;
 use core::num::NonZeroU32;
 
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 
 use crate::errors::{DaachorseError, Result};
 use crate::utils::FromU32;
-use crate::{MatchKind, Output};
+use crate::{BuildPhase, DuplicatePolicy, MatchKind, Output, ProgressReporter};
 
 // The root state id of SparseNFA.
 pub const ROOT_STATE_ID: u32 = 0;
@@ -29,7 +31,7 @@ impl EdgeLabel for char {
 }
 
 /// Mapping edge lables to child ids using `BTreeMap`.
-type EdgeMap<L> = alloc::collections::BTreeMap<L, u32>;
+type EdgeMap<L> = BTreeMap<L, u32>;
 
 /// State of [`NfaBuilder`].
 #[derive(Clone)]
@@ -57,6 +59,10 @@ pub struct NfaBuilder<L, V> {
     pub(crate) outputs: Vec<Output<V>>, // in which common parts are merged.
     pub(crate) len: usize,
     pub(crate) match_kind: MatchKind,
+    pub(crate) duplicate_policy: DuplicatePolicy<V>,
+    // Values following the first one for each state, registered with DuplicatePolicy::KeepAll.
+    multi_values: BTreeMap<u32, Vec<V>>,
+    num_multi_values: usize,
 }
 
 impl<L, V> NfaBuilder<L, V>
@@ -64,7 +70,7 @@ where
     L: EdgeLabel,
     V: Copy,
 {
-    pub(crate) fn new(match_kind: MatchKind) -> Self {
+    pub(crate) fn new(match_kind: MatchKind, duplicate_policy: DuplicatePolicy<V>) -> Self {
         Self {
             states: vec![
                 RefCell::new(NfaBuilderState::<L, V>::default()), // root
@@ -73,6 +79,9 @@ where
             outputs: vec![],
             len: 0,
             match_kind,
+            duplicate_policy,
+            multi_values: BTreeMap::new(),
+            num_multi_values: 0,
         }
     }
 
@@ -83,8 +92,7 @@ where
             .fold(0, |acc, c| acc + c.num_bytes())
             .try_into()
             .map_err(|_| DaachorseError::invalid_argument("pattern.len()", "<=", u32::MAX))?;
-        let pattern_len = NonZeroU32::new(pattern_len)
-            .ok_or_else(|| DaachorseError::invalid_argument("pattern.len()", ">=", 1))?;
+        let pattern_len = NonZeroU32::new(pattern_len).ok_or_else(DaachorseError::empty_pattern)?;
 
         let mut state_id = ROOT_STATE_ID;
         for &c in pattern {
@@ -112,15 +120,29 @@ where
         }
 
         let output = &mut self.states[usize::from_u32(state_id)].borrow_mut().output;
-        if output.replace((value, pattern_len)).is_some() {
-            return Err(DaachorseError::duplicate_pattern(format!("{pattern:?}")));
+        if let Some((old_value, _)) = output {
+            *old_value = match self.duplicate_policy {
+                DuplicatePolicy::Error => {
+                    return Err(DaachorseError::duplicate_pattern(format!("{pattern:?}")));
+                }
+                DuplicatePolicy::KeepFirst => *old_value,
+                DuplicatePolicy::KeepLast => value,
+                DuplicatePolicy::Merge(f) => f(*old_value, value),
+                DuplicatePolicy::KeepAll => {
+                    self.multi_values.entry(state_id).or_default().push(value);
+                    self.num_multi_values += 1;
+                    *old_value
+                }
+            };
+            return Ok(());
         }
+        *output = Some((value, pattern_len));
 
         self.len += 1;
         Ok(())
     }
 
-    pub(crate) fn build_fails(&self) -> Vec<u32> {
+    pub(crate) fn build_fails(&self, progress: &mut ProgressReporter) -> Vec<u32> {
         let mut q = Vec::with_capacity(self.states.len());
         for &child_id in self.states[usize::from_u32(ROOT_STATE_ID)]
             .borrow()
@@ -130,10 +152,14 @@ where
             q.push(child_id);
         }
 
+        // The root and dead states are not processed.
+        let total = Some(self.states.len() - 2);
+        progress.start(BuildPhase::FailLinks, total);
         let mut qi = 0;
         while qi < q.len() {
             let state_id = usize::from_u32(q[qi]);
             qi += 1;
+            progress.tick(BuildPhase::FailLinks, qi, total);
 
             let s = &self.states[state_id].borrow();
             for (&c, &child_id) in &s.edges {
@@ -152,10 +178,11 @@ where
                 q.push(child_id);
             }
         }
+        progress.finish(BuildPhase::FailLinks, q.len());
         q
     }
 
-    pub(crate) fn build_fails_leftmost(&self) -> Vec<u32> {
+    pub(crate) fn build_fails_leftmost(&self, progress: &mut ProgressReporter) -> Vec<u32> {
         let mut q = Vec::with_capacity(self.states.len());
         for &child_id in self.states[usize::from_u32(ROOT_STATE_ID)]
             .borrow()
@@ -165,10 +192,14 @@ where
             q.push(child_id);
         }
 
+        // The root and dead states are not processed.
+        let total = Some(self.states.len() - 2);
+        progress.start(BuildPhase::FailLinks, total);
         let mut qi = 0;
         while qi < q.len() {
             let state_id = usize::from_u32(q[qi]);
             qi += 1;
+            progress.tick(BuildPhase::FailLinks, qi, total);
 
             let s = &mut self.states[state_id].borrow_mut();
 
@@ -203,6 +234,7 @@ where
                 q.push(child_id);
             }
         }
+        progress.finish(BuildPhase::FailLinks, q.len());
         q
     }
 
@@ -215,16 +247,52 @@ where
         for &state_id in q {
             let s = &mut self.states[usize::from_u32(state_id)].borrow_mut();
             if let Some(output) = s.output {
-                s.output_pos = NonZeroU32::new(u32::try_from(self.outputs.len() + 1).unwrap());
                 let parent = self.states[usize::from_u32(s.fail)].borrow().output_pos;
-                self.outputs
-                    .push(Output::new(output.0, output.1.get(), parent));
+                let values = self.multi_values.get(&state_id).map_or(&[][..], |v| &v[..]);
+                s.output_pos = push_outputs(&mut self.outputs, output, values, parent);
             } else {
                 s.output_pos = self.states[usize::from_u32(s.fail)].borrow().output_pos;
             }
         }
     }
 
+    /// Merges the trie of `other`, whose root has no labels in common with that of `self`.
+    #[cfg(feature = "std")]
+    pub(crate) fn graft(&mut self, other: Self) -> Result<()> {
+        // The root and dead states of other are not copied.
+        let num_states = self.states.len() + other.states.len() - 2;
+        if num_states > usize::from_u32(u32::MAX) {
+            return Err(DaachorseError::automaton_scale("state_id", u32::MAX));
+        }
+        // other has at least the root and dead states, and the sum fits in u32.
+        let offset = u32::try_from(self.states.len() - 2).unwrap();
+
+        let mut states = other.states.into_iter().map(RefCell::into_inner);
+        let root = states.next().unwrap();
+        states.next().unwrap(); // dead
+        {
+            let edges = &mut self.states[usize::from_u32(ROOT_STATE_ID)]
+                .borrow_mut()
+                .edges;
+            for (c, child_id) in root.edges {
+                debug_assert!(!edges.contains_key(&c));
+                edges.insert(c, child_id + offset);
+            }
+        }
+        for mut state in states {
+            for child_id in state.edges.values_mut() {
+                *child_id += offset;
+            }
+            self.states.push(RefCell::new(state));
+        }
+        for (state_id, values) in other.multi_values {
+            self.multi_values.insert(state_id + offset, values);
+        }
+        self.len += other.len;
+        self.num_multi_values += other.num_multi_values;
+        Ok(())
+    }
+
     #[inline(always)]
     fn child_id(&self, state_id: u32, c: L) -> Option<u32> {
         self.states[usize::from_u32(state_id)]
@@ -234,3 +302,226 @@ where
             .copied()
     }
 }
+
+/// Read access to the states of an NFA whose fails and outputs are built, used to arrange the
+/// double array.
+pub trait NfaView<L, V> {
+    /// Returns the number of states including the root and dead states.
+    fn num_states(&self) -> usize;
+
+    /// Pushes the pairs of labels and child ids of the state in ascending order of labels.
+    fn push_edges(&self, state_id: u32, edges: &mut Vec<(L, u32)>);
+
+    fn fail(&self, state_id: u32) -> u32;
+
+    fn output_pos(&self, state_id: u32) -> Option<NonZeroU32>;
+
+    fn into_outputs(self) -> Vec<Output<V>>;
+
+    /// Returns the estimated heap memory usage in bytes.
+    fn heap_bytes(&self) -> usize;
+}
+
+impl<L, V> NfaView<L, V> for NfaBuilder<L, V>
+where
+    L: EdgeLabel,
+{
+    fn num_states(&self) -> usize {
+        self.states.len()
+    }
+
+    fn push_edges(&self, state_id: u32, edges: &mut Vec<(L, u32)>) {
+        let s = &self.states[usize::from_u32(state_id)].borrow();
+        edges.extend(s.edges.iter().map(|(&c, &child_id)| (c, child_id)));
+    }
+
+    fn fail(&self, state_id: u32) -> u32 {
+        self.states[usize::from_u32(state_id)].borrow().fail
+    }
+
+    fn output_pos(&self, state_id: u32) -> Option<NonZeroU32> {
+        self.states[usize::from_u32(state_id)].borrow().output_pos
+    }
+
+    fn into_outputs(self) -> Vec<Output<V>> {
+        self.outputs
+    }
+
+    fn heap_bytes(&self) -> usize {
+        // Each state other than the root and dead states is an entry in the edge map of its
+        // parent, and the nodes of the maps are assumed to be half full.
+        self.states.capacity() * mem::size_of::<RefCell<NfaBuilderState<L, V>>>()
+            + (self.states.len() - 2) * mem::size_of::<(L, u32)>() * 2
+            + self.outputs.capacity() * mem::size_of::<Output<V>>()
+            + self.num_multi_values * mem::size_of::<V>()
+    }
+}
+
+/// Returns the fail of the child with label `c` of a state whose fail is `fail_id`.
+///
+/// The fails of the states shallower than the child must be given in `fails`. If `leftmost` is
+/// true, the fails of the states having outputs must be set to the dead state.
+#[inline(always)]
+pub fn find_fail<L, F>(child_id: F, fails: &[u32], mut fail_id: u32, c: L, leftmost: bool) -> u32
+where
+    F: Fn(u32, L) -> Option<u32>,
+    L: Copy,
+{
+    // If the parent has the dead fail, the child also has the dead fail.
+    if fail_id == DEAD_STATE_ID {
+        return DEAD_STATE_ID;
+    }
+    loop {
+        if let Some(child_fail_id) = child_id(fail_id, c) {
+            return child_fail_id;
+        }
+        let next_fail_id = fails[usize::from_u32(fail_id)];
+        if leftmost && next_fail_id == DEAD_STATE_ID {
+            return DEAD_STATE_ID;
+        }
+        if fail_id == ROOT_STATE_ID && next_fail_id == ROOT_STATE_ID {
+            return ROOT_STATE_ID;
+        }
+        fail_id = next_fail_id;
+    }
+}
+
+/// Pushes the outputs of a state having `output` and the additional `values`, followed by the
+/// outputs at `parent`, and returns the position of the first one.
+pub fn push_outputs<V>(
+    outputs: &mut Vec<Output<V>>,
+    output: (V, NonZeroU32),
+    values: &[V],
+    parent: Option<NonZeroU32>,
+) -> Option<NonZeroU32>
+where
+    V: Copy,
+{
+    let output_pos = NonZeroU32::new(u32::try_from(outputs.len() + 1).unwrap());
+    // The values of the same pattern are chained in front of the suffixes.
+    for &value in core::iter::once(&output.0).chain(values) {
+        let next_pos = u32::try_from(outputs.len() + 2).unwrap();
+        outputs.push(Output::new(
+            value,
+            output.1.get(),
+            NonZeroU32::new(next_pos),
+        ));
+    }
+    // The last value is followed by the suffixes.
+    outputs.last_mut().unwrap().parent = parent;
+    output_pos
+}
+
+// The minimum number of states in a BFS level to compute the fails in parallel.
+#[cfg(feature = "std")]
+const MIN_PARALLEL_LEVEL_LEN: usize = 1 << 12;
+
+#[cfg(feature = "std")]
+impl<L, V> NfaBuilder<L, V>
+where
+    L: EdgeLabel + Send + Sync,
+    V: Copy + Send + Sync,
+{
+    /// Computes the same fails as [`Self::build_fails()`] or [`Self::build_fails_leftmost()`],
+    /// processing the states of each BFS level in parallel.
+    ///
+    /// The fail of a state only depends on the fails of shallower states, so the states in the
+    /// same level are independent of each other.
+    pub(crate) fn par_build_fails(
+        &mut self,
+        num_threads: usize,
+        progress: &mut ProgressReporter,
+    ) -> Vec<u32> {
+        let leftmost = self.match_kind.is_leftmost();
+        // The root and dead states are not processed.
+        let total = self.states.len() - 2;
+        progress.start(BuildPhase::FailLinks, Some(total));
+        let states: Vec<_> = mem::take(&mut self.states)
+            .into_iter()
+            .map(RefCell::into_inner)
+            .collect();
+        let mut fails: Vec<_> = states.iter().map(|s| s.fail).collect();
+
+        let mut q: Vec<u32> = states[usize::from_u32(ROOT_STATE_ID)]
+            .edges
+            .values()
+            .copied()
+            .collect();
+        let mut level_start = 0;
+        while level_start < q.len() {
+            let level_end = q.len();
+            let level = &q[level_start..level_end];
+            if leftmost {
+                // Sets the output state to the dead fail.
+                for &state_id in level {
+                    if states[usize::from_u32(state_id)].output.is_some() {
+                        fails[usize::from_u32(state_id)] = DEAD_STATE_ID;
+                    }
+                }
+            }
+
+            let children = if num_threads > 1 && level.len() >= MIN_PARALLEL_LEVEL_LEN {
+                let chunk_len = (level.len() + num_threads - 1) / num_threads;
+                let (states, fails) = (&states, &fails);
+                std::thread::scope(|s| {
+                    let handles: Vec<_> = level
+                        .chunks(chunk_len)
+                        .map(|chunk| {
+                            s.spawn(move || Self::find_child_fails(states, fails, chunk, leftmost))
+                        })
+                        .collect();
+                    handles
+                        .into_iter()
+                        .flat_map(|h| h.join().unwrap())
+                        .collect()
+                })
+            } else {
+                Self::find_child_fails(&states, &fails, level, leftmost)
+            };
+
+            for (child_id, fail_id) in children {
+                fails[usize::from_u32(child_id)] = fail_id;
+                q.push(child_id);
+            }
+            level_start = level_end;
+            if level_start < total {
+                progress.report(BuildPhase::FailLinks, level_start, Some(total));
+            }
+        }
+
+        self.states = states
+            .into_iter()
+            .zip(fails)
+            .map(|(mut state, fail)| {
+                state.fail = fail;
+                RefCell::new(state)
+            })
+            .collect();
+        progress.finish(BuildPhase::FailLinks, q.len());
+        q
+    }
+
+    /// Returns the pairs of the children of `parents` and their fails, in BFS order.
+    fn find_child_fails(
+        states: &[NfaBuilderState<L, V>],
+        fails: &[u32],
+        parents: &[u32],
+        leftmost: bool,
+    ) -> Vec<(u32, u32)> {
+        let mut children = vec![];
+        for &state_id in parents {
+            let s = &states[usize::from_u32(state_id)];
+            for (&c, &child_id) in &s.edges {
+                let fail_id = find_fail(
+                    |state_id, c| states[usize::from_u32(state_id)].edges.get(&c).copied(),
+                    fails,
+                    fails[usize::from_u32(state_id)],
+                    c,
+                    leftmost,
+                );
+                children.push((child_id, fail_id));
+            }
+        }
+        children
+    }
+}
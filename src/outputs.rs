@@ -0,0 +1,379 @@
+//! Storage of the outputs of an automaton.
+
+use core::mem;
+use core::num::NonZeroU32;
+
+use alloc::vec::Vec;
+
+use crate::errors::{DaachorseError, Result};
+use crate::intpack::{bit_width, PackedVec};
+use crate::serializer::{self, Serializable, SerializableVec, Sink};
+use crate::utils::FromU32;
+use crate::Output;
+
+// The mark of the outputs on the chain being traversed in verification.
+const ON_PATH: u8 = 1;
+// The mark of the outputs on verified chains.
+const VERIFIED: u8 = 2;
+
+/// Outputs of an automaton, stored as they are or in the compact representation.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum OutputStore<V> {
+    Plain(Vec<Output<V>>),
+    Compact(CompactOutputs<V>),
+}
+
+impl<V> Default for OutputStore<V> {
+    fn default() -> Self {
+        Self::Plain(vec![])
+    }
+}
+
+impl<V> OutputStore<V> {
+    pub const fn is_compact(&self) -> bool {
+        matches!(self, Self::Compact(_))
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Plain(outputs) => outputs.len(),
+            Self::Compact(outputs) => outputs.values.len(),
+        }
+    }
+
+    pub fn heap_bytes(&self) -> usize {
+        match self {
+            Self::Plain(outputs) => outputs.len() * mem::size_of::<Output<V>>(),
+            Self::Compact(outputs) => {
+                outputs.values.len() * mem::size_of::<V>() + outputs.fields.heap_bytes()
+            }
+        }
+    }
+}
+
+impl<V> OutputStore<V>
+where
+    V: Copy,
+{
+    pub fn new(outputs: Vec<Output<V>>, compact: bool) -> Self {
+        if compact {
+            Self::Compact(CompactOutputs::new(&outputs))
+        } else {
+            Self::Plain(outputs)
+        }
+    }
+
+    /// # Panics
+    ///
+    /// `i` must be smaller than the number of outputs.
+    #[inline(always)]
+    pub fn get(&self, i: usize) -> Output<V> {
+        assert!(i < self.len());
+        // i is checked above.
+        unsafe { self.get_unchecked(i) }
+    }
+
+    /// # Safety
+    ///
+    /// `i` must be smaller than the number of outputs.
+    #[inline(always)]
+    pub unsafe fn get_unchecked(&self, i: usize) -> Output<V> {
+        match self {
+            Self::Plain(outputs) => *outputs.get_unchecked(i),
+            Self::Compact(outputs) => outputs.get_unchecked(i),
+        }
+    }
+
+    /// Returns the `i`-th output, assuming that the store is compact if and only if `compact` is
+    /// true. When `compact` is a constant, the kind of the store is not checked.
+    ///
+    /// # Safety
+    ///
+    /// `i` must be smaller than the number of outputs, and `compact` must be the same as
+    /// [`Self::is_compact()`].
+    #[inline(always)]
+    pub unsafe fn get_unchecked_as(&self, i: usize, compact: bool) -> Output<V> {
+        match self {
+            Self::Plain(outputs) if !compact => *outputs.get_unchecked(i),
+            Self::Compact(outputs) if compact => outputs.get_unchecked(i),
+            _ => core::hint::unreachable_unchecked(),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Output<V>> + '_ {
+        // Every index is smaller than the number of outputs.
+        (0..self.len()).map(|i| unsafe { self.get_unchecked(i) })
+    }
+
+    /// Checks that the parents of the outputs form chains ending in [`None`] without cycles, along
+    /// which the lengths do not increase.
+    pub fn verify(&self) -> Result<()> {
+        let error = |invariant, i: usize| {
+            Err(DaachorseError::invalid_automaton(
+                invariant,
+                u32::try_from(i).ok(),
+            ))
+        };
+        if let Self::Compact(outputs) = self {
+            // The lengths and parents are u32 values.
+            if !outputs.fields.is_valid()
+                || usize::from_u32(outputs.fields.len()) != outputs.values.len()
+                || u32::from(outputs.length_bits) > u32::BITS
+                || outputs.fields.width() > u32::from(outputs.length_bits) + u32::BITS
+            {
+                return Err(DaachorseError::invalid_automaton(
+                    "compact outputs have a packed field for each value",
+                    None,
+                ));
+            }
+        }
+        let mut marks = vec![0; self.len()];
+        let mut path = vec![];
+        for i in 0..self.len() {
+            let mut j = i;
+            while marks[j] != VERIFIED {
+                if marks[j] == ON_PATH {
+                    return error("output chains have no cycles", j);
+                }
+                marks[j] = ON_PATH;
+                path.push(j);
+                let output = self.get(j);
+                if output.length() == 0 {
+                    return error("output lengths are positive", j);
+                }
+                let parent = match output.parent() {
+                    Some(parent) => usize::from_u32(parent.get() - 1),
+                    None => break,
+                };
+                if self.len() <= parent {
+                    return error("output parents are in the outputs", j);
+                }
+                if output.length() < self.get(parent).length() {
+                    return error("output lengths do not increase along chains", j);
+                }
+                j = parent;
+            }
+            for &j in &path {
+                marks[j] = VERIFIED;
+            }
+            path.clear();
+        }
+        Ok(())
+    }
+
+    pub fn into_vec(self) -> Vec<Output<V>> {
+        match self {
+            Self::Plain(outputs) => outputs,
+            Self::Compact(_) => self.iter().collect(),
+        }
+    }
+}
+
+impl<V> OutputStore<V>
+where
+    V: Serializable,
+{
+    pub fn serialize_into<W>(&self, dst: &mut W) -> Result<(), W::Error>
+    where
+        W: Sink + ?Sized,
+    {
+        match self {
+            Self::Plain(outputs) => {
+                serializer::serialize_value_into(&0u8, dst)?;
+                outputs.serialize_into(dst)
+            }
+            Self::Compact(outputs) => {
+                serializer::serialize_value_into(&1u8, dst)?;
+                outputs.values.serialize_into(dst)?;
+                outputs.fields.serialize_into(dst)?;
+                serializer::serialize_value_into(&outputs.length_bits, dst)
+            }
+        }
+    }
+
+    pub fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
+        let (tag, src) = u8::deserialize_from_slice(src);
+        if tag == 0 {
+            let (outputs, src) = Vec::<Output<V>>::deserialize_from_slice(src);
+            return (Self::Plain(outputs), src);
+        }
+        let (values, src) = Vec::<V>::deserialize_from_slice(src);
+        let (fields, src) = PackedVec::deserialize_from_slice(src);
+        let (length_bits, src) = u8::deserialize_from_slice(src);
+        (
+            Self::Compact(CompactOutputs {
+                values,
+                fields,
+                length_bits,
+            }),
+            src,
+        )
+    }
+
+    pub fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])> {
+        let (tag, src) = u8::deserialize_from_slice_checked(src)?;
+        match tag {
+            0 => {
+                let (outputs, src) = Vec::<Output<V>>::deserialize_from_slice_checked(src)?;
+                Some((Self::Plain(outputs), src))
+            }
+            1 => {
+                let (values, src) = Vec::<V>::deserialize_from_slice_checked(src)?;
+                let (fields, src) = PackedVec::deserialize_from_slice_checked(src)?;
+                let (length_bits, src) = u8::deserialize_from_slice_checked(src)?;
+                Some((
+                    Self::Compact(CompactOutputs {
+                        values,
+                        fields,
+                        length_bits,
+                    }),
+                    src,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn serialized_bytes(&self) -> usize {
+        u8::serialized_bytes()
+            + match self {
+                Self::Plain(outputs) => outputs.serialized_bytes(),
+                Self::Compact(outputs) => {
+                    outputs.values.serialized_bytes()
+                        + outputs.fields.serialized_bytes()
+                        + u8::serialized_bytes()
+                }
+            }
+    }
+}
+
+/// Outputs whose lengths and parents are bit-packed with the minimum widths.
+///
+/// The length and parent of each output are packed into a single field, where the length occupies
+/// the lower `length_bits` bits.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct CompactOutputs<V> {
+    values: Vec<V>,
+    fields: PackedVec,
+    length_bits: u8,
+}
+
+impl<V> CompactOutputs<V>
+where
+    V: Copy,
+{
+    fn new(outputs: &[Output<V>]) -> Self {
+        let max_length = outputs.iter().map(|out| out.length()).max().unwrap_or(0);
+        let length_bits = bit_width(u64::from(max_length));
+        let fields: Vec<_> = outputs
+            .iter()
+            .map(|out| {
+                let parent = out.parent().map_or(0, NonZeroU32::get);
+                u64::from(parent) << length_bits | u64::from(out.length())
+            })
+            .collect();
+        Self {
+            values: outputs.iter().map(|out| out.value()).collect(),
+            fields: PackedVec::new(&fields),
+            // The width is at most 32.
+            length_bits: u8::try_from(length_bits).unwrap(),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `i` must be smaller than the number of outputs.
+    #[inline(always)]
+    unsafe fn get_unchecked(&self, i: usize) -> Output<V> {
+        let length_bits = u32::from(self.length_bits);
+        let field = self.fields.get_unchecked(i);
+        // The length and parent were given as u32 values.
+        let length = u32::try_from(field & ((1 << length_bits) - 1)).unwrap_unchecked();
+        let parent = u32::try_from(field >> length_bits).unwrap_unchecked();
+        Output::new(
+            *self.values.get_unchecked(i),
+            length,
+            NonZeroU32::new(parent),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::serializer::VecSink;
+
+    // The last output has the maximum length and parent.
+    fn generate_outputs(num_outputs: u32, max_length: u32, max_parent: u32) -> Vec<Output<u32>> {
+        let mut outputs: Vec<_> = (1..num_outputs)
+            .map(|i| {
+                let length = i % max_length + 1;
+                let parent = NonZeroU32::new(i.wrapping_mul(0x9e37_79b9) % max_parent.max(1));
+                Output::new(i, length, parent)
+            })
+            .collect();
+        outputs.push(Output::new(0, max_length, NonZeroU32::new(max_parent)));
+        outputs
+    }
+
+    #[test]
+    fn test_compact_outputs() {
+        for (max_length, max_parent) in [(1, 0), (7, 100), (300, 1 << 20), (u32::MAX, u32::MAX)] {
+            let outputs = generate_outputs(1000, max_length, max_parent);
+            let store = OutputStore::new(outputs.clone(), true);
+            assert!(store.is_compact());
+            assert_eq!(outputs.len(), store.len());
+            for (i, &out) in outputs.iter().enumerate() {
+                assert_eq!(out, store.get(i));
+            }
+            assert_eq!(outputs, store.into_vec());
+        }
+    }
+
+    #[test]
+    fn test_compact_outputs_heap_bytes() {
+        let outputs = generate_outputs(1000, 15, 1000);
+        let plain = OutputStore::new(outputs.clone(), false);
+        let compact = OutputStore::new(outputs, true);
+        // 4 bits of lengths and 10 bits of parents are packed.
+        assert_eq!(1000 * 12, plain.heap_bytes());
+        assert_eq!(1000 * 4 + (1000 * 14 + 63) / 64 * 8, compact.heap_bytes());
+    }
+
+    #[test]
+    fn test_verify() {
+        let output = |length, parent| Output::new(0, length, NonZeroU32::new(parent));
+        for compact in [false, true] {
+            // Chains 0 -> 1 -> 2 and 3 -> 1 -> 2
+            let outputs = vec![output(3, 2), output(2, 3), output(1, 0), output(2, 2)];
+            assert!(OutputStore::new(outputs, compact).verify().is_ok());
+
+            for outputs in [
+                // Cycle
+                vec![output(3, 2), output(3, 3), output(3, 2)],
+                // Out of range
+                vec![output(3, 2), output(2, 4), output(1, 0)],
+                // Increasing lengths
+                vec![output(3, 2), output(4, 3), output(1, 0)],
+                // Empty output
+                vec![output(3, 2), output(0, 0)],
+            ] {
+                assert!(OutputStore::new(outputs, compact).verify().is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_serialize() {
+        for compact in [false, true] {
+            let store = OutputStore::new(generate_outputs(100, 10, 50), compact);
+            let mut bytes = vec![];
+            store.serialize_into(&mut VecSink(&mut bytes)).unwrap();
+            assert_eq!(store.serialized_bytes(), bytes.len());
+            let (other, rest) = OutputStore::<u32>::deserialize_from_slice(&bytes);
+            assert!(rest.is_empty());
+            assert_eq!(store, other);
+        }
+    }
+}
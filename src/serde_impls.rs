@@ -0,0 +1,381 @@
+//! Implementations of the [`serde`] traits.
+//!
+//! The automata are represented as byte strings in the format of their `serialize()` functions,
+//! and they are deserialized through the checked loaders, i.e., `deserialize()`.
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use alloc::vec::Vec;
+
+use serde::de::{self, Deserialize, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use crate::errors::Result;
+use crate::{
+    CharwiseDoubleArrayAhoCorasick, DoubleArrayAhoCorasick, Match, MatchKind, Serializable,
+};
+
+/// Automata deserialized from byte strings.
+trait CheckedLoad: Sized {
+    const EXPECTING: &'static str;
+
+    fn load(source: &[u8]) -> Result<(Self, &[u8])>;
+}
+
+impl<V> CheckedLoad for DoubleArrayAhoCorasick<V>
+where
+    V: Serializable + Copy,
+{
+    const EXPECTING: &'static str = "a serialized DoubleArrayAhoCorasick";
+
+    fn load(source: &[u8]) -> Result<(Self, &[u8])> {
+        Self::deserialize(source)
+    }
+}
+
+impl<V> CheckedLoad for CharwiseDoubleArrayAhoCorasick<V>
+where
+    V: Serializable + Copy,
+{
+    const EXPECTING: &'static str = "a serialized CharwiseDoubleArrayAhoCorasick";
+
+    fn load(source: &[u8]) -> Result<(Self, &[u8])> {
+        Self::deserialize(source)
+    }
+}
+
+struct AutomatonVisitor<T>(PhantomData<T>);
+
+impl<'de, T> de::Visitor<'de> for AutomatonVisitor<T>
+where
+    T: CheckedLoad,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(T::EXPECTING)
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> core::result::Result<T, E>
+    where
+        E: de::Error,
+    {
+        let (pma, rest) = T::load(v).map_err(E::custom)?;
+        if !rest.is_empty() {
+            return Err(E::invalid_length(v.len(), &self));
+        }
+        Ok(pma)
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> core::result::Result<T, E>
+    where
+        E: de::Error,
+    {
+        self.visit_bytes(&v)
+    }
+
+    // Formats without native byte strings, e.g., JSON, represent them as sequences.
+    fn visit_seq<A>(self, mut seq: A) -> core::result::Result<T, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        // The size hint is untrusted, so the preallocation is capped.
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(1 << 16));
+        while let Some(b) = seq.next_element()? {
+            bytes.push(b);
+        }
+        self.visit_bytes(&bytes)
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<V> Serialize for DoubleArrayAhoCorasick<V>
+where
+    V: Serializable,
+{
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.serialize())
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'de, V> Deserialize<'de> for DoubleArrayAhoCorasick<V>
+where
+    V: Serializable + Copy,
+{
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(AutomatonVisitor(PhantomData))
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<V> Serialize for CharwiseDoubleArrayAhoCorasick<V>
+where
+    V: Serializable,
+{
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.serialize())
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'de, V> Deserialize<'de> for CharwiseDoubleArrayAhoCorasick<V>
+where
+    V: Serializable + Copy,
+{
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(AutomatonVisitor(PhantomData))
+    }
+}
+
+const MATCH_KIND_VARIANTS: &[&str] = &["Standard", "LeftmostLongest", "LeftmostFirst"];
+
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for MatchKind {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_unit_variant("MatchKind", u32::from(u8::from(*self)), self.name())
+    }
+}
+
+/// The identifier of a variant of [`MatchKind`], given by the name or the index.
+struct MatchKindVariant(MatchKind);
+
+impl<'de> Deserialize<'de> for MatchKindVariant {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = MatchKindVariant;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a variant of MatchKind")
+            }
+
+            fn visit_u64<E>(self, v: u64) -> core::result::Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                u8::try_from(v)
+                    .ok()
+                    .map(MatchKind::from)
+                    .filter(|&kind| u64::from(u8::from(kind)) == v)
+                    .map(MatchKindVariant)
+                    .ok_or_else(|| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+            }
+
+            fn visit_str<E>(self, v: &str) -> core::result::Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                match v {
+                    "Standard" => Ok(MatchKindVariant(MatchKind::Standard)),
+                    "LeftmostLongest" => Ok(MatchKindVariant(MatchKind::LeftmostLongest)),
+                    "LeftmostFirst" => Ok(MatchKindVariant(MatchKind::LeftmostFirst)),
+                    _ => Err(E::unknown_variant(v, MATCH_KIND_VARIANTS)),
+                }
+            }
+        }
+
+        deserializer.deserialize_identifier(Visitor)
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'de> Deserialize<'de> for MatchKind {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = MatchKind;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("enum MatchKind")
+            }
+
+            fn visit_enum<A>(self, data: A) -> core::result::Result<MatchKind, A::Error>
+            where
+                A: EnumAccess<'de>,
+            {
+                let (MatchKindVariant(kind), variant) = data.variant()?;
+                variant.unit_variant()?;
+                Ok(kind)
+            }
+        }
+
+        deserializer.deserialize_enum("MatchKind", MATCH_KIND_VARIANTS, Visitor)
+    }
+}
+
+const MATCH_FIELDS: &[&str] = &["start", "end", "value"];
+
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<V> Serialize for Match<V>
+where
+    V: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Match", MATCH_FIELDS.len())?;
+        state.serialize_field("start", &(self.end - self.length))?;
+        state.serialize_field("end", &self.end)?;
+        state.serialize_field("value", &self.value)?;
+        state.end()
+    }
+}
+
+/// The identifier of a field of [`Match`], given by the name or the index.
+enum MatchField {
+    Start,
+    End,
+    Value,
+}
+
+impl<'de> Deserialize<'de> for MatchField {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = MatchField;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a field of Match")
+            }
+
+            fn visit_u64<E>(self, v: u64) -> core::result::Result<MatchField, E>
+            where
+                E: de::Error,
+            {
+                match v {
+                    0 => Ok(MatchField::Start),
+                    1 => Ok(MatchField::End),
+                    2 => Ok(MatchField::Value),
+                    _ => Err(E::invalid_value(de::Unexpected::Unsigned(v), &self)),
+                }
+            }
+
+            fn visit_str<E>(self, v: &str) -> core::result::Result<MatchField, E>
+            where
+                E: de::Error,
+            {
+                match v {
+                    "start" => Ok(MatchField::Start),
+                    "end" => Ok(MatchField::End),
+                    "value" => Ok(MatchField::Value),
+                    _ => Err(E::unknown_field(v, MATCH_FIELDS)),
+                }
+            }
+        }
+
+        deserializer.deserialize_identifier(Visitor)
+    }
+}
+
+struct MatchVisitor<V>(PhantomData<V>);
+
+impl<V> MatchVisitor<V> {
+    fn build<E>(start: usize, end: usize, value: V) -> core::result::Result<Match<V>, E>
+    where
+        E: de::Error,
+    {
+        if end < start {
+            return Err(E::custom(format_args!(
+                "the start position {start} exceeds the end position {end}"
+            )));
+        }
+        Ok(Match {
+            length: end - start,
+            end,
+            value,
+        })
+    }
+}
+
+impl<'de, V> de::Visitor<'de> for MatchVisitor<V>
+where
+    V: Deserialize<'de>,
+{
+    type Value = Match<V>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("struct Match")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> core::result::Result<Match<V>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let start = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let end = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let value = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+        Self::build(start, end, value)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> core::result::Result<Match<V>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut start = None;
+        let mut end = None;
+        let mut value = None;
+        while let Some(field) = map.next_key()? {
+            match field {
+                MatchField::Start if start.is_none() => start = Some(map.next_value()?),
+                MatchField::End if end.is_none() => end = Some(map.next_value()?),
+                MatchField::Value if value.is_none() => value = Some(map.next_value()?),
+                MatchField::Start => return Err(de::Error::duplicate_field("start")),
+                MatchField::End => return Err(de::Error::duplicate_field("end")),
+                MatchField::Value => return Err(de::Error::duplicate_field("value")),
+            }
+        }
+        let start = start.ok_or_else(|| de::Error::missing_field("start"))?;
+        let end = end.ok_or_else(|| de::Error::missing_field("end"))?;
+        let value = value.ok_or_else(|| de::Error::missing_field("value"))?;
+        Self::build(start, end, value)
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'de, V> Deserialize<'de> for Match<V>
+where
+    V: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("Match", MATCH_FIELDS, MatchVisitor(PhantomData))
+    }
+}
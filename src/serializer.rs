@@ -1,9 +1,15 @@
 //! Utilities for serializing/deserializing data.
 
-use core::num::NonZeroU32;
+use core::convert::Infallible;
+use core::mem;
+use core::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+    NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize,
+};
 
 use alloc::vec::Vec;
 
+use crate::errors::{self, DaachorseError};
 use crate::utils::FromU32;
 use crate::Empty;
 
@@ -11,6 +17,15 @@ use crate::Empty;
 ///
 /// If the type of output value of the automaton implements this trait, the automaton can be
 /// serialized.
+///
+/// The serialized data must not depend on the platform. The implementations in this crate store
+/// integers in little-endian byte order, and `usize` and `isize` as 64-bit integers, so that data
+/// serialized on one platform can be deserialized on another one with a different endianness or
+/// pointer width.
+///
+/// This trait is implemented for the primitive integers, [`bool`], [`char`], the `NonZero*`
+/// integers, [`Option`], tuples of up to 12 elements and arrays of serializable types. Structs
+/// can implement it with [`impl_serializable!`](crate::impl_serializable).
 pub trait Serializable: Sized {
     /// A function called during serialization.
     ///
@@ -25,8 +40,27 @@ pub trait Serializable: Sized {
     /// # Arguments
     ///
     /// * `src` - the source slice containing the serialized data.
+    ///
+    /// # Panics
+    ///
+    /// It may panic if `src` does not start with valid serialized data.
     fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]);
 
+    /// Same as [`Self::deserialize_from_slice()`], but returns [`None`] instead of panicking if
+    /// `src` does not start with valid serialized data. This function is called by the checked
+    /// loaders such as [`DoubleArrayAhoCorasick::deserialize()`](crate::DoubleArrayAhoCorasick::deserialize).
+    ///
+    /// The default implementation only checks the length of `src`, so it must be overridden if
+    /// some byte sequences are invalid.
+    ///
+    /// # Arguments
+    ///
+    /// * `src` - the source slice containing the serialized data.
+    #[inline(always)]
+    fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])> {
+        (Self::serialized_bytes() <= src.len()).then(|| Self::deserialize_from_slice(src))
+    }
+
     /// Returns the size of serialized data.
     fn serialized_bytes() -> usize;
 }
@@ -58,31 +92,127 @@ define_serializable_primitive!(u16, 2);
 define_serializable_primitive!(u32, 4);
 define_serializable_primitive!(u64, 8);
 define_serializable_primitive!(u128, 16);
-#[cfg(target_pointer_width = "32")]
-define_serializable_primitive!(usize, 4);
-#[cfg(target_pointer_width = "64")]
-define_serializable_primitive!(usize, 8);
 
 define_serializable_primitive!(i8, 1);
 define_serializable_primitive!(i16, 2);
 define_serializable_primitive!(i32, 4);
 define_serializable_primitive!(i64, 8);
 define_serializable_primitive!(i128, 16);
-#[cfg(target_pointer_width = "32")]
-define_serializable_primitive!(isize, 4);
-#[cfg(target_pointer_width = "64")]
-define_serializable_primitive!(isize, 8);
 
-impl Serializable for Option<NonZeroU32> {
+// Pointer-sized integers are stored as 64-bit integers to make the data independent of the
+// pointer width. Loading a value out of the range of the platform fails.
+macro_rules! define_serializable_pointer_sized {
+    ($type:ty, $repr:ty) => {
+        impl Serializable for $type {
+            #[inline(always)]
+            fn serialize_to_vec(&self, dst: &mut Vec<u8>) {
+                <$repr>::try_from(*self).unwrap().serialize_to_vec(dst);
+            }
+
+            #[inline(always)]
+            fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
+                Self::deserialize_from_slice_checked(src).unwrap()
+            }
+
+            #[inline(always)]
+            fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])> {
+                let (x, src) = <$repr>::deserialize_from_slice_checked(src)?;
+                Some((Self::try_from(x).ok()?, src))
+            }
+
+            #[inline(always)]
+            fn serialized_bytes() -> usize {
+                <$repr>::serialized_bytes()
+            }
+        }
+    };
+}
+
+define_serializable_pointer_sized!(usize, u64);
+define_serializable_pointer_sized!(isize, i64);
+
+macro_rules! define_serializable_nonzero {
+    ($type:ty, $inner:ty) => {
+        impl Serializable for $type {
+            #[inline(always)]
+            fn serialize_to_vec(&self, dst: &mut Vec<u8>) {
+                self.get().serialize_to_vec(dst);
+            }
+
+            #[inline(always)]
+            fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
+                Self::deserialize_from_slice_checked(src).unwrap()
+            }
+
+            #[inline(always)]
+            fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])> {
+                let (x, src) = <$inner>::deserialize_from_slice_checked(src)?;
+                Some((Self::new(x)?, src))
+            }
+
+            #[inline(always)]
+            fn serialized_bytes() -> usize {
+                <$inner>::serialized_bytes()
+            }
+        }
+    };
+}
+
+define_serializable_nonzero!(NonZeroU8, u8);
+define_serializable_nonzero!(NonZeroU16, u16);
+define_serializable_nonzero!(NonZeroU32, u32);
+define_serializable_nonzero!(NonZeroU64, u64);
+define_serializable_nonzero!(NonZeroU128, u128);
+define_serializable_nonzero!(NonZeroUsize, usize);
+
+define_serializable_nonzero!(NonZeroI8, i8);
+define_serializable_nonzero!(NonZeroI16, i16);
+define_serializable_nonzero!(NonZeroI32, i32);
+define_serializable_nonzero!(NonZeroI64, i64);
+define_serializable_nonzero!(NonZeroI128, i128);
+define_serializable_nonzero!(NonZeroIsize, isize);
+
+impl Serializable for bool {
+    #[inline(always)]
+    fn serialize_to_vec(&self, dst: &mut Vec<u8>) {
+        u8::from(*self).serialize_to_vec(dst);
+    }
+
+    #[inline(always)]
+    fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
+        Self::deserialize_from_slice_checked(src).unwrap()
+    }
+
+    #[inline(always)]
+    fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])> {
+        match u8::deserialize_from_slice_checked(src)? {
+            (0, src) => Some((false, src)),
+            (1, src) => Some((true, src)),
+            _ => None,
+        }
+    }
+
+    #[inline(always)]
+    fn serialized_bytes() -> usize {
+        u8::serialized_bytes()
+    }
+}
+
+impl Serializable for char {
     #[inline(always)]
     fn serialize_to_vec(&self, dst: &mut Vec<u8>) {
-        self.map_or(0, NonZeroU32::get).serialize_to_vec(dst);
+        u32::from(*self).serialize_to_vec(dst);
     }
 
     #[inline(always)]
     fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
-        let (x, src) = u32::deserialize_from_slice(src);
-        (NonZeroU32::new(x), src)
+        Self::deserialize_from_slice_checked(src).unwrap()
+    }
+
+    #[inline(always)]
+    fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])> {
+        let (x, src) = u32::deserialize_from_slice_checked(src)?;
+        Some((Self::from_u32(x)?, src))
     }
 
     #[inline(always)]
@@ -91,11 +221,291 @@ impl Serializable for Option<NonZeroU32> {
     }
 }
 
+/// The value is preceded by a tag byte, 0 for [`None`] and 1 for [`Some`]. [`None`] is padded
+/// with zeros so that the size is fixed.
+impl<T> Serializable for Option<T>
+where
+    T: Serializable,
+{
+    #[inline(always)]
+    fn serialize_to_vec(&self, dst: &mut Vec<u8>) {
+        match self {
+            Some(x) => {
+                1u8.serialize_to_vec(dst);
+                x.serialize_to_vec(dst);
+            }
+            None => {
+                0u8.serialize_to_vec(dst);
+                dst.resize(dst.len() + T::serialized_bytes(), 0);
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
+        let (tag, src) = u8::deserialize_from_slice(src);
+        if tag == 0 {
+            return (None, &src[T::serialized_bytes()..]);
+        }
+        let (x, src) = T::deserialize_from_slice(src);
+        (Some(x), src)
+    }
+
+    #[inline(always)]
+    fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])> {
+        match u8::deserialize_from_slice_checked(src)? {
+            (0, src) => Some((None, src.get(T::serialized_bytes()..)?)),
+            (1, src) => {
+                let (x, src) = T::deserialize_from_slice_checked(src)?;
+                Some((Some(x), src))
+            }
+            _ => None,
+        }
+    }
+
+    #[inline(always)]
+    fn serialized_bytes() -> usize {
+        u8::serialized_bytes() + T::serialized_bytes()
+    }
+}
+
+macro_rules! define_serializable_tuple {
+    ($($type:ident $var:ident),+) => {
+        impl<$($type),+> Serializable for ($($type,)+)
+        where
+            $($type: Serializable),+
+        {
+            #[inline(always)]
+            fn serialize_to_vec(&self, dst: &mut Vec<u8>) {
+                let ($($var,)+) = self;
+                $($var.serialize_to_vec(dst);)+
+            }
+
+            #[inline(always)]
+            fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
+                $(let ($var, src) = $type::deserialize_from_slice(src);)+
+                (($($var,)+), src)
+            }
+
+            #[inline(always)]
+            fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])> {
+                $(let ($var, src) = $type::deserialize_from_slice_checked(src)?;)+
+                Some((($($var,)+), src))
+            }
+
+            #[inline(always)]
+            fn serialized_bytes() -> usize {
+                0 $(+ $type::serialized_bytes())+
+            }
+        }
+    };
+}
+
+define_serializable_tuple!(T0 x0);
+define_serializable_tuple!(T0 x0, T1 x1);
+define_serializable_tuple!(T0 x0, T1 x1, T2 x2);
+define_serializable_tuple!(T0 x0, T1 x1, T2 x2, T3 x3);
+define_serializable_tuple!(T0 x0, T1 x1, T2 x2, T3 x3, T4 x4);
+define_serializable_tuple!(T0 x0, T1 x1, T2 x2, T3 x3, T4 x4, T5 x5);
+define_serializable_tuple!(T0 x0, T1 x1, T2 x2, T3 x3, T4 x4, T5 x5, T6 x6);
+define_serializable_tuple!(T0 x0, T1 x1, T2 x2, T3 x3, T4 x4, T5 x5, T6 x6, T7 x7);
+define_serializable_tuple!(T0 x0, T1 x1, T2 x2, T3 x3, T4 x4, T5 x5, T6 x6, T7 x7, T8 x8);
+define_serializable_tuple!(T0 x0, T1 x1, T2 x2, T3 x3, T4 x4, T5 x5, T6 x6, T7 x7, T8 x8, T9 x9);
+define_serializable_tuple!(
+    T0 x0, T1 x1, T2 x2, T3 x3, T4 x4, T5 x5, T6 x6, T7 x7, T8 x8, T9 x9, T10 x10
+);
+define_serializable_tuple!(
+    T0 x0, T1 x1, T2 x2, T3 x3, T4 x4, T5 x5, T6 x6, T7 x7, T8 x8, T9 x9, T10 x10, T11 x11
+);
+
+impl<T, const N: usize> Serializable for [T; N]
+where
+    T: Serializable,
+{
+    #[inline(always)]
+    fn serialize_to_vec(&self, dst: &mut Vec<u8>) {
+        self.iter().for_each(|x| x.serialize_to_vec(dst));
+    }
+
+    #[inline(always)]
+    fn deserialize_from_slice(mut src: &[u8]) -> (Self, &[u8]) {
+        let xs = core::array::from_fn(|_| {
+            let (x, rest) = T::deserialize_from_slice(src);
+            src = rest;
+            x
+        });
+        (xs, src)
+    }
+
+    #[inline(always)]
+    fn deserialize_from_slice_checked(mut src: &[u8]) -> Option<(Self, &[u8])> {
+        let mut xs = Vec::with_capacity(N);
+        for _ in 0..N {
+            let (x, rest) = T::deserialize_from_slice_checked(src)?;
+            xs.push(x);
+            src = rest;
+        }
+        Some((xs.try_into().ok()?, src))
+    }
+
+    #[inline(always)]
+    fn serialized_bytes() -> usize {
+        T::serialized_bytes() * N
+    }
+}
+
+/// A destination of serialized data, such as a file.
+///
+/// This trait is implemented for [`VecSink`] appending the data to a vector and, with the `std`
+/// feature, for [`IoSink`] writing the data into [`std::io::Write`].
+pub trait Sink {
+    /// The error type returned when writing fails.
+    type Error;
+
+    /// Writes all the given bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - the bytes to be written.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned when the bytes cannot be written.
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// A sink appending the data to a vector.
+pub struct VecSink<'a>(pub &'a mut Vec<u8>);
+
+impl Sink for VecSink<'_> {
+    type Error = Infallible;
+
+    #[inline(always)]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.0.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// A sink writing the data into [`std::io::Write`], such as a file.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub struct IoSink<W>(pub W);
+
+#[cfg(feature = "std")]
+impl<W> Sink for IoSink<W>
+where
+    W: std::io::Write,
+{
+    type Error = std::io::Error;
+
+    #[inline(always)]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.0.write_all(bytes)
+    }
+}
+
+/// Fixed-size values written into sinks without allocation, in the same format as
+/// [`Serializable`].
+pub trait FixedBytes {
+    type Bytes: AsRef<[u8]>;
+
+    fn to_bytes(&self) -> Self::Bytes;
+}
+
+macro_rules! define_fixed_bytes_primitive {
+    ($type:ty) => {
+        impl FixedBytes for $type {
+            type Bytes = [u8; mem::size_of::<$type>()];
+
+            #[inline(always)]
+            fn to_bytes(&self) -> Self::Bytes {
+                self.to_le_bytes()
+            }
+        }
+    };
+}
+
+define_fixed_bytes_primitive!(u8);
+define_fixed_bytes_primitive!(u32);
+
+/// Writes a single value into the sink.
+#[inline(always)]
+pub fn serialize_value_into<S, W>(x: &S, dst: &mut W) -> Result<(), W::Error>
+where
+    S: FixedBytes,
+    W: Sink + ?Sized,
+{
+    dst.write_bytes(x.to_bytes().as_ref())
+}
+
+/// The magic bytes at the beginning of a serialized [`DoubleArrayAhoCorasick`](crate::DoubleArrayAhoCorasick).
+pub const BYTEWISE_MAGIC: [u8; 8] = *b"DAACBYTE";
+
+/// The magic bytes at the beginning of a serialized
+/// [`CharwiseDoubleArrayAhoCorasick`](crate::CharwiseDoubleArrayAhoCorasick).
+pub const CHARWISE_MAGIC: [u8; 8] = *b"DAACCHAR";
+
+/// The version of the serialized format, incremented whenever the layout changes.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Writes the header consisting of `magic` and [`FORMAT_VERSION`].
+pub fn serialize_header_into<W>(magic: &[u8; 8], dst: &mut W) -> Result<(), W::Error>
+where
+    W: Sink + ?Sized,
+{
+    dst.write_bytes(magic)?;
+    serialize_value_into(&FORMAT_VERSION, dst)
+}
+
+/// Returns the length of the header in bytes.
+pub const fn header_bytes() -> usize {
+    BYTEWISE_MAGIC.len() + mem::size_of::<u32>()
+}
+
+/// Reads the header written by [`serialize_header_into()`] and returns the rest of `src`.
+///
+/// # Errors
+///
+/// [`DaachorseError`] is returned when `src` does not start with the header of `magic` and
+/// [`FORMAT_VERSION`].
+pub fn deserialize_header<'a>(magic: &[u8; 8], src: &'a [u8]) -> errors::Result<&'a [u8]> {
+    let src = src
+        .strip_prefix(&magic[..])
+        .ok_or_else(|| DaachorseError::unsupported_format(None, FORMAT_VERSION))?;
+    let (version, src) = u32::deserialize_from_slice_checked(src)
+        .ok_or_else(|| DaachorseError::unsupported_format(None, FORMAT_VERSION))?;
+    if version != FORMAT_VERSION {
+        return Err(DaachorseError::unsupported_format(
+            Some(version),
+            FORMAT_VERSION,
+        ));
+    }
+    Ok(src)
+}
+
+/// Same as [`deserialize_header()`], but panics instead of returning an error.
+pub fn deserialize_header_unchecked<'a>(magic: &[u8; 8], src: &'a [u8]) -> &'a [u8] {
+    match deserialize_header(magic, src) {
+        Ok(src) => src,
+        Err(e) => panic!("{e}"),
+    }
+}
+
+/// The number of items serialized at once, bounding the size of the intermediate buffer.
+const CHUNK_LEN: usize = 1 << 12;
+
 pub trait SerializableVec: Sized {
-    fn serialize_to_vec(&self, dst: &mut Vec<u8>);
+    fn serialize_into<W>(&self, dst: &mut W) -> Result<(), W::Error>
+    where
+        W: Sink + ?Sized;
 
     fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]);
 
+    /// Same as [`Self::deserialize_from_slice()`], but returns [`None`] instead of panicking if
+    /// `src` is too short.
+    fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])>;
+
     fn serialized_bytes(&self) -> usize;
 }
 
@@ -103,10 +513,18 @@ impl<S> SerializableVec for Vec<S>
 where
     S: Serializable,
 {
-    #[inline(always)]
-    fn serialize_to_vec(&self, dst: &mut Vec<u8>) {
-        u32::try_from(self.len()).unwrap().serialize_to_vec(dst);
-        self.iter().for_each(|x| x.serialize_to_vec(dst));
+    fn serialize_into<W>(&self, dst: &mut W) -> Result<(), W::Error>
+    where
+        W: Sink + ?Sized,
+    {
+        serialize_value_into(&u32::try_from(self.len()).unwrap(), dst)?;
+        let mut buf = Vec::with_capacity(S::serialized_bytes() * self.len().min(CHUNK_LEN));
+        for chunk in self.chunks(CHUNK_LEN) {
+            buf.clear();
+            chunk.iter().for_each(|x| x.serialize_to_vec(&mut buf));
+            dst.write_bytes(&buf)?;
+        }
+        Ok(())
     }
 
     #[inline(always)]
@@ -121,6 +539,23 @@ where
         (dst, src)
     }
 
+    #[inline(always)]
+    fn deserialize_from_slice_checked(src: &[u8]) -> Option<(Self, &[u8])> {
+        let (len, mut src) = u32::deserialize_from_slice_checked(src)?;
+        let len = usize::from_u32(len);
+        // Checks the length in advance not to allocate memory beyond the source.
+        if len.checked_mul(S::serialized_bytes())? > src.len() {
+            return None;
+        }
+        let mut dst = Self::with_capacity(len);
+        for _ in 0..len {
+            let (x, rest) = S::deserialize_from_slice_checked(src)?;
+            dst.push(x);
+            src = rest;
+        }
+        Some((dst, src))
+    }
+
     fn serialized_bytes(&self) -> usize {
         u32::serialized_bytes() + S::serialized_bytes() * self.len()
     }
@@ -141,6 +576,75 @@ impl Serializable for Empty {
     }
 }
 
+/// Implements [`Serializable`] for a struct with named fields by serializing the fields in the
+/// given order.
+///
+/// The fields and their types are listed after the struct name, and every field type must
+/// implement [`Serializable`].
+///
+/// # Examples
+///
+/// ```
+/// use aneubeck_daachorse::{impl_serializable, DoubleArrayAhoCorasick};
+///
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// struct Entry {
+///     id: u32,
+///     tag: [u8; 2],
+///     score: Option<u16>,
+/// }
+///
+/// impl_serializable!(Entry {
+///     id: u32,
+///     tag: [u8; 2],
+///     score: Option<u16>,
+/// });
+///
+/// let patvals = vec![
+///     ("ab", Entry { id: 0, tag: *b"xy", score: Some(10) }),
+///     ("bcd", Entry { id: 1, tag: *b"zw", score: None }),
+/// ];
+/// let pma = DoubleArrayAhoCorasick::with_values(patvals).unwrap();
+/// let bytes = pma.serialize();
+///
+/// let (pma, _) = DoubleArrayAhoCorasick::<Entry>::deserialize(&bytes).unwrap();
+/// let m = pma.find_iter("abcd").next().unwrap();
+/// assert_eq!(Entry { id: 0, tag: *b"xy", score: Some(10) }, m.value());
+/// ```
+#[macro_export]
+macro_rules! impl_serializable {
+    ($name:ident { $($field:ident: $type:ty),+ $(,)? }) => {
+        impl $crate::Serializable for $name {
+            #[inline(always)]
+            fn serialize_to_vec(&self, dst: &mut $crate::__private::Vec<u8>) {
+                $($crate::Serializable::serialize_to_vec(&self.$field, dst);)+
+            }
+
+            #[inline(always)]
+            fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
+                $(let ($field, src) = <$type as $crate::Serializable>::deserialize_from_slice(src);)+
+                (Self { $($field),+ }, src)
+            }
+
+            #[inline(always)]
+            fn deserialize_from_slice_checked(
+                src: &[u8],
+            ) -> ::core::option::Option<(Self, &[u8])> {
+                $(
+                    let ($field, src) =
+                        <$type as $crate::Serializable>::deserialize_from_slice_checked(src)?;
+                )+
+                ::core::option::Option::Some((Self { $($field),+ }, src))
+            }
+
+            #[inline(always)]
+            fn serialized_bytes() -> usize {
+                0 $(+ <$type as $crate::Serializable>::serialized_bytes())+
+            }
+        }
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -158,24 +662,163 @@ mod tests {
         assert_eq!(x, y);
     }
 
+    #[test]
+    fn test_usize() {
+        let mut data = vec![];
+        0x01234567usize.serialize_to_vec(&mut data);
+        (-2isize).serialize_to_vec(&mut data);
+        assert_eq!(
+            vec![
+                0x67, 0x45, 0x23, 0x01, 0x00, 0x00, 0x00, 0x00, // usize
+                0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // isize
+            ],
+            data
+        );
+        assert_eq!(8, usize::serialized_bytes());
+        assert_eq!(8, isize::serialized_bytes());
+        let (x, rest) = <(usize, isize)>::deserialize_from_slice(&data);
+        assert!(rest.is_empty());
+        assert_eq!((0x01234567, -2), x);
+
+        let mut data = vec![];
+        (1u64 << 32).serialize_to_vec(&mut data);
+        (-1i64 << 31).serialize_to_vec(&mut data);
+        (-1i64 << 32).serialize_to_vec(&mut data);
+        let x = usize::deserialize_from_slice_checked(&data[..8]).map(|(x, _)| x);
+        let y = isize::deserialize_from_slice_checked(&data[8..16]).map(|(x, _)| x);
+        let z = isize::deserialize_from_slice_checked(&data[16..]).map(|(x, _)| x);
+        assert_eq!(Some(-1 << 31), y);
+        #[cfg(target_pointer_width = "64")]
+        {
+            assert_eq!(Some(1 << 32), x);
+            assert_eq!(Some(-1 << 32), z);
+        }
+        #[cfg(target_pointer_width = "32")]
+        {
+            assert_eq!(None, x);
+            assert_eq!(None, z);
+        }
+    }
+
     #[test]
     fn test_nzu32() {
-        let x = NonZeroU32::new(0x01234567u32);
+        let x = NonZeroU32::new(0x01234567u32).unwrap();
         let mut data = vec![];
         x.serialize_to_vec(&mut data);
         assert_eq!(vec![0x67, 0x45, 0x23, 0x01], data);
-        assert_eq!(4, Option::<NonZeroU32>::serialized_bytes());
+        assert_eq!(4, NonZeroU32::serialized_bytes());
+        data.push(42);
+        let (y, rest) = NonZeroU32::deserialize_from_slice(&data);
+        assert_eq!(&[42], rest);
+        assert_eq!(x, y);
+        assert_eq!(
+            None,
+            NonZeroU32::deserialize_from_slice_checked(&[0, 0, 0, 0])
+        );
+        assert_eq!(None, NonZeroI8::deserialize_from_slice_checked(&[0]));
+    }
+
+    #[test]
+    fn test_bool_char() {
+        let mut data = vec![];
+        true.serialize_to_vec(&mut data);
+        false.serialize_to_vec(&mut data);
+        '世'.serialize_to_vec(&mut data);
+        assert_eq!(vec![0x01, 0x00, 0x16, 0x4e, 0x00, 0x00], data);
+        let (x, rest) = <(bool, bool, char)>::deserialize_from_slice(&data);
+        assert!(rest.is_empty());
+        assert_eq!((true, false, '世'), x);
+
+        assert_eq!(None, bool::deserialize_from_slice_checked(&[2]));
+        assert_eq!(
+            None,
+            char::deserialize_from_slice_checked(&[0x00, 0xd8, 0x00, 0x00])
+        );
+        assert_eq!(
+            None,
+            char::deserialize_from_slice_checked(&[0x00, 0x00, 0x11, 0x00])
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_char_panic() {
+        char::deserialize_from_slice(&[0x00, 0xd8, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_option() {
+        let x = [Some(0x0123u16), None, Some(0x4567)];
+        let mut data = vec![];
+        x.serialize_to_vec(&mut data);
+        assert_eq!(
+            vec![
+                0x01, 0x23, 0x01, // Some
+                0x00, 0x00, 0x00, // None
+                0x01, 0x67, 0x45, // Some
+            ],
+            data
+        );
+        assert_eq!(9, <[Option<u16>; 3]>::serialized_bytes());
+        data.push(42);
+        let (y, rest) = <[Option<u16>; 3]>::deserialize_from_slice(&data);
+        assert_eq!(&[42], rest);
+        assert_eq!(x, y);
+        let (y, rest) = <[Option<u16>; 3]>::deserialize_from_slice_checked(&data).unwrap();
+        assert_eq!(&[42], rest);
+        assert_eq!(x, y);
+
+        assert_eq!(
+            None,
+            Option::<u16>::deserialize_from_slice_checked(&[0x02, 0x00, 0x00])
+        );
+        assert_eq!(
+            None,
+            Option::<u16>::deserialize_from_slice_checked(&[0x00, 0x00])
+        );
+        assert_eq!(
+            Some((None, &[][..])),
+            Option::<NonZeroU16>::deserialize_from_slice_checked(&[0x00, 0x00, 0x00])
+        );
+        assert_eq!(
+            None,
+            Option::<NonZeroU16>::deserialize_from_slice_checked(&[0x01, 0x00, 0x00])
+        );
+    }
+
+    #[test]
+    fn test_tuple_array() {
+        let x = (0x01234567u32, [0x89u8, 0xab], (-1i16,), 'a');
+        let mut data = vec![];
+        x.serialize_to_vec(&mut data);
+        assert_eq!(
+            vec![
+                0x67, 0x45, 0x23, 0x01, // u32
+                0x89, 0xab, // [u8; 2]
+                0xff, 0xff, // (i16,)
+                0x61, 0x00, 0x00, 0x00, // char
+            ],
+            data
+        );
+        assert_eq!(12, <(u32, [u8; 2], (i16,), char)>::serialized_bytes());
         data.push(42);
-        let (y, rest) = Option::<NonZeroU32>::deserialize_from_slice(&data);
+        let (y, rest) = <(u32, [u8; 2], (i16,), char)>::deserialize_from_slice(&data);
         assert_eq!(&[42], rest);
         assert_eq!(x, y);
+        for len in 0..12 {
+            assert!(
+                <(u32, [u8; 2], (i16,), char)>::deserialize_from_slice_checked(&data[..len])
+                    .is_none()
+            );
+        }
+        assert_eq!(0, <[u32; 0]>::serialized_bytes());
     }
 
     #[test]
     fn test_vec_u32() {
         let x = vec![0x01234567u32, 0x89abcdefu32, 0x02468aceu32];
         let mut data = vec![];
-        x.serialize_to_vec(&mut data);
+        x.serialize_into(&mut VecSink(&mut data)).unwrap();
         assert_eq!(
             vec![
                 0x03, 0x00, 0x00, 0x00, // len
@@ -190,5 +833,21 @@ mod tests {
         let (y, rest) = Vec::<u32>::deserialize_from_slice(&data);
         assert_eq!(&[42], rest);
         assert_eq!(x, y);
+        let (y, rest) = Vec::<u32>::deserialize_from_slice_checked(&data).unwrap();
+        assert_eq!(&[42], rest);
+        assert_eq!(x, y);
+    }
+
+    #[test]
+    fn test_vec_u32_checked() {
+        let x = vec![0x01234567u32, 0x89abcdefu32, 0x02468aceu32];
+        let mut data = vec![];
+        x.serialize_into(&mut VecSink(&mut data)).unwrap();
+        for len in 0..data.len() {
+            assert!(Vec::<u32>::deserialize_from_slice_checked(&data[..len]).is_none());
+        }
+        let data = [0xff, 0xff, 0xff, 0xff, 0x00];
+        assert!(Vec::<u32>::deserialize_from_slice_checked(&data).is_none());
+        assert_eq!(None, u32::deserialize_from_slice_checked(&data[..3]));
     }
 }
@@ -0,0 +1,289 @@
+use core::mem;
+use core::num::NonZeroU32;
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::errors::{DaachorseError, Result};
+use crate::nfa_builder::{
+    find_fail, push_outputs, EdgeLabel, NfaView, DEAD_STATE_ID, ROOT_STATE_ID,
+};
+use crate::utils::FromU32;
+use crate::{BuildPhase, DuplicatePolicy, MatchKind, Output, ProgressReporter};
+
+/// Builder of an Aho-Corasick automaton from lexicographically sorted patterns.
+///
+/// Unlike [`NfaBuilder`](crate::nfa_builder::NfaBuilder), the trie is built without a map for
+/// each state. Since the states are created in depth-first order from sorted patterns, only the
+/// path of the previous pattern needs to be kept to find the common prefix, and the children of
+/// each state are finally arranged into a contiguous range of a single array.
+pub struct SortedNfaBuilder<L, V> {
+    // The parent and label of each state, used until the edges are arranged.
+    parents: Vec<u32>,
+    labels: Vec<L>,
+    // The path of the previous pattern from the root.
+    path: Vec<u32>,
+    prev_pattern: Vec<L>,
+    state_outputs: Vec<Option<(V, NonZeroU32)>>,
+    // Values following the first one for each state, registered with DuplicatePolicy::KeepAll.
+    multi_values: BTreeMap<u32, Vec<V>>,
+    num_multi_values: usize,
+
+    // The children of state i are edges[edge_starts[i]..edge_starts[i + 1]].
+    edge_starts: Vec<u32>,
+    edges: Vec<(L, u32)>,
+    fails: Vec<u32>,
+    output_pos: Vec<Option<NonZeroU32>>,
+    pub(crate) outputs: Vec<Output<V>>,
+
+    pub(crate) len: usize,
+    match_kind: MatchKind,
+    duplicate_policy: DuplicatePolicy<V>,
+}
+
+impl<L, V> SortedNfaBuilder<L, V>
+where
+    L: EdgeLabel + Default,
+    V: Copy,
+{
+    pub(crate) fn new(match_kind: MatchKind, duplicate_policy: DuplicatePolicy<V>) -> Self {
+        Self {
+            // root and dead
+            parents: vec![ROOT_STATE_ID; 2],
+            labels: vec![L::default(); 2],
+            path: vec![ROOT_STATE_ID],
+            prev_pattern: vec![],
+            state_outputs: vec![None; 2],
+            multi_values: BTreeMap::new(),
+            num_multi_values: 0,
+            edge_starts: vec![],
+            edges: vec![],
+            fails: vec![],
+            output_pos: vec![],
+            outputs: vec![],
+            len: 0,
+            match_kind,
+            duplicate_policy,
+        }
+    }
+
+    /// Adds a pattern that is greater than or equal to the previous one.
+    pub(crate) fn add(&mut self, pattern: &[L], value: V) -> Result<()> {
+        let pattern_len = pattern
+            .iter()
+            .fold(0, |acc, c| acc + c.num_bytes())
+            .try_into()
+            .map_err(|_| DaachorseError::invalid_argument("pattern.len()", "<=", u32::MAX))?;
+        let pattern_len = NonZeroU32::new(pattern_len).ok_or_else(DaachorseError::empty_pattern)?;
+
+        let prefix_len = self
+            .prev_pattern
+            .iter()
+            .zip(pattern)
+            .take_while(|(a, b)| a == b)
+            .count();
+        if prefix_len < self.prev_pattern.len()
+            && (prefix_len == pattern.len() || pattern[prefix_len] < self.prev_pattern[prefix_len])
+        {
+            return Err(DaachorseError::unsorted_pattern(format!("{pattern:?}")));
+        }
+        self.prev_pattern.clear();
+        self.prev_pattern.extend_from_slice(pattern);
+
+        // The path of the previous pattern can be shorter than the common prefix if the pattern
+        // was not registered with MatchKind::LeftmostFirst.
+        self.path.truncate(prefix_len + 1);
+        if self.match_kind.is_leftmost_first() {
+            // If a prefix has an output, the descendants will never searched.
+            let depth = self.path.len().min(pattern.len());
+            for &state_id in &self.path[..depth] {
+                if self.state_outputs[usize::from_u32(state_id)].is_some() {
+                    return Ok(());
+                }
+            }
+        }
+
+        for &c in &pattern[self.path.len() - 1..] {
+            let state_id = u32::try_from(self.parents.len())
+                .map_err(|_| DaachorseError::automaton_scale("state_id", u32::MAX))?;
+            self.parents.push(*self.path.last().unwrap());
+            self.labels.push(c);
+            self.state_outputs.push(None);
+            self.path.push(state_id);
+        }
+
+        let state_id = *self.path.last().unwrap();
+        let output = &mut self.state_outputs[usize::from_u32(state_id)];
+        if let Some((old_value, _)) = output {
+            *old_value = match self.duplicate_policy {
+                DuplicatePolicy::Error => {
+                    return Err(DaachorseError::duplicate_pattern(format!("{pattern:?}")));
+                }
+                DuplicatePolicy::KeepFirst => *old_value,
+                DuplicatePolicy::KeepLast => value,
+                DuplicatePolicy::Merge(f) => f(*old_value, value),
+                DuplicatePolicy::KeepAll => {
+                    self.multi_values.entry(state_id).or_default().push(value);
+                    self.num_multi_values += 1;
+                    *old_value
+                }
+            };
+            return Ok(());
+        }
+        *output = Some((value, pattern_len));
+
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Arranges the edges and builds the fails and outputs.
+    pub(crate) fn build(&mut self, progress: &mut ProgressReporter) {
+        self.build_edges();
+        let q = self.build_fails(progress);
+        self.build_outputs(&q);
+    }
+
+    fn build_edges(&mut self) {
+        let num_states = self.parents.len();
+        let mut edge_starts = vec![0; num_states + 1];
+        // Skips the root and dead states having no parents.
+        for &parent_id in &self.parents[2..] {
+            edge_starts[usize::from_u32(parent_id) + 1] += 1;
+        }
+        for i in 0..num_states {
+            edge_starts[i + 1] += edge_starts[i];
+        }
+
+        // The children of each state are created in ascending order of labels.
+        let mut edges = vec![(L::default(), 0); num_states - 2];
+        let mut next = edge_starts.clone();
+        for (child_id, (&parent_id, &c)) in (2..).zip(self.parents.iter().zip(&self.labels).skip(2))
+        {
+            let pos = &mut next[usize::from_u32(parent_id)];
+            edges[usize::from_u32(*pos)] = (c, child_id);
+            *pos += 1;
+        }
+
+        self.edge_starts = edge_starts;
+        self.edges = edges;
+        self.parents = vec![];
+        self.labels = vec![];
+        self.path = vec![];
+        self.prev_pattern = vec![];
+    }
+
+    fn build_fails(&mut self, progress: &mut ProgressReporter) -> Vec<u32> {
+        let leftmost = self.match_kind.is_leftmost();
+        let mut fails = vec![ROOT_STATE_ID; self.state_outputs.len()];
+        // The root and dead states are not processed.
+        let total = Some(self.state_outputs.len() - 2);
+        progress.start(BuildPhase::FailLinks, total);
+
+        let mut q = Vec::with_capacity(self.state_outputs.len());
+        q.extend(
+            self.children(ROOT_STATE_ID)
+                .iter()
+                .map(|&(_, child_id)| child_id),
+        );
+
+        let mut qi = 0;
+        while qi < q.len() {
+            let state_id = q[qi];
+            qi += 1;
+            progress.tick(BuildPhase::FailLinks, qi, total);
+
+            // Sets the output state to the dead fail.
+            if leftmost && self.state_outputs[usize::from_u32(state_id)].is_some() {
+                fails[usize::from_u32(state_id)] = DEAD_STATE_ID;
+            }
+            for &(c, child_id) in self.children(state_id) {
+                fails[usize::from_u32(child_id)] = find_fail(
+                    |state_id, c| self.child_id(state_id, c),
+                    &fails,
+                    fails[usize::from_u32(state_id)],
+                    c,
+                    leftmost,
+                );
+                q.push(child_id);
+            }
+        }
+        self.fails = fails;
+        progress.finish(BuildPhase::FailLinks, q.len());
+        q
+    }
+
+    fn build_outputs(&mut self, q: &[u32]) {
+        let mut output_pos = vec![None; self.state_outputs.len()];
+        for &state_id in q {
+            let fail_id = self.fails[usize::from_u32(state_id)];
+            let parent = output_pos[usize::from_u32(fail_id)];
+            output_pos[usize::from_u32(state_id)] =
+                if let Some(output) = self.state_outputs[usize::from_u32(state_id)] {
+                    let values = self.multi_values.get(&state_id).map_or(&[][..], |v| &v[..]);
+                    push_outputs(&mut self.outputs, output, values, parent)
+                } else {
+                    parent
+                };
+        }
+        self.output_pos = output_pos;
+        self.state_outputs = vec![];
+        self.multi_values = BTreeMap::new();
+        self.num_multi_values = 0;
+    }
+
+    #[inline(always)]
+    fn children(&self, state_id: u32) -> &[(L, u32)] {
+        let start = usize::from_u32(self.edge_starts[usize::from_u32(state_id)]);
+        let end = usize::from_u32(self.edge_starts[usize::from_u32(state_id) + 1]);
+        &self.edges[start..end]
+    }
+
+    #[inline(always)]
+    fn child_id(&self, state_id: u32, c: L) -> Option<u32> {
+        let children = self.children(state_id);
+        children
+            .binary_search_by_key(&c, |&(label, _)| label)
+            .ok()
+            .map(|i| children[i].1)
+    }
+}
+
+impl<L, V> NfaView<L, V> for SortedNfaBuilder<L, V>
+where
+    L: EdgeLabel + Default,
+    V: Copy,
+{
+    fn num_states(&self) -> usize {
+        self.fails.len()
+    }
+
+    fn push_edges(&self, state_id: u32, edges: &mut Vec<(L, u32)>) {
+        edges.extend_from_slice(self.children(state_id));
+    }
+
+    fn fail(&self, state_id: u32) -> u32 {
+        self.fails[usize::from_u32(state_id)]
+    }
+
+    fn output_pos(&self, state_id: u32) -> Option<NonZeroU32> {
+        self.output_pos[usize::from_u32(state_id)]
+    }
+
+    fn into_outputs(self) -> Vec<Output<V>> {
+        self.outputs
+    }
+
+    fn heap_bytes(&self) -> usize {
+        self.parents.capacity() * mem::size_of::<u32>()
+            + self.labels.capacity() * mem::size_of::<L>()
+            + self.path.capacity() * mem::size_of::<u32>()
+            + self.prev_pattern.capacity() * mem::size_of::<L>()
+            + self.state_outputs.capacity() * mem::size_of::<Option<(V, NonZeroU32)>>()
+            + self.num_multi_values * mem::size_of::<V>()
+            + self.edge_starts.capacity() * mem::size_of::<u32>()
+            + self.edges.capacity() * mem::size_of::<(L, u32)>()
+            + self.fails.capacity() * mem::size_of::<u32>()
+            + self.output_pos.capacity() * mem::size_of::<Option<NonZeroU32>>()
+            + self.outputs.capacity() * mem::size_of::<Output<V>>()
+    }
+}
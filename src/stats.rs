@@ -0,0 +1,231 @@
+//! Statistics of automata.
+
+use alloc::vec::Vec;
+
+use crate::utils::FromU32;
+
+/// Statistics of an automaton, returned by
+/// [`DoubleArrayAhoCorasick::stats()`](crate::DoubleArrayAhoCorasick::stats) and
+/// [`CharwiseDoubleArrayAhoCorasick::stats()`](crate::CharwiseDoubleArrayAhoCorasick::stats).
+///
+/// The states are counted by traversing the trie from the root, so the dead state is not
+/// included.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutomatonStats {
+    pub(crate) num_patterns: usize,
+    pub(crate) num_states: usize,
+    pub(crate) num_elements: usize,
+    pub(crate) num_vacant_elements: usize,
+    pub(crate) max_depth: u32,
+    pub(crate) avg_depth: f64,
+    pub(crate) fail_depths: Vec<usize>,
+    pub(crate) output_chain_lengths: Vec<usize>,
+    pub(crate) states_bytes: usize,
+    pub(crate) outputs_bytes: usize,
+    pub(crate) mapper_bytes: usize,
+    pub(crate) dense_bytes: usize,
+    pub(crate) alphabet_size: Option<u32>,
+}
+
+impl AutomatonStats {
+    /// The number of distinct patterns.
+    #[inline(always)]
+    #[must_use]
+    pub const fn num_patterns(&self) -> usize {
+        self.num_patterns
+    }
+
+    /// The number of states reachable from the root, including the root.
+    #[inline(always)]
+    #[must_use]
+    pub const fn num_states(&self) -> usize {
+        self.num_states
+    }
+
+    /// The total number of elements in the double array.
+    #[inline(always)]
+    #[must_use]
+    pub const fn num_elements(&self) -> usize {
+        self.num_elements
+    }
+
+    /// The number of elements in the double array used by no state.
+    #[inline(always)]
+    #[must_use]
+    pub const fn num_vacant_elements(&self) -> usize {
+        self.num_vacant_elements
+    }
+
+    /// The ratio of the elements used by states to all the elements in the double array.
+    #[must_use]
+    pub fn occupancy(&self) -> f64 {
+        // The number of elements is at most u32::MAX.
+        let num_used =
+            f64::from(u32::try_from(self.num_elements - self.num_vacant_elements).unwrap());
+        let num_elements = f64::from(u32::try_from(self.num_elements).unwrap());
+        num_used / num_elements
+    }
+
+    /// The maximum depth of the states, i.e., the length of the longest pattern.
+    #[inline(always)]
+    #[must_use]
+    pub const fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
+    /// The average depth of the states.
+    #[inline(always)]
+    #[must_use]
+    pub const fn avg_depth(&self) -> f64 {
+        self.avg_depth
+    }
+
+    /// Histogram of the depths of the failure states, where the `i`-th value is the number of
+    /// states whose failure links point to states at depth `i`.
+    ///
+    /// The root and the states whose failure links point to the dead state in leftmost automata
+    /// are not counted.
+    #[inline(always)]
+    #[must_use]
+    pub fn fail_depths(&self) -> &[usize] {
+        &self.fail_depths
+    }
+
+    /// Histogram of the lengths of the output chains, where the `i`-th value is the number of
+    /// states reporting `i` matches when reached.
+    #[inline(always)]
+    #[must_use]
+    pub fn output_chain_lengths(&self) -> &[usize] {
+        &self.output_chain_lengths
+    }
+
+    /// The heap bytes used by the states, including the tails of
+    /// [`DoubleArrayAhoCorasick`](crate::DoubleArrayAhoCorasick).
+    #[inline(always)]
+    #[must_use]
+    pub const fn states_bytes(&self) -> usize {
+        self.states_bytes
+    }
+
+    /// The heap bytes used by the outputs.
+    #[inline(always)]
+    #[must_use]
+    pub const fn outputs_bytes(&self) -> usize {
+        self.outputs_bytes
+    }
+
+    /// The heap bytes used by the mapper of the labels.
+    #[inline(always)]
+    #[must_use]
+    pub const fn mapper_bytes(&self) -> usize {
+        self.mapper_bytes
+    }
+
+    /// The heap bytes used by the dense transitions of
+    /// [`DoubleArrayAhoCorasick`](crate::DoubleArrayAhoCorasick).
+    #[inline(always)]
+    #[must_use]
+    pub const fn dense_bytes(&self) -> usize {
+        self.dense_bytes
+    }
+
+    /// The size of the alphabet of
+    /// [`CharwiseDoubleArrayAhoCorasick`](crate::CharwiseDoubleArrayAhoCorasick), or [`None`]
+    /// for [`DoubleArrayAhoCorasick`](crate::DoubleArrayAhoCorasick).
+    #[inline(always)]
+    #[must_use]
+    pub const fn alphabet_size(&self) -> Option<u32> {
+        self.alphabet_size
+    }
+}
+
+/// Accumulates the statistics of the states.
+#[derive(Default)]
+pub struct StatsCollector {
+    num_patterns: usize,
+    num_states: u32,
+    depth_sum: f64,
+    max_depth: u32,
+    fail_depths: Vec<usize>,
+    output_chain_lengths: Vec<usize>,
+}
+
+impl StatsCollector {
+    /// Adds a state at `depth` having `num_outputs` outputs, whose failure state is at
+    /// `fail_depth`. `has_pattern` indicates whether the state represents a pattern.
+    pub fn add_state(
+        &mut self,
+        depth: u32,
+        fail_depth: Option<u32>,
+        num_outputs: usize,
+        has_pattern: bool,
+    ) {
+        self.num_states += 1;
+        self.depth_sum += f64::from(depth);
+        self.max_depth = self.max_depth.max(depth);
+        if let Some(fail_depth) = fail_depth {
+            increment(&mut self.fail_depths, usize::from_u32(fail_depth));
+        }
+        increment(&mut self.output_chain_lengths, num_outputs);
+        if has_pattern {
+            self.num_patterns += 1;
+        }
+    }
+
+    /// Returns the statistics of the added states. The other fields are filled with zeros.
+    pub fn finish(self) -> AutomatonStats {
+        AutomatonStats {
+            num_patterns: self.num_patterns,
+            num_states: usize::from_u32(self.num_states),
+            num_elements: 0,
+            num_vacant_elements: 0,
+            max_depth: self.max_depth,
+            avg_depth: self.depth_sum / f64::from(self.num_states.max(1)),
+            fail_depths: self.fail_depths,
+            output_chain_lengths: self.output_chain_lengths,
+            states_bytes: 0,
+            outputs_bytes: 0,
+            mapper_bytes: 0,
+            dense_bytes: 0,
+            alphabet_size: None,
+        }
+    }
+}
+
+fn increment(histogram: &mut Vec<usize>, i: usize) {
+    if histogram.len() <= i {
+        histogram.resize(i + 1, 0);
+    }
+    histogram[i] += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_collector() {
+        let mut collector = StatsCollector::default();
+        collector.add_state(0, None, 0, false);
+        collector.add_state(1, Some(0), 1, true);
+        collector.add_state(2, Some(1), 2, true);
+        collector.add_state(3, Some(0), 0, false);
+        let stats = collector.finish();
+        assert_eq!(2, stats.num_patterns());
+        assert_eq!(4, stats.num_states());
+        assert_eq!(3, stats.max_depth());
+        assert_eq!(1.5, stats.avg_depth());
+        assert_eq!(&[2, 1], stats.fail_depths());
+        assert_eq!(&[2, 1, 1], stats.output_chain_lengths());
+    }
+
+    #[test]
+    fn test_occupancy() {
+        let stats = AutomatonStats {
+            num_elements: 256,
+            num_vacant_elements: 64,
+            ..StatsCollector::default().finish()
+        };
+        assert_eq!(0.75, stats.occupancy());
+    }
+}
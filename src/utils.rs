@@ -11,3 +11,13 @@ impl FromU32 for usize {
         unsafe { Self::try_from(src).unwrap_unchecked() }
     }
 }
+
+/// Yields the values until the first error, which is stored in `error`.
+pub fn stop_at_error<'a, I, T, E>(iter: I, error: &'a mut Option<E>) -> impl Iterator<Item = T> + 'a
+where
+    I: IntoIterator<Item = Result<T, E>>,
+    I::IntoIter: 'a,
+{
+    iter.into_iter()
+        .map_while(move |item| item.map_err(|e| *error = Some(e)).ok())
+}
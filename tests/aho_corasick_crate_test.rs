@@ -500,6 +500,38 @@ testconfig!(
     |_| ()
 );
 
+// Bytewise Daachorse tests with precomputed transitions
+#[test]
+fn search_with_dense_states() {
+    for num_dense_states in [1, 3, u32::MAX] {
+        let builder = |kind| {
+            DoubleArrayAhoCorasickBuilder::new()
+                .match_kind(kind)
+                .num_dense_states(num_dense_states)
+        };
+        run_search_tests(AC_STANDARD_NON_OVERLAPPING, |test| {
+            let pma = builder(MatchKind::Standard).build(test.patterns).unwrap();
+            pma.find_iter(test.haystack).collect()
+        });
+        run_search_tests(AC_STANDARD_OVERLAPPING, |test| {
+            let pma = builder(MatchKind::Standard).build(test.patterns).unwrap();
+            pma.find_overlapping_iter(test.haystack).collect()
+        });
+        run_search_tests(AC_LEFTMOST_LONGEST, |test| {
+            let pma = builder(MatchKind::LeftmostLongest)
+                .build(test.patterns)
+                .unwrap();
+            pma.leftmost_find_iter(test.haystack).collect()
+        });
+        run_search_tests(AC_LEFTMOST_FIRST, |test| {
+            let pma = builder(MatchKind::LeftmostFirst)
+                .build(test.patterns)
+                .unwrap();
+            pma.leftmost_find_iter(test.haystack).collect()
+        });
+    }
+}
+
 // Charwise Daachorse tests
 testconfig!(
     non_overlapping,
@@ -0,0 +1,170 @@
+use std::sync::{Arc, Mutex};
+
+use aneubeck_daachorse::errors::DaachorseError;
+use aneubeck_daachorse::{
+    BuildPhase, BuildProgress, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasickBuilder,
+};
+
+fn generate_patterns(num_patterns: usize) -> Vec<String> {
+    (0..num_patterns)
+        .map(|i| format!("{i}パターン{i}"))
+        .collect()
+}
+
+/// Checks that the phases are reported in order, each of which starts from 0 and finishes with
+/// the total count.
+fn check_progress(reports: &[BuildProgress], num_patterns: usize, num_states: usize) {
+    let phases = [
+        BuildPhase::TrieInsertion,
+        BuildPhase::FailLinks,
+        BuildPhase::DoubleArrayPlacement,
+    ];
+    let mut it = reports.iter().peekable();
+    for phase in phases {
+        let first = it.next().unwrap();
+        assert_eq!(phase, first.phase());
+        assert_eq!(0, first.count());
+        let mut last = first;
+        while let Some(p) = it.next_if(|p| p.phase() == phase) {
+            assert!(last.count() < p.count());
+            if let Some(total) = p.total() {
+                assert!(p.count() <= total);
+            }
+            last = p;
+        }
+        assert_eq!(Some(last.count()), last.total());
+        match phase {
+            BuildPhase::TrieInsertion => assert_eq!(num_patterns, last.count()),
+            // The root and dead states are not processed.
+            BuildPhase::FailLinks => assert_eq!(num_states - 1, last.count()),
+            BuildPhase::DoubleArrayPlacement => assert_eq!(num_states, last.count()),
+        }
+    }
+    assert_eq!(None, it.next());
+}
+
+/// Returns the reports shared with the callback pushing them.
+fn recorder() -> (
+    Arc<Mutex<Vec<BuildProgress>>>,
+    impl FnMut(BuildProgress) + Send + 'static,
+) {
+    let reports = Arc::new(Mutex::new(vec![]));
+    let sink = Arc::clone(&reports);
+    (reports, move |p| sink.lock().unwrap().push(p))
+}
+
+#[test]
+fn test_progress() {
+    let patterns = (0..70_000).map(|i| i.to_string()).collect::<Vec<_>>();
+    let (reports, callback) = recorder();
+    let pma = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .progress(callback)
+        .build(&patterns)
+        .unwrap();
+    let reports = reports.lock().unwrap();
+    // Intermediate progress is also reported.
+    assert!(reports.len() > 6);
+    check_progress(&reports, patterns.len(), pma.num_states());
+}
+
+#[test]
+fn test_progress_sorted() {
+    let mut patterns = (0..70_000).map(|i| i.to_string()).collect::<Vec<_>>();
+    patterns.sort_unstable();
+    let (reports, callback) = recorder();
+    let pma = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .progress(callback)
+        .build_sorted(&patterns)
+        .unwrap();
+    let reports = reports.lock().unwrap();
+    check_progress(&reports, patterns.len(), pma.num_states());
+}
+
+#[test]
+fn test_progress_charwise() {
+    let patterns = (0..70_000).map(|i| i.to_string()).collect::<Vec<_>>();
+    let (reports, callback) = recorder();
+    let pma = CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new()
+        .progress(callback)
+        .build(&patterns)
+        .unwrap();
+    let reports = reports.lock().unwrap();
+    check_progress(&reports, patterns.len(), pma.num_states());
+}
+
+#[test]
+fn test_memory_budget() {
+    let patterns = generate_patterns(10_000);
+    let pma = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .build(&patterns)
+        .unwrap();
+    let heap_bytes = pma.heap_bytes();
+    let mut sorted = patterns.clone();
+    sorted.sort_unstable();
+
+    for budget in [0, 1 << 10, heap_bytes / 2, heap_bytes - 1] {
+        let e = DoubleArrayAhoCorasickBuilder::<u32>::new()
+            .memory_budget(budget)
+            .build(&patterns)
+            .err()
+            .unwrap();
+        assert!(matches!(e, DaachorseError::AutomatonScale(_)));
+        assert_eq!(
+            format!("AutomatonScaleError: memory_bytes must be <= {budget}"),
+            e.to_string()
+        );
+
+        let e = DoubleArrayAhoCorasickBuilder::<u32>::new()
+            .memory_budget(budget)
+            .build_sorted(&sorted)
+            .err()
+            .unwrap();
+        assert!(matches!(e, DaachorseError::AutomatonScale(_)));
+    }
+
+    let other = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .memory_budget(usize::MAX)
+        .build(&patterns)
+        .unwrap();
+    assert_eq!(pma.serialize(), other.serialize());
+}
+
+#[test]
+fn test_memory_budget_dense() {
+    let patterns = ["bcd", "ab", "a"];
+    let pma = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .build(patterns)
+        .unwrap();
+    // The precomputed transitions take 1 KiB per state.
+    let e = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .memory_budget(pma.heap_bytes() + 1024)
+        .num_dense_states(4)
+        .build(patterns)
+        .err()
+        .unwrap();
+    assert!(matches!(e, DaachorseError::AutomatonScale(_)));
+}
+
+#[test]
+fn test_memory_budget_charwise() {
+    let patterns = generate_patterns(10_000);
+    let pma = CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new()
+        .build(&patterns)
+        .unwrap();
+    let heap_bytes = pma.heap_bytes();
+
+    for budget in [0, 1 << 10, heap_bytes / 2, heap_bytes - 1] {
+        let e = CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new()
+            .memory_budget(budget)
+            .build(&patterns)
+            .err()
+            .unwrap();
+        assert!(matches!(e, DaachorseError::AutomatonScale(_)));
+    }
+
+    let other = CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new()
+        .memory_budget(usize::MAX)
+        .build(&patterns)
+        .unwrap();
+    assert_eq!(pma.serialize(), other.serialize());
+}
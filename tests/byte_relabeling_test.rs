@@ -0,0 +1,123 @@
+mod common;
+
+use aneubeck_daachorse::{
+    DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, DuplicatePolicy, MatchKind,
+};
+
+use common::{assert_same_matches, generate_patterns, Xorshift, MATCH_KINDS};
+
+// Bytes scattered over the whole range.
+const ALPHABET: [u8; 8] = [0x00, 0x21, 0x42, 0x63, 0x84, 0xa5, 0xc6, 0xe7];
+
+fn generate_haystack() -> Vec<u8> {
+    let mut rng = Xorshift(0x1234_5678_9abc_def1);
+    let mut haystack: Vec<u8> = (0..300)
+        .flat_map(|_| rng.pattern::<_, Vec<u8>>(&ALPHABET, 10))
+        .collect();
+    // Bytes out of the alphabet
+    haystack.extend_from_slice(b"\xff\x01abc\x00\x21\xfe");
+    haystack
+}
+
+#[test]
+fn test_byte_relabeling() {
+    let patterns: Vec<Vec<u8>> = generate_patterns(2000, &ALPHABET, 8);
+    let haystack = generate_haystack();
+    for match_kind in MATCH_KINDS {
+        let expected = DoubleArrayAhoCorasickBuilder::new()
+            .match_kind(match_kind)
+            .build(&patterns)
+            .unwrap();
+        let pma = DoubleArrayAhoCorasickBuilder::new()
+            .match_kind(match_kind)
+            .relabel_bytes(true)
+            .build(&patterns)
+            .unwrap();
+        assert_eq!(expected.num_states(), pma.num_states());
+        assert!(pma.heap_bytes() <= expected.heap_bytes());
+
+        let bytes = pma.serialize();
+        let (other, rest) = unsafe { DoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+        assert!(rest.is_empty());
+        assert_eq!(bytes, other.serialize());
+
+        assert_same_matches(&expected, &pma, match_kind, &haystack);
+        assert_same_matches(&expected, &other, match_kind, &haystack);
+    }
+}
+
+#[test]
+fn test_byte_relabeling_options() {
+    let patterns: Vec<Vec<u8>> = generate_patterns(2000, &ALPHABET, 8);
+    let haystack = generate_haystack();
+    let expected = DoubleArrayAhoCorasick::new(&patterns).unwrap();
+    let builder = || DoubleArrayAhoCorasickBuilder::new().relabel_bytes(true);
+
+    let pma = builder().num_dense_states(100).build(&patterns).unwrap();
+    assert_same_matches(&expected, &pma, MatchKind::Standard, &haystack);
+
+    let pma = builder().tail_compression(true).build(&patterns).unwrap();
+    assert_same_matches(&expected, &pma, MatchKind::Standard, &haystack);
+
+    let pma = builder().build_sorted(&patterns).unwrap();
+    assert_same_matches(&expected, &pma, MatchKind::Standard, &haystack);
+}
+
+#[test]
+fn test_byte_relabeling_editor() {
+    let patterns: Vec<Vec<u8>> = generate_patterns(2000, &ALPHABET, 8);
+    let haystack = generate_haystack();
+    let (first, second) = patterns.split_at(patterns.len() / 2);
+
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .on_duplicate(DuplicatePolicy::KeepAll)
+        .relabel_bytes(true)
+        .build(first)
+        .unwrap();
+    let mut editor = pma.into_editor().unwrap();
+    for (value, pattern) in (u32::try_from(first.len()).unwrap()..).zip(second) {
+        editor.insert(pattern, value).unwrap();
+    }
+    for pattern in first.iter().step_by(3) {
+        assert!(editor.remove(pattern).is_some());
+    }
+    let edited = editor.finish();
+
+    let mut editor = DoubleArrayAhoCorasick::new(first)
+        .unwrap()
+        .into_editor()
+        .unwrap();
+    for (value, pattern) in (u32::try_from(first.len()).unwrap()..).zip(second) {
+        editor.insert(pattern, value).unwrap();
+    }
+    for pattern in first.iter().step_by(3) {
+        assert!(editor.remove(pattern).is_some());
+    }
+    let expected = editor.finish();
+    assert_same_matches(&expected, &edited, MatchKind::Standard, &haystack);
+
+    let mut editor = edited.into_editor().unwrap();
+    editor.compact().unwrap();
+    let compacted = editor.finish();
+    assert_same_matches(&expected, &compacted, MatchKind::Standard, &haystack);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_byte_relabeling_parallel() {
+    let patterns: Vec<Vec<u8>> = generate_patterns(2000, &ALPHABET, 8);
+    let haystack = generate_haystack();
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .relabel_bytes(true)
+        .par_build(&patterns, 4)
+        .unwrap();
+    let other = DoubleArrayAhoCorasickBuilder::new()
+        .relabel_bytes(true)
+        .build(&patterns)
+        .unwrap();
+    assert_eq!(other.serialize(), pma.serialize());
+    assert_eq!(
+        other.find_overlapping_iter(&haystack).collect::<Vec<_>>(),
+        pma.par_find_overlapping(&haystack, 4),
+    );
+}
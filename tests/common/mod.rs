@@ -0,0 +1,116 @@
+//! Helpers shared by the integration tests.
+
+// Each test crate uses only a part of the helpers.
+#![allow(dead_code)]
+
+use aneubeck_daachorse::{CharwiseDoubleArrayAhoCorasick, DoubleArrayAhoCorasick, MatchKind};
+
+pub const MATCH_KINDS: [MatchKind; 3] = [
+    MatchKind::Standard,
+    MatchKind::LeftmostLongest,
+    MatchKind::LeftmostFirst,
+];
+
+/// Seed giving the patterns of the tests.
+pub const SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Random number generator giving the same sequence on every platform.
+pub struct Xorshift(pub u64);
+
+impl Xorshift {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns a random sequence of 1 to `max_len` items of `alphabet`.
+    pub fn pattern<T, P>(&mut self, alphabet: &[T], max_len: u64) -> P
+    where
+        T: Copy,
+        P: FromIterator<T>,
+    {
+        let len = self.next() % max_len + 1;
+        (0..len)
+            .map(|_| alphabet[usize::try_from(self.next()).unwrap() % alphabet.len()])
+            .collect()
+    }
+}
+
+/// Generates sorted and deduplicated patterns from `num_patterns` random ones.
+pub fn generate_patterns<T, P>(num_patterns: usize, alphabet: &[T], max_len: u64) -> Vec<P>
+where
+    T: Copy,
+    P: FromIterator<T> + Ord,
+{
+    let mut rng = Xorshift(SEED);
+    let mut patterns: Vec<P> = (0..num_patterns)
+        .map(|_| rng.pattern(alphabet, max_len))
+        .collect();
+    patterns.sort_unstable();
+    patterns.dedup();
+    patterns
+}
+
+pub fn assert_same_matches<H>(
+    expected: &DoubleArrayAhoCorasick<u32>,
+    pma: &DoubleArrayAhoCorasick<u32>,
+    match_kind: MatchKind,
+    haystack: &H,
+) where
+    H: AsRef<[u8]> + ?Sized,
+{
+    if match_kind == MatchKind::Standard {
+        assert_eq!(
+            expected.find_iter(haystack).collect::<Vec<_>>(),
+            pma.find_iter(haystack).collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            expected.find_overlapping_iter(haystack).collect::<Vec<_>>(),
+            pma.find_overlapping_iter(haystack).collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            expected
+                .find_overlapping_no_suffix_iter(haystack)
+                .collect::<Vec<_>>(),
+            pma.find_overlapping_no_suffix_iter(haystack)
+                .collect::<Vec<_>>(),
+        );
+    } else {
+        assert_eq!(
+            expected.leftmost_find_iter(haystack).collect::<Vec<_>>(),
+            pma.leftmost_find_iter(haystack).collect::<Vec<_>>(),
+        );
+    }
+}
+
+pub fn assert_same_matches_charwise(
+    expected: &CharwiseDoubleArrayAhoCorasick<u32>,
+    pma: &CharwiseDoubleArrayAhoCorasick<u32>,
+    match_kind: MatchKind,
+    haystack: &str,
+) {
+    if match_kind == MatchKind::Standard {
+        assert_eq!(
+            expected.find_iter(haystack).collect::<Vec<_>>(),
+            pma.find_iter(haystack).collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            expected.find_overlapping_iter(haystack).collect::<Vec<_>>(),
+            pma.find_overlapping_iter(haystack).collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            expected
+                .find_overlapping_no_suffix_iter(haystack)
+                .collect::<Vec<_>>(),
+            pma.find_overlapping_no_suffix_iter(haystack)
+                .collect::<Vec<_>>(),
+        );
+    } else {
+        assert_eq!(
+            expected.leftmost_find_iter(haystack).collect::<Vec<_>>(),
+            pma.leftmost_find_iter(haystack).collect::<Vec<_>>(),
+        );
+    }
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use aneubeck_daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+    DoubleArrayAhoCorasickBuilder, DuplicatePolicy, MatchKind,
+};
+
+use common::{Xorshift, MATCH_KINDS, SEED};
+
+fn generate_patvals() -> Vec<(String, u64)> {
+    let mut rng = Xorshift(SEED);
+    (0..3000)
+        .map(|_| {
+            let pattern = rng.pattern(&['a', 'b', 'c', 'あ'], 8);
+            (pattern, rng.next())
+        })
+        .collect()
+}
+
+fn generate_haystack() -> String {
+    generate_patvals()
+        .into_iter()
+        .map(|(p, _)| p)
+        .take(500)
+        .collect()
+}
+
+#[test]
+fn test_compact_outputs() {
+    let patvals = generate_patvals();
+    let haystack = generate_haystack();
+    for match_kind in MATCH_KINDS {
+        let builder = || {
+            DoubleArrayAhoCorasickBuilder::new()
+                .match_kind(match_kind)
+                .on_duplicate(DuplicatePolicy::KeepAll)
+        };
+        let expected = builder().build_with_values(patvals.clone()).unwrap();
+        let pma = builder()
+            .compact_outputs(true)
+            .build_with_values(patvals.clone())
+            .unwrap();
+        assert!(pma.heap_bytes() < expected.heap_bytes());
+        assert!(pma.serialize().len() < expected.serialize().len());
+
+        let bytes = pma.serialize();
+        let (other, rest) = unsafe { DoubleArrayAhoCorasick::<u64>::deserialize_unchecked(&bytes) };
+        assert!(rest.is_empty());
+        assert_eq!(bytes, other.serialize());
+
+        for pma in [&pma, &other] {
+            if match_kind == MatchKind::Standard {
+                assert_eq!(
+                    expected.find_iter(&haystack).collect::<Vec<_>>(),
+                    pma.find_iter(&haystack).collect::<Vec<_>>(),
+                );
+                assert_eq!(
+                    expected
+                        .find_overlapping_iter(&haystack)
+                        .collect::<Vec<_>>(),
+                    pma.find_overlapping_iter(&haystack).collect::<Vec<_>>(),
+                );
+                assert_eq!(
+                    expected
+                        .find_overlapping_no_suffix_iter(&haystack)
+                        .collect::<Vec<_>>(),
+                    pma.find_overlapping_no_suffix_iter(&haystack)
+                        .collect::<Vec<_>>(),
+                );
+            } else {
+                assert_eq!(
+                    expected.leftmost_find_iter(&haystack).collect::<Vec<_>>(),
+                    pma.leftmost_find_iter(&haystack).collect::<Vec<_>>(),
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_compact_outputs_charwise() {
+    let patvals = generate_patvals();
+    let haystack = generate_haystack();
+    for match_kind in MATCH_KINDS {
+        let builder = || {
+            CharwiseDoubleArrayAhoCorasickBuilder::new()
+                .match_kind(match_kind)
+                .on_duplicate(DuplicatePolicy::KeepAll)
+        };
+        let expected = builder().build_with_values(patvals.clone()).unwrap();
+        let pma = builder()
+            .compact_outputs(true)
+            .build_with_values(patvals.clone())
+            .unwrap();
+        assert!(pma.heap_bytes() < expected.heap_bytes());
+        assert!(pma.serialize().len() < expected.serialize().len());
+
+        let bytes = pma.serialize();
+        let (other, rest) =
+            unsafe { CharwiseDoubleArrayAhoCorasick::<u64>::deserialize_unchecked(&bytes) };
+        assert!(rest.is_empty());
+        assert_eq!(bytes, other.serialize());
+
+        for pma in [&pma, &other] {
+            if match_kind == MatchKind::Standard {
+                assert_eq!(
+                    expected.find_iter(&haystack).collect::<Vec<_>>(),
+                    pma.find_iter(&haystack).collect::<Vec<_>>(),
+                );
+                assert_eq!(
+                    expected
+                        .find_overlapping_iter(&haystack)
+                        .collect::<Vec<_>>(),
+                    pma.find_overlapping_iter(&haystack).collect::<Vec<_>>(),
+                );
+                assert_eq!(
+                    expected
+                        .find_overlapping_no_suffix_iter(&haystack)
+                        .collect::<Vec<_>>(),
+                    pma.find_overlapping_no_suffix_iter(&haystack)
+                        .collect::<Vec<_>>(),
+                );
+            } else {
+                assert_eq!(
+                    expected.leftmost_find_iter(&haystack).collect::<Vec<_>>(),
+                    pma.leftmost_find_iter(&haystack).collect::<Vec<_>>(),
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_compact_outputs_editor() {
+    let patvals = generate_patvals();
+    let (first, second) = patvals.split_at(2000);
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .on_duplicate(DuplicatePolicy::KeepAll)
+        .compact_outputs(true)
+        .build_with_values(first.iter().cloned())
+        .unwrap();
+    let mut editor = pma.into_editor().unwrap();
+    for (pattern, value) in second {
+        // Duplicate patterns are rejected by the editor.
+        let _ = editor.insert(pattern, *value);
+    }
+    let edited = editor.finish();
+
+    let expected = DoubleArrayAhoCorasickBuilder::new()
+        .on_duplicate(DuplicatePolicy::KeepAll)
+        .build_with_values(first.iter().cloned())
+        .unwrap();
+    let mut editor = expected.into_editor().unwrap();
+    for (pattern, value) in second {
+        let _ = editor.insert(pattern, *value);
+    }
+    let expected = editor.finish();
+
+    // The compact representation is kept.
+    assert!(edited.heap_bytes() < expected.heap_bytes());
+    let haystack = generate_haystack();
+    assert_eq!(
+        expected
+            .find_overlapping_iter(&haystack)
+            .collect::<Vec<_>>(),
+        edited.find_overlapping_iter(&haystack).collect::<Vec<_>>(),
+    );
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use aneubeck_daachorse::{
+    CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasickBuilder, MatchKind,
+};
+
+use common::{generate_patterns, MATCH_KINDS};
+
+// Characters escaped in the DOT format, a multibyte one, and ordinary ones.
+const ALPHABET: [char; 6] = ['a', 'b', '"', '\\', 'あ', '\n'];
+
+struct DotCounts {
+    states: usize,
+    gotos: usize,
+    fails: usize,
+    has_dead_state: bool,
+    has_omitted: bool,
+}
+
+fn count(dot: &str) -> DotCounts {
+    assert!(dot.starts_with("digraph automaton {\n"));
+    assert!(dot.ends_with("}\n"));
+    let lines: Vec<_> = dot.lines().collect();
+    DotCounts {
+        states: lines
+            .iter()
+            .filter(|l| l.contains("shape=circle]") || l.contains("shape=doublecircle]"))
+            .count(),
+        gotos: lines
+            .iter()
+            .filter(|l| l.contains(" -> ") && !l.contains("style=dashed"))
+            .count(),
+        fails: lines.iter().filter(|l| l.contains("style=dashed")).count(),
+        has_dead_state: lines.iter().any(|l| l.contains("DEAD\", shape=box]")),
+        has_omitted: lines.iter().any(|l| l.starts_with("  omitted ")),
+    }
+}
+
+#[test]
+fn test_to_dot() {
+    let patterns: Vec<String> = generate_patterns(300, &ALPHABET, 6);
+    for match_kind in MATCH_KINDS {
+        let pma = DoubleArrayAhoCorasickBuilder::<u32>::new()
+            .match_kind(match_kind)
+            .build(&patterns)
+            .unwrap();
+        let counts = count(&pma.to_dot(usize::MAX));
+        assert_eq!(pma.num_states(), counts.states);
+        assert_eq!(pma.num_states() - 1, counts.gotos);
+        assert_eq!(pma.num_states() - 1, counts.fails);
+        assert_eq!(match_kind != MatchKind::Standard, counts.has_dead_state);
+        assert!(!counts.has_omitted);
+    }
+}
+
+#[test]
+fn test_to_dot_max_states() {
+    let patterns: Vec<String> = generate_patterns(300, &ALPHABET, 6);
+    let pma = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .build(&patterns)
+        .unwrap();
+    let dot = pma.to_dot(10);
+    let counts = count(&dot);
+    assert_eq!(10, counts.states);
+    assert_eq!(9, counts.gotos);
+    assert_eq!(9, counts.fails);
+    assert!(dot.contains(&format!("{} more states omitted", pma.num_states() - 10)));
+
+    let counts = count(&pma.to_dot(0));
+    assert_eq!(0, counts.states);
+    assert!(counts.has_omitted);
+}
+
+#[test]
+fn test_to_dot_options() {
+    let patterns: Vec<String> = generate_patterns(300, &ALPHABET, 6);
+    let expected = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .build(&patterns)
+        .unwrap()
+        .to_dot(usize::MAX);
+    let builder = DoubleArrayAhoCorasickBuilder::<u32>::new;
+    for pma in [
+        builder().tail_compression(true).build(&patterns).unwrap(),
+        builder().relabel_bytes(true).build(&patterns).unwrap(),
+        builder().reorder_states(true).build(&patterns).unwrap(),
+    ] {
+        let dot = pma.to_dot(usize::MAX);
+        let counts = count(&dot);
+        assert_eq!(pma.num_states(), counts.states);
+        assert_eq!(pma.num_states() - 1, counts.gotos);
+        // The labels of the states do not depend on their ids.
+        let mut expected_labels: Vec<_> = expected.lines().filter_map(state_label).collect();
+        let mut labels: Vec<_> = dot.lines().filter_map(state_label).collect();
+        expected_labels.sort_unstable();
+        labels.sort_unstable();
+        assert_eq!(expected_labels, labels);
+    }
+}
+
+// Returns the label of a state line without the state id.
+fn state_label(line: &str) -> Option<&str> {
+    let label = line.split_once(" [label=\"")?.1;
+    let label = label.split_once("\\n")?.1;
+    (!line.contains(" -> ")).then_some(label)
+}
+
+#[test]
+fn test_to_dot_charwise() {
+    let patterns: Vec<String> = generate_patterns(300, &ALPHABET, 6);
+    for match_kind in MATCH_KINDS {
+        let pma = CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new()
+            .match_kind(match_kind)
+            .build(&patterns)
+            .unwrap();
+        let dot = pma.to_dot(usize::MAX);
+        let counts = count(&dot);
+        assert_eq!(pma.num_states(), counts.states);
+        assert_eq!(pma.num_states() - 1, counts.gotos);
+        assert_eq!(pma.num_states() - 1, counts.fails);
+        assert_eq!(match_kind != MatchKind::Standard, counts.has_dead_state);
+        // The labels are decoded characters.
+        assert!(dot.contains("[label=\"あ\"];"));
+        assert!(dot.contains("[label=\"\\\\n\"];"));
+
+        // Every single character is a pattern with MatchKind::LeftmostFirst, so the automaton
+        // may have fewer states.
+        let counts = count(&pma.to_dot(10));
+        assert_eq!(pma.num_states().min(10), counts.states);
+        assert_eq!(10 < pma.num_states(), counts.has_omitted);
+    }
+}
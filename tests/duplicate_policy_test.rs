@@ -0,0 +1,107 @@
+use aneubeck_daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+    DoubleArrayAhoCorasickBuilder, DuplicatePolicy, MatchKind,
+};
+
+const PATVALS: [(&str, u32); 5] = [("abc", 1), ("bc", 2), ("abc", 4), ("c", 8), ("abc", 16)];
+
+fn build(match_kind: MatchKind, policy: DuplicatePolicy<u32>) -> DoubleArrayAhoCorasick<u32> {
+    DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(match_kind)
+        .on_duplicate(policy)
+        .build_with_values(PATVALS)
+        .unwrap()
+}
+
+fn build_charwise(
+    match_kind: MatchKind,
+    policy: DuplicatePolicy<u32>,
+) -> CharwiseDoubleArrayAhoCorasick<u32> {
+    CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(match_kind)
+        .on_duplicate(policy)
+        .build_with_values(PATVALS)
+        .unwrap()
+}
+
+#[test]
+fn test_error() {
+    for match_kind in [
+        MatchKind::Standard,
+        MatchKind::LeftmostLongest,
+        MatchKind::LeftmostFirst,
+    ] {
+        let pma: Result<DoubleArrayAhoCorasick<u32>, _> = DoubleArrayAhoCorasickBuilder::new()
+            .match_kind(match_kind)
+            .on_duplicate(DuplicatePolicy::Error)
+            .build_with_values(PATVALS);
+        assert!(pma.is_err());
+
+        let pma: Result<CharwiseDoubleArrayAhoCorasick<u32>, _> =
+            CharwiseDoubleArrayAhoCorasickBuilder::new()
+                .match_kind(match_kind)
+                .on_duplicate(DuplicatePolicy::Error)
+                .build_with_values(PATVALS);
+        assert!(pma.is_err());
+    }
+}
+
+#[test]
+fn test_policies() {
+    for (policy, expected) in [
+        (DuplicatePolicy::KeepFirst, 1),
+        (DuplicatePolicy::KeepLast, 16),
+        (DuplicatePolicy::Merge(|a, b| a | b), 21),
+    ] {
+        let pma = build(MatchKind::Standard, policy);
+        let values: Vec<_> = pma
+            .find_overlapping_iter("abc")
+            .map(|m| m.value())
+            .collect();
+        assert_eq!(vec![expected, 2, 8], values);
+        assert_eq!(7, pma.num_states());
+
+        let pma = build_charwise(MatchKind::Standard, policy);
+        let values: Vec<_> = pma
+            .find_overlapping_iter("abc")
+            .map(|m| m.value())
+            .collect();
+        assert_eq!(vec![expected, 2, 8], values);
+        assert_eq!(7, pma.num_states());
+
+        for match_kind in [MatchKind::LeftmostLongest, MatchKind::LeftmostFirst] {
+            let pma = build(match_kind, policy);
+            let values: Vec<_> = pma.leftmost_find_iter("abc").map(|m| m.value()).collect();
+            assert_eq!(vec![expected], values);
+
+            let pma = build_charwise(match_kind, policy);
+            let values: Vec<_> = pma.leftmost_find_iter("abc").map(|m| m.value()).collect();
+            assert_eq!(vec![expected], values);
+        }
+    }
+}
+
+#[test]
+fn test_indices() {
+    let patterns = ["abc", "bc", "abc"];
+
+    let pma: DoubleArrayAhoCorasick<usize> = DoubleArrayAhoCorasickBuilder::new()
+        .on_duplicate(DuplicatePolicy::KeepLast)
+        .build(patterns)
+        .unwrap();
+    let values: Vec<_> = pma
+        .find_overlapping_iter("abc")
+        .map(|m| m.value())
+        .collect();
+    assert_eq!(vec![2, 1], values);
+
+    let pma: CharwiseDoubleArrayAhoCorasick<usize> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .on_duplicate(DuplicatePolicy::KeepFirst)
+        .build(patterns)
+        .unwrap();
+    let values: Vec<_> = pma
+        .find_overlapping_iter("abc")
+        .map(|m| m.value())
+        .collect();
+    assert_eq!(vec![0, 1], values);
+}
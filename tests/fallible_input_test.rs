@@ -0,0 +1,124 @@
+use std::cell::Cell;
+use std::io::{BufRead, Cursor};
+
+use aneubeck_daachorse::errors::{BuildError, DaachorseError};
+use aneubeck_daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+    DoubleArrayAhoCorasickBuilder,
+};
+
+const PATTERNS: &str = "bcd\nab\na\n全世界\n世界\n";
+
+#[test]
+fn test_try_build() {
+    let expected = DoubleArrayAhoCorasick::<u32>::new(PATTERNS.lines()).unwrap();
+    let pma = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .try_build(Cursor::new(PATTERNS).lines())
+        .unwrap();
+    assert_eq!(expected.serialize(), pma.serialize());
+
+    let expected = CharwiseDoubleArrayAhoCorasick::<u32>::new(PATTERNS.lines()).unwrap();
+    let pma = CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new()
+        .try_build(Cursor::new(PATTERNS).lines())
+        .unwrap();
+    assert_eq!(expected.serialize(), pma.serialize());
+}
+
+#[test]
+fn test_try_build_with_values() {
+    let patvals = || PATTERNS.lines().zip(10..);
+    let expected = DoubleArrayAhoCorasick::<u32>::with_values(patvals()).unwrap();
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .try_build_with_values(patvals().map(Ok::<_, ()>))
+        .unwrap();
+    assert_eq!(expected.serialize(), pma.serialize());
+
+    let expected = CharwiseDoubleArrayAhoCorasick::<u32>::with_values(patvals()).unwrap();
+    let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .try_build_with_values(patvals().map(Ok::<_, ()>))
+        .unwrap();
+    assert_eq!(expected.serialize(), pma.serialize());
+}
+
+// Yields the patterns with an error at `error_pos`, counting the items read.
+fn failing_input<'a>(
+    patterns: &'a [&'a str],
+    error_pos: usize,
+    num_read: &'a Cell<usize>,
+) -> impl Iterator<Item = Result<&'a str, usize>> + 'a {
+    patterns.iter().enumerate().map(move |(i, &p)| {
+        num_read.set(num_read.get() + 1);
+        if i == error_pos {
+            Err(i)
+        } else {
+            Ok(p)
+        }
+    })
+}
+
+#[test]
+fn test_input_error() {
+    let patterns = ["bcd", "ab", "a", "abc", "b"];
+    for error_pos in 0..patterns.len() {
+        let num_read = Cell::new(0);
+        let result = DoubleArrayAhoCorasickBuilder::<u32>::new()
+            .try_build(failing_input(&patterns, error_pos, &num_read));
+        assert!(matches!(result, Err(BuildError::Input(i)) if i == error_pos));
+        assert_eq!(error_pos + 1, num_read.get());
+
+        let num_read = Cell::new(0);
+        let result = CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new()
+            .try_build(failing_input(&patterns, error_pos, &num_read));
+        assert!(matches!(result, Err(BuildError::Input(i)) if i == error_pos));
+        assert_eq!(error_pos + 1, num_read.get());
+    }
+}
+
+#[test]
+fn test_daachorse_error_before_input_error() {
+    let patterns = ["bcd", "ab", "bcd", "abc", "b"];
+    let num_read = Cell::new(0);
+    let result = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .try_build(failing_input(&patterns, 3, &num_read));
+    assert!(matches!(
+        result,
+        Err(BuildError::Daachorse(DaachorseError::DuplicatePattern(_)))
+    ));
+    assert_eq!(3, num_read.get());
+
+    let num_read = Cell::new(0);
+    let result = CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new()
+        .try_build(failing_input(&patterns, 3, &num_read));
+    assert!(matches!(
+        result,
+        Err(BuildError::Daachorse(DaachorseError::DuplicatePattern(_)))
+    ));
+    assert_eq!(3, num_read.get());
+
+    let result = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .num_free_blocks(0)
+        .try_build(failing_input(&patterns, 0, &num_read));
+    assert!(matches!(result, Err(BuildError::Daachorse(_))));
+
+    let result = DoubleArrayAhoCorasickBuilder::<u8>::new()
+        .try_build((0..300).map(|i| Ok::<_, ()>(format!("{i}"))));
+    assert!(matches!(
+        result,
+        Err(BuildError::Daachorse(DaachorseError::InvalidConversion(_)))
+    ));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_std_error() {
+    fn build(input: &str) -> Result<DoubleArrayAhoCorasick<u32>, Box<dyn std::error::Error>> {
+        Ok(DoubleArrayAhoCorasickBuilder::new().try_build(Cursor::new(input).lines())?)
+    }
+    assert!(build(PATTERNS).is_ok());
+    let e = build("abc\nabc\n").err().unwrap();
+    assert_eq!(
+        "DuplicatePatternError: [97, 98, 99] (pattern index 1)",
+        e.to_string()
+    );
+    assert!(e.source().is_none());
+}
@@ -1,4 +1,7 @@
-use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder};
+use aneubeck_daachorse::errors::DaachorseError;
+use aneubeck_daachorse::{
+    CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder,
+};
 
 #[test]
 fn test_large_num_free_blocks() {
@@ -7,3 +10,38 @@ fn test_large_num_free_blocks() {
         .build(["pattern"]);
     assert!(pma.is_err());
 }
+
+#[test]
+fn test_zero_num_free_blocks() {
+    let builder = DoubleArrayAhoCorasickBuilder::<usize>::new().num_free_blocks(0);
+    assert!(builder.validate().is_err());
+    assert!(builder.build(["pattern"]).is_err());
+
+    let builder = CharwiseDoubleArrayAhoCorasickBuilder::<usize>::new().num_free_blocks(0);
+    assert!(builder.validate().is_err());
+    assert!(builder.build(["pattern"]).is_err());
+}
+
+#[test]
+fn test_validate() {
+    let builder = DoubleArrayAhoCorasickBuilder::<usize>::new().num_free_blocks(u32::MAX);
+    let e = builder.validate().err().unwrap();
+    assert!(matches!(e, DaachorseError::AutomatonScale(_)));
+
+    assert!(DoubleArrayAhoCorasickBuilder::<usize>::new()
+        .num_free_blocks(1)
+        .validate()
+        .is_ok());
+    assert!(CharwiseDoubleArrayAhoCorasickBuilder::<usize>::new()
+        .num_free_blocks(1)
+        .validate()
+        .is_ok());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_zero_num_threads() {
+    let pma: Result<DoubleArrayAhoCorasick<usize>, _> =
+        DoubleArrayAhoCorasickBuilder::new().par_build(["pattern"], 0);
+    assert!(matches!(pma, Err(DaachorseError::InvalidArgument(_))));
+}
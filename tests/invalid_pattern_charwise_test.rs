@@ -1,3 +1,4 @@
+use aneubeck_daachorse::errors::DaachorseError;
 use aneubeck_daachorse::{CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, MatchKind};
 
 #[test]
@@ -95,3 +96,25 @@ fn test_duplicate_patterns_with_matchkind_standard() {
             .build(["abc", "123", "abc"]);
     assert!(pma.is_err());
 }
+
+#[test]
+fn test_pattern_index() {
+    let patterns = ["全世界", "世界", "", "全世界"];
+    let e = CharwiseDoubleArrayAhoCorasick::<usize>::new(patterns)
+        .err()
+        .unwrap();
+    assert_eq!(Some(2), e.pattern_index());
+    assert!(matches!(e, DaachorseError::EmptyPattern(_)));
+
+    let patterns = ["全世界", "世界", "に", "全世界"];
+    let e = CharwiseDoubleArrayAhoCorasick::<usize>::new(patterns)
+        .err()
+        .unwrap();
+    assert_eq!(Some(3), e.pattern_index());
+
+    let e = CharwiseDoubleArrayAhoCorasickBuilder::<usize>::new()
+        .build_sorted(["世界", "全世界", "全世界"])
+        .err()
+        .unwrap();
+    assert_eq!(Some(2), e.pattern_index());
+}
@@ -1,3 +1,4 @@
+use aneubeck_daachorse::errors::DaachorseError;
 use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
 
 #[test]
@@ -5,6 +6,29 @@ fn test_empty_pattern() {
     assert!(DoubleArrayAhoCorasick::<usize>::new([""]).is_err());
 }
 
+#[test]
+fn test_empty_pattern_error() {
+    let patterns = ["abc", "", "123"];
+    for e in [
+        DoubleArrayAhoCorasick::<usize>::new(patterns)
+            .err()
+            .unwrap(),
+        DoubleArrayAhoCorasickBuilder::<usize>::new()
+            .build_sorted(["", "123"])
+            .err()
+            .unwrap(),
+    ] {
+        assert!(matches!(e, DaachorseError::EmptyPattern(_)));
+    }
+    let e = DoubleArrayAhoCorasick::<usize>::new(patterns)
+        .err()
+        .unwrap();
+    assert_eq!(
+        "EmptyPatternError: patterns must not be empty (pattern index 1)",
+        format!("{e}")
+    );
+}
+
 #[test]
 fn test_empty_set() {
     assert!(DoubleArrayAhoCorasick::<usize>::new(Vec::<String>::new()).is_err());
@@ -86,3 +110,61 @@ fn test_duplicate_patterns_with_matchkind_standard() {
         .build(["abc", "123", "abc"]);
     assert!(pma.is_err());
 }
+
+#[test]
+fn test_pattern_index() {
+    let patterns = ["abc", "123", "", "abc"];
+    let e = DoubleArrayAhoCorasick::<usize>::new(patterns)
+        .err()
+        .unwrap();
+    assert_eq!(Some(2), e.pattern_index());
+    assert!(format!("{e}").ends_with("(pattern index 2)"));
+
+    let patterns = ["abc", "123", "xyz", "abc"];
+    let e = DoubleArrayAhoCorasick::<usize>::new(patterns)
+        .err()
+        .unwrap();
+    assert_eq!(Some(3), e.pattern_index());
+    assert!(format!("{e}").ends_with("(pattern index 3)"));
+
+    let patvals = [("abc", 0), ("123", 1), ("abc", 2)];
+    let e = DoubleArrayAhoCorasick::<usize>::with_values(patvals)
+        .err()
+        .unwrap();
+    assert_eq!(Some(2), e.pattern_index());
+
+    let e = DoubleArrayAhoCorasickBuilder::<usize>::new()
+        .build_sorted(["123", "abc", "abc"])
+        .err()
+        .unwrap();
+    assert_eq!(Some(2), e.pattern_index());
+
+    let e = DoubleArrayAhoCorasick::<usize>::new(Vec::<String>::new())
+        .err()
+        .unwrap();
+    assert_eq!(None, e.pattern_index());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_pattern_index_parallel() {
+    let patterns = ["abc", "123", "xyz", "abc", "", "123"];
+    let e = DoubleArrayAhoCorasickBuilder::<usize>::new()
+        .par_build(patterns, 3)
+        .err()
+        .unwrap();
+    assert_eq!(Some(3), e.pattern_index());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_std_error() {
+    fn build() -> Result<DoubleArrayAhoCorasick<usize>, Box<dyn std::error::Error>> {
+        Ok(DoubleArrayAhoCorasick::new(["abc", "abc"])?)
+    }
+    let e = build().err().unwrap();
+    assert_eq!(
+        "DuplicatePatternError: [97, 98, 99] (pattern index 1)",
+        e.to_string()
+    );
+}
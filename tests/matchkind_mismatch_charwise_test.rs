@@ -1,4 +1,8 @@
-use aneubeck_daachorse::{CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, MatchKind};
+use aneubeck_daachorse::errors::DaachorseError;
+use aneubeck_daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DuplicatePolicy,
+    MatchKind,
+};
 
 #[test]
 #[should_panic]
@@ -69,3 +73,101 @@ fn test_leftmost_find_iter_with_standard() {
         .unwrap();
     pma.leftmost_find_iter("");
 }
+
+#[test]
+fn test_merge_with_different_match_kinds() {
+    let pma1: CharwiseDoubleArrayAhoCorasick<usize> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::Standard)
+        .build(["pattern"])
+        .unwrap();
+    let pma2: CharwiseDoubleArrayAhoCorasick<usize> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .build(["pattern"])
+        .unwrap();
+    let e = CharwiseDoubleArrayAhoCorasick::merge(&[&pma1, &pma2], DuplicatePolicy::KeepFirst)
+        .err()
+        .unwrap();
+    assert!(matches!(e, DaachorseError::MatchKindMismatch(_)));
+    assert_eq!(
+        "MatchKindMismatchError: match_kind must be Standard, but is LeftmostFirst",
+        format!("{e}"),
+    );
+}
+
+#[test]
+fn test_try_methods() {
+    let haystack = || "pattern".bytes();
+    let standard: CharwiseDoubleArrayAhoCorasick<u32> =
+        CharwiseDoubleArrayAhoCorasickBuilder::new()
+            .match_kind(MatchKind::Standard)
+            .build(["pattern"])
+            .unwrap();
+    assert!(standard.try_find_iter("").is_ok());
+    assert!(standard.try_find_iter_from_iter(haystack()).is_ok());
+    assert!(standard.try_find_overlapping_iter("").is_ok());
+    assert!(standard
+        .try_find_overlapping_iter_from_iter(haystack())
+        .is_ok());
+    assert!(standard.try_find_overlapping_no_suffix_iter("").is_ok());
+    assert!(standard
+        .try_find_overlapping_no_suffix_iter_from_iter(haystack())
+        .is_ok());
+    assert!(matches!(
+        standard.try_leftmost_find_iter(""),
+        Err(DaachorseError::MatchKindMismatch(e)) if e.match_kind() == MatchKind::Standard,
+    ));
+
+    for match_kind in [MatchKind::LeftmostLongest, MatchKind::LeftmostFirst] {
+        let leftmost: CharwiseDoubleArrayAhoCorasick<u32> =
+            CharwiseDoubleArrayAhoCorasickBuilder::new()
+                .match_kind(match_kind)
+                .build(["pattern"])
+                .unwrap();
+        assert!(leftmost.try_find_iter("").is_err());
+        assert!(leftmost.try_find_iter_from_iter(haystack()).is_err());
+        assert!(leftmost.try_find_overlapping_iter("").is_err());
+        assert!(leftmost
+            .try_find_overlapping_iter_from_iter(haystack())
+            .is_err());
+        assert!(leftmost.try_find_overlapping_no_suffix_iter("").is_err());
+        assert!(leftmost
+            .try_find_overlapping_no_suffix_iter_from_iter(haystack())
+            .is_err());
+        assert!(leftmost.try_leftmost_find_iter("").is_ok());
+    }
+}
+
+#[test]
+fn test_try_from_iter_with_invalid_utf8() {
+    let pma: CharwiseDoubleArrayAhoCorasick<u32> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .build(["世界", "に"])
+        .unwrap();
+    let valid = "全世界中に".as_bytes();
+    let matches: Vec<_> = pma
+        .try_find_iter_from_iter(valid.iter().copied())
+        .unwrap()
+        .map(|m| m.map(|m| (m.start(), m.end(), m.value())))
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(vec![(3, 9, 0), (12, 15, 1)], matches);
+
+    // "世" followed by a broken "界".
+    let invalid = [0xe4, 0xb8, 0x96, 0xe7, 0x95, b'a'];
+    let check = |e: DaachorseError| match e {
+        DaachorseError::InvalidUtf8(e) => assert_eq!(3, e.valid_up_to()),
+        e => panic!("unexpected error: {e}"),
+    };
+    let mut it = pma.try_find_iter_from_iter(invalid.into_iter()).unwrap();
+    check(it.next().unwrap().unwrap_err());
+    assert!(it.next().is_none());
+    let mut it = pma
+        .try_find_overlapping_iter_from_iter(invalid.into_iter())
+        .unwrap();
+    check(it.next().unwrap().unwrap_err());
+    assert!(it.next().is_none());
+    let mut it = pma
+        .try_find_overlapping_no_suffix_iter_from_iter(invalid.into_iter())
+        .unwrap();
+    check(it.next().unwrap().unwrap_err());
+    assert!(it.next().is_none());
+}
@@ -1,4 +1,7 @@
-use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
+use aneubeck_daachorse::errors::DaachorseError;
+use aneubeck_daachorse::{
+    DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, DuplicatePolicy, MatchKind,
+};
 
 #[test]
 #[should_panic]
@@ -69,3 +72,102 @@ fn test_leftmost_find_iter_with_standard() {
         .unwrap();
     pma.leftmost_find_iter("");
 }
+
+#[test]
+fn test_merge_with_different_match_kinds() {
+    let pma1: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::Standard)
+        .build(["pattern"])
+        .unwrap();
+    let pma2: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build(["pattern"])
+        .unwrap();
+    let e = DoubleArrayAhoCorasick::merge(&[&pma1, &pma2], DuplicatePolicy::KeepFirst)
+        .err()
+        .unwrap();
+    assert!(matches!(e, DaachorseError::MatchKindMismatch(_)));
+    assert_eq!(
+        "MatchKindMismatchError: match_kind must be Standard, but is LeftmostLongest",
+        format!("{e}"),
+    );
+}
+
+#[test]
+fn test_try_methods() {
+    let standard: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::Standard)
+        .build(["pattern"])
+        .unwrap();
+    assert!(standard.try_find_iter("").is_ok());
+    assert!(standard.try_find_iter_from_iter([].into_iter()).is_ok());
+    assert!(standard.try_find_overlapping_iter("").is_ok());
+    assert!(standard
+        .try_find_overlapping_iter_from_iter([].into_iter())
+        .is_ok());
+    assert!(standard.try_find_overlapping_no_suffix_iter("").is_ok());
+    assert!(standard
+        .try_find_overlapping_no_suffix_iter_from_iter([].into_iter())
+        .is_ok());
+    assert!(matches!(
+        standard.try_leftmost_find_iter(""),
+        Err(DaachorseError::MatchKindMismatch(e)) if e.match_kind() == MatchKind::Standard,
+    ));
+    assert!(standard.into_editor().is_ok());
+
+    for match_kind in [MatchKind::LeftmostLongest, MatchKind::LeftmostFirst] {
+        let leftmost: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+            .match_kind(match_kind)
+            .build(["pattern"])
+            .unwrap();
+        assert!(leftmost.try_find_iter("").is_err());
+        assert!(leftmost.try_find_iter_from_iter([].into_iter()).is_err());
+        assert!(leftmost.try_find_overlapping_iter("").is_err());
+        assert!(leftmost
+            .try_find_overlapping_iter_from_iter([].into_iter())
+            .is_err());
+        assert!(leftmost.try_find_overlapping_no_suffix_iter("").is_err());
+        assert!(leftmost
+            .try_find_overlapping_no_suffix_iter_from_iter([].into_iter())
+            .is_err());
+        assert!(leftmost.try_leftmost_find_iter("").is_ok());
+        assert!(matches!(
+            leftmost.into_editor(),
+            Err(DaachorseError::MatchKindMismatch(e)) if e.match_kind() == match_kind,
+        ));
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_try_par_methods() {
+    let standard: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::Standard)
+        .build(["pattern"])
+        .unwrap();
+    assert!(standard.try_par_find_overlapping("", 2).is_ok());
+    assert!(matches!(
+        standard.try_par_find_overlapping("", 0),
+        Err(DaachorseError::InvalidArgument(_)),
+    ));
+    assert!(matches!(
+        standard.try_par_leftmost_find("", 2),
+        Err(DaachorseError::MatchKindMismatch(e)) if e.match_kind() == MatchKind::Standard,
+    ));
+
+    for match_kind in [MatchKind::LeftmostLongest, MatchKind::LeftmostFirst] {
+        let leftmost: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+            .match_kind(match_kind)
+            .build(["pattern"])
+            .unwrap();
+        assert!(matches!(
+            leftmost.try_par_find_overlapping("", 2),
+            Err(DaachorseError::MatchKindMismatch(e)) if e.match_kind() == match_kind,
+        ));
+        assert!(leftmost.try_par_leftmost_find("", 2).is_ok());
+        assert!(matches!(
+            leftmost.try_par_leftmost_find("", 0),
+            Err(DaachorseError::InvalidArgument(_)),
+        ));
+    }
+}
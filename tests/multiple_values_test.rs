@@ -0,0 +1,119 @@
+use aneubeck_daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+    DoubleArrayAhoCorasickBuilder, DuplicatePolicy, MatchKind,
+};
+
+const PATVALS: [(&str, u32); 5] = [
+    ("Paris", 0),
+    ("is", 1),
+    ("Paris", 2),
+    ("ris", 3),
+    ("Paris", 4),
+];
+
+fn build(match_kind: MatchKind) -> DoubleArrayAhoCorasick<u32> {
+    DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(match_kind)
+        .on_duplicate(DuplicatePolicy::KeepAll)
+        .build_with_values(PATVALS)
+        .unwrap()
+}
+
+fn build_charwise(match_kind: MatchKind) -> CharwiseDoubleArrayAhoCorasick<u32> {
+    CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(match_kind)
+        .on_duplicate(DuplicatePolicy::KeepAll)
+        .build_with_values(PATVALS)
+        .unwrap()
+}
+
+#[test]
+fn test_overlapping() {
+    let expected = vec![(0, 5, 0), (0, 5, 2), (0, 5, 4), (2, 5, 3), (3, 5, 1)];
+
+    let pma = build(MatchKind::Standard);
+    let matches: Vec<_> = pma
+        .find_overlapping_iter("Paris")
+        .map(|m| (m.start(), m.end(), m.value()))
+        .collect();
+    assert_eq!(expected, matches);
+
+    let pma = build_charwise(MatchKind::Standard);
+    let matches: Vec<_> = pma
+        .find_overlapping_iter("Paris")
+        .map(|m| (m.start(), m.end(), m.value()))
+        .collect();
+    assert_eq!(expected, matches);
+}
+
+#[test]
+fn test_non_overlapping() {
+    let expected = vec![(0, 5, 0), (0, 5, 2), (0, 5, 4), (6, 8, 1)];
+
+    let pma = build(MatchKind::Standard);
+    let matches: Vec<_> = pma
+        .find_iter("Paris is")
+        .map(|m| (m.start(), m.end(), m.value()))
+        .collect();
+    assert_eq!(expected, matches);
+    let matches: Vec<_> = pma
+        .find_overlapping_no_suffix_iter("Paris is")
+        .map(|m| (m.start(), m.end(), m.value()))
+        .collect();
+    assert_eq!(expected, matches);
+
+    let pma = build_charwise(MatchKind::Standard);
+    let matches: Vec<_> = pma
+        .find_iter("Paris is")
+        .map(|m| (m.start(), m.end(), m.value()))
+        .collect();
+    assert_eq!(expected, matches);
+    let matches: Vec<_> = pma
+        .find_overlapping_no_suffix_iter("Paris is")
+        .map(|m| (m.start(), m.end(), m.value()))
+        .collect();
+    assert_eq!(expected, matches);
+
+    for match_kind in [MatchKind::LeftmostLongest, MatchKind::LeftmostFirst] {
+        let pma = build(match_kind);
+        let matches: Vec<_> = pma
+            .leftmost_find_iter("Paris is")
+            .map(|m| (m.start(), m.end(), m.value()))
+            .collect();
+        assert_eq!(expected, matches);
+
+        let pma = build_charwise(match_kind);
+        let matches: Vec<_> = pma
+            .leftmost_find_iter("Paris is")
+            .map(|m| (m.start(), m.end(), m.value()))
+            .collect();
+        assert_eq!(expected, matches);
+    }
+}
+
+#[test]
+fn test_merge() {
+    let other = DoubleArrayAhoCorasick::with_values([("Paris", 5), ("ris", 6)]).unwrap();
+    let pma = DoubleArrayAhoCorasick::merge(
+        &[&build(MatchKind::Standard), &other],
+        DuplicatePolicy::KeepAll,
+    )
+    .unwrap();
+    let values: Vec<_> = pma
+        .find_overlapping_iter("Paris")
+        .map(|m| m.value())
+        .collect();
+    assert_eq!(vec![0, 2, 4, 5, 3, 6, 1], values);
+
+    let other = CharwiseDoubleArrayAhoCorasick::with_values([("Paris", 5), ("ris", 6)]).unwrap();
+    let pma = CharwiseDoubleArrayAhoCorasick::merge(
+        &[&build_charwise(MatchKind::Standard), &other],
+        DuplicatePolicy::KeepFirst,
+    )
+    .unwrap();
+    let values: Vec<_> = pma
+        .find_overlapping_iter("Paris")
+        .map(|m| m.value())
+        .collect();
+    assert_eq!(vec![0, 3, 1], values);
+}
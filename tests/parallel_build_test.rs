@@ -0,0 +1,112 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use aneubeck_daachorse::errors::DaachorseError;
+use aneubeck_daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, DuplicatePolicy};
+
+use common::{Xorshift, MATCH_KINDS, SEED};
+
+fn generate_patterns(num_patterns: usize) -> Vec<Vec<u8>> {
+    let alphabet: Vec<u8> = (b'a'..b'a' + 20).collect();
+    let mut rng = Xorshift(SEED);
+    (0..num_patterns)
+        .map(|_| rng.pattern(&alphabet, 6))
+        .collect()
+}
+
+#[test]
+fn test_identical_serialization() {
+    let patterns = generate_patterns(30000);
+    let patvals: Vec<_> = patterns
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (p, u32::try_from(i).unwrap()))
+        .collect();
+    for match_kind in MATCH_KINDS {
+        for policy in [
+            DuplicatePolicy::KeepFirst,
+            DuplicatePolicy::KeepLast,
+            DuplicatePolicy::Merge(|a, b| a ^ b),
+            DuplicatePolicy::KeepAll,
+        ] {
+            let builder = || {
+                DoubleArrayAhoCorasickBuilder::new()
+                    .match_kind(match_kind)
+                    .on_duplicate(policy)
+            };
+            let expected = builder().build_with_values(patvals.clone()).unwrap();
+            for num_threads in [1, 2, 3, 8] {
+                let pma = builder()
+                    .par_build_with_values(patvals.clone(), num_threads)
+                    .unwrap();
+                assert_eq!(expected.serialize(), pma.serialize());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_par_build() {
+    let patterns = ["bcd", "ab", "a", "abcde", "cd", "xyz"];
+    let expected: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .build(patterns)
+        .unwrap();
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .par_build(patterns, 3)
+        .unwrap();
+    assert_eq!(expected.serialize(), pma.serialize());
+}
+
+#[test]
+fn test_same_error() {
+    for patterns in [
+        vec!["bcd", "ab", "bcd", "", "ab"],
+        vec!["bcd", "ab", "", "bcd", "ab"],
+        vec!["bcd", "ab", "xy", "ab", "bcd"],
+        vec![],
+    ] {
+        let expected = DoubleArrayAhoCorasickBuilder::<u32>::new()
+            .build(&patterns)
+            .err()
+            .unwrap();
+        for num_threads in [1, 2, 3, 8] {
+            let e = DoubleArrayAhoCorasickBuilder::<u32>::new()
+                .par_build(&patterns, num_threads)
+                .err()
+                .unwrap();
+            assert_eq!(expected.to_string(), e.to_string());
+        }
+    }
+}
+
+#[test]
+fn test_memory_budget() {
+    let patterns = generate_patterns(30000);
+    let builder =
+        || DoubleArrayAhoCorasickBuilder::<u32>::new().on_duplicate(DuplicatePolicy::KeepFirst);
+    let expected = builder().build(&patterns).unwrap();
+    let heap_bytes = expected.heap_bytes();
+    for num_threads in [1, 2, 3, 8] {
+        // The budget is exceeded during the insertion.
+        let e = builder()
+            .memory_budget(1 << 10)
+            .par_build(&patterns, num_threads)
+            .err()
+            .unwrap();
+        assert!(matches!(e, DaachorseError::AutomatonScale(_)));
+
+        let e = builder()
+            .memory_budget(heap_bytes - 1)
+            .par_build(&patterns, num_threads)
+            .err()
+            .unwrap();
+        assert!(matches!(e, DaachorseError::AutomatonScale(_)));
+
+        let pma = builder()
+            .memory_budget(usize::MAX)
+            .par_build(&patterns, num_threads)
+            .unwrap();
+        assert_eq!(expected.serialize(), pma.serialize());
+    }
+}
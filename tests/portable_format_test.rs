@@ -0,0 +1,130 @@
+use aneubeck_daachorse::errors::DaachorseError;
+use aneubeck_daachorse::{CharwiseDoubleArrayAhoCorasick, DoubleArrayAhoCorasick};
+
+const PATTERNS: [&str; 2] = ["\u{1}\u{2}", "\u{2}"];
+
+// The automaton of `PATTERNS` with the values `1 << 40` and 3 as `u64`, which must be identical
+// on every platform.
+const GOLDEN: [u8; 174] = [
+    // magic
+    b'D', b'A', b'A', b'C', b'C', b'H', b'A', b'R', //
+    // version
+    1, 0, 0, 0, //
+    // states
+    6, 0, 0, 0, //
+    2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, //
+    0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, //
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, //
+    4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, //
+    0, 0, 0, 0, 3, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, //
+    0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, //
+    // mapper
+    3, 0, 0, 0, //
+    255, 255, 255, 255, 1, 0, 0, 0, 0, 0, 0, 0, //
+    2, 0, 0, 0, //
+    // outputs
+    0, 2, 0, 0, 0, //
+    3, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, //
+    0, 0, 0, 0, 0, 1, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, //
+    // match_kind
+    0, //
+    // num_states
+    4, 0, 0, 0,
+];
+
+fn values<V: Copy>(pma: &CharwiseDoubleArrayAhoCorasick<V>) -> Vec<V> {
+    pma.find_overlapping_iter("\u{1}\u{2}")
+        .map(|m| m.value())
+        .collect()
+}
+
+#[test]
+fn test_golden() {
+    let pma =
+        CharwiseDoubleArrayAhoCorasick::with_values(PATTERNS.into_iter().zip([1u64 << 40, 3]))
+            .unwrap();
+    assert_eq!(GOLDEN, &pma.serialize()[..]);
+
+    let (pma, rest) = CharwiseDoubleArrayAhoCorasick::<u64>::deserialize(&GOLDEN).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(vec![1 << 40, 3], values(&pma));
+
+    // The value 1 << 40 does not fit in 32-bit usize.
+    let result = CharwiseDoubleArrayAhoCorasick::<usize>::deserialize(&GOLDEN);
+    #[cfg(target_pointer_width = "64")]
+    assert_eq!(vec![1 << 40, 3], values(&result.unwrap().0));
+    #[cfg(target_pointer_width = "32")]
+    assert!(result.is_err());
+}
+
+// Pointer-sized values are stored in the same format as 64-bit values.
+#[test]
+fn test_pointer_sized_values() {
+    let patvals = |values: [u64; 2]| PATTERNS.into_iter().zip(values);
+    let expected = CharwiseDoubleArrayAhoCorasick::with_values(patvals([0x0123_4567, 3])).unwrap();
+    let pma = CharwiseDoubleArrayAhoCorasick::with_values(
+        PATTERNS.into_iter().zip([0x0123_4567usize, 3]),
+    )
+    .unwrap();
+    assert_eq!(expected.serialize(), pma.serialize());
+    let (other, _) =
+        CharwiseDoubleArrayAhoCorasick::<usize>::deserialize(&expected.serialize()).unwrap();
+    assert_eq!(vec![0x0123_4567, 3], values(&other));
+
+    let expected = DoubleArrayAhoCorasick::with_values(patvals([0x0123_4567, 3])).unwrap();
+    let pma = DoubleArrayAhoCorasick::with_values(PATTERNS.into_iter().zip([0x0123_4567usize, 3]))
+        .unwrap();
+    assert_eq!(expected.serialize(), pma.serialize());
+
+    let expected =
+        DoubleArrayAhoCorasick::with_values(PATTERNS.into_iter().zip([-(1i64 << 31), -3])).unwrap();
+    let pma = DoubleArrayAhoCorasick::with_values(PATTERNS.into_iter().zip([-(1isize << 31), -3]))
+        .unwrap();
+    assert_eq!(expected.serialize(), pma.serialize());
+    let (other, _) = DoubleArrayAhoCorasick::<isize>::deserialize(&expected.serialize()).unwrap();
+    let values: Vec<_> = other
+        .find_overlapping_iter("\u{1}\u{2}")
+        .map(|m| m.value())
+        .collect();
+    assert_eq!(vec![-(1 << 31), -3], values);
+}
+
+#[test]
+fn test_unsupported_version() {
+    let mut bytes = GOLDEN;
+    bytes[8] = 2;
+    match CharwiseDoubleArrayAhoCorasick::<u64>::deserialize(&bytes) {
+        Err(DaachorseError::UnsupportedFormat(e)) => assert_eq!(Some(2), e.version()),
+        _ => panic!("version 2 must be rejected"),
+    }
+}
+
+#[test]
+fn test_unsupported_magic() {
+    // Bytewise automata are not accepted by the charwise loader, and vice versa.
+    let bytewise = DoubleArrayAhoCorasick::<u64>::new(PATTERNS)
+        .unwrap()
+        .serialize();
+    match CharwiseDoubleArrayAhoCorasick::<u64>::deserialize(&bytewise) {
+        Err(DaachorseError::UnsupportedFormat(e)) => assert_eq!(None, e.version()),
+        _ => panic!("bytewise data must be rejected"),
+    }
+    match DoubleArrayAhoCorasick::<u64>::deserialize(&GOLDEN) {
+        Err(DaachorseError::UnsupportedFormat(e)) => assert_eq!(None, e.version()),
+        _ => panic!("charwise data must be rejected"),
+    }
+    match DoubleArrayAhoCorasick::<u64>::deserialize(&bytewise[..4]) {
+        Err(DaachorseError::UnsupportedFormat(e)) => assert_eq!(None, e.version()),
+        _ => panic!("truncated header must be rejected"),
+    }
+}
+
+#[test]
+#[should_panic]
+fn test_unsupported_version_unchecked() {
+    let mut bytes = GOLDEN;
+    bytes[8] = 2;
+    unsafe {
+        let _ = CharwiseDoubleArrayAhoCorasick::<u64>::deserialize_unchecked(&bytes);
+    }
+}
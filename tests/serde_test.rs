@@ -0,0 +1,148 @@
+#![cfg(feature = "serde")]
+
+use aneubeck_daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+    DoubleArrayAhoCorasickBuilder, Match, MatchKind,
+};
+
+const PATTERNS: [&str; 5] = ["bcd", "ab", "a", "全世界", "世界"];
+const HAYSTACK: &str = "abcd全世界";
+
+fn overlapping_matches(pma: &DoubleArrayAhoCorasick<u32>) -> Vec<Match<u32>> {
+    pma.find_overlapping_iter(HAYSTACK).collect()
+}
+
+fn overlapping_matches_charwise(pma: &CharwiseDoubleArrayAhoCorasick<u32>) -> Vec<Match<u32>> {
+    pma.find_overlapping_iter(HAYSTACK).collect()
+}
+
+#[test]
+fn test_bincode() {
+    let pma = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .compact_outputs(true)
+        .relabel_bytes(true)
+        .build(PATTERNS)
+        .unwrap();
+    let bytes = bincode::serialize(&pma).unwrap();
+    let other: DoubleArrayAhoCorasick<u32> = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(pma.serialize(), other.serialize());
+    assert_eq!(overlapping_matches(&pma), overlapping_matches(&other));
+
+    let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(PATTERNS).unwrap();
+    let bytes = bincode::serialize(&pma).unwrap();
+    let other: CharwiseDoubleArrayAhoCorasick<u32> = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(pma.serialize(), other.serialize());
+    assert_eq!(
+        overlapping_matches_charwise(&pma),
+        overlapping_matches_charwise(&other)
+    );
+}
+
+#[test]
+fn test_json() {
+    let pma = DoubleArrayAhoCorasick::<u32>::new(PATTERNS).unwrap();
+    let json = serde_json::to_string(&pma).unwrap();
+    let other: DoubleArrayAhoCorasick<u32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(pma.serialize(), other.serialize());
+
+    let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(PATTERNS).unwrap();
+    let json = serde_json::to_string(&pma).unwrap();
+    let other: CharwiseDoubleArrayAhoCorasick<u32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(pma.serialize(), other.serialize());
+}
+
+#[test]
+fn test_embedded() {
+    let pma = DoubleArrayAhoCorasick::<u32>::new(PATTERNS).unwrap();
+    let charwise_pma = CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build(PATTERNS)
+        .unwrap();
+    let config = (
+        42u32,
+        &pma,
+        MatchKind::LeftmostLongest,
+        &charwise_pma,
+        "end",
+    );
+    let bytes = bincode::serialize(&config).unwrap();
+    #[allow(clippy::type_complexity)]
+    let (x, other, match_kind, charwise_other, s): (
+        u32,
+        DoubleArrayAhoCorasick<u32>,
+        MatchKind,
+        CharwiseDoubleArrayAhoCorasick<u32>,
+        String,
+    ) = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(42, x);
+    assert_eq!(pma.serialize(), other.serialize());
+    assert_eq!(MatchKind::LeftmostLongest, match_kind);
+    assert_eq!(charwise_pma.serialize(), charwise_other.serialize());
+    assert_eq!("end", s);
+}
+
+#[test]
+fn test_corrupted() {
+    let pma = DoubleArrayAhoCorasick::<u32>::new(PATTERNS).unwrap();
+    let mut bytes = bincode::serialize(&pma).unwrap();
+    // Drops the last byte of the automaton, keeping the length prefix of bincode consistent.
+    bytes.pop();
+    bytes[0] -= 1;
+    assert!(bincode::deserialize::<DoubleArrayAhoCorasick<u32>>(&bytes).is_err());
+
+    // Appends a byte not consumed by the automaton.
+    let mut bytes = bincode::serialize(&pma).unwrap();
+    bytes.push(0);
+    bytes[0] += 1;
+    assert!(bincode::deserialize::<DoubleArrayAhoCorasick<u32>>(&bytes).is_err());
+
+    // Breaks the match kind, which is followed by the number of states in u32.
+    let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(PATTERNS).unwrap();
+    let mut bytes = bincode::serialize(&pma).unwrap();
+    let pos = bytes.len() - 5;
+    assert!(bincode::deserialize::<CharwiseDoubleArrayAhoCorasick<u32>>(&bytes).is_ok());
+    bytes[pos] = 3;
+    assert!(bincode::deserialize::<CharwiseDoubleArrayAhoCorasick<u32>>(&bytes).is_err());
+}
+
+#[test]
+fn test_match_kind() {
+    for (match_kind, json) in [
+        (MatchKind::Standard, "\"Standard\""),
+        (MatchKind::LeftmostLongest, "\"LeftmostLongest\""),
+        (MatchKind::LeftmostFirst, "\"LeftmostFirst\""),
+    ] {
+        assert_eq!(json, serde_json::to_string(&match_kind).unwrap());
+        assert_eq!(match_kind, serde_json::from_str(json).unwrap());
+
+        let bytes = bincode::serialize(&match_kind).unwrap();
+        assert_eq!(match_kind, bincode::deserialize(&bytes).unwrap());
+    }
+    assert!(serde_json::from_str::<MatchKind>("\"Leftmost\"").is_err());
+    assert!(bincode::deserialize::<MatchKind>(&3u32.to_le_bytes()).is_err());
+}
+
+#[test]
+fn test_match() {
+    let pma = DoubleArrayAhoCorasick::<u32>::new(PATTERNS).unwrap();
+    let matches = overlapping_matches(&pma);
+
+    let json = serde_json::to_string(&matches[0]).unwrap();
+    assert_eq!(r#"{"start":0,"end":1,"value":2}"#, json);
+    let bytes = bincode::serialize(&matches).unwrap();
+    assert_eq!(
+        matches,
+        bincode::deserialize::<Vec<Match<u32>>>(&bytes).unwrap()
+    );
+    let json = serde_json::to_string(&matches).unwrap();
+    assert_eq!(
+        matches,
+        serde_json::from_str::<Vec<Match<u32>>>(&json).unwrap()
+    );
+
+    let m: Match<u32> = serde_json::from_str(r#"{"value":7,"end":5,"start":3}"#).unwrap();
+    assert_eq!((3, 5, 7), (m.start(), m.end(), m.value()));
+    assert!(serde_json::from_str::<Match<u32>>(r#"{"start":5,"end":3,"value":7}"#).is_err());
+    assert!(serde_json::from_str::<Match<u32>>(r#"{"start":3,"value":7}"#).is_err());
+    assert!(serde_json::from_str::<Match<u32>>(r#"{"start":3,"end":5,"value":7,"x":0}"#).is_err());
+}
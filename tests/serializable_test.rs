@@ -0,0 +1,98 @@
+use core::num::NonZeroU64;
+
+use aneubeck_daachorse::{
+    impl_serializable, CharwiseDoubleArrayAhoCorasick, DoubleArrayAhoCorasick,
+    DoubleArrayAhoCorasickBuilder, Serializable,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Entry {
+    id: NonZeroU64,
+    pos: (u32, u16),
+    flags: [bool; 2],
+    label: Option<char>,
+}
+
+impl_serializable!(Entry {
+    id: NonZeroU64,
+    pos: (u32, u16),
+    flags: [bool; 2],
+    label: Option<char>,
+});
+
+fn entry(id: u64, label: Option<char>) -> Entry {
+    Entry {
+        id: NonZeroU64::new(id).unwrap(),
+        pos: (u32::try_from(id).unwrap() * 10, 7),
+        flags: [id % 2 == 0, true],
+        label,
+    }
+}
+
+#[test]
+fn test_macro() {
+    assert_eq!(8 + 6 + 2 + 5, Entry::serialized_bytes());
+    let x = entry(3, Some('世'));
+    let mut data = vec![];
+    x.serialize_to_vec(&mut data);
+    assert_eq!(Entry::serialized_bytes(), data.len());
+    data.push(42);
+    assert_eq!((x, &[42][..]), Entry::deserialize_from_slice(&data));
+    assert_eq!(
+        Some((x, &[42][..])),
+        Entry::deserialize_from_slice_checked(&data)
+    );
+    assert_eq!(None, Entry::deserialize_from_slice_checked(&data[..20]));
+}
+
+#[test]
+fn test_values() {
+    let patvals = [
+        ("bcd", entry(1, Some('b'))),
+        ("ab", entry(2, None)),
+        ("a", entry(3, Some('あ'))),
+    ];
+    for compact_outputs in [false, true] {
+        let pma = DoubleArrayAhoCorasickBuilder::new()
+            .compact_outputs(compact_outputs)
+            .build_with_values(patvals)
+            .unwrap();
+        let bytes = pma.serialize();
+        let (other, rest) = DoubleArrayAhoCorasick::<Entry>::deserialize(&bytes).unwrap();
+        assert!(rest.is_empty());
+        let values: Vec<_> = other
+            .find_overlapping_iter("abcd")
+            .map(|m| m.value())
+            .collect();
+        assert_eq!(vec![patvals[2].1, patvals[1].1, patvals[0].1], values);
+    }
+
+    let patvals = [
+        ("全世界", ('全', 1u8)),
+        ("世界", ('世', 2)),
+        ("に", ('に', 3)),
+    ];
+    let pma = CharwiseDoubleArrayAhoCorasick::with_values(patvals).unwrap();
+    let bytes = pma.serialize();
+    let (other, rest) = CharwiseDoubleArrayAhoCorasick::<(char, u8)>::deserialize(&bytes).unwrap();
+    assert!(rest.is_empty());
+    let values: Vec<_> = other
+        .find_overlapping_iter("全世界中に")
+        .map(|m| m.value())
+        .collect();
+    assert_eq!(vec![('全', 1), ('世', 2), ('に', 3)], values);
+}
+
+#[test]
+fn test_invalid_values() {
+    let pma = DoubleArrayAhoCorasick::with_values([("ab", '\u{10ffff}'), ("bc", 'x')]).unwrap();
+    let mut bytes = pma.serialize();
+    assert!(DoubleArrayAhoCorasick::<char>::deserialize(&bytes).is_ok());
+    let pos = bytes
+        .windows(4)
+        .position(|w| w == [0xff, 0xff, 0x10, 0x00])
+        .unwrap();
+    // Replaces the value with a code point out of the range.
+    bytes[pos..pos + 4].copy_from_slice(&0x110000u32.to_le_bytes());
+    assert!(DoubleArrayAhoCorasick::<char>::deserialize(&bytes).is_err());
+}
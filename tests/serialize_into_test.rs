@@ -0,0 +1,111 @@
+use aneubeck_daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+    DoubleArrayAhoCorasickBuilder, Sink,
+};
+
+fn generate_patterns() -> Vec<String> {
+    (0..20000).map(|i| format!("{i}の{}", i * 7)).collect()
+}
+
+// Collects the written bytes, recording the largest write and failing after `limit` bytes.
+struct TestSink {
+    bytes: Vec<u8>,
+    max_write_len: usize,
+    limit: usize,
+}
+
+impl TestSink {
+    const fn new(limit: usize) -> Self {
+        Self {
+            bytes: vec![],
+            max_write_len: 0,
+            limit,
+        }
+    }
+}
+
+impl Sink for TestSink {
+    type Error = usize;
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), usize> {
+        if self.bytes.len() + bytes.len() > self.limit {
+            return Err(self.bytes.len());
+        }
+        self.bytes.extend_from_slice(bytes);
+        self.max_write_len = self.max_write_len.max(bytes.len());
+        Ok(())
+    }
+}
+
+#[test]
+fn test_serialize_into() {
+    let patterns = generate_patterns();
+    for pma in [
+        DoubleArrayAhoCorasick::<u32>::new(&patterns).unwrap(),
+        DoubleArrayAhoCorasickBuilder::new()
+            .num_dense_states(100)
+            .compact_outputs(true)
+            .tail_compression(true)
+            .relabel_bytes(true)
+            .build(&patterns)
+            .unwrap(),
+    ] {
+        let bytes = pma.serialize();
+        assert_eq!(bytes.len(), pma.serialized_len());
+
+        let mut sink = TestSink::new(usize::MAX);
+        pma.serialize_into(&mut sink).unwrap();
+        assert_eq!(bytes, sink.bytes);
+        assert!(sink.max_write_len < bytes.len() / 4);
+
+        let mut sink = TestSink::new(bytes.len() / 2);
+        let written = pma.serialize_into(&mut sink).unwrap_err();
+        assert!(written <= bytes.len() / 2);
+        assert_eq!(&bytes[..written], &sink.bytes[..]);
+    }
+}
+
+#[test]
+fn test_serialize_into_charwise() {
+    let patterns = generate_patterns();
+    for pma in [
+        CharwiseDoubleArrayAhoCorasick::<u32>::new(&patterns).unwrap(),
+        CharwiseDoubleArrayAhoCorasickBuilder::new()
+            .compact_outputs(true)
+            .build(&patterns)
+            .unwrap(),
+    ] {
+        let bytes = pma.serialize();
+        assert_eq!(bytes.len(), pma.serialized_len());
+
+        let mut sink = TestSink::new(usize::MAX);
+        pma.serialize_into(&mut sink).unwrap();
+        assert_eq!(bytes, sink.bytes);
+        assert!(sink.max_write_len < bytes.len() / 4);
+
+        let mut sink = TestSink::new(bytes.len() / 2);
+        let written = pma.serialize_into(&mut sink).unwrap_err();
+        assert!(written <= bytes.len() / 2);
+        assert_eq!(&bytes[..written], &sink.bytes[..]);
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_serialize_into_writer() {
+    use std::io::{BufWriter, Write};
+
+    use aneubeck_daachorse::IoSink;
+
+    let patterns = generate_patterns();
+    let pma = DoubleArrayAhoCorasick::<u32>::new(&patterns).unwrap();
+    let mut writer = IoSink(BufWriter::new(vec![]));
+    pma.serialize_into(&mut writer).unwrap();
+    writer.0.flush().unwrap();
+    assert_eq!(pma.serialize(), writer.0.into_inner().unwrap());
+
+    let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(&patterns).unwrap();
+    let mut buf = [0; 16];
+    let err = pma.serialize_into(&mut IoSink(&mut buf[..])).unwrap_err();
+    assert_eq!(std::io::ErrorKind::WriteZero, err.kind());
+}
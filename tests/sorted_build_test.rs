@@ -0,0 +1,136 @@
+mod common;
+
+use aneubeck_daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+    DoubleArrayAhoCorasickBuilder, DuplicatePolicy,
+};
+
+use common::{Xorshift, MATCH_KINDS, SEED};
+
+// Duplicates are kept to test the policies.
+fn generate_patterns(num_patterns: usize, alphabet: &[char]) -> Vec<String> {
+    let mut rng = Xorshift(SEED);
+    let mut patterns: Vec<String> = (0..num_patterns)
+        .map(|_| rng.pattern(alphabet, 6))
+        .collect();
+    patterns.sort_unstable();
+    patterns
+}
+
+const POLICIES: [DuplicatePolicy<u32>; 4] = [
+    DuplicatePolicy::KeepFirst,
+    DuplicatePolicy::KeepLast,
+    DuplicatePolicy::Merge(|a, b| a ^ b),
+    DuplicatePolicy::KeepAll,
+];
+
+#[test]
+fn test_identical_serialization() {
+    let patterns = generate_patterns(10000, &['a', 'b', 'c', 'd', 'e', 'f']);
+    let patvals: Vec<_> = patterns
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (p, u32::try_from(i).unwrap()))
+        .collect();
+    for match_kind in MATCH_KINDS {
+        for policy in POLICIES {
+            let builder = || {
+                DoubleArrayAhoCorasickBuilder::new()
+                    .match_kind(match_kind)
+                    .on_duplicate(policy)
+            };
+            let expected = builder().build_with_values(patvals.clone()).unwrap();
+            let pma = builder().build_sorted_with_values(patvals.clone()).unwrap();
+            assert_eq!(expected.serialize(), pma.serialize());
+        }
+    }
+}
+
+#[test]
+fn test_identical_serialization_charwise() {
+    let patterns = generate_patterns(10000, &['a', 'b', 'あ', 'い', '全', '世', '界']);
+    let patvals: Vec<_> = patterns
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (p, u32::try_from(i).unwrap()))
+        .collect();
+    for match_kind in MATCH_KINDS {
+        for policy in POLICIES {
+            let builder = || {
+                CharwiseDoubleArrayAhoCorasickBuilder::new()
+                    .match_kind(match_kind)
+                    .on_duplicate(policy)
+            };
+            let expected = builder().build_with_values(patvals.clone()).unwrap();
+            let pma = builder().build_sorted_with_values(patvals.clone()).unwrap();
+            assert_eq!(expected.serialize(), pma.serialize());
+        }
+    }
+}
+
+#[test]
+fn test_build_sorted() {
+    let patterns = ["a", "ab", "abcde", "bcd", "cd", "xyz"];
+    let expected: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .build(patterns)
+        .unwrap();
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .build_sorted(patterns)
+        .unwrap();
+    assert_eq!(expected.serialize(), pma.serialize());
+
+    let expected: CharwiseDoubleArrayAhoCorasick<u32> =
+        CharwiseDoubleArrayAhoCorasickBuilder::new()
+            .build(patterns)
+            .unwrap();
+    let pma: CharwiseDoubleArrayAhoCorasick<u32> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .build_sorted(patterns)
+        .unwrap();
+    assert_eq!(expected.serialize(), pma.serialize());
+}
+
+#[test]
+fn test_unsorted_patterns() {
+    for patterns in [
+        vec!["ab", "a"],
+        vec!["a", "b", "ab"],
+        vec!["全世界", "世界"],
+    ] {
+        let e = DoubleArrayAhoCorasickBuilder::<u32>::new()
+            .build_sorted(&patterns)
+            .err()
+            .unwrap();
+        assert!(e.to_string().starts_with("UnsortedPatternError"));
+
+        let e = CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new()
+            .build_sorted(&patterns)
+            .err()
+            .unwrap();
+        assert!(e.to_string().starts_with("UnsortedPatternError"));
+    }
+}
+
+#[test]
+fn test_same_error() {
+    for patterns in [vec!["a", "ab", "ab"], vec!["", "a"], vec![]] {
+        let expected = DoubleArrayAhoCorasickBuilder::<u32>::new()
+            .build(&patterns)
+            .err()
+            .unwrap();
+        let e = DoubleArrayAhoCorasickBuilder::<u32>::new()
+            .build_sorted(&patterns)
+            .err()
+            .unwrap();
+        assert_eq!(expected.to_string(), e.to_string());
+
+        let expected = CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new()
+            .build(&patterns)
+            .err()
+            .unwrap();
+        let e = CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new()
+            .build_sorted(&patterns)
+            .err()
+            .unwrap();
+        assert_eq!(expected.to_string(), e.to_string());
+    }
+}
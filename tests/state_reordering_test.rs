@@ -0,0 +1,151 @@
+mod common;
+
+use aneubeck_daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+    DoubleArrayAhoCorasickBuilder, MatchKind,
+};
+
+use common::{
+    assert_same_matches, assert_same_matches_charwise, generate_patterns, Xorshift, MATCH_KINDS,
+};
+
+const ALPHABET: [char; 12] = [
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'あ', 'い', 'う', 'え',
+];
+
+fn generate_haystack(seed: u64) -> String {
+    let mut rng = Xorshift(seed);
+    (0..500)
+        .map(|_| rng.pattern::<_, String>(&ALPHABET, 6))
+        .collect()
+}
+
+fn assert_improved(expected_hot_blocks: usize, hot_blocks: usize) {
+    // The automaton fits in a single block when built with MatchKind::LeftmostFirst because every
+    // single letter is a pattern.
+    if expected_hot_blocks == 1 {
+        assert_eq!(1, hot_blocks);
+    } else {
+        assert!(hot_blocks < expected_hot_blocks);
+    }
+}
+
+#[test]
+fn test_reorder_states() {
+    let patterns: Vec<String> = generate_patterns(5000, &ALPHABET, 10);
+    let sample = generate_haystack(0x1234_5678_9abc_def1);
+    let haystack = generate_haystack(0x0fed_cba9_8765_4321);
+    for match_kind in MATCH_KINDS {
+        let builder = || DoubleArrayAhoCorasickBuilder::new().match_kind(match_kind);
+        let expected = builder().build(&patterns).unwrap();
+
+        let pma = builder().reorder_states(true).build(&patterns).unwrap();
+        assert_eq!(expected.heap_bytes(), pma.heap_bytes());
+        assert_improved(
+            expected.layout_stats().hot_blocks(),
+            pma.layout_stats().hot_blocks(),
+        );
+        assert_same_matches(&expected, &pma, match_kind, &haystack);
+
+        let pma = builder().reorder_sample(&sample).build(&patterns).unwrap();
+        assert_improved(
+            expected.layout_stats_with_sample(&sample).hot_blocks(),
+            pma.layout_stats_with_sample(&sample).hot_blocks(),
+        );
+        assert_eq!(
+            expected.layout_stats_with_sample(&sample).total_weight(),
+            pma.layout_stats_with_sample(&sample).total_weight(),
+        );
+        assert_same_matches(&expected, &pma, match_kind, &haystack);
+
+        let bytes = pma.serialize();
+        let (other, rest) = unsafe { DoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+        assert!(rest.is_empty());
+        assert_same_matches(&expected, &other, match_kind, &haystack);
+    }
+}
+
+#[test]
+fn test_reorder_states_options() {
+    let patterns: Vec<String> = generate_patterns(5000, &ALPHABET, 10);
+    let haystack = generate_haystack(0x0fed_cba9_8765_4321);
+    let expected = DoubleArrayAhoCorasick::new(&patterns).unwrap();
+    let builder = || DoubleArrayAhoCorasickBuilder::new().reorder_states(true);
+
+    let pma = builder().num_dense_states(100).build(&patterns).unwrap();
+    assert_same_matches(&expected, &pma, MatchKind::Standard, &haystack);
+
+    let pma = builder().tail_compression(true).build(&patterns).unwrap();
+    assert_same_matches(&expected, &pma, MatchKind::Standard, &haystack);
+
+    let pma = builder().relabel_bytes(true).build(&patterns).unwrap();
+    assert_same_matches(&expected, &pma, MatchKind::Standard, &haystack);
+
+    let pma = builder().build_sorted(&patterns).unwrap();
+    assert_same_matches(&expected, &pma, MatchKind::Standard, &haystack);
+}
+
+#[test]
+fn test_reorder_states_editor() {
+    let patterns: Vec<String> = generate_patterns(5000, &ALPHABET, 10);
+    let haystack = generate_haystack(0x0fed_cba9_8765_4321);
+    let (first, second) = patterns.split_at(patterns.len() / 2);
+
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .reorder_states(true)
+        .build(first)
+        .unwrap();
+    let mut editor = pma.into_editor().unwrap();
+    for (value, pattern) in (u32::try_from(first.len()).unwrap()..).zip(second) {
+        editor.insert(pattern, value).unwrap();
+    }
+    for pattern in first.iter().step_by(3) {
+        assert!(editor.remove(pattern).is_some());
+    }
+    let edited = editor.finish();
+
+    let mut editor = DoubleArrayAhoCorasick::new(first)
+        .unwrap()
+        .into_editor()
+        .unwrap();
+    for (value, pattern) in (u32::try_from(first.len()).unwrap()..).zip(second) {
+        editor.insert(pattern, value).unwrap();
+    }
+    for pattern in first.iter().step_by(3) {
+        assert!(editor.remove(pattern).is_some());
+    }
+    let expected = editor.finish();
+    assert_same_matches(&expected, &edited, MatchKind::Standard, &haystack);
+}
+
+#[test]
+fn test_reorder_states_charwise() {
+    let patterns: Vec<String> = generate_patterns(5000, &ALPHABET, 10);
+    let sample = generate_haystack(0x1234_5678_9abc_def1);
+    let haystack = generate_haystack(0x0fed_cba9_8765_4321);
+    for match_kind in MATCH_KINDS {
+        let builder = || CharwiseDoubleArrayAhoCorasickBuilder::new().match_kind(match_kind);
+        let expected = builder().build(&patterns).unwrap();
+
+        let pma = builder().reorder_states(true).build(&patterns).unwrap();
+        assert_eq!(expected.num_elements(), pma.num_elements());
+        assert_improved(
+            expected.layout_stats().hot_blocks(),
+            pma.layout_stats().hot_blocks(),
+        );
+        assert_same_matches_charwise(&expected, &pma, match_kind, &haystack);
+
+        let pma = builder().reorder_sample(&sample).build(&patterns).unwrap();
+        assert_improved(
+            expected.layout_stats_with_sample(&sample).hot_blocks(),
+            pma.layout_stats_with_sample(&sample).hot_blocks(),
+        );
+        assert_same_matches_charwise(&expected, &pma, match_kind, &haystack);
+
+        let bytes = pma.serialize();
+        let (other, rest) =
+            unsafe { CharwiseDoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+        assert!(rest.is_empty());
+        assert_same_matches_charwise(&expected, &other, match_kind, &haystack);
+    }
+}
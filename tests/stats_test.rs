@@ -0,0 +1,131 @@
+mod common;
+
+use aneubeck_daachorse::{
+    AutomatonStats, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasickBuilder,
+    DuplicatePolicy, MatchKind,
+};
+
+use common::generate_patterns;
+
+const ALPHABET: [char; 6] = ['a', 'b', 'c', 'd', 'あ', 'い'];
+
+fn assert_consistent(stats: &AutomatonStats, num_states: usize, match_kind: MatchKind) {
+    assert_eq!(num_states, stats.num_states());
+    assert_eq!(
+        num_states,
+        stats.output_chain_lengths().iter().sum::<usize>()
+    );
+    if match_kind == MatchKind::Standard {
+        // Every state except the root has a failure state.
+        assert_eq!(num_states - 1, stats.fail_depths().iter().sum::<usize>());
+    }
+    assert!(stats.num_vacant_elements() < stats.num_elements());
+    assert!(0.0 < stats.occupancy() && stats.occupancy() <= 1.0);
+    assert!(stats.avg_depth() <= f64::from(stats.max_depth()));
+}
+
+#[test]
+fn test_stats() {
+    let patterns: Vec<String> = generate_patterns(3000, &ALPHABET, 12);
+    let max_len = patterns.iter().map(String::len).max().unwrap();
+    for match_kind in [MatchKind::Standard, MatchKind::LeftmostLongest] {
+        let pma = DoubleArrayAhoCorasickBuilder::<u32>::new()
+            .match_kind(match_kind)
+            .build(&patterns)
+            .unwrap();
+        let stats = pma.stats();
+        assert_consistent(&stats, pma.num_states(), match_kind);
+        assert_eq!(patterns.len(), stats.num_patterns());
+        assert_eq!(max_len, usize::try_from(stats.max_depth()).unwrap());
+        assert_eq!(None, stats.alphabet_size());
+        assert_eq!(
+            pma.heap_bytes(),
+            stats.states_bytes() + stats.outputs_bytes() + stats.mapper_bytes()
+        );
+    }
+}
+
+#[test]
+fn test_stats_options() {
+    let patterns: Vec<String> = generate_patterns(3000, &ALPHABET, 12);
+    let expected = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .build(&patterns)
+        .unwrap()
+        .stats();
+    let builder = DoubleArrayAhoCorasickBuilder::<u32>::new;
+    for pma in [
+        builder().num_dense_states(10).build(&patterns).unwrap(),
+        builder().tail_compression(true).build(&patterns).unwrap(),
+        builder().compact_outputs(true).build(&patterns).unwrap(),
+        builder().relabel_bytes(true).build(&patterns).unwrap(),
+    ] {
+        let stats = pma.stats();
+        assert_consistent(&stats, pma.num_states(), MatchKind::Standard);
+        assert_eq!(expected.num_patterns(), stats.num_patterns());
+        assert_eq!(expected.max_depth(), stats.max_depth());
+        assert_eq!(expected.fail_depths(), stats.fail_depths());
+        assert_eq!(
+            expected.output_chain_lengths(),
+            stats.output_chain_lengths()
+        );
+        assert_eq!(
+            pma.heap_bytes(),
+            stats.states_bytes()
+                + stats.outputs_bytes()
+                + stats.mapper_bytes()
+                + stats.dense_bytes()
+        );
+    }
+}
+
+#[test]
+fn test_stats_multiple_values() {
+    let patvals = [("abc", 0), ("abc", 1), ("bc", 2), ("c", 3)];
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .on_duplicate(DuplicatePolicy::KeepAll)
+        .build_with_values(patvals)
+        .unwrap();
+    let stats = pma.stats();
+    assert_eq!(3, stats.num_patterns());
+    // "abc" reports its two values and those of "bc" and "c".
+    assert_eq!(&[4, 1, 1, 0, 1], stats.output_chain_lengths());
+}
+
+#[test]
+fn test_stats_editor() {
+    let patterns: Vec<String> = generate_patterns(3000, &ALPHABET, 12);
+    let pma = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .build(&patterns)
+        .unwrap();
+    let mut editor = pma.into_editor().unwrap();
+    for pattern in patterns.iter().step_by(2) {
+        editor.remove(pattern).unwrap();
+    }
+    let pma = editor.finish();
+    let stats = pma.stats();
+    assert_consistent(&stats, pma.num_states(), MatchKind::Standard);
+    assert_eq!(patterns.len() / 2, stats.num_patterns());
+}
+
+#[test]
+fn test_stats_charwise() {
+    let patterns: Vec<String> = generate_patterns(3000, &ALPHABET, 12);
+    let max_len = patterns.iter().map(|p| p.chars().count()).max().unwrap();
+    for match_kind in [MatchKind::Standard, MatchKind::LeftmostLongest] {
+        let pma = CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new()
+            .match_kind(match_kind)
+            .build(&patterns)
+            .unwrap();
+        let stats = pma.stats();
+        assert_consistent(&stats, pma.num_states(), match_kind);
+        assert_eq!(patterns.len(), stats.num_patterns());
+        assert_eq!(max_len, usize::try_from(stats.max_depth()).unwrap());
+        assert_eq!(Some(6), stats.alphabet_size());
+        assert_eq!(pma.num_elements(), stats.num_elements());
+        assert_eq!(0, stats.dense_bytes());
+        assert_eq!(
+            pma.heap_bytes(),
+            stats.states_bytes() + stats.outputs_bytes() + stats.mapper_bytes()
+        );
+    }
+}
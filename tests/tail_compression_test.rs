@@ -0,0 +1,151 @@
+mod common;
+
+use aneubeck_daachorse::errors::DaachorseError;
+use aneubeck_daachorse::{
+    DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, DuplicatePolicy, MatchKind,
+};
+
+use common::{assert_same_matches, Xorshift, MATCH_KINDS, SEED};
+
+const LOWERCASE: [char; 26] = [
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
+    't', 'u', 'v', 'w', 'x', 'y', 'z',
+];
+
+fn generate_urls(num_urls: usize, short_patterns: &[&str]) -> Vec<String> {
+    let mut rng = Xorshift(SEED);
+    let hosts: Vec<String> = (0..50).map(|_| rng.pattern(&LOWERCASE, 10)).collect();
+    let mut urls: Vec<_> = (0..num_urls)
+        .map(|_| {
+            let host = &hosts[usize::try_from(rng.next() % 50).unwrap()];
+            let dir: String = rng.pattern(&LOWERCASE, 8);
+            let file: String = rng.pattern(&LOWERCASE, 12);
+            format!("https://www.{host}.com/{dir}/{file}.html")
+        })
+        .collect();
+    urls.extend(short_patterns.iter().map(|&p| String::from(p)));
+    urls.sort_unstable();
+    urls.dedup();
+    urls
+}
+
+fn generate_haystack(urls: &[String]) -> String {
+    let mut rng = Xorshift(0x1234_5678_9abc_def1);
+    (0..300)
+        .map(|_| {
+            let url = &urls[usize::try_from(rng.next()).unwrap() % urls.len()];
+            // Cuts the URLs so that the search also fails inside the tails.
+            let len = usize::try_from(rng.next()).unwrap() % url.len() + 1;
+            let word: String = rng.pattern(&LOWERCASE, 5);
+            format!("{} {word}", &url[..len])
+        })
+        .collect()
+}
+
+// Short patterns matching inside the URLs, some of which are stored in tails.
+const SHORT_PATTERNS: [&str; 5] = ["com", "www", "html", "/a", "ab"];
+
+#[test]
+fn test_tail_compression() {
+    let urls = generate_urls(3000, &SHORT_PATTERNS);
+    let haystack = generate_haystack(&urls);
+    for match_kind in MATCH_KINDS {
+        let builder = || DoubleArrayAhoCorasickBuilder::new().match_kind(match_kind);
+        let expected = builder().build(&urls).unwrap();
+        let pma = builder().tail_compression(true).build(&urls).unwrap();
+        assert_eq!(expected.num_states(), pma.num_states());
+        assert_same_matches(&expected, &pma, match_kind, &haystack);
+
+        let bytes = pma.serialize();
+        let (other, rest) = unsafe { DoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+        assert!(rest.is_empty());
+        assert_eq!(bytes, other.serialize());
+        assert_same_matches(&expected, &other, match_kind, &haystack);
+    }
+}
+
+#[test]
+fn test_tail_compression_memory() {
+    let urls = generate_urls(10000, &[]);
+    let expected = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .build(&urls)
+        .unwrap();
+    let pma = DoubleArrayAhoCorasickBuilder::<u32>::new()
+        .tail_compression(true)
+        .build(&urls)
+        .unwrap();
+    let states_bytes = |pma: &DoubleArrayAhoCorasick<u32>| {
+        // Excludes the outputs, which are the same in both.
+        pma.heap_bytes() - urls.len() * 12
+    };
+    assert!(states_bytes(&pma) * 3 < states_bytes(&expected));
+}
+
+#[test]
+fn test_tail_compression_options() {
+    let urls = generate_urls(3000, &SHORT_PATTERNS);
+    let haystack = generate_haystack(&urls);
+    let expected = DoubleArrayAhoCorasickBuilder::new().build(&urls).unwrap();
+
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .tail_compression(true)
+        .num_dense_states(300)
+        .compact_outputs(true)
+        .build(&urls)
+        .unwrap();
+    assert_same_matches(&expected, &pma, MatchKind::Standard, &haystack);
+
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .tail_compression(true)
+        .build_sorted(&urls)
+        .unwrap();
+    assert_same_matches(&expected, &pma, MatchKind::Standard, &haystack);
+
+    let merged = DoubleArrayAhoCorasick::merge(&[&pma], DuplicatePolicy::Error).unwrap();
+    assert_eq!(expected.serialize(), merged.serialize());
+}
+
+#[test]
+fn test_tail_compression_single_pattern() {
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .tail_compression(true)
+        .build(["abcabd"])
+        .unwrap();
+    let mut it = pma.find_overlapping_iter("abcabcabd");
+    let m = it.next().unwrap();
+    assert_eq!((3, 9, 0), (m.start(), m.end(), m.value()));
+    assert_eq!(None, it.next());
+}
+
+#[test]
+fn test_tail_compression_editor() {
+    let urls = generate_urls(100, &SHORT_PATTERNS);
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .tail_compression(true)
+        .build(&urls)
+        .unwrap();
+    assert!(matches!(
+        pma.into_editor(),
+        Err(DaachorseError::InvalidConversion(_))
+    ));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_tail_compression_parallel() {
+    let urls = generate_urls(3000, &SHORT_PATTERNS);
+    let haystack = generate_haystack(&urls);
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .tail_compression(true)
+        .par_build(&urls, 4)
+        .unwrap();
+    let other = DoubleArrayAhoCorasickBuilder::new()
+        .tail_compression(true)
+        .build(&urls)
+        .unwrap();
+    assert_eq!(other.serialize(), pma.serialize());
+    assert_eq!(
+        other.find_overlapping_iter(&haystack).collect::<Vec<_>>(),
+        pma.par_find_overlapping(&haystack, 4),
+    );
+}
@@ -0,0 +1,206 @@
+mod common;
+
+use aneubeck_daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+    DoubleArrayAhoCorasickBuilder, DuplicatePolicy,
+};
+
+use common::{generate_patterns, MATCH_KINDS};
+
+const ALPHABET: [char; 6] = ['a', 'b', 'c', 'd', 'あ', 'い'];
+
+#[test]
+fn test_verify() {
+    let patterns: Vec<String> = generate_patterns(2000, &ALPHABET, 10);
+    for match_kind in MATCH_KINDS {
+        let builder = || DoubleArrayAhoCorasickBuilder::<u32>::new().match_kind(match_kind);
+        for pma in [
+            builder().build(&patterns).unwrap(),
+            builder().build_sorted(&patterns).unwrap(),
+            builder().num_dense_states(100).build(&patterns).unwrap(),
+            builder().compact_outputs(true).build(&patterns).unwrap(),
+            builder().tail_compression(true).build(&patterns).unwrap(),
+            builder().relabel_bytes(true).build(&patterns).unwrap(),
+            builder().reorder_states(true).build(&patterns).unwrap(),
+            builder()
+                .num_dense_states(100)
+                .compact_outputs(true)
+                .tail_compression(true)
+                .relabel_bytes(true)
+                .reorder_states(true)
+                .build(&patterns)
+                .unwrap(),
+        ] {
+            pma.verify().unwrap();
+
+            let bytes = pma.serialize();
+            let (other, rest) =
+                unsafe { DoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+            assert!(rest.is_empty());
+            other.verify().unwrap();
+
+            let (other, rest) = DoubleArrayAhoCorasick::<u32>::deserialize(&bytes).unwrap();
+            assert!(rest.is_empty());
+            assert_eq!(bytes, other.serialize());
+        }
+    }
+}
+
+#[test]
+fn test_verify_multiple_values() {
+    let patterns: Vec<String> = generate_patterns(2000, &ALPHABET, 10);
+    let patvals = patterns
+        .iter()
+        .zip(0..)
+        .chain(patterns.iter().step_by(3).zip(10000..));
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .on_duplicate(DuplicatePolicy::KeepAll)
+        .build_with_values(patvals.clone())
+        .unwrap();
+    pma.verify().unwrap();
+
+    let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .on_duplicate(DuplicatePolicy::KeepAll)
+        .build_with_values(patvals)
+        .unwrap();
+    pma.verify().unwrap();
+}
+
+#[test]
+fn test_verify_editor() {
+    let patterns: Vec<String> = generate_patterns(2000, &ALPHABET, 10);
+    let (first, second) = patterns.split_at(patterns.len() / 2);
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .relabel_bytes(true)
+        .build(first)
+        .unwrap();
+    let mut editor = pma.into_editor().unwrap();
+    for (value, pattern) in (u32::try_from(first.len()).unwrap()..).zip(second) {
+        editor.insert(pattern, value).unwrap();
+    }
+    for pattern in first.iter().step_by(3) {
+        assert!(editor.remove(pattern).is_some());
+    }
+    let pma = editor.finish();
+    pma.verify().unwrap();
+
+    let mut editor = pma.into_editor().unwrap();
+    editor.compact().unwrap();
+    editor.finish().verify().unwrap();
+}
+
+#[test]
+fn test_verify_merge() {
+    let patterns: Vec<String> = generate_patterns(2000, &ALPHABET, 10);
+    let (first, second) = patterns.split_at(patterns.len() / 2);
+    let pma1 = DoubleArrayAhoCorasick::<u32>::new(first).unwrap();
+    let pma2 = DoubleArrayAhoCorasick::<u32>::new(second).unwrap();
+    DoubleArrayAhoCorasick::merge(&[&pma1, &pma2], DuplicatePolicy::KeepFirst)
+        .unwrap()
+        .verify()
+        .unwrap();
+
+    let pma1 = CharwiseDoubleArrayAhoCorasick::<u32>::new(first).unwrap();
+    let pma2 = CharwiseDoubleArrayAhoCorasick::<u32>::new(second).unwrap();
+    CharwiseDoubleArrayAhoCorasick::merge(&[&pma1, &pma2], DuplicatePolicy::KeepFirst)
+        .unwrap()
+        .verify()
+        .unwrap();
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_verify_parallel() {
+    let patterns: Vec<String> = generate_patterns(2000, &ALPHABET, 10);
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .par_build(&patterns, 4)
+        .unwrap();
+    pma.verify().unwrap();
+}
+
+#[test]
+fn test_verify_charwise() {
+    let patterns: Vec<String> = generate_patterns(2000, &ALPHABET, 10);
+    for match_kind in MATCH_KINDS {
+        let builder = || CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new().match_kind(match_kind);
+        for pma in [
+            builder().build(&patterns).unwrap(),
+            builder().build_sorted(&patterns).unwrap(),
+            builder().compact_outputs(true).build(&patterns).unwrap(),
+            builder().reorder_states(true).build(&patterns).unwrap(),
+        ] {
+            pma.verify().unwrap();
+
+            let bytes = pma.serialize();
+            let (other, rest) =
+                unsafe { CharwiseDoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+            assert!(rest.is_empty());
+            other.verify().unwrap();
+
+            let (other, rest) = CharwiseDoubleArrayAhoCorasick::<u32>::deserialize(&bytes).unwrap();
+            assert!(rest.is_empty());
+            assert_eq!(bytes, other.serialize());
+        }
+    }
+}
+
+// Checks that corrupted data is rejected or loaded into a valid automaton, without panicking.
+#[test]
+fn test_deserialize_corrupted() {
+    let patterns = ["bcd", "ab", "a", "abcde", "全世界", "世界"];
+    let haystack = "abcdefg全世界中に";
+    let builder = || DoubleArrayAhoCorasickBuilder::<u32>::new();
+    for pma in [
+        builder().build(patterns).unwrap(),
+        builder()
+            .num_dense_states(3)
+            .compact_outputs(true)
+            .tail_compression(true)
+            .relabel_bytes(true)
+            .build(patterns)
+            .unwrap(),
+    ] {
+        let bytes = pma.serialize();
+        for len in 0..bytes.len() {
+            assert!(DoubleArrayAhoCorasick::<u32>::deserialize(&bytes[..len]).is_err());
+        }
+        for i in 0..bytes.len() {
+            for mask in [0x01, 0x10, 0x80, 0xff] {
+                let mut corrupted = bytes.clone();
+                corrupted[i] ^= mask;
+                if let Ok((other, _)) = DoubleArrayAhoCorasick::<u32>::deserialize(&corrupted) {
+                    if let Ok(it) = other.try_find_overlapping_iter(haystack) {
+                        it.for_each(drop);
+                    }
+                }
+            }
+        }
+    }
+
+    // Uses small code points to keep the table of the code mapper short.
+    let patterns = ["bcd", "ab", "a", "abcde", "àéè", "éè"];
+    let haystack = "abcdefgàéèに";
+    let builder = || CharwiseDoubleArrayAhoCorasickBuilder::<u32>::new();
+    for pma in [
+        builder().build(patterns).unwrap(),
+        builder().compact_outputs(true).build(patterns).unwrap(),
+    ] {
+        let bytes = pma.serialize();
+        for len in 0..bytes.len() {
+            assert!(CharwiseDoubleArrayAhoCorasick::<u32>::deserialize(&bytes[..len]).is_err());
+        }
+        for i in 0..bytes.len() {
+            for mask in [0x01, 0x10, 0x80, 0xff] {
+                let mut corrupted = bytes.clone();
+                corrupted[i] ^= mask;
+                if let Ok((other, _)) =
+                    CharwiseDoubleArrayAhoCorasick::<u32>::deserialize(&corrupted)
+                {
+                    if let Ok(it) = other.try_find_overlapping_iter(haystack) {
+                        it.for_each(drop);
+                    }
+                }
+            }
+        }
+    }
+}